@@ -0,0 +1,180 @@
+//! Flashcards of the repertoire for Anki, one for each position where it's the player's move with
+//! the board on the front and the prep's move on the back. They're written as the plain text
+//! Anki imports, with the board drawn as an SVG in the card so nothing else needs copying over.
+use crate::db::{next_moves, OpeningGraph};
+use shakmaty::fen::Epd;
+use shakmaty::{Chess, Color, EnPassantMode, File, Position, Rank, Square};
+use std::collections::HashSet;
+
+/// Size of a square in the SVG
+const SQUARE: usize = 30;
+
+#[derive(Clone, Debug)]
+pub struct Card {
+    /// To get to the position from the start
+    pub moves: Vec<String>,
+    pub position: Chess,
+    /// The prep's moves here, more than one if it branches
+    pub expected: Vec<String>,
+}
+
+/// Every position in the prep where it's `player`'s move, the first way it's reached is the one
+/// shown if lines transpose
+pub fn cards(openings: &OpeningGraph, player: Color) -> Vec<Card> {
+    let mut cards = vec![];
+    let mut seen = HashSet::new();
+    let mut stack = vec![(None, Chess::new(), vec![])];
+    while let Some((node, position, moves)) = stack.pop() {
+        let next = next_moves(openings, node);
+        if next.is_empty() {
+            continue;
+        }
+        let epd = Epd::from_position(position.clone(), EnPassantMode::Legal).to_string();
+        if position.turn() == player && seen.insert(epd) {
+            let mut expected = next
+                .iter()
+                .map(|x| openings[*x].to_string())
+                .collect::<Vec<_>>();
+            expected.sort();
+            expected.dedup();
+            cards.push(Card {
+                moves: moves.clone(),
+                position: position.clone(),
+                expected,
+            });
+        }
+        for node in next {
+            let san = &openings[node];
+            let Ok(mv) = san.san.to_move(&position) else {
+                continue;
+            };
+            let mut moves = moves.clone();
+            moves.push(san.to_string());
+            stack.push((Some(node), position.clone().play(&mv).unwrap(), moves));
+        }
+    }
+    cards.sort_by(|a, b| a.moves.cmp(&b.moves));
+    cards
+}
+
+/// The moves numbered, ending with the one to find e.g. `1. e4 e5 2. ?`
+fn numbered(moves: &[String]) -> String {
+    let mut text = String::new();
+    for (i, san) in moves.iter().enumerate() {
+        if i % 2 == 0 {
+            text += &format!("{}. ", i / 2 + 1);
+        }
+        text += &format!("{} ", san);
+    }
+    if moves.len().is_multiple_of(2) {
+        text += &format!("{}. ?", moves.len() / 2 + 1);
+    } else {
+        text += "?";
+    }
+    text
+}
+
+/// The board from `orientation`'s side
+pub(crate) fn board_svg(position: &Chess, orientation: Color) -> String {
+    let size = SQUARE * 8;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">",
+        size
+    );
+    for rank in Rank::ALL {
+        for file in File::ALL {
+            let square = Square::from_coords(file, rank);
+            let (x, y) = match orientation {
+                Color::White => (usize::from(file), 7 - usize::from(rank)),
+                Color::Black => (7 - usize::from(file), usize::from(rank)),
+            };
+            let fill = if square.is_light() {
+                "#f0d9b5"
+            } else {
+                "#b58863"
+            };
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{SQUARE}\" height=\"{SQUARE}\" fill=\"{}\"/>",
+                x * SQUARE,
+                y * SQUARE,
+                fill
+            );
+            if let Some(piece) = position.board().piece_at(square) {
+                let glyph = match piece.role {
+                    shakmaty::Role::King => '♚',
+                    shakmaty::Role::Queen => '♛',
+                    shakmaty::Role::Rook => '♜',
+                    shakmaty::Role::Bishop => '♝',
+                    shakmaty::Role::Knight => '♞',
+                    shakmaty::Role::Pawn => '♟',
+                };
+                let (fill, stroke) = piece.color.fold_wb(("#fff", "#000"), ("#000", "#fff"));
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" fill=\"{}\" stroke=\"{}\" stroke-width=\"0.5\">{}</text>",
+                    x * SQUARE + SQUARE / 2,
+                    y * SQUARE + SQUARE * 5 / 6,
+                    SQUARE * 5 / 6,
+                    fill,
+                    stroke,
+                    glyph
+                );
+            }
+        }
+    }
+    svg + "</svg>"
+}
+
+/// The cards as a file for Anki's text import, tagged with the colour and `tag` if there is one
+pub fn anki_text(cards: &[Card], player: Color, tag: Option<&str>) -> String {
+    let mut tags = format!("chess-driller {}", player);
+    if let Some(tag) = tag {
+        tags += &format!(" {}", tag.replace(char::is_whitespace, "_"));
+    }
+    let mut text = "#separator:tab\n#html:true\n#tags column:3\n".to_string();
+    for card in cards {
+        let epd = Epd::from_position(card.position.clone(), EnPassantMode::Legal);
+        let front = format!(
+            "{}<br><div>{}</div><div><small>{}</small></div>",
+            board_svg(&card.position, player),
+            numbered(&card.moves),
+            epd
+        );
+        let back = card.expected.join(" or ");
+        text += &format!("{}\t{}\t{}\n", front, back, tags);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn make_cards() {
+        let mut db = OpeningDatabase::default();
+        let prep = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 *";
+        db.add_prep(Color::White, prep.as_bytes()).unwrap();
+        let cards = cards(db.graph(Color::White), Color::White);
+        let lines = cards
+            .iter()
+            .map(|x| (numbered(&x.moves), x.expected.join(" ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                ("1. ?".to_string(), "e4".to_string()),
+                ("1. e4 c5 2. ?".to_string(), "Nf3".to_string()),
+                ("1. e4 e5 2. ?".to_string(), "Nf3".to_string()),
+                ("1. e4 e5 2. Nf3 Nc6 3. ?".to_string(), "Bb5".to_string()),
+            ]
+        );
+        let text = anki_text(&cards, Color::White, Some("Ruy Lopez"));
+        assert_eq!(text.lines().count(), 7);
+        assert!(text
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("\tBb5\tchess-driller white Ruy_Lopez"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Engine evals, NAGs and clocks for the moves of a game, for PGNs that show them in other tools.
+use crate::cache::EvalCache;
+use crate::clock::Clock;
+use crate::engine::{Limits, Score, UciEngine};
+use shakmaty::san::SanPlus;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Position};
+use std::str::FromStr;
+
+/// Differences past this don't matter, going from mate in 3 to a rook up isn't a blunder
+const DECIDED: i32 = 1000;
+
+/// What's said about a move in an exported PGN
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /// From white's point of view after the move, `None` once the game is over
+    pub eval: Option<Score>,
+    /// `$6` for a dubious move, `$2` for a mistake and `$4` for a blunder
+    pub nag: Option<u8>,
+    /// Kept from the game the moves came from
+    pub clock: Clock,
+}
+
+impl Annotation {
+    /// Written after the move's SAN
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        if let Some(nag) = self.nag {
+            pgn += &format!(" ${}", nag);
+        }
+        let mut tags = match self.eval {
+            Some(Score::Cp(x)) => vec![format!("[%eval {:.2}]", x as f64 / 100.0)],
+            Some(Score::Mate(x)) => vec![format!("[%eval #{}]", x)],
+            None => vec![],
+        };
+        if !self.clock.is_empty() {
+            tags.push(self.clock.to_pgn());
+        }
+        if !tags.is_empty() {
+            pgn += &format!(" {{ {} }}", tags.join(" "));
+        }
+        pgn
+    }
+}
+
+/// NAG for a move that loses this many centipawns
+fn nag(loss: i32) -> Option<u8> {
+    match loss {
+        300.. => Some(4),
+        100.. => Some(2),
+        50.. => Some(6),
+        _ => None,
+    }
+}
+
+/// Search every position in the game from `start` within `limits` and say how good each move is compared to
+/// the engine's choice
+pub fn annotate(
+    engine: &UciEngine,
+    cache: &EvalCache,
+    start: &Chess,
+    moves: &[SanPlus],
+    limits: Limits,
+) -> anyhow::Result<Vec<Annotation>> {
+    let mut positions = vec![start.clone()];
+    let mut played = vec![];
+    for san in moves {
+        let position = positions.last().unwrap();
+        let mv = san.san.to_move(position)?;
+        played.push(Uci::from_move(&mv, position.castles().mode()));
+        positions.push(position.clone().play(&mv)?);
+    }
+    // Scores for the side to move
+    let mut evals = vec![];
+    for position in &positions {
+        evals.push(match position.outcome() {
+            Some(_) => None,
+            None => Some(cache.analyze(engine, position, limits)?),
+        });
+    }
+
+    let mut annotations = vec![];
+    for (i, pair) in positions.windows(2).enumerate() {
+        let after = &pair[1];
+        let eval = match &evals[i + 1] {
+            Some(x) => Some(x.score.for_white(after.turn())),
+            // Otherwise it's a draw, mate needs no eval
+            None if !after.is_checkmate() => Some(Score::Cp(0)),
+            None => None,
+        };
+        // For the side that moved
+        let reached = match (&evals[i + 1], after.is_checkmate()) {
+            (_, true) => DECIDED,
+            (Some(x), _) => -x.score.centipawns(),
+            (None, _) => 0,
+        };
+        let nag = evals[i].as_ref().and_then(|best| {
+            // Searching the position after goes a move deeper, which can be enough to disagree
+            if best.best_move().and_then(|x| Uci::from_str(x).ok()) == Some(played[i].clone()) {
+                return None;
+            }
+            let best = best.score.centipawns().clamp(-DECIDED, DECIDED);
+            nag(best - reached.clamp(-DECIDED, DECIDED))
+        });
+        annotations.push(Annotation {
+            eval,
+            nag,
+            ..Default::default()
+        });
+    }
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_annotations() {
+        let blunder = Annotation {
+            eval: Some(Score::Cp(-312)),
+            nag: nag(350),
+            ..Default::default()
+        };
+        assert_eq!(blunder.to_pgn(), " $4 { [%eval -3.12] }");
+        let mate = Annotation {
+            eval: Some(Score::Mate(-2)),
+            nag: nag(60),
+            ..Default::default()
+        };
+        assert_eq!(mate.to_pgn(), " $6 { [%eval #-2] }");
+        assert_eq!(Annotation::default().to_pgn(), "");
+        assert_eq!(nag(99), Some(6));
+        assert_eq!(nag(10), None);
+    }
+}
@@ -0,0 +1,219 @@
+//! Old attempts folded into daily totals so `attempts.jsonl` doesn't grow forever. The stats
+//! add the totals back in, and where each line had got to in the schedule is kept as a review
+//! for it so the next one's due on the same day it would have been.
+use crate::config::{Config, Drill};
+use crate::db::OpeningDatabase;
+use crate::schedule::{load_seeds, prep_lines, schedule, seeds_file, Seed};
+use crate::stats::{attempts_file, load_attempts, opening, save_attempts, Attempt, Totals};
+use anyhow::Context;
+use chrono::{Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use shakmaty::Color;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Totals for the attempts at an opening from a chapter done on a day
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchivedDay {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub color: String,
+    pub chapter: Option<String>,
+    pub opening: Vec<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Archive {
+    /// Attempts started before this day have been archived, YYYY-MM-DD
+    pub before: Option<String>,
+    pub days: Vec<ArchivedDay>,
+    /// Where each line had got to in the schedule by then
+    pub seeds: Vec<Seed>,
+}
+
+pub fn archive_file(config: &Config) -> PathBuf {
+    config.data_dir().join("archive.json")
+}
+
+pub fn load_archive(path: &Path) -> anyhow::Result<Archive> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).with_context(|| format!("Bad {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Archive::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_archive(path: &Path, archive: &Archive) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_string(archive)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// First day of attempts that are kept when only the last `months` are
+pub fn keep_from(today: NaiveDate, months: u32) -> NaiveDate {
+    today - Months::new(months)
+}
+
+/// Move the attempts started before `before` into the archive, returning the ones left. `lines`
+/// are each colour's lines in the prep, `seeds` the reviews imported from elsewhere and
+/// `settings` has the intervals they're scheduled with.
+pub fn archive_attempts(
+    archive: &mut Archive,
+    attempts: Vec<Attempt>,
+    before: NaiveDate,
+    lines: Vec<(String, Vec<Vec<String>>)>,
+    seeds: &[Seed],
+    settings: &Drill,
+) -> Vec<Attempt> {
+    let cutoff = before.to_string();
+    let (old, kept): (Vec<_>, Vec<_>) = attempts
+        .into_iter()
+        .partition(|x| x.started.get(..10).is_some_and(|x| x < cutoff.as_str()));
+    if old.is_empty() {
+        return kept;
+    }
+
+    let mut seeds = seeds.to_vec();
+    seeds.append(&mut archive.seeds);
+    for (color, lines) in lines {
+        for line in schedule(lines, &color, &old, &seeds, before, settings) {
+            if line.reviews == 0 {
+                continue;
+            }
+            let reviewed = line.due - Days::new(line.interval.round() as u64);
+            archive.seeds.push(Seed {
+                color: color.clone(),
+                line: line.line,
+                interval: line.interval,
+                reviewed: reviewed.to_string(),
+            });
+        }
+    }
+
+    let mut days: BTreeMap<(String, String, Option<String>, Vec<String>), Totals> = archive
+        .days
+        .drain(..)
+        .map(|x| ((x.day, x.color, x.chapter, x.opening), x.totals))
+        .collect();
+    for attempt in &old {
+        let day = attempt.started.get(..10).unwrap_or_default().to_string();
+        days.entry((
+            day,
+            attempt.color.clone(),
+            attempt.chapter.clone(),
+            opening(&attempt.line).to_vec(),
+        ))
+        .or_default()
+        .add(attempt);
+    }
+    archive.days = days
+        .into_iter()
+        .map(|((day, color, chapter, opening), totals)| ArchivedDay {
+            day,
+            color,
+            chapter,
+            opening,
+            totals,
+        })
+        .collect();
+    archive.before = archive.before.take().max(Some(cutoff));
+    kept
+}
+
+/// Archive the attempts from before the last `months`, returning how many there were
+pub fn archive_history(
+    config: &Config,
+    db: &OpeningDatabase,
+    months: u32,
+    today: NaiveDate,
+) -> anyhow::Result<usize> {
+    let path = attempts_file(config);
+    let attempts = load_attempts(&path)?;
+    let count = attempts.len();
+    let mut archive = load_archive(&archive_file(config))?;
+    let seeds = load_seeds(&seeds_file(config))?;
+    let lines = [Color::White, Color::Black]
+        .into_iter()
+        .map(|x| (x.to_string(), prep_lines(db.graph(x))))
+        .collect();
+    let kept = archive_attempts(
+        &mut archive,
+        attempts,
+        keep_from(today, months),
+        lines,
+        &seeds,
+        &config.drill,
+    );
+    if kept.len() == count {
+        return Ok(0);
+    }
+    // The archive goes first, if it's the attempts that don't get written they're archived
+    // again next time rather than lost
+    save_archive(&archive_file(config), &archive)?;
+    save_attempts(&path, &kept)?;
+    info!("Archived {} attempts", count - kept.len());
+    Ok(count - kept.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::DrillResult;
+
+    #[test]
+    fn archive_old_attempts() {
+        let attempt = |started: &str| Attempt {
+            line: "e4 e5 Nf3".split(' ').map(|x| x.to_string()).collect(),
+            color: "white".to_string(),
+            chapter: None,
+            started: started.to_string(),
+            result: DrillResult::Completed,
+            seconds: 10.0,
+            moves: 2,
+            hints: 0,
+            mistakes: vec![],
+        };
+        let attempts = vec![
+            attempt("2026-01-01T10:00:00+00:00"),
+            attempt("2026-01-01T11:00:00+00:00"),
+            attempt("2026-01-02T10:00:00+00:00"),
+            attempt("2026-06-01T10:00:00+00:00"),
+        ];
+        let line = || vec!["e4 e5 Nf3".split(' ').map(|x| x.to_string()).collect()];
+        let today = NaiveDate::from_ymd_opt(2026, 6, 2).unwrap();
+        let settings = Drill::default();
+        let before = schedule(line(), "white", &attempts, &[], today, &settings);
+
+        let mut archive = Archive::default();
+        let cutoff = keep_from(today, 3);
+        let kept = archive_attempts(
+            &mut archive,
+            attempts,
+            cutoff,
+            vec![("white".to_string(), line()), ("black".to_string(), vec![])],
+            &[],
+            &settings,
+        );
+        assert_eq!(kept.len(), 1);
+        assert_eq!(archive.before.as_deref(), Some("2026-03-02"));
+        let days = archive
+            .days
+            .iter()
+            .map(|x| (x.day.as_str(), x.totals.attempts))
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![("2026-01-01", 2), ("2026-01-02", 1)]);
+
+        // The schedule comes out the same from what's left
+        let after = schedule(line(), "white", &kept, &archive.seeds, today, &settings);
+        assert_eq!(after[0].due, before[0].due);
+        assert_eq!(after[0].interval, before[0].interval);
+    }
+}
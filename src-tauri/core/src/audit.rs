@@ -0,0 +1,171 @@
+//! Checks the repertoire with the engine for moves that give away more than they should compared
+//! to the engine's choice.
+use crate::cache::EvalCache;
+use crate::config;
+use crate::db::{next_moves, OpeningGraph};
+use crate::engine::{Limits, Score, UciEngine};
+use crate::graph::NodeIndex;
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::san::San;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Color, EnPassantMode, Move, Position};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A move in the repertoire the engine doesn't like
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// Moves from the start up to and including the dubious one
+    pub moves: Vec<String>,
+    /// What the engine would play instead
+    pub best: String,
+    /// Centipawns the repertoire move is worse by
+    pub loss: i32,
+    /// Evals for the player
+    pub played_eval: Score,
+    pub best_eval: Score,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub color: String,
+    /// How long each position was searched
+    pub limits: Limits,
+    pub threshold: i32,
+    /// Positions the engine looked at
+    pub positions: usize,
+    /// Worst first
+    pub findings: Vec<Finding>,
+}
+
+/// A move the player makes in the repertoire
+struct Check {
+    moves: Vec<String>,
+    played: Move,
+    before: Chess,
+    after: Chess,
+}
+
+fn fen(position: &Chess) -> String {
+    Fen::from_position(position.clone(), EnPassantMode::Legal).to_string()
+}
+
+/// Go through every move the player makes in the repertoire comparing it to the engine's best
+/// move. `progress` is told how many positions have been looked at out of how many, an error from
+/// it stops the audit.
+pub fn audit(
+    engine: &UciEngine,
+    cache: &EvalCache,
+    openings: &OpeningGraph,
+    player: Color,
+    settings: &config::Audit,
+    mut progress: impl FnMut(usize, usize) -> anyhow::Result<()>,
+) -> anyhow::Result<AuditReport> {
+    let checks = player_moves(openings, player);
+    let mut positions = checks
+        .iter()
+        .flat_map(|x| [&x.before, &x.after])
+        .map(|x| (fen(x), x))
+        .collect::<Vec<_>>();
+    positions.sort_by(|a, b| a.0.cmp(&b.0));
+    positions.dedup_by(|a, b| a.0 == b.0);
+
+    let limits = settings.limits();
+    let mut evals = HashMap::new();
+    for (i, (fen, position)) in positions.iter().enumerate() {
+        progress(i, positions.len())?;
+        evals.insert(fen.clone(), cache.analyze(engine, position, limits)?);
+    }
+    progress(positions.len(), positions.len())?;
+
+    let mut findings = vec![];
+    for check in &checks {
+        let best = &evals[&fen(&check.before)];
+        // Scored for the opponent as it's their move after
+        let played = &evals[&fen(&check.after)];
+        let played_eval = played.score.flip();
+        let loss = best.score.centipawns() - played_eval.centipawns();
+        if loss <= settings.threshold {
+            continue;
+        }
+        let Some(best_move) = best
+            .best_move()
+            .and_then(|x| Uci::from_str(x).ok())
+            .and_then(|x| x.to_move(&check.before).ok())
+        else {
+            continue;
+        };
+        // Searching the position after goes a move deeper, which can be enough to disagree
+        if best_move == check.played {
+            continue;
+        }
+        findings.push(Finding {
+            moves: check.moves.clone(),
+            best: San::from_move(&check.before, &best_move).to_string(),
+            loss,
+            played_eval,
+            best_eval: best.score,
+        });
+    }
+    findings.sort_by_key(|x| -x.loss);
+    Ok(AuditReport {
+        color: player.to_string(),
+        limits,
+        threshold: settings.threshold,
+        positions: positions.len(),
+        findings,
+    })
+}
+
+/// Every move the player makes in the prep along with the positions either side of it
+fn player_moves(openings: &OpeningGraph, player: Color) -> Vec<Check> {
+    let mut checks = vec![];
+    let mut stack: Vec<(NodeIndex, Chess, Vec<String>)> = next_moves(openings, None)
+        .into_iter()
+        .map(|x| (x, Chess::new(), vec![]))
+        .collect();
+    while let Some((node, position, mut moves)) = stack.pop() {
+        let san = &openings[node];
+        let Ok(mv) = san.san.to_move(&position) else {
+            continue;
+        };
+        let Ok(after) = position.clone().play(&mv) else {
+            continue;
+        };
+        moves.push(san.to_string());
+        if position.turn() == player {
+            checks.push(Check {
+                moves: moves.clone(),
+                played: mv,
+                before: position,
+                after: after.clone(),
+            });
+        }
+        for next in next_moves(openings, Some(node)) {
+            stack.push((next, after.clone(), moves.clone()));
+        }
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn find_player_moves() {
+        let mut db = OpeningDatabase::default();
+        db.add_prep(
+            Color::White,
+            "1. e4 e5 2. Nf3 (2. Bc4 Nf6) Nc6 *".as_bytes(),
+        )
+        .unwrap();
+        let checks = player_moves(db.graph(Color::White), Color::White);
+        let mut moves = checks.iter().map(|x| x.moves.join(" ")).collect::<Vec<_>>();
+        moves.sort();
+        assert_eq!(moves, vec!["e4", "e4 e5 Bc4", "e4 e5 Nf3"]);
+        assert_eq!(checks[0].after.turn(), Color::Black);
+    }
+}
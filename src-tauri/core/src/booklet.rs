@@ -0,0 +1,409 @@
+//! A booklet of the repertoire to print or read offline, as a single HTML page. Each chapter has
+//! its moves with the PGN's comments, a diagram wherever the prep branches and at the end of the
+//! main line, and how drilling it has gone. Printing it from a browser makes the PDF, each chapter
+//! starts on a new page.
+use crate::anki::board_svg;
+use crate::config::Config;
+use crate::db::{chapter_name, read_prep, repertoire_files, replies, OpeningGraph};
+use crate::graph::NodeIndex;
+use crate::schedule::{load_schedule, LineSchedule};
+use crate::stats::{attempts_file, load_attempts, Attempt};
+use chrono::NaiveDate;
+use pgn_reader::{BufferedReader, RawComment, SanPlus, Skip, Visitor};
+use shakmaty::{Chess, Color, Position};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+pub struct BookletChapter {
+    pub name: String,
+    pub openings: OpeningGraph,
+    /// The PGN's comments by the moves leading up to them, the ones before the first move are
+    /// under no moves
+    pub comments: HashMap<Vec<String>, Vec<String>>,
+    pub schedule: Vec<LineSchedule>,
+}
+
+pub struct Booklet {
+    pub player: Color,
+    pub chapters: Vec<BookletChapter>,
+    /// Mistakes made in drills by the moves before them
+    pub mistakes: HashMap<Vec<String>, usize>,
+    pub made: NaiveDate,
+}
+
+/// The repertoire for `player`, or one chapter of it, with what's needed for the booklet
+pub fn load_booklet(
+    config: &Config,
+    player: Color,
+    chapter: Option<&str>,
+    today: NaiveDate,
+) -> anyhow::Result<Booklet> {
+    let mut files = repertoire_files(&config.repertoire(player))?
+        .into_iter()
+        .filter(|x| chapter.is_none_or(|chapter| chapter_name(x) == chapter))
+        .collect::<Vec<_>>();
+    if let (Some(chapter), true) = (chapter, files.is_empty()) {
+        anyhow::bail!("No chapter called {}", chapter);
+    }
+    files.sort_by_key(|x| chapter_name(x));
+    let mut chapters = vec![];
+    for path in files {
+        let pgn = fs::read_to_string(&path)?;
+        let openings = read_prep(pgn.as_bytes(), OpeningGraph::default())?;
+        chapters.push(BookletChapter {
+            name: chapter_name(&path),
+            schedule: load_schedule(config, player, &openings, today)?,
+            comments: read_comments(pgn.as_bytes())?,
+            openings,
+        });
+    }
+    let attempts = load_attempts(&attempts_file(config))?;
+    Ok(Booklet {
+        player,
+        chapters,
+        mistakes: mistake_counts(&attempts, player),
+        made: today,
+    })
+}
+
+struct CommentReader {
+    line: Vec<String>,
+    /// The line each variation started from
+    variations: Vec<Vec<String>>,
+    comments: HashMap<Vec<String>, Vec<String>>,
+}
+
+impl Visitor for CommentReader {
+    type Result = ();
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.line.push(san_plus.to_string());
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let comment = without_commands(&String::from_utf8_lossy(comment.as_bytes()));
+        if !comment.is_empty() {
+            self.comments
+                .entry(self.line.clone())
+                .or_default()
+                .push(comment);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.variations.push(self.line.clone());
+        self.line.pop();
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.line = self.variations.pop().unwrap_or_default();
+    }
+
+    fn end_game(&mut self) {}
+}
+
+/// A comment with the `[%clk ...]` style commands other programs put in them taken out
+fn without_commands(comment: &str) -> String {
+    let mut text = String::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        text += &rest[..start];
+        rest = rest[start..]
+            .find(']')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    text += rest;
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The comments in a prep file's first game, the same one the prep is read from
+pub fn read_comments(pgn: impl io::Read) -> anyhow::Result<HashMap<Vec<String>, Vec<String>>> {
+    let mut reader = CommentReader {
+        line: vec![],
+        variations: vec![],
+        comments: HashMap::new(),
+    };
+    BufferedReader::new(pgn).read_game(&mut reader)?;
+    Ok(reader.comments)
+}
+
+/// How often each of `player`'s moves has been got wrong, by the moves before it
+pub fn mistake_counts(attempts: &[Attempt], player: Color) -> HashMap<Vec<String>, usize> {
+    let mut counts = HashMap::new();
+    let color = player.to_string();
+    for attempt in attempts.iter().filter(|x| x.color == color) {
+        for mistake in &attempt.mistakes {
+            if let Some(moves) = attempt.line.get(..mistake.ply) {
+                *counts.entry(moves.to_vec()).or_default() += 1;
+            }
+        }
+    }
+    counts
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes out a chapter's moves
+struct Writer<'a> {
+    booklet: &'a Booklet,
+    chapter: &'a BookletChapter,
+    html: String,
+    mistakes: usize,
+}
+
+impl Writer<'_> {
+    fn diagram(&mut self, position: &Chess, line: &[String]) {
+        self.html += &format!(
+            "<figure>{}<figcaption>{}</figcaption></figure>",
+            board_svg(position, self.booklet.player),
+            escape(&line_end(line))
+        );
+    }
+
+    /// Write a move and anything after it that's not a move, then where it leaves things.
+    /// Whether black's move after it needs its number again is in the third. Mistakes made in
+    /// the position go with the `main` move.
+    fn write_move(
+        &mut self,
+        node: NodeIndex,
+        position: &Chess,
+        line: &[String],
+        number: bool,
+        main: bool,
+    ) -> Option<(Chess, Vec<String>, bool)> {
+        let openings = &self.chapter.openings;
+        let san = &openings[node];
+        let ply = line.len();
+        let mut text = match (ply.is_multiple_of(2), number) {
+            (true, _) => format!("{}. ", ply / 2 + 1),
+            (false, true) => format!("{}... ", ply / 2 + 1),
+            (false, false) => String::new(),
+        };
+        text += &san.to_string();
+        self.html += &format!("<b>{}</b>", escape(&text));
+        let player_move = Color::from_white(ply.is_multiple_of(2)) == self.booklet.player;
+        if let Some(count) = self
+            .booklet
+            .mistakes
+            .get(line)
+            .filter(|_| player_move && main)
+        {
+            self.mistakes += count;
+            self.html += &format!(
+                "<sup class=\"missed\" title=\"Times got wrong in drills\">✗{}</sup>",
+                count
+            );
+        }
+        self.html.push(' ');
+        let Ok(mv) = san.san.to_move(position) else {
+            self.html += "<span class=\"comment\">can't be played here</span> ";
+            return None;
+        };
+        let mut position = position.clone();
+        position.play_unchecked(&mv);
+        let mut line = line.to_vec();
+        line.push(san.to_string());
+        let mut interrupted = false;
+        if let Some(comments) = self.chapter.comments.get(&line) {
+            for comment in comments {
+                self.html += &format!("<span class=\"comment\">{}</span> ", escape(comment));
+            }
+            interrupted = true;
+        }
+        if replies(openings, Some(node)).len() > 1 {
+            self.diagram(&position, &line);
+            interrupted = true;
+        }
+        Some((position, line, interrupted))
+    }
+
+    /// Like [`crate::db::repertoire_pgn`], the first node is carried on with and the others are
+    /// variations of it
+    fn write_moves(
+        &mut self,
+        nodes: &[NodeIndex],
+        position: &Chess,
+        line: &[String],
+        number: bool,
+        main: bool,
+    ) {
+        let Some((first, others)) = nodes.split_first() else {
+            return;
+        };
+        let after = self.write_move(*first, position, line, number, true);
+        for other in others {
+            self.html += "<span class=\"variation\">(";
+            if let Some((position, line, interrupted)) =
+                self.write_move(*other, position, line, true, false)
+            {
+                let next = replies(&self.chapter.openings, Some(*other));
+                self.write_moves(&next, &position, &line, interrupted, false);
+            }
+            self.html = self.html.trim_end().to_string() + ")</span> ";
+        }
+        let Some((position, line, interrupted)) = after else {
+            return;
+        };
+        let next = replies(&self.chapter.openings, Some(*first));
+        if next.is_empty() && main {
+            self.diagram(&position, &line);
+        }
+        let number = interrupted || !others.is_empty();
+        self.write_moves(&next, &position, &line, number, main);
+    }
+}
+
+/// The last move of a line with its number, for under a diagram
+fn line_end(line: &[String]) -> String {
+    let ply = line.len() - 1;
+    let dots = if ply.is_multiple_of(2) { "." } else { "..." };
+    format!("After {}{} {}", ply / 2 + 1, dots, line[ply])
+}
+
+fn chapter_html(booklet: &Booklet, chapter: &BookletChapter, id: usize) -> String {
+    let mut writer = Writer {
+        booklet,
+        chapter,
+        html: String::new(),
+        mistakes: 0,
+    };
+    for comment in chapter.comments.get(&vec![]).into_iter().flatten() {
+        writer.html += &format!("<p class=\"comment\">{}</p>", escape(comment));
+    }
+    writer.html += "<div class=\"moves\">";
+    // Each first move is a game of its own, see `repertoire_pgn`
+    for root in replies(&chapter.openings, None) {
+        writer.write_moves(&[root], &Chess::new(), &[], false, true);
+        writer.html += "<br>";
+    }
+    writer.html += "</div>";
+
+    let due = chapter
+        .schedule
+        .iter()
+        .filter(|x| x.due <= booklet.made)
+        .count();
+    let reviewed = chapter.schedule.iter().filter(|x| x.reviews > 0).count();
+    let stats = format!(
+        "{} lines, {} drilled and {} due. {} mistakes in drills.",
+        chapter.schedule.len(),
+        reviewed,
+        due,
+        writer.mistakes
+    );
+    format!(
+        "<section id=\"chapter-{}\"><h2>{}</h2><p class=\"stats\">{}</p>{}</section>\n",
+        id,
+        escape(&chapter.name),
+        stats,
+        writer.html
+    )
+}
+
+const STYLE: &str = "body { font-family: Georgia, serif; max-width: 42em; margin: 2em auto; \
+line-height: 1.6; } section { break-before: page; } .moves b { font-weight: normal; } \
+.variation { color: #555; } .comment { font-style: italic; color: #264; } \
+.missed { color: #b00; } .stats { color: #555; } \
+figure { display: block; text-align: center; margin: 1em 0; break-inside: avoid; } \
+figcaption { font-size: 0.9em; color: #555; } \
+@media print { body { margin: 0; max-width: none; } a { color: inherit; } }";
+
+/// The whole booklet as an HTML page
+pub fn booklet_html(booklet: &Booklet) -> String {
+    let title = format!("The {} repertoire", booklet.player);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>Made on {}. The ✗ after a move is \
+         how many times it's been got wrong in drills.</p>\n<ol>\n",
+        title, STYLE, title, booklet.made
+    );
+    for (i, chapter) in booklet.chapters.iter().enumerate() {
+        html += &format!(
+            "<li><a href=\"#chapter-{}\">{}</a></li>\n",
+            i,
+            escape(&chapter.name)
+        );
+    }
+    html += "</ol>\n";
+    for (i, chapter) in booklet.chapters.iter().enumerate() {
+        html += &chapter_html(booklet, chapter, i);
+    }
+    html + "</body>\n</html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::prep_lines;
+    use crate::stats::{DrillResult, Mistake};
+
+    #[test]
+    fn make_booklet() {
+        let pgn = "{ The Ruy Lopez } 1. e4 e5 2. Nf3 Nc6 { [%clk 0:05:00] Main line } \
+                   3. Bb5 (3. Bc4 { Italian }) a6 *";
+        let openings = read_prep(pgn.as_bytes(), OpeningGraph::default()).unwrap();
+        let comments = read_comments(pgn.as_bytes()).unwrap();
+        assert_eq!(comments[&vec![]], vec!["The Ruy Lopez"]);
+        assert_eq!(
+            comments[&["e4", "e5", "Nf3", "Nc6"].map(String::from).to_vec()],
+            vec!["Main line"]
+        );
+        assert_eq!(
+            comments[&["e4", "e5", "Nf3", "Nc6", "Bc4"].map(String::from).to_vec()],
+            vec!["Italian"]
+        );
+
+        let attempt = Attempt {
+            line: ["e4", "e5", "Nf3", "Nc6", "Bc4"].map(String::from).to_vec(),
+            color: "white".to_string(),
+            chapter: None,
+            started: "2026-10-14T09:00:00+00:00".to_string(),
+            result: DrillResult::Failed,
+            seconds: 10.0,
+            moves: 3,
+            hints: 0,
+            mistakes: vec![Mistake {
+                ply: 4,
+                played: "Bc4".to_string(),
+                expected: vec!["Bb5".to_string()],
+            }],
+        };
+        let made = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let schedule = prep_lines(&openings)
+            .into_iter()
+            .map(|line| LineSchedule {
+                line,
+                interval: 0.0,
+                due: made,
+                reviews: 0,
+            })
+            .collect();
+        let booklet = Booklet {
+            player: Color::White,
+            chapters: vec![BookletChapter {
+                name: "Ruy <Lopez>".to_string(),
+                openings,
+                comments,
+                schedule,
+            }],
+            mistakes: mistake_counts(&[attempt], Color::White),
+            made,
+        };
+        let html = booklet_html(&booklet);
+        assert!(html.contains("<h2>Ruy &lt;Lopez&gt;</h2>"));
+        assert!(html.contains("2 lines, 0 drilled and 2 due. 1 mistakes in drills."));
+        assert!(html.contains("<b>Nc6</b> <span class=\"comment\">Main line</span> <figure>"));
+        assert!(html.contains("<b>3. Bb5</b><sup class=\"missed\""));
+        assert!(html.contains("<span class=\"variation\">(<b>3. Bc4</b> <span class=\"comment\">Italian</span>)</span> <b>3... a6</b>"));
+        // Where it branches after 2... Nc6 and the end of the main line
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(html.contains("After 3... a6"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Evals kept between runs so positions the engine has already searched deep enough aren't searched
+//! again. They're appended to a JSON lines file as they come in and read back on startup.
+use crate::engine::{Eval, Limits, UciEngine};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::{Chess, EnPassantMode};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::{error, info};
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    hash: u64,
+    /// Best first
+    lines: Vec<Eval>,
+}
+
+#[derive(Default)]
+pub struct EvalCache {
+    evals: Mutex<HashMap<u64, Vec<Eval>>>,
+    file: Option<Mutex<File>>,
+}
+
+fn hash(position: &Chess) -> u64 {
+    position.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0
+}
+
+/// Searches with only a depth give the same answer every time, others depend on the machine
+fn depth_only(limits: Limits) -> Option<u32> {
+    match limits {
+        Limits {
+            depth: Some(depth),
+            movetime: None,
+            nodes: None,
+        } => Some(depth),
+        _ => None,
+    }
+}
+
+impl EvalCache {
+    /// Load the cache in `path` and add to it from now on, it's only kept in memory if the file
+    /// can't be opened
+    pub fn open(path: &Path) -> Self {
+        let mut evals = HashMap::new();
+        if let Ok(file) = File::open(path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                // A line could be half written if the app was killed
+                if let Ok(entry) = serde_json::from_str::<Entry>(&line) {
+                    evals.insert(entry.hash, entry.lines);
+                }
+            }
+            info!("Loaded {} cached evals", evals.len());
+        }
+        let file = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path));
+        let file = match file {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(e) => {
+                error!("Can't save evals to {}: {}", path.display(), e);
+                None
+            }
+        };
+        Self {
+            evals: Mutex::new(evals),
+            file,
+        }
+    }
+
+    /// At least `lines` of the best lines for the position, if it's been searched `depth` deep
+    pub fn get(&self, position: &Chess, depth: u32, lines: usize) -> Option<Vec<Eval>> {
+        self.evals
+            .lock()
+            .unwrap()
+            .get(&hash(position))
+            .filter(|x| x.len() >= lines && x[0].depth >= depth)
+            .cloned()
+    }
+
+    /// Keep the lines for a position unless the ones there already went deeper
+    pub fn insert(&self, position: &Chess, lines: Vec<Eval>) {
+        let Some(best) = lines.first() else {
+            return;
+        };
+        let hash = hash(position);
+        let mut evals = self.evals.lock().unwrap();
+        if let Some(old) = evals.get(&hash) {
+            if (old[0].depth, old.len()) >= (best.depth, lines.len()) {
+                return;
+            }
+        }
+        if let Some(file) = self.file.as_ref() {
+            let entry = Entry { hash, lines };
+            let res = serde_json::to_string(&entry)
+                .map_err(|e| e.to_string())
+                .and_then(|x| writeln!(file.lock().unwrap(), "{}", x).map_err(|e| e.to_string()));
+            if let Err(e) = res {
+                error!("Couldn't save eval: {}", e);
+            }
+            evals.insert(hash, entry.lines);
+        } else {
+            evals.insert(hash, lines);
+        }
+    }
+
+    /// Search the position with the engine unless it's been searched to the depth asked for
+    pub fn analyze(
+        &self,
+        engine: &UciEngine,
+        position: &Chess,
+        limits: Limits,
+    ) -> anyhow::Result<Eval> {
+        let depth = depth_only(limits);
+        if let Some(mut lines) = depth.and_then(|x| self.get(position, x, 1)) {
+            return Ok(lines.swap_remove(0));
+        }
+        let fen = Fen::from_position(position.clone(), EnPassantMode::Legal).to_string();
+        let eval = engine
+            .analyze(&fen, limits)
+            .recv()
+            .map_err(|_| anyhow!("The engine stopped"))??;
+        if depth.is_some() {
+            self.insert(position, vec![eval.clone()]);
+        }
+        Ok(eval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Score;
+    use shakmaty::Position;
+
+    fn eval(depth: u32, multipv: u32) -> Eval {
+        Eval {
+            score: Score::Cp(30),
+            wdl: None,
+            depth,
+            pv: vec!["e2e4".to_string()],
+            multipv,
+        }
+    }
+
+    #[test]
+    fn keep_the_deepest() {
+        let path = std::env::temp_dir().join(format!("chess-driller-evals-{}", std::process::id()));
+        let start = Chess::new();
+        let cache = EvalCache::open(&path);
+        cache.insert(&start, vec![eval(12, 1)]);
+        cache.insert(&start, vec![eval(10, 1), eval(10, 2)]);
+        assert_eq!(cache.get(&start, 12, 1).map(|x| x.len()), Some(1));
+        assert!(cache.get(&start, 14, 1).is_none());
+        assert!(cache.get(&start, 1, 2).is_none());
+        cache.insert(&start, vec![eval(20, 1), eval(20, 2)]);
+
+        // Move counters don't matter
+        let moved = ["Nf3", "Nf6", "Ng1", "Ng8"]
+            .iter()
+            .fold(start.clone(), |x, san| {
+                let mv = san
+                    .parse::<shakmaty::san::San>()
+                    .unwrap()
+                    .to_move(&x)
+                    .unwrap();
+                x.play(&mv).unwrap()
+            });
+        let reloaded = EvalCache::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reloaded.get(&moved, 18, 2).map(|x| x[0].depth), Some(20));
+    }
+}
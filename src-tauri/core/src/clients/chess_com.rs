@@ -6,6 +6,7 @@
 //! "https://api.chess.com/pub/player/$USER/games/$YEAR/$MONTH/pgn" year and month are numbers
 use crate::config::Config;
 use crate::db::OpeningDatabase;
+use crate::plugins::GameSource;
 use anyhow::Context;
 use chrono::Datelike;
 use reqwest::blocking::*;
@@ -36,51 +37,57 @@ impl ChessComClient {
 
     pub fn download_all_games(&self, config: &Config) -> anyhow::Result<OpeningDatabase> {
         let mut db = OpeningDatabase::default();
-        let chess_com_games = config.data_dir().join("chess.com");
         for user in &config.chess_com {
-            let archives = match self.get_user_archives(user) {
-                Ok(a) => a,
+            self.download_user_games(config, user)?;
+            for path in self.downloaded(config, user) {
+                if let Err(e) = db.add_multigame_pgn(fs::File::open(&path)?, user.to_string()) {
+                    error!("Failed to add {} to opening tree: {}", path.display(), e);
+                }
+            }
+        }
+        Ok(db)
+    }
+
+    /// Download all the games for a single user caching them in the cache directory
+    pub fn download_user_games(&self, config: &Config, user: &str) -> anyhow::Result<()> {
+        let chess_com_games = config.cache_dir().join("chess.com");
+        let user_folder = chess_com_games.join(user);
+        if user_folder.exists() {
+            info!("Skipping download you already have games for {}", user);
+            return Ok(());
+        }
+        let archives = match self.get_user_archives(user) {
+            Ok(a) => a,
+            Err(e) => {
+                error!("Couldn't get player archives for {}: {}", user, e);
+                return Ok(());
+            }
+        };
+        fs::create_dir_all(&user_folder).unwrap();
+        for (i, archive) in archives.iter().enumerate() {
+            let archive = if archive.ends_with("/pgn") {
+                Cow::Borrowed(archive)
+            } else {
+                let mut s = archive.to_string();
+                if !s.ends_with("/") {
+                    s.push('/');
+                }
+                s.push_str("pgn");
+                Cow::Owned(s)
+            };
+            info!("Processing archive: {}", archive);
+            let pgn = match self.download_pgn(archive.as_ref()) {
+                Ok(pgn) => pgn,
                 Err(e) => {
-                    error!("Couldn't get player archives for {}: {}", user, e);
+                    error!("downloading: {}", e);
                     continue;
                 }
             };
-            let user_folder = chess_com_games.join(user);
-            if user_folder.exists() {
-                info!("Skipping download you already have games for {}", user);
-                continue;
-            } else {
-                fs::create_dir_all(&user_folder).unwrap();
-            }
-            for (i, archive) in archives.iter().enumerate() {
-                let archive = if archive.ends_with("/pgn") {
-                    Cow::Borrowed(archive)
-                } else {
-                    let mut s = archive.to_string();
-                    if !s.ends_with("/") {
-                        s.push('/');
-                    }
-                    s.push_str("pgn");
-                    Cow::Owned(s)
-                };
-                info!("Processing archive: {}", archive);
-                let pgn = match self.download_pgn(archive.as_ref()) {
-                    Ok(pgn) => pgn,
-                    Err(e) => {
-                        error!("downloading: {}", e);
-                        continue;
-                    }
-                };
-
-                fs::write(user_folder.join(format!("{}.pgn", i)), pgn.as_bytes())
-                    .context("Failed to cache in config dir")?;
-
-                if let Err(e) = db.add_multigame_pgn(pgn.as_bytes(), user.to_string()) {
-                    error!("Failed to add to opening tree: {}", e);
-                }
-            }
+
+            fs::write(user_folder.join(format!("{}.pgn", i)), pgn.as_bytes())
+                .context("Failed to cache in cache dir")?;
         }
-        Ok(db)
+        Ok(())
     }
 
     pub fn get_user_archives(&self, user: &str) -> anyhow::Result<Vec<String>> {
@@ -131,6 +138,21 @@ impl ChessComClient {
     }
 }
 
+/// The month files are only downloaded the first time, they're read from the cache after that
+impl GameSource for ChessComClient {
+    fn name(&self) -> &str {
+        "chess.com"
+    }
+
+    fn users(&self, config: &Config) -> Vec<String> {
+        config.chess_com.clone()
+    }
+
+    fn download(&self, config: &Config, user: &str) -> anyhow::Result<()> {
+        self.download_user_games(config, user)
+    }
+}
+
 fn archive_url(user: &str, year: u16, month: u8) -> String {
     format!(
         "https://api.chess.com/pub/player/{}/games/{}/{}/pgn",
@@ -0,0 +1,4 @@
+#[cfg(feature = "chess-com")]
+pub mod chess_com;
+#[cfg(feature = "webdav")]
+pub mod webdav;
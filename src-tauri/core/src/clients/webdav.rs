@@ -0,0 +1,59 @@
+//! A copy of the training history on a WebDAV server, see [`crate::sync`]
+use crate::config::Remote;
+use crate::plugins::Storage;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+
+pub struct SyncClient {
+    client: Client,
+    remote: Remote,
+}
+
+impl SyncClient {
+    pub fn new(remote: &Remote) -> Self {
+        Self {
+            client: Client::new(),
+            remote: remote.clone(),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, name: &str) -> RequestBuilder {
+        let url = format!("{}/{}", self.remote.url.trim_end_matches('/'), name);
+        let request = self.client.request(method, url);
+        match self.remote.user.as_ref() {
+            Some(user) => request.basic_auth(user, self.remote.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// The remote copy of a file, empty if there isn't one yet
+    fn get(&self, name: &str) -> anyhow::Result<String> {
+        let response = self.request(reqwest::Method::GET, name).send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(String::new());
+        }
+        Ok(response.error_for_status()?.text()?)
+    }
+
+    fn put(&self, name: &str, body: String) -> anyhow::Result<()> {
+        self.request(reqwest::Method::PUT, name)
+            .body(body)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+impl Storage for SyncClient {
+    fn name(&self) -> String {
+        self.remote.url.clone()
+    }
+
+    fn get(&self, file: &str) -> anyhow::Result<String> {
+        SyncClient::get(self, file)
+    }
+
+    fn put(&self, file: &str, text: String) -> anyhow::Result<()> {
+        SyncClient::put(self, file, text)
+    }
+}
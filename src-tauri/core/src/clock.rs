@@ -0,0 +1,237 @@
+//! `[%clk]` and `[%emt]` in PGN comments, the time left after a move and how long it took. Games
+//! downloaded with them keep them when written back out, and they show where the time goes in
+//! the opening.
+use crate::results::OPENING_PLIES;
+use crate::scan::PlayedGame;
+use serde::Serialize;
+use shakmaty::Color;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The times in a move's comment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clock {
+    /// On the mover's clock after the move, from `[%clk]`
+    pub left: Option<Duration>,
+    /// Spent on the move, from `[%emt]`
+    pub spent: Option<Duration>,
+}
+
+impl Clock {
+    /// The clock tags in a comment, anything else in it is ignored
+    pub fn from_comment(comment: &str) -> Self {
+        let mut clock = Self::default();
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%") {
+            let Some(end) = rest[start..].find(']') else {
+                break;
+            };
+            let tag = &rest[start + 2..start + end];
+            rest = &rest[start + end + 1..];
+            match tag.trim().split_once(char::is_whitespace) {
+                Some(("clk", x)) => clock.left = parse_clock(x.trim()),
+                Some(("emt", x)) => clock.spent = parse_clock(x.trim()),
+                _ => {}
+            }
+        }
+        clock
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_none() && self.spent.is_none()
+    }
+
+    /// The tags to go in a comment, empty if there are no times
+    pub fn to_pgn(&self) -> String {
+        let mut tags = vec![];
+        if let Some(x) = self.left {
+            tags.push(format!("[%clk {}]", format_clock(x)));
+        }
+        if let Some(x) = self.spent {
+            tags.push(format!("[%emt {}]", format_clock(x)));
+        }
+        tags.join(" ")
+    }
+}
+
+/// `h:mm:ss`, chess.com adds tenths of a second like `0:02:59.9`
+pub fn parse_clock(text: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+}
+
+/// The other way round from [`parse_clock`], tenths are only written when there are some
+pub fn format_clock(time: Duration) -> String {
+    let tenths = time.as_millis() / 100;
+    let seconds = tenths / 10;
+    let mut text = format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if !tenths.is_multiple_of(10) {
+        text += &format!(".{}", tenths % 10);
+    }
+    text
+}
+
+/// Starting time and increment from a `TimeControl` header like `180+2`, `None` for daily games
+/// and ones that weren't timed
+fn time_control(header: &str) -> Option<(Duration, Duration)> {
+    let (base, increment) = header.split_once('+').unwrap_or((header, "0"));
+    Some((
+        Duration::from_secs(base.parse().ok()?),
+        Duration::from_secs(increment.parse().ok()?),
+    ))
+}
+
+impl PlayedGame {
+    /// How long each move took, from its `[%emt]` or else how far the mover's clock went down
+    /// since their last move. The first moves need the `TimeControl` header to know what the
+    /// clocks started on.
+    pub fn move_times(&self) -> Vec<Option<Duration>> {
+        let control = self.time_control.as_deref().and_then(time_control);
+        let increment = control.map_or(Duration::ZERO, |x| x.1);
+        let mut before = [control.map(|x| x.0); 2];
+        self.clocks
+            .iter()
+            .enumerate()
+            .map(|(ply, clock)| {
+                let last = std::mem::replace(&mut before[ply % 2], clock.left);
+                clock
+                    .spent
+                    .or_else(|| Some((last? + increment).saturating_sub(clock.left?)))
+            })
+            .collect()
+    }
+}
+
+/// A move the player takes a while over
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SlowMove {
+    /// Counting from 1 for white's first move
+    pub ply: usize,
+    pub san: String,
+    /// On average over the games
+    pub seconds: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OpeningTime {
+    pub color: String,
+    pub eco: Option<String>,
+    pub moves: Vec<String>,
+    /// Games with the player's times in
+    pub games: usize,
+    /// Spent on the player's moves in the opening, on average
+    pub seconds: f64,
+    /// The player's move that took longest on average
+    pub slowest: Option<SlowMove>,
+}
+
+/// The total seconds for each ply of an opening and how many games had a time for it
+type PlyTimes = Vec<(f64, usize)>;
+
+/// Where the player spends their time in each opening, grouped by the first few moves like
+/// [`crate::compare_results`]. Games the player's clock wasn't kept for are left out.
+pub fn opening_times(games: &[(PlayedGame, Color)]) -> Vec<OpeningTime> {
+    let mut openings: HashMap<(Color, Vec<String>), (OpeningTime, PlyTimes)> = HashMap::new();
+    for (game, color) in games {
+        let times = game.move_times();
+        let plies = game.moves.len().min(OPENING_PLIES);
+        let own = (0..plies)
+            .filter(|ply| Color::from_white(ply % 2 == 0) == *color)
+            .filter_map(|ply| Some((ply, times.get(ply).copied().flatten()?)))
+            .collect::<Vec<_>>();
+        if own.is_empty() {
+            continue;
+        }
+        let moves = game.moves[..plies]
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let (opening, totals) = openings.entry((*color, moves.clone())).or_insert_with(|| {
+            let opening = OpeningTime {
+                color: color.to_string(),
+                eco: None,
+                moves,
+                games: 0,
+                seconds: 0.0,
+                slowest: None,
+            };
+            (opening, vec![(0.0, 0); plies])
+        });
+        if opening.eco.is_none() {
+            opening.eco = game.eco.clone();
+        }
+        opening.games += 1;
+        for (ply, time) in own {
+            opening.seconds += time.as_secs_f64();
+            totals[ply].0 += time.as_secs_f64();
+            totals[ply].1 += 1;
+        }
+    }
+
+    let mut openings = openings
+        .into_values()
+        .map(|(mut opening, totals)| {
+            opening.seconds /= opening.games as f64;
+            opening.slowest = totals
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, games))| *games > 0)
+                .map(|(ply, (seconds, games))| SlowMove {
+                    ply: ply + 1,
+                    san: opening.moves[ply].clone(),
+                    seconds: seconds / *games as f64,
+                })
+                .max_by(|a, b| a.seconds.total_cmp(&b.seconds));
+            opening
+        })
+        .collect::<Vec<_>>();
+    openings.sort_by(|a, b| (b.games, &a.color, &a.moves).cmp(&(a.games, &b.color, &b.moves)));
+    openings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::read_games;
+
+    #[test]
+    fn clocks_round_trip() {
+        let pgn = "[White \"me\"]\n[Black \"a\"]\n[Result \"1-0\"]\n[TimeControl \"180+2\"]\n\n\
+            1. e4 { [%clk 0:03:01] } 1... e5 { [%clk 0:02:59.9] } 2. Nf3 { [%clk 0:02:50] } \
+            2... Nc6 { [%emt 0:00:04] } 1-0\n\n\
+            [White \"b\"]\n[Black \"me\"]\n[Result \"0-1\"]\n\n1. e4 e5 0-1\n";
+        let games = read_games(pgn.as_bytes(), "me").unwrap();
+        let (game, _) = &games[0];
+        assert_eq!(game.clocks[1].left, Some(Duration::from_millis(179_900)));
+        assert_eq!(game.clocks[3].spent, Some(Duration::from_secs(4)));
+        let seconds = game
+            .move_times()
+            .iter()
+            .map(|x| x.map(|x| x.as_secs_f64().round()))
+            .collect::<Vec<_>>();
+        assert_eq!(seconds, vec![Some(1.0), Some(2.0), Some(13.0), Some(4.0)]);
+
+        let written = game.to_pgn();
+        assert!(written.contains("1. e4 { [%clk 0:03:01] } 1... e5 { [%clk 0:02:59.9] }"));
+        let again = read_games(written.as_bytes(), "me").unwrap();
+        assert_eq!(again[0].0.clocks, game.clocks);
+        assert_eq!(again[0].0.moves, game.moves);
+
+        let times = opening_times(&games);
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].seconds.round(), 14.0);
+        let slowest = times[0].slowest.as_ref().unwrap();
+        assert_eq!((slowest.ply, slowest.san.as_str()), (3, "Nf3"));
+        assert_eq!(parse_clock("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_clock("soon"), None);
+        assert_eq!(format_clock(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -0,0 +1,160 @@
+//! How drilling's going in every profile side by side, for installs shared by a coach and their
+//! students or a family.
+use crate::archive::{archive_file, load_archive};
+use crate::config::Config;
+use crate::stats::{attempts_file, csv_row, load_attempts, summarize, Streak, Totals};
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+/// Days counted as recent
+const RECENT_DAYS: u64 = 30;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileStats {
+    /// `None` for the default one
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+    /// Just the last 30 days
+    pub recent: Totals,
+    /// Days with any drills
+    pub days: usize,
+    pub streak: Streak,
+    /// YYYY-MM-DD
+    pub last: Option<String>,
+}
+
+impl ProfileStats {
+    fn name(&self) -> &str {
+        self.profile.as_deref().unwrap_or("Default")
+    }
+}
+
+/// Share of the moves that were right, as a percentage
+fn accuracy(totals: &Totals) -> String {
+    if totals.moves == 0 {
+        return "-".to_string();
+    }
+    let right = 1.0 - totals.mistakes as f64 / totals.moves as f64;
+    format!("{:.0}%", 100.0 * right)
+}
+
+/// Stats for the default profile and every other one
+pub fn compare_profiles(today: NaiveDate) -> anyhow::Result<Vec<ProfileStats>> {
+    let mut profiles = vec![];
+    let names = std::iter::once(None).chain(Config::profiles().into_iter().map(Some));
+    for name in names {
+        let config = Config::load_profile(name.as_deref())?;
+        let attempts = load_attempts(&attempts_file(&config))?;
+        let archive = load_archive(&archive_file(&config))?;
+        let summary = summarize(&attempts, &archive.days);
+        let since = (today - Days::new(RECENT_DAYS)).to_string();
+        let mut recent = Totals::default();
+        for day in summary.days.iter().filter(|x| x.day > since) {
+            recent.merge(&day.totals);
+        }
+        profiles.push(ProfileStats {
+            profile: name,
+            totals: summary.totals,
+            recent,
+            days: summary.days.len(),
+            streak: summary.streak,
+            last: summary.days.last().map(|x| x.day.clone()),
+        });
+    }
+    Ok(profiles)
+}
+
+const HEADER: [&str; 8] = [
+    "Profile",
+    "Drills",
+    "Completed",
+    "Accuracy",
+    "Hours",
+    "Drills in the last 30 days",
+    "Accuracy in the last 30 days",
+    "Days drilled",
+];
+
+fn fields(profile: &ProfileStats) -> Vec<String> {
+    vec![
+        profile.name().to_string(),
+        profile.totals.attempts.to_string(),
+        profile.totals.completed.to_string(),
+        accuracy(&profile.totals),
+        format!("{:.1}", profile.totals.seconds / 3600.0),
+        profile.recent.attempts.to_string(),
+        accuracy(&profile.recent),
+        profile.days.to_string(),
+    ]
+}
+
+pub fn profiles_csv(profiles: &[ProfileStats]) -> String {
+    let header = HEADER.map(|x| x.to_lowercase().replace(' ', "_"));
+    let mut csv = csv_row(&header);
+    for profile in profiles {
+        csv += &csv_row(&fields(profile));
+    }
+    csv
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A page with a table of the profiles that opens in any browser
+pub fn profiles_html(profiles: &[ProfileStats], today: NaiveDate) -> String {
+    let row = |cell: &str, fields: &[String]| {
+        let cells = fields
+            .iter()
+            .map(|x| format!("<{0}>{1}</{0}>", cell, escape_html(x)))
+            .collect::<String>();
+        format!("<tr>{}</tr>\n", cells)
+    };
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Profiles on {0}</title>\n\
+         <style>body {{ font-family: sans-serif; }} td, th {{ padding: 0.2em 0.8em; text-align: right; }} \
+         td:first-child, th:first-child {{ text-align: left; }}</style>\n</head>\n<body>\n\
+         <h1>Profiles on {0}</h1>\n<table>\n",
+        today
+    );
+    html += &row("th", &HEADER.map(|x| x.to_string()));
+    for profile in profiles {
+        html += &row("td", &fields(profile));
+    }
+    html + "</table>\n</body>\n</html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_comparison() {
+        let profile = |name: Option<&str>, moves| ProfileStats {
+            profile: name.map(|x| x.to_string()),
+            totals: Totals {
+                attempts: 10,
+                completed: 8,
+                moves,
+                mistakes: 5,
+                seconds: 5400.0,
+            },
+            recent: Totals::default(),
+            days: 3,
+            streak: Streak::default(),
+            last: Some("2026-10-01".to_string()),
+        };
+        let profiles = [profile(None, 50), profile(Some("<Sam>"), 0)];
+        let csv = profiles_csv(&profiles);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "profile,drills,completed,accuracy,hours,drills_in_the_last_30_days,accuracy_in_the_last_30_days,days_drilled");
+        assert_eq!(lines[1], "Default,10,8,90%,1.5,0,-,3");
+        assert_eq!(lines[2], "<Sam>,10,8,-,1.5,0,-,3");
+        let html = profiles_html(&profiles, NaiveDate::from_ymd_opt(2026, 10, 2).unwrap());
+        assert!(html.contains("<tr><td>&lt;Sam&gt;</td><td>10</td>"));
+    }
+}
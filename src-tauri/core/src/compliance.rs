@@ -0,0 +1,192 @@
+//! Checks games against the repertoire to see how far each one followed it and who left it
+//! first, for a coach going through a student's tournament games.
+use crate::db::{replies, OpeningGraph};
+use crate::scan::PlayedGame;
+use crate::stats::csv_row;
+use serde::Serialize;
+use shakmaty::Color;
+
+/// Where a game first left the repertoire
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Deviation {
+    /// Counting from 1 for white's first move
+    pub ply: usize,
+    /// Whether it was the player's move that left it, rather than their opponent's
+    pub by_player: bool,
+    pub played: String,
+    /// The moves the repertoire has there
+    pub expected: Vec<String>,
+    /// For the player's deviations, the game up to there and then the repertoire's main line
+    pub suggested: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GameCompliance {
+    pub white: String,
+    pub black: String,
+    pub date: String,
+    pub result: String,
+    pub link: Option<String>,
+    /// The colour the player had
+    pub color: String,
+    /// Moves played that were in the repertoire
+    pub followed: usize,
+    /// `None` if the game stayed in the repertoire until it or the game ran out
+    pub deviation: Option<Deviation>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ComplianceReport {
+    pub games: Vec<GameCompliance>,
+    /// Games that never left the repertoire
+    pub followed: usize,
+    pub player_deviations: usize,
+    pub opponent_deviations: usize,
+}
+
+/// How far a game followed the repertoire for the player's colour. Check marks are ignored as
+/// games and prep files don't always agree on them.
+pub fn check_game(openings: &OpeningGraph, game: &PlayedGame, player: Color) -> GameCompliance {
+    let mut node = None;
+    let mut followed = 0;
+    let mut deviation = None;
+    for (ply, san) in game.moves.iter().enumerate() {
+        let prep = replies(openings, node);
+        if prep.is_empty() {
+            break;
+        }
+        if let Some(next) = prep.iter().find(|x| openings[**x].san == san.san) {
+            node = Some(*next);
+            followed += 1;
+            continue;
+        }
+        let by_player = Color::from_white(ply.is_multiple_of(2)) == player;
+        let mut suggested = vec![];
+        if by_player {
+            suggested.extend(game.moves[..ply].iter().map(|x| x.to_string()));
+            let mut next = Some(prep[0]);
+            while let Some(x) = next {
+                suggested.push(openings[x].to_string());
+                next = replies(openings, Some(x)).first().copied();
+            }
+        }
+        deviation = Some(Deviation {
+            ply: ply + 1,
+            by_player,
+            played: san.to_string(),
+            expected: prep.iter().map(|x| openings[*x].to_string()).collect(),
+            suggested,
+        });
+        break;
+    }
+    GameCompliance {
+        white: game.white.clone(),
+        black: game.black.clone(),
+        date: game.date.clone(),
+        result: game.result.clone(),
+        link: game.link.clone(),
+        color: player.to_string(),
+        followed,
+        deviation,
+    }
+}
+
+/// Check every game against the repertoire for the colour the player had in it
+pub fn check_games(
+    games: &[(PlayedGame, Color)],
+    white: &OpeningGraph,
+    black: &OpeningGraph,
+) -> ComplianceReport {
+    let mut report = ComplianceReport::default();
+    for (game, color) in games {
+        let openings = color.fold_wb(white, black);
+        let checked = check_game(openings, game, *color);
+        match &checked.deviation {
+            None => report.followed += 1,
+            Some(x) if x.by_player => report.player_deviations += 1,
+            Some(_) => report.opponent_deviations += 1,
+        }
+        report.games.push(checked);
+    }
+    report
+}
+
+/// A ply's move with its number in front, e.g. `3... Nf6`
+pub fn numbered_move(ply: usize, san: &str) -> String {
+    let dots = if ply.is_multiple_of(2) { "..." } else { "." };
+    format!("{}{} {}", ply.div_ceil(2), dots, san)
+}
+
+/// One row per game
+pub fn compliance_csv(report: &ComplianceReport) -> String {
+    let mut csv = String::from(
+        "white,black,date,result,color,followed,deviation_ply,deviated_by,played,expected,suggested,link\n",
+    );
+    for game in &report.games {
+        let deviation = game.deviation.as_ref();
+        csv += &csv_row(&[
+            game.white.clone(),
+            game.black.clone(),
+            game.date.clone(),
+            game.result.clone(),
+            game.color.clone(),
+            game.followed.to_string(),
+            deviation.map(|x| x.ply.to_string()).unwrap_or_default(),
+            deviation
+                .map(|x| match x.by_player {
+                    true => "player",
+                    false => "opponent",
+                })
+                .unwrap_or_default()
+                .to_string(),
+            deviation.map(|x| x.played.clone()).unwrap_or_default(),
+            deviation.map(|x| x.expected.join(" ")).unwrap_or_default(),
+            deviation.map(|x| x.suggested.join(" ")).unwrap_or_default(),
+            game.link.clone().unwrap_or_default(),
+        ]);
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::read_games;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn find_deviations() {
+        let mut db = OpeningDatabase::default();
+        db.add_prep(
+            Color::White,
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 (3. d4) a6 *".as_bytes(),
+        )
+        .unwrap();
+        db.add_prep(Color::Black, "1. e4 c6 2. d4 d5 *".as_bytes())
+            .unwrap();
+        let pgn = "[White \"me\"]\n[Black \"a\"]\n[Result \"1-0\"]\n\n\
+            1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 1-0\n\n\
+            [White \"b\"]\n[Black \"me\"]\n[Result \"0-1\"]\n\n1. e4 c6 2. Nc3 d5 0-1\n\n\
+            [White \"me\"]\n[Black \"c\"]\n[Result \"1/2-1/2\"]\n\n1. e4 e5 2. Nf3 Nc6 3. d4 exd4 1/2-1/2\n";
+        let games = read_games(pgn.as_bytes(), "me").unwrap();
+        let report = check_games(&games, db.graph(Color::White), db.graph(Color::Black));
+        assert_eq!(report.followed, 1);
+        assert_eq!(report.player_deviations, 1);
+        assert_eq!(report.opponent_deviations, 1);
+
+        let deviation = report.games[0].deviation.as_ref().unwrap();
+        assert_eq!(report.games[0].followed, 4);
+        assert_eq!(deviation.ply, 5);
+        assert!(deviation.by_player);
+        assert_eq!(deviation.expected, vec!["Bb5", "d4"]);
+        assert_eq!(deviation.suggested.join(" "), "e4 e5 Nf3 Nc6 Bb5 a6");
+        assert_eq!(numbered_move(deviation.ply, &deviation.played), "3. Bc4");
+
+        let deviation = report.games[1].deviation.as_ref().unwrap();
+        assert!(!deviation.by_player);
+        assert!(deviation.suggested.is_empty());
+        assert_eq!(numbered_move(deviation.ply, &deviation.played), "2. Nc3");
+        assert_eq!(report.games[2].followed, 5);
+        assert_eq!(compliance_csv(&report).lines().count(), 4);
+    }
+}
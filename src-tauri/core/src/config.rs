@@ -0,0 +1,1468 @@
+use crate::db::{default_repertoire, OpeningDatabase};
+use crate::engine::Limits;
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use shakmaty::Color;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::level_filters::LevelFilter;
+
+/// Environment variables starting with this override settings in the config file
+const ENV_PREFIX: &str = "CHESS_DRILLER_";
+
+/// Version of the config file, bump it and add a migration when a setting is renamed or changes
+/// meaning so older files still load
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Each of these takes a config file from the version at its index to the next one
+const MIGRATIONS: [fn(&mut serde_json::Map<String, serde_json::Value>); 1] = [
+    // Files from before there was a version, nothing changed
+    |_| {},
+];
+
+/// Settings written out to move them to another machine
+#[derive(Serialize, Deserialize)]
+struct SettingsBundle {
+    /// Marks the file as one of ours
+    chess_driller: u32,
+    exported: String,
+    settings: Config,
+}
+
+/// Board themes and piece sets the frontend has, these need to match `themes.jsx`
+const BOARD_THEMES: [&str; 4] = ["brown", "green", "blue", "grey"];
+const PIECE_SETS: [&str; 2] = ["default", "unicode"];
+/// Languages the frontend has translations for, these need to match `i18n.js`
+const LOCALES: [&str; 4] = ["en", "de", "es", "ru"];
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Which version of the app the file was written for, see [`CONFIG_VERSION`]
+    #[serde(default)]
+    pub version: Version,
+    /// Chess.com usernames for the user
+    #[serde(default, rename = "chess.com")]
+    pub chess_com: Vec<String>,
+    /// Seconds allowed per move when drilling, no clock is shown if unset
+    #[serde(default)]
+    pub move_time_limit: Option<u64>,
+    /// Language for the app e.g. `es`, `de` or `ru`, the system's if unset
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// How moves are typed in and written
+    #[serde(default)]
+    pub notation: Notation,
+    /// How the board and pieces look
+    #[serde(default)]
+    pub appearance: Appearance,
+    /// Keys used to control the board
+    #[serde(default)]
+    pub keybindings: Keybindings,
+    /// How pieces get moved with the mouse
+    #[serde(default)]
+    pub input: Input,
+    /// Sounds on the board
+    #[serde(default)]
+    pub sound: Sound,
+    /// Reading moves out loud
+    #[serde(default)]
+    pub speech: Speech,
+    /// How drills are run
+    #[serde(default)]
+    pub drill: Drill,
+    /// Programs to run during drills to change how they go
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Programs that add game sources, places to sync to or booklet formats, by name
+    #[serde(default)]
+    pub plugins: BTreeMap<String, Plugin>,
+    /// Desktop notifications from `chess-driller remind`
+    #[serde(default)]
+    pub reminders: Reminders,
+    /// UCI engine to play against once out of the prep
+    #[serde(default)]
+    pub engine: Engine,
+    /// Other engines that can be switched to by name, set up the same way as `engine`
+    #[serde(default)]
+    pub engines: BTreeMap<String, Engine>,
+    /// How the repertoire is checked with the engine
+    #[serde(default)]
+    pub audit: Audit,
+    /// How downloaded games are checked for blunders
+    #[serde(default)]
+    pub scan: Scan,
+    /// What goes in PGNs copied out of the app
+    #[serde(default)]
+    pub export: Export,
+    /// What gets logged and where
+    #[serde(default)]
+    pub logging: Logging,
+    /// Record everything done on the board to this file so the session can be replayed later
+    #[serde(default)]
+    pub record_events: Option<PathBuf>,
+    /// Folder of repertoire PGNs, `prep` in the working directory if unset
+    #[serde(default)]
+    pub prep: Option<PathBuf>,
+    /// PGN files or folders of them for the white repertoire, used instead of the white folder in
+    /// `prep`
+    #[serde(default)]
+    pub white_repertoire: Vec<PathBuf>,
+    /// Same as `white_repertoire` for black
+    #[serde(default)]
+    pub black_repertoire: Vec<PathBuf>,
+    /// Folder of `.fen` files of endgame positions to drill, `endgames` in `prep` if unset
+    #[serde(default)]
+    pub endgames: Option<PathBuf>,
+    /// Master games to compare the repertoire with, a PGN or a folder of them, `reference` in
+    /// `prep` if unset
+    #[serde(default)]
+    pub reference: Option<PathBuf>,
+    /// Where saved games and other things made by the app go, defaults to the platform's data
+    /// directory
+    #[serde(default, rename = "data_dir")]
+    pub data_path: Option<PathBuf>,
+    /// Server to keep training progress in step with on other machines, it isn't synced if unset
+    #[serde(default)]
+    pub sync: Option<Remote>,
+    /// Attempts older than this many months are archived as daily totals, they're all kept if
+    /// unset
+    #[serde(default)]
+    pub keep_months: Option<u32>,
+    /// Keep a log in the data directory of what gets used in the app and when, to look back on
+    /// in the statistics. It never leaves the machine.
+    #[serde(default)]
+    pub usage_log: bool,
+    /// Where downloaded games go, they can be downloaded again so this defaults to the platform's
+    /// cache directory
+    #[serde(default, rename = "cache_dir")]
+    pub cache_path: Option<PathBuf>,
+    /// Profile this config was loaded for, `None` is the top level config
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Version(pub u32);
+
+impl Default for Version {
+    fn default() -> Self {
+        Self(CONFIG_VERSION)
+    }
+}
+
+/// Each action is bound to the value of the browser `KeyboardEvent.key` that triggers it. Keys can
+/// be prefixed with `Ctrl+` and several keys can be given separated by `|`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub flip: String,
+    /// Drill the other colour from the start
+    pub switch_color: String,
+    pub reset: String,
+    pub start: String,
+    pub menu: String,
+    /// Step back through the move history
+    pub back: String,
+    /// Step forward through the move history
+    pub forward: String,
+    /// Switch to the previous move the prep has instead of the one played
+    pub previous_variation: String,
+    /// Switch to the next move the prep has instead of the one played
+    pub next_variation: String,
+    /// Take back the last move
+    pub undo: String,
+    /// Start typing in a move
+    pub enter_move: String,
+    /// Copy the FEN of the position shown to the clipboard
+    pub copy_fen: String,
+    /// Copy the PGN of the game to the clipboard
+    pub copy_pgn: String,
+    /// Set up the position from a FEN on the clipboard
+    pub paste_fen: String,
+    /// Show which pieces the prep wants moved
+    pub hint: String,
+    /// Play the engine from the position shown
+    pub spar: String,
+    /// Turn the engine's eval and best move on and off
+    pub analysis: String,
+    /// Open the folder with the log file in it
+    pub show_log: String,
+    /// Set up a random Chess960 starting position
+    pub chess960: String,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            flip: "f".to_string(),
+            switch_color: "F".to_string(),
+            reset: "r".to_string(),
+            start: "s".to_string(),
+            menu: "m".to_string(),
+            back: "ArrowLeft".to_string(),
+            forward: "ArrowRight".to_string(),
+            previous_variation: "ArrowUp".to_string(),
+            next_variation: "ArrowDown".to_string(),
+            undo: "Ctrl+z|Backspace".to_string(),
+            enter_move: "Enter".to_string(),
+            copy_fen: "Ctrl+c".to_string(),
+            // Shift makes the key upper case
+            copy_pgn: "Ctrl+C".to_string(),
+            paste_fen: "Ctrl+v".to_string(),
+            hint: "h".to_string(),
+            spar: "e".to_string(),
+            analysis: "a".to_string(),
+            show_log: "Ctrl+l".to_string(),
+            chess960: "9".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    /// Name of the board colour theme
+    pub board_theme: String,
+    /// Name of the piece set
+    pub piece_set: String,
+    /// Show the files and ranks along the edge of the board
+    pub show_coordinates: bool,
+    pub highlights: Highlights,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            board_theme: "brown".to_string(),
+            piece_set: "default".to_string(),
+            show_coordinates: true,
+            highlights: Highlights::default(),
+        }
+    }
+}
+
+/// Colours used to pick out squares on the board, these are CSS colours
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Highlights {
+    /// Piece picked up by clicking it or a long press
+    pub selected: String,
+    /// Dots on the squares a selected piece can move to
+    pub legal_move: String,
+    /// Pieces the prep wants moved when a hint is asked for
+    pub hint: String,
+    /// Square the gamepad is pointing at
+    pub cursor: String,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Self {
+            selected: "rgba(255, 255, 0, 0.4)".to_string(),
+            legal_move: "rgba(0, 0, 0, 0.3)".to_string(),
+            hint: "rgba(0, 200, 255, 0.4)".to_string(),
+            cursor: "#6366f1".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Input {
+    /// Pixels a piece has to be dragged before it counts as a drag, anything shorter is a click.
+    /// Trackpads tend to need this higher.
+    pub drag_threshold: f64,
+    /// Set false to only move pieces by clicking them and then where they go
+    pub drag_pieces: bool,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self {
+            drag_threshold: 4.0,
+            drag_pieces: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sound {
+    /// From 0 for silent up to 1
+    pub volume: f64,
+    pub muted: bool,
+}
+
+impl Default for Sound {
+    fn default() -> Self {
+        Self {
+            volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Speech {
+    /// Say the opponent's replies and hints out loud
+    pub enabled: bool,
+    /// How fast to talk, 1 is normal
+    pub rate: f64,
+    /// Name of the system voice to use, the default one is used if unset or it isn't there
+    pub voice: Option<String>,
+}
+
+impl Default for Speech {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate: 1.0,
+            voice: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Drill {
+    /// Stop once the player has made this many moves, otherwise carry on until the prep ends
+    pub max_depth: Option<u32>,
+    /// How the prep's replies are picked
+    pub replies: ReplyPolicy,
+    /// Wrong moves allowed in a position before the drill ends, the ones before that are taken
+    /// back to try again
+    pub attempts: u32,
+    /// Whether hints can be asked for
+    pub hints: bool,
+    /// Carry on playing the engine once out of the prep
+    pub spar: bool,
+    /// Have the engine show why the prep's move is better after a wrong one
+    pub explain: bool,
+    /// Seconds between saving a drill that's going, to carry on with after a crash. 0 turns it
+    /// off.
+    pub autosave: u64,
+    /// Days until a line's due again after its first clean drill, and after a mistake
+    pub first_interval: f64,
+    /// How many times longer the wait gets after each clean drill after that
+    pub interval_growth: f64,
+}
+
+impl Default for Drill {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            replies: ReplyPolicy::Random,
+            attempts: 1,
+            hints: true,
+            spar: true,
+            explain: true,
+            autosave: 5,
+            first_interval: 1.0,
+            interval_growth: 2.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Reminders {
+    /// Say when lines are due for review, once a day
+    pub due: bool,
+    /// Drills to do each day, there's no goal if unset
+    pub daily_goal: Option<u32>,
+    /// Time of day, HH:MM, to say the goal hasn't been met yet if it hasn't
+    pub goal_time: String,
+    /// Minutes between checks
+    pub every: u64,
+}
+
+impl Default for Reminders {
+    fn default() -> Self {
+        Self {
+            due: true,
+            daily_goal: None,
+            goal_time: "19:00".to_string(),
+            every: 30,
+        }
+    }
+}
+
+/// Commands for the hooks, the program followed by its arguments. See [`crate::hooks`] for what
+/// each one is given and can answer with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Picks the line a drill heads for from the schedule
+    pub select_line: Option<Vec<String>>,
+    /// Decides whether each of the player's moves counts as a mistake
+    pub assess_move: Option<Vec<String>>,
+    /// Told about every attempt once it's finished
+    pub session_end: Option<Vec<String>>,
+}
+
+/// A program that's given a JSON request on stdin and answers on stdout, see
+/// [`crate::plugins`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Plugin {
+    /// Downloads the games of `users` like chess.com does
+    Games {
+        command: Vec<String>,
+        #[serde(default)]
+        users: Vec<String>,
+    },
+    /// Keeps a copy of the training history like `sync` does
+    Storage { command: Vec<String> },
+    /// Writes booklets, when one's exported with this plugin's name
+    Booklet {
+        command: Vec<String>,
+        /// Of the file written
+        #[serde(default = "default_extension")]
+        extension: String,
+    },
+}
+
+fn default_extension() -> String {
+    "html".to_string()
+}
+
+impl Plugin {
+    pub fn command(&self) -> &[String] {
+        match self {
+            Self::Games { command, .. }
+            | Self::Storage { command }
+            | Self::Booklet { command, .. } => command,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyPolicy {
+    /// Any of the replies in the prep
+    Random,
+    /// Always the main line, the first move in the PGN
+    Mainline,
+    /// Random but weighted by how many lines follow each reply, so every line comes up as often
+    Weighted,
+    /// Towards the line that's most overdue for review, or due soonest if none are
+    Due,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Engine {
+    /// Engine binary, if unset stockfish is looked for on the `PATH` and in the usual places
+    pub path: Option<PathBuf>,
+    /// Size of the engine's hash table in MB
+    pub hash: u32,
+    pub threads: u32,
+    /// Stockfish's `Skill Level` from 0 to 20, unset plays at full strength
+    pub skill_level: Option<u8>,
+    /// Have the engine play at about this Elo, stockfish goes down to 1320
+    pub elo: Option<u32>,
+    /// Milliseconds the engine gets per move
+    pub movetime: u64,
+    /// How many of the best lines are shown when analysing
+    pub lines: u32,
+    /// When analysis of the position shown stops, with none set it carries on until the position
+    /// changes or analysis is turned off
+    pub analysis: Limits,
+    /// Folder of Syzygy tablebases for the engine, endgame drills are checked against them
+    pub syzygy_path: Option<PathBuf>,
+}
+
+impl Engine {
+    /// Add anything wrong with the engine's settings to `problems`, `name` is where it is in the
+    /// config
+    fn validate(&self, name: &str, problems: &mut Vec<String>) {
+        if let Some(path) = self.path.as_ref().filter(|x| !x.is_file()) {
+            problems.push(format!(
+                "The engine {} doesn't exist, fix `path` in {} or remove it to look for stockfish",
+                path.display(),
+                name
+            ));
+        }
+        if let Some(path) = self.syzygy_path.as_ref().filter(|x| !x.is_dir()) {
+            problems.push(format!(
+                "The tablebase folder {} doesn't exist, fix `syzygy_path` in {} or remove it",
+                path.display(),
+                name
+            ));
+        }
+        if self.threads == 0 || self.hash == 0 || self.movetime == 0 {
+            problems.push(format!(
+                "`threads`, `hash` and `movetime` in {} need to be at least 1",
+                name
+            ));
+        }
+        if self.lines == 0 {
+            problems.push(format!("`lines` in {} needs to be at least 1", name));
+        }
+        if self.skill_level.is_some_and(|x| x > 20) {
+            problems.push(format!("`skill_level` in {} goes from 0 to 20", name));
+        }
+        problems.extend(limits_problem(
+            &format!("`analysis` in {}", name),
+            self.analysis,
+            false,
+        ));
+    }
+}
+
+/// What's wrong with when `name`'s searches stop, batch ones have to stop by themselves
+fn limits_problem(name: &str, limits: Limits, batch: bool) -> Option<String> {
+    let values = [limits.depth.map(u64::from), limits.movetime, limits.nodes];
+    if values.contains(&Some(0)) {
+        Some(format!(
+            "`depth`, `movetime` and `nodes` in {} need to be at least 1",
+            name
+        ))
+    } else if batch && values == [None; 3] {
+        // Otherwise the first search would never finish
+        Some(format!(
+            "{} needs a `depth`, `movetime` or `nodes` to stop at",
+            name
+        ))
+    } else {
+        None
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self {
+            path: None,
+            hash: 16,
+            threads: 1,
+            skill_level: None,
+            elo: None,
+            movetime: 1000,
+            lines: 3,
+            analysis: Limits::default(),
+            syzygy_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Audit {
+    /// Which of `engines` to use, `engine` if unset
+    pub engine: Option<String>,
+    /// How deep the engine searches each position
+    pub depth: Option<u32>,
+    /// Milliseconds the engine gets for each position
+    pub movetime: Option<u64>,
+    /// Nodes the engine gets for each position
+    pub nodes: Option<u64>,
+    /// Centipawns a move can be worse than the engine's best before it's reported
+    pub threshold: i32,
+}
+
+impl Audit {
+    pub fn limits(&self) -> Limits {
+        Limits {
+            depth: self.depth,
+            movetime: self.movetime,
+            nodes: self.nodes,
+        }
+    }
+}
+
+impl Default for Audit {
+    fn default() -> Self {
+        Self {
+            engine: None,
+            depth: Some(18),
+            movetime: None,
+            nodes: None,
+            threshold: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scan {
+    /// Which of `engines` to use, `engine` if unset
+    pub engine: Option<String>,
+    /// How deep the engine searches each position, lower than the audit as there are a lot more
+    pub depth: Option<u32>,
+    /// Milliseconds the engine gets for each position
+    pub movetime: Option<u64>,
+    /// Nodes the engine gets for each position
+    pub nodes: Option<u64>,
+    /// Centipawns a move has to lose to be a blunder
+    pub threshold: i32,
+    /// How many of the player's moves after leaving the repertoire are checked
+    pub after_book: usize,
+    /// Only the most recent games are scanned
+    pub games: usize,
+}
+
+impl Scan {
+    pub fn limits(&self) -> Limits {
+        Limits {
+            depth: self.depth,
+            movetime: self.movetime,
+            nodes: self.nodes,
+        }
+    }
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Self {
+            engine: None,
+            depth: Some(12),
+            movetime: None,
+            nodes: None,
+            threshold: 200,
+            after_book: 3,
+            games: 50,
+        }
+    }
+}
+
+/// A folder on a WebDAV server, or anything else that takes plain GETs and PUTs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Remote {
+    /// The files go in this folder
+    pub url: String,
+    /// For basic auth
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Notation {
+    /// Language for the piece letters in moves, like `de` for `Sf3`, `locale`'s if unset
+    pub pieces: Option<String>,
+    /// Show the pieces in the move list as figurines like `♘f3`
+    pub figurine: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Export {
+    /// Have the engine go through the game and add `[%eval]` comments and NAGs for bad moves
+    pub evals: bool,
+    /// Which of `engines` to use, `engine` if unset
+    pub engine: Option<String>,
+    /// How deep the engine searches each position
+    pub depth: Option<u32>,
+    /// Milliseconds the engine gets for each position
+    pub movetime: Option<u64>,
+    /// Nodes the engine gets for each position
+    pub nodes: Option<u64>,
+}
+
+impl Export {
+    pub fn limits(&self) -> Limits {
+        Limits {
+            depth: self.depth,
+            movetime: self.movetime,
+            nodes: self.nodes,
+        }
+    }
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            evals: false,
+            engine: None,
+            depth: Some(14),
+            movetime: None,
+            nodes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    /// Level for the app's own logs, one of `off`, `error`, `warn`, `info`, `debug` or `trace`
+    pub level: String,
+    /// File to log to as well as the console, `chess-driller.log` in the data directory if unset
+    pub file: Option<PathBuf>,
+    /// Levels for particular modules, e.g. `"chess_driller_core::clients": "debug"` or
+    /// `"reqwest": "warn"`
+    pub filters: BTreeMap<String, String>,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            file: None,
+            filters: BTreeMap::new(),
+        }
+    }
+}
+
+impl Logging {
+    /// The levels in the form `RUST_LOG` takes them
+    pub fn directives(&self) -> String {
+        let mut directives = vec![
+            format!("chess_driller={}", self.level),
+            format!("chess_driller_core={}", self.level),
+        ];
+        directives.extend(
+            self.filters
+                .iter()
+                .map(|(x, level)| format!("{}={}", x, level)),
+        );
+        directives.join(",")
+    }
+}
+
+impl Engine {
+    /// The engine to run, `None` if there isn't one set and stockfish can't be found
+    pub fn binary(&self) -> Option<PathBuf> {
+        if let Some(path) = self.path.as_ref() {
+            return Some(path.clone());
+        }
+        let name = if cfg!(windows) {
+            "stockfish.exe"
+        } else {
+            "stockfish"
+        };
+        let on_path = env::var_os("PATH")
+            .map(|x| env::split_paths(&x).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let usual = ["/usr/games", "/usr/local/bin", "/opt/homebrew/bin"]
+            .iter()
+            .map(PathBuf::from);
+        on_path
+            .into_iter()
+            .chain(usual)
+            .map(|x| x.join(name))
+            .find(|x| x.is_file())
+    }
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Self> {
+        Self::load_profile(None)
+    }
+
+    /// Load the config for a profile. Each profile has its own folder with a config file and data
+    /// in it, which is created the first time the profile is used.
+    pub fn load_profile(profile: Option<&str>) -> anyhow::Result<Self> {
+        if let Some(name) = profile {
+            anyhow::ensure!(
+                !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\']),
+                "Invalid profile name: {}",
+                name
+            );
+        }
+        let config_dir = Self::profile_dir(profile);
+
+        let mut config = if !config_dir.exists() {
+            println!(
+                "Config file doesn't exist creating one in {}",
+                config_dir.display()
+            );
+            if let Err(e) = fs::create_dir_all(&config_dir) {
+                eprintln!("Couldn't create config dir: {}", e);
+                Self::default()
+            } else {
+                Self::create_default(&config_dir.join("config.json"))
+            }
+        } else {
+            let config_file = config_dir.join("config.json");
+            if let Ok(data) = fs::read(&config_file) {
+                Self::parse(&data)?
+            } else {
+                Self::create_default(&config_file)
+            }
+        };
+        config.profile = profile.map(|x| x.to_string());
+        Ok(config)
+    }
+
+    /// Load a profile's config, unlike `load_profile` mistakes in the file don't stop it loading.
+    /// Anything that can't be read uses the default, and everything wrong with the config is
+    /// returned along with how to fix it.
+    ///
+    /// `CHESS_DRILLER_*` environment variables override the file, which in turn overrides the
+    /// defaults. Command line flags go on top of all that with `Cli::apply`.
+    pub fn load_checked(profile: Option<&str>) -> anyhow::Result<(Self, Vec<String>)> {
+        let mut problems = vec![];
+        let mut config = match Self::load_profile(profile) {
+            Ok(config) => config,
+            Err(e) if e.is::<serde_json::Error>() => {
+                let path = Self::profile_dir(profile).join("config.json");
+                Self::read_lenient(&fs::read(&path)?, &path, &mut problems)
+            }
+            Err(e) => return Err(e),
+        };
+        config.profile = profile.map(|x| x.to_string());
+        problems.extend(config.apply_env(env::vars()));
+        problems.extend(config.validate());
+        Ok((config, problems))
+    }
+
+    /// Override settings with `CHESS_DRILLER_*` variables. The rest of the name is the setting,
+    /// with `__` going into a section e.g. `CHESS_DRILLER_MOVE_TIME_LIMIT=10` or
+    /// `CHESS_DRILLER_APPEARANCE__BOARD_THEME=green`. Lists are separated by commas.
+    pub fn apply_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Vec<String> {
+        use serde_json::Value;
+
+        let mut problems = vec![];
+        let mut value = serde_json::to_value(&*self).unwrap();
+        for (key, raw) in vars {
+            let Some(name) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            // Picked before the config is loaded, and the version comes from the file
+            if name == "PROFILE" || name == "VERSION" {
+                continue;
+            }
+            let before = value.clone();
+            let target = name
+                .split("__")
+                .map(|x| match x.to_lowercase().as_str() {
+                    "chess_com" => "chess.com".to_string(),
+                    x => x.to_string(),
+                })
+                .try_fold(&mut value, |value, key| value.get_mut(&key));
+            let Some(target) = target else {
+                problems.push(format!("{} isn't the name of a setting", key));
+                continue;
+            };
+            *target = match target {
+                Value::Array(_) => Value::Array(
+                    raw.split(',')
+                        .map(|x| x.trim())
+                        .filter(|x| !x.is_empty())
+                        .map(|x| Value::String(x.to_string()))
+                        .collect(),
+                ),
+                Value::String(_) => Value::String(raw.clone()),
+                _ => serde_json::from_str(&raw).unwrap_or(Value::String(raw.clone())),
+            };
+            if let Err(e) = serde_json::from_value::<Config>(value.clone()) {
+                problems.push(format!("{}={} is ignored ({})", key, raw, e));
+                value = before;
+            }
+        }
+        let profile = self.profile.take();
+        *self = serde_json::from_value(value).unwrap();
+        self.profile = profile;
+        problems
+    }
+
+    /// Read a config file, upgrading it if it's from an older version
+    fn parse(data: &[u8]) -> serde_json::Result<Self> {
+        let mut value = serde_json::from_slice(data)?;
+        migrate(&mut value);
+        serde_json::from_value(value)
+    }
+
+    /// Read each setting on its own so one mistake doesn't lose the rest
+    fn read_lenient(data: &[u8], path: &Path, problems: &mut Vec<String>) -> Self {
+        let value = serde_json::from_slice::<serde_json::Value>(data).map(|mut x| {
+            migrate(&mut x);
+            x
+        });
+        let fields = match value {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            Ok(_) => {
+                problems.push(format!(
+                    "{} should be a JSON object, fix it or delete it to start again from the defaults",
+                    path.display()
+                ));
+                return Self::default();
+            }
+            Err(e) => {
+                problems.push(format!(
+                    "{} isn't valid JSON ({}), fix it or delete it to start again from the defaults",
+                    path.display(),
+                    e
+                ));
+                return Self::default();
+            }
+        };
+        let mut good = serde_json::Map::new();
+        for (key, value) in fields {
+            let single =
+                serde_json::Value::Object([(key.clone(), value.clone())].into_iter().collect());
+            match serde_json::from_value::<Config>(single) {
+                Ok(_) => {
+                    good.insert(key, value);
+                }
+                Err(e) => problems.push(format!(
+                    "`{}` in {} is wrong ({}), the default is used until it's fixed",
+                    key,
+                    path.display(),
+                    e
+                )),
+            }
+        }
+        serde_json::from_value(serde_json::Value::Object(good)).unwrap_or_default()
+    }
+
+    /// Check the settings make sense, returning what's wrong with them and how to fix it
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.version.0 > CONFIG_VERSION {
+            problems.push(format!(
+                "The config file is from a newer version of chess-driller (version {}), settings this one doesn't know are ignored and changes won't be saved",
+                self.version.0
+            ));
+        }
+        for user in &self.chess_com {
+            let valid = (3..=25).contains(&user.len())
+                && user
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-');
+            if !valid {
+                problems.push(format!(
+                    "\"{}\" in `chess.com` isn't a chess.com username, they're 3 to 25 letters, numbers, _ or -",
+                    user
+                ));
+            }
+        }
+        if NaiveTime::parse_from_str(&self.reminders.goal_time, "%H:%M").is_err() {
+            problems.push(format!(
+                "\"{}\" in `reminders.goal_time` isn't a time of day, give it as HH:MM like 19:00",
+                self.reminders.goal_time
+            ));
+        }
+        if self.reminders.every == 0 {
+            problems.push("`reminders.every` has to be at least a minute".to_string());
+        }
+        let hooks = [
+            ("select_line", &self.hooks.select_line),
+            ("assess_move", &self.hooks.assess_move),
+            ("session_end", &self.hooks.session_end),
+        ];
+        for (name, _) in hooks
+            .iter()
+            .filter(|(_, x)| x.as_ref().is_some_and(|x| x.is_empty()))
+        {
+            problems.push(format!(
+                "`hooks.{}` is empty, give the program to run and its arguments or remove it",
+                name
+            ));
+        }
+        if let Some(prep) = self.prep.as_ref() {
+            if !prep.is_dir() {
+                problems.push(format!(
+                    "The `prep` folder {} doesn't exist, create it or point `prep` at your PGNs",
+                    prep.display()
+                ));
+            }
+        }
+        for (name, repertoire) in [
+            ("white_repertoire", &self.white_repertoire),
+            ("black_repertoire", &self.black_repertoire),
+        ] {
+            for path in repertoire.iter().filter(|x| !x.exists()) {
+                problems.push(format!(
+                    "{} in `{}` doesn't exist, check the path or remove it",
+                    path.display(),
+                    name
+                ));
+            }
+        }
+        if self.move_time_limit == Some(0) {
+            problems.push(
+                "`move_time_limit` is 0, give it a number of seconds or remove it to hide the clock"
+                    .to_string(),
+            );
+        }
+        if !BOARD_THEMES.contains(&self.appearance.board_theme.as_str()) {
+            problems.push(format!(
+                "There's no board theme called \"{}\", use one of {}",
+                self.appearance.board_theme,
+                BOARD_THEMES.join(", ")
+            ));
+        }
+        if !PIECE_SETS.contains(&self.appearance.piece_set.as_str()) {
+            problems.push(format!(
+                "There's no piece set called \"{}\", use one of {}",
+                self.appearance.piece_set,
+                PIECE_SETS.join(", ")
+            ));
+        }
+        if let Some(locale) = self.locale.as_deref().filter(|x| !LOCALES.contains(x)) {
+            problems.push(format!(
+                "There's no translation for \"{}\", use one of {}",
+                locale,
+                LOCALES.join(", ")
+            ));
+        }
+        if let Some(pieces) = self
+            .notation
+            .pieces
+            .as_deref()
+            .filter(|x| !LOCALES.contains(x))
+        {
+            problems.push(format!(
+                "There are no piece letters for \"{}\", use one of {}",
+                pieces,
+                LOCALES.join(", ")
+            ));
+        }
+        for (name, plugin) in &self.plugins {
+            if plugin.command().is_empty() {
+                problems.push(format!("The {} plugin needs a `command` to run", name));
+            }
+        }
+        if !(self.input.drag_threshold >= 0.0 && self.input.drag_threshold.is_finite()) {
+            problems.push("`drag_threshold` needs to be 0 pixels or more".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.sound.volume) {
+            problems.push("`volume` in `sound` needs to be from 0 to 1".to_string());
+        }
+        if !(0.1..=10.0).contains(&self.speech.rate) {
+            problems.push("`rate` in `speech` needs to be from 0.1 to 10".to_string());
+        }
+        if self.drill.attempts == 0 {
+            problems.push("`attempts` in `drill` needs to be at least 1".to_string());
+        }
+        if !(self.drill.first_interval > 0.0 && self.drill.first_interval.is_finite()) {
+            problems.push("`first_interval` in `drill` needs to be more than 0 days".to_string());
+        }
+        if !(self.drill.interval_growth >= 1.0 && self.drill.interval_growth.is_finite()) {
+            problems.push("`interval_growth` in `drill` needs to be at least 1".to_string());
+        }
+        if self.drill.max_depth == Some(0) {
+            problems.push(
+                "`max_depth` in `drill` needs to be at least 1, or removed to drill the whole prep"
+                    .to_string(),
+            );
+        }
+        let engines = std::iter::once(("`engine`".to_string(), &self.engine)).chain(
+            self.engines
+                .iter()
+                .map(|(name, x)| (format!("\"{}\" in `engines`", name), x)),
+        );
+        for (name, engine) in engines {
+            engine.validate(&name, &mut problems);
+        }
+        let picked = [
+            ("audit", &self.audit.engine),
+            ("scan", &self.scan.engine),
+            ("export", &self.export.engine),
+        ];
+        for (section, name) in picked {
+            if let Some(name) = name.as_ref().filter(|x| !self.engines.contains_key(*x)) {
+                problems.push(format!(
+                    "There's no engine called \"{}\" in `engines` for `{}`",
+                    name, section
+                ));
+            }
+        }
+        if self.audit.threshold < 0 || self.scan.threshold < 0 {
+            problems.push("`threshold` in `audit` and `scan` can't be negative".to_string());
+        }
+        let limits = [
+            ("`audit`", self.audit.limits()),
+            ("`scan`", self.scan.limits()),
+            ("`export`", self.export.limits()),
+        ];
+        for (name, limits) in limits {
+            problems.extend(limits_problem(name, limits, true));
+        }
+        for (name, level) in std::iter::once(("the app", &self.logging.level))
+            .chain(self.logging.filters.iter().map(|(x, y)| (x.as_str(), y)))
+        {
+            if LevelFilter::from_str(level).is_err() {
+                problems.push(format!(
+                    "\"{}\" in `logging` for {} isn't a log level, use one of off, error, warn, info, debug or trace",
+                    level, name
+                ));
+            }
+        }
+        if let Some(folder) = self.record_events.as_ref().and_then(|x| x.parent()) {
+            if !folder.as_os_str().is_empty() && !folder.is_dir() {
+                problems.push(format!(
+                    "Can't record events as {} doesn't exist, create it or change `record_events`",
+                    folder.display()
+                ));
+            }
+        }
+        problems
+    }
+
+    /// Everything but the settings that only make sense on this machine as one JSON file
+    pub fn export_bundle(&self) -> anyhow::Result<String> {
+        let mut settings = self.clone();
+        settings.clear_local();
+        let bundle = SettingsBundle {
+            chess_driller: 1,
+            exported: chrono::Local::now().to_rfc3339(),
+            settings,
+        };
+        Ok(serde_json::to_string_pretty(&bundle)?)
+    }
+
+    /// Take the settings from an exported bundle, keeping this machine's paths
+    pub fn import_bundle(&mut self, bundle: &str) -> anyhow::Result<()> {
+        let mut bundle: serde_json::Value = serde_json::from_str(bundle)
+            .map_err(|e| anyhow::anyhow!("Not an exported settings file: {}", e))?;
+        if let Some(settings) = bundle.get_mut("settings") {
+            migrate(settings);
+        }
+        let bundle: SettingsBundle = serde_json::from_value(bundle)
+            .map_err(|e| anyhow::anyhow!("Not an exported settings file: {}", e))?;
+        let mut settings = bundle.settings;
+        // Anything from a newer version that this one doesn't know is gone by now
+        settings.version = self.version;
+        settings.prep = self.prep.take();
+        settings.white_repertoire = std::mem::take(&mut self.white_repertoire);
+        settings.black_repertoire = std::mem::take(&mut self.black_repertoire);
+        settings.data_path = self.data_path.take();
+        settings.cache_path = self.cache_path.take();
+        settings.record_events = self.record_events.take();
+        settings.engine.path = self.engine.path.take();
+        settings.engine.syzygy_path = self.engine.syzygy_path.take();
+        if let Some(sync) = settings.sync.as_mut() {
+            sync.password = self.sync.as_mut().and_then(|x| x.password.take());
+        }
+        for (name, engine) in settings.engines.iter_mut() {
+            if let Some(local) = self.engines.get_mut(name) {
+                engine.path = local.path.take();
+                engine.syzygy_path = local.syzygy_path.take();
+            }
+        }
+        settings.endgames = self.endgames.take();
+        settings.reference = self.reference.take();
+        settings.profile = self.profile.take();
+        // Hooks and plugins run programs, so they're never taken from a file from elsewhere
+        settings.hooks = std::mem::take(&mut self.hooks);
+        settings.plugins = std::mem::take(&mut self.plugins);
+        *self = settings;
+        Ok(())
+    }
+
+    /// Forget paths, they won't be the same on another machine
+    fn clear_local(&mut self) {
+        self.prep = None;
+        self.white_repertoire.clear();
+        self.black_repertoire.clear();
+        self.data_path = None;
+        self.cache_path = None;
+        self.record_events = None;
+        for engine in std::iter::once(&mut self.engine).chain(self.engines.values_mut()) {
+            engine.path = None;
+            engine.syzygy_path = None;
+        }
+        self.endgames = None;
+        self.reference = None;
+        self.profile = None;
+        self.hooks = Hooks::default();
+        self.plugins.clear();
+        if let Some(sync) = self.sync.as_mut() {
+            sync.password = None;
+        }
+    }
+
+    /// Names of the profiles that have been made
+    pub fn profiles() -> Vec<String> {
+        let mut profiles = fs::read_dir(Self::config_dir().join("profiles"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|x| x.path().is_dir())
+            .filter_map(|x| x.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        profiles.sort();
+        profiles
+    }
+
+    fn create_default(save_dir: &Path) -> Self {
+        let res = Self::default();
+        let save = serde_json::to_vec_pretty(&res).unwrap();
+        if let Err(e) = fs::write(save_dir, &save) {
+            eprintln!("Failed to write out config file: {}", e);
+        }
+        res
+    }
+
+    /// Write the config back out so changes made in the app persist
+    pub fn save(&self) -> anyhow::Result<()> {
+        // Saving would lose the settings only the newer version knows about
+        anyhow::ensure!(
+            self.version.0 <= CONFIG_VERSION,
+            "{} is from a newer version of chess-driller, not saving over it",
+            self.path().display()
+        );
+        let save = serde_json::to_vec_pretty(self)?;
+        fs::write(self.path(), save)?;
+        Ok(())
+    }
+
+    /// Take the settings that can change while running from a freshly loaded config, the rest
+    /// only change on a restart
+    pub fn reload(&mut self, config: Config) {
+        self.move_time_limit = config.move_time_limit;
+        self.locale = config.locale;
+        self.notation = config.notation;
+        self.appearance = config.appearance;
+        self.keybindings = config.keybindings;
+        self.input = config.input;
+        self.sound = config.sound;
+        self.speech = config.speech;
+        self.drill = config.drill;
+        self.hooks = config.hooks;
+        self.reminders = config.reminders;
+        self.keep_months = config.keep_months;
+        self.usage_log = config.usage_log;
+    }
+
+    /// The language moves are typed in and shown with, English if there's none set
+    pub fn piece_locale(&self) -> &str {
+        self.notation
+            .pieces
+            .as_deref()
+            .or(self.locale.as_deref())
+            .unwrap_or("en")
+    }
+
+    pub fn path(&self) -> PathBuf {
+        Self::profile_dir(self.profile.as_deref()).join("config.json")
+    }
+
+    /// Where a player's prep is loaded from
+    pub fn repertoire(&self, player: Color) -> Vec<PathBuf> {
+        let repertoire = match player {
+            Color::White => &self.white_repertoire,
+            Color::Black => &self.black_repertoire,
+        };
+        if repertoire.is_empty() {
+            default_repertoire(self.prep_root(), player)
+        } else {
+            repertoire.clone()
+        }
+    }
+
+    /// Settings for the engine in `engines` called `name`, or `engine` for none
+    pub fn engine_named(&self, name: Option<&str>) -> anyhow::Result<&Engine> {
+        match name {
+            Some(name) => self
+                .engines
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("There's no engine called \"{}\"", name)),
+            None => Ok(&self.engine),
+        }
+    }
+
+    /// Where the endgame positions are loaded from
+    pub fn endgames(&self) -> PathBuf {
+        self.endgames
+            .clone()
+            .unwrap_or_else(|| self.prep_root().join("endgames"))
+    }
+
+    /// Where the reference games are loaded from
+    pub fn reference(&self) -> PathBuf {
+        self.reference
+            .clone()
+            .unwrap_or_else(|| self.prep_root().join("reference"))
+    }
+
+    pub fn prep_root(&self) -> &Path {
+        self.prep
+            .as_deref()
+            .unwrap_or(OpeningDatabase::default_root())
+    }
+
+    fn config_dir() -> PathBuf {
+        dirs::config_dir().unwrap_or_default().join("chess-driller")
+    }
+
+    fn profile_dir(profile: Option<&str>) -> PathBuf {
+        Self::in_profile(Self::config_dir(), profile)
+    }
+
+    /// Each profile has its own folder inside the app's one
+    fn in_profile(dir: PathBuf, profile: Option<&str>) -> PathBuf {
+        match profile {
+            Some(name) => dir.join("profiles").join(name),
+            None => dir,
+        }
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        match self.data_path.as_ref() {
+            Some(path) => path.clone(),
+            None => Self::in_profile(
+                dirs::data_dir().unwrap_or_default().join("chess-driller"),
+                self.profile.as_deref(),
+            ),
+        }
+    }
+
+    pub fn log_file(&self) -> PathBuf {
+        match self.logging.file.as_ref() {
+            Some(path) => path.clone(),
+            None => self.data_dir().join("chess-driller.log"),
+        }
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        match self.cache_path.as_ref() {
+            Some(path) => path.clone(),
+            None => Self::in_profile(
+                dirs::cache_dir().unwrap_or_default().join("chess-driller"),
+                self.profile.as_deref(),
+            ),
+        }
+    }
+}
+
+/// Bring an older config file up to date, ones from newer versions are left as they are
+fn migrate(value: &mut serde_json::Value) {
+    let Some(fields) = value.as_object_mut() else {
+        return;
+    };
+    let version = fields.get("version").and_then(|x| x.as_u64()).unwrap_or(0);
+    if version >= CONFIG_VERSION as u64 {
+        return;
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(fields);
+    }
+    fields.insert("version".to_string(), CONFIG_VERSION.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_directories() {
+        let mut config = Config {
+            profile: Some("alice".to_string()),
+            ..Default::default()
+        };
+        assert!(config.data_dir().ends_with("chess-driller/profiles/alice"));
+        assert!(config.cache_dir().ends_with("chess-driller/profiles/alice"));
+        assert!(config.path().ends_with("profiles/alice/config.json"));
+
+        config.cache_path = Some(PathBuf::from("/tmp/games"));
+        assert_eq!(config.cache_dir(), Path::new("/tmp/games"));
+    }
+
+    #[test]
+    fn report_all_problems() {
+        let data = br#"{
+            "chess.com": ["ok_user", "not a user"],
+            "move_time_limit": "ten",
+            "appearance": { "board_theme": "purple" },
+            "drill": { "attempts": 0 },
+            "engine": { "analysis": { "movetime": 500 } },
+            "audit": { "depth": null },
+            "scan": { "threshold": 100 }
+        }"#;
+        let mut problems = vec![];
+        let config = Config::read_lenient(data, Path::new("config.json"), &mut problems);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("`move_time_limit`"));
+        assert_eq!(config.chess_com.len(), 2);
+        assert_eq!(config.engine.analysis.movetime, Some(500));
+        assert_eq!(config.scan.depth, Some(12));
+
+        let problems = config.validate();
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems
+            .contains(&"`audit` needs a `depth`, `movetime` or `nodes` to stop at".to_string()));
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn export_and_import() {
+        let mut config = Config {
+            prep: Some(PathBuf::from("/home/me/prep")),
+            ..Default::default()
+        };
+        config.appearance.board_theme = "blue".to_string();
+        config.keybindings.flip = "x".to_string();
+        let bundle = config.export_bundle().unwrap();
+        assert!(!bundle.contains("/home/me/prep"));
+
+        let mut other = Config {
+            prep: Some(PathBuf::from("/elsewhere")),
+            ..Default::default()
+        };
+        other.import_bundle(&bundle).unwrap();
+        assert_eq!(other.appearance.board_theme, "blue");
+        assert_eq!(other.keybindings.flip, "x");
+        assert_eq!(other.prep, Some(PathBuf::from("/elsewhere")));
+        assert!(other.import_bundle("{}").is_err());
+    }
+
+    #[test]
+    fn migrate_old_versions() {
+        let config = Config::parse(br#"{ "chess.com": ["someone"] }"#).unwrap();
+        assert_eq!(config.version, Version(CONFIG_VERSION));
+        assert_eq!(config.chess_com, vec!["someone"]);
+        assert!(serde_json::to_string(&Config::default())
+            .unwrap()
+            .contains(&format!("\"version\":{}", CONFIG_VERSION)));
+
+        let newer = format!(
+            r#"{{ "version": {}, "new_setting": 1 }}"#,
+            CONFIG_VERSION + 1
+        );
+        let config = Config::parse(newer.as_bytes()).unwrap();
+        assert_eq!(config.version, Version(CONFIG_VERSION + 1));
+        assert_eq!(config.validate().len(), 1);
+        assert!(config.save().is_err());
+    }
+
+    #[test]
+    fn log_levels() {
+        let mut config = Config::default();
+        config
+            .logging
+            .filters
+            .insert("reqwest".to_string(), "warn".to_string());
+        assert_eq!(
+            config.logging.directives(),
+            "chess_driller=info,chess_driller_core=info,reqwest=warn"
+        );
+        assert!(config.validate().is_empty());
+
+        config.logging.level = "loud".to_string();
+        assert_eq!(config.validate().len(), 1);
+    }
+
+    #[test]
+    fn environment_overrides() {
+        let vars = [
+            ("CHESS_DRILLER_MOVE_TIME_LIMIT", "10"),
+            ("CHESS_DRILLER_CHESS_COM", "one, two"),
+            ("CHESS_DRILLER_APPEARANCE__BOARD_THEME", "green"),
+            ("CHESS_DRILLER_ENGINE__PATH", "/usr/bin/stockfish"),
+            ("CHESS_DRILLER_DRILL__ATTEMPTS", "lots"),
+            ("CHESS_DRILLER_NOT_A_SETTING", "1"),
+            ("HOME", "/root"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let mut config = Config::default();
+        let problems = config.apply_env(vars);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert_eq!(config.move_time_limit, Some(10));
+        assert_eq!(config.chess_com, vec!["one", "two"]);
+        assert_eq!(config.appearance.board_theme, "green");
+        assert_eq!(
+            config.engine.path,
+            Some(PathBuf::from("/usr/bin/stockfish"))
+        );
+        assert_eq!(config.drill.attempts, 1);
+    }
+}
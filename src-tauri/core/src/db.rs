@@ -0,0 +1,1234 @@
+//! Store the opening preparation we want to work over - might rename it in future but it is kind
+//! of a mini stripped-down move database.
+use crate::config::{Drill, ReplyPolicy};
+use crate::game::parse_position;
+pub use crate::graph::{NodeIndex, OpeningGraph};
+use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use shakmaty::fen::Epd;
+use shakmaty::{ByColor, Chess, Color, EnPassantMode, Position};
+use std::path::{Path, PathBuf};
+use std::{fs, io, thread};
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MoveAssessment {
+    /// You're still in prep
+    InPrep,
+    /// You've gone wrong somewhere
+    OutOfPrep,
+    /// Wrong move but there are attempts left, it should be taken back and tried again
+    TryAgain,
+    /// Mission completed!
+    PrepEnded,
+}
+
+#[derive(Default, Clone)]
+pub struct OpeningDatabase {
+    white_openings: OpeningGraph,
+    black_openings: OpeningGraph,
+    /// Where each repertoire's lines start from, the normal starting position unless the prep
+    /// files have a `FEN` header, as Chess960 ones do
+    starts: ByColor<Chess>,
+}
+
+#[derive(Clone)]
+pub struct GameState {
+    pub current_move: Option<NodeIndex>,
+    player_turn: bool,
+    still_running: bool,
+    /// Moves the player has got right
+    depth: u32,
+    /// Wrong moves tried in the current position
+    mistakes: u32,
+    settings: Drill,
+    /// Line for the replies to follow when they're picked by what's due
+    target: Vec<String>,
+}
+
+impl OpeningDatabase {
+    pub fn default_root() -> &'static Path {
+        Path::new("prep")
+    }
+
+    pub fn load_default() -> anyhow::Result<Self> {
+        Self::load(Self::default_root())
+    }
+
+    pub fn load(root: &Path) -> anyhow::Result<Self> {
+        let white_openings = load_folder(&root.join(color_folder(Color::White)))?;
+        let black_openings = load_folder(&root.join(color_folder(Color::Black)))?;
+        let starts = ByColor {
+            white: load_start(&default_repertoire(root, Color::White), None)?,
+            black: load_start(&default_repertoire(root, Color::Black), None)?,
+        };
+
+        Ok(Self {
+            white_openings,
+            black_openings,
+            starts,
+        })
+    }
+
+    /// Replace the openings for a player with a single chapter (prep file) from their repertoire,
+    /// or all of them if no chapter is given.
+    pub fn load_chapter(
+        &mut self,
+        player: Color,
+        repertoire: &[PathBuf],
+        chapter: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let graph = load_chapter(repertoire, chapter)?;
+        let start = load_start(repertoire, chapter)?;
+        self.set_graph(player, graph);
+        self.set_start(player, start);
+        Ok(())
+    }
+
+    /// Add all the games in a PGN to the openings for a player, they have to start from the same
+    /// position as the ones already there
+    pub fn add_prep(&mut self, player: Color, mut prep: impl io::Read) -> anyhow::Result<()> {
+        let mut pgn = vec![];
+        prep.read_to_end(&mut pgn)?;
+        let start = read_start(&pgn[..])?;
+        if self.graph(player).node_count() > 0 && start != *self.start(player) {
+            anyhow::bail!(
+                "It starts from a different position to the {} repertoire",
+                player
+            );
+        }
+        let graphs = in_parallel(split_games(&pgn, parse_threads()), |games| {
+            let mut reader = BufferedReader::new(games);
+            let mut visitor = PgnVisitor::new_with_graph(OpeningGraph::default());
+            while reader.has_more()? {
+                reader.read_game(&mut visitor)?;
+            }
+            match visitor.pgn {
+                Pgn::Single { player } => Ok(player),
+                _ => unreachable!(),
+            }
+        })?;
+        let mut graph = self.graph(player).clone();
+        for part in &graphs {
+            merge_graph(&mut graph, part);
+        }
+        graph.compact();
+        self.set_graph(player, graph);
+        self.set_start(player, start);
+        Ok(())
+    }
+
+    pub fn set_graph(&mut self, player: Color, graph: OpeningGraph) {
+        match player {
+            Color::White => self.white_openings = graph,
+            Color::Black => self.black_openings = graph,
+        }
+    }
+
+    #[inline(always)]
+    pub fn graph(&self, player: Color) -> &OpeningGraph {
+        match player {
+            Color::White => &self.white_openings,
+            _ => &self.black_openings,
+        }
+    }
+
+    pub fn start(&self, player: Color) -> &Chess {
+        self.starts.get(player)
+    }
+
+    pub fn set_start(&mut self, player: Color, start: Chess) {
+        *self.starts.get_mut(player) = start;
+    }
+
+    pub fn start_drill(&self, player: Color, moves: &[SanPlus]) -> Option<GameState> {
+        let openings = self.graph(player);
+        let mut state = GameState {
+            player_turn: self.start(player).turn() == player,
+            current_move: None,
+            still_running: true,
+            depth: 0,
+            mistakes: 0,
+            settings: Drill::default(),
+            target: vec![],
+        };
+
+        for m in moves {
+            let prep = state.apply_move(m, openings);
+            if prep != MoveAssessment::InPrep {
+                return None;
+            }
+        }
+        Some(state)
+    }
+
+    pub fn load_multigame_pgn(pgns: impl io::Read, player: String) -> anyhow::Result<Self> {
+        let mut this = Self::default();
+        this.add_multigame_pgn(pgns, player)?;
+        Ok(this)
+    }
+
+    pub fn add_multigame_pgn(
+        &mut self,
+        mut pgns: impl io::Read,
+        player: String,
+    ) -> anyhow::Result<()> {
+        let mut pgn = vec![];
+        pgns.read_to_end(&mut pgn)?;
+        let graphs = in_parallel(split_games(&pgn, parse_threads()), |games| {
+            let mut reader = BufferedReader::new(games);
+            let mut visitor = PgnVisitor::new_game_recorder(
+                OpeningGraph::default(),
+                OpeningGraph::default(),
+                player.clone(),
+            );
+            while reader.has_more()? {
+                reader.read_game(&mut visitor)?;
+            }
+            match visitor.pgn {
+                Pgn::Dual { white, black } => Ok((white, black)),
+                _ => panic!("Didn't get our opening tree for white and black"),
+            }
+        })?;
+        for (white, black) in &graphs {
+            merge_graph(&mut self.white_openings, white);
+            merge_graph(&mut self.black_openings, black);
+        }
+        self.white_openings.compact();
+        self.black_openings.compact();
+        Ok(())
+    }
+}
+
+impl GameState {
+    pub fn with_settings(mut self, settings: Drill) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_target(mut self, target: Vec<String>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn still_running(&self) -> bool {
+        self.still_running
+    }
+
+    pub fn check_move(&self, openings: &OpeningGraph) -> MoveAssessment {
+        if let Some(current) = self.current_move {
+            if openings.children(current).next().is_some() {
+                MoveAssessment::InPrep
+            } else {
+                MoveAssessment::PrepEnded
+            }
+        } else {
+            MoveAssessment::OutOfPrep
+        }
+    }
+
+    pub fn make_move(&mut self, openings: &OpeningGraph) -> Option<SanPlus> {
+        if !self.still_running {
+            return None;
+        }
+        let candidates = if let Some(index) = self.current_move {
+            openings.children(index).collect::<Vec<NodeIndex>>()
+        } else {
+            self.find_roots(openings)
+        };
+        // Head for the target line whatever the policy, it's only set for due lines or by a hook
+        let ply = self.current_move.map_or(0, |x| ply(openings, x) + 1);
+        let target = self.target.get(ply);
+        let on_target = candidates
+            .iter()
+            .find(|x| Some(&openings[**x].to_string()) == target);
+        let choice = match self.settings.replies {
+            _ if on_target.is_some() => on_target?,
+            ReplyPolicy::Random => fastrand::choice(candidates.iter())?,
+            // Nodes are added as the PGN is read so the main line comes before its variations
+            ReplyPolicy::Mainline => candidates.iter().min()?,
+            ReplyPolicy::Due => fastrand::choice(candidates.iter())?,
+            ReplyPolicy::Weighted => {
+                let weights = candidates
+                    .iter()
+                    .map(|x| count_lines(openings, *x))
+                    .collect::<Vec<_>>();
+                let mut pick = fastrand::usize(..weights.iter().sum::<usize>().max(1));
+                candidates
+                    .iter()
+                    .zip(weights)
+                    .find(|(_, weight)| {
+                        if pick < *weight {
+                            true
+                        } else {
+                            pick -= weight;
+                            false
+                        }
+                    })
+                    .map(|(x, _)| x)
+                    .or(candidates.first())?
+            }
+        };
+        self.current_move = Some(*choice);
+        self.player_turn = !self.player_turn;
+        Some(openings[*choice].clone())
+    }
+
+    pub fn apply_move(&mut self, san: &SanPlus, openings: &OpeningGraph) -> MoveAssessment {
+        let mut has_neighbors = false;
+        let mut possible_moves = vec![];
+        if let Some(index) = self.current_move {
+            for next in openings.children(index) {
+                has_neighbors = true;
+                possible_moves.push(&openings[next]);
+                if &openings[next] == san {
+                    self.current_move = Some(next);
+                    return self.correct_move();
+                }
+            }
+        } else {
+            for root in self.find_roots(openings).iter() {
+                has_neighbors = true;
+                possible_moves.push(&openings[*root]);
+                if &openings[*root] == san {
+                    self.current_move = Some(*root);
+                    return self.correct_move();
+                }
+            }
+        }
+        if has_neighbors {
+            let possible_moves = possible_moves
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            info!(
+                "You chose: {}. Instead you should have chose one of: {}",
+                san, possible_moves
+            );
+            if self.player_turn {
+                self.mistakes += 1;
+                if self.mistakes < self.settings.attempts {
+                    return MoveAssessment::TryAgain;
+                }
+            }
+            self.still_running = false;
+            MoveAssessment::OutOfPrep
+        } else {
+            self.still_running = false;
+            MoveAssessment::PrepEnded
+        }
+    }
+
+    /// Moves the prep has for the player in this position, empty if it isn't their turn or the
+    /// drill is over
+    pub fn hint(&self, openings: &OpeningGraph) -> Vec<SanPlus> {
+        if !self.still_running || !self.player_turn {
+            return vec![];
+        }
+        next_moves(openings, self.current_move)
+            .iter()
+            .map(|x| openings[*x].clone())
+            .collect()
+    }
+
+    pub fn is_player_turn(&self) -> bool {
+        self.player_turn
+    }
+
+    fn correct_move(&mut self) -> MoveAssessment {
+        if self.player_turn {
+            self.depth += 1;
+            self.mistakes = 0;
+            if self.settings.max_depth.is_some_and(|x| self.depth >= x) {
+                self.still_running = false;
+                self.player_turn = !self.player_turn;
+                return MoveAssessment::PrepEnded;
+            }
+        }
+        self.player_turn = !self.player_turn;
+        MoveAssessment::InPrep
+    }
+
+    fn find_roots(&self, openings: &OpeningGraph) -> Vec<NodeIndex> {
+        next_moves(openings, None)
+    }
+}
+
+/// Moves before a node's
+fn ply(openings: &OpeningGraph, mut node: NodeIndex) -> usize {
+    let mut ply = 0;
+    while let Some(parent) = openings.parent(node) {
+        node = parent;
+        ply += 1;
+    }
+    ply
+}
+
+/// Moves in the prep following a node, or the starting moves if there's no node.
+pub fn next_moves(openings: &OpeningGraph, node: Option<NodeIndex>) -> Vec<NodeIndex> {
+    match node {
+        Some(node) => openings.children(node).collect(),
+        None => openings.roots().to_vec(),
+    }
+}
+
+/// Number of lines in the prep going through a node
+pub(crate) fn count_lines(openings: &OpeningGraph, node: NodeIndex) -> usize {
+    let mut lines = 0;
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        let len = stack.len();
+        stack.extend(openings.children(node));
+        if stack.len() == len {
+            lines += 1;
+        }
+    }
+    lines
+}
+
+/// Search the prep going from `start` for a position returning the moves that reach it. Move
+/// counters are ignored so a position pasted from elsewhere still matches.
+pub fn find_position(
+    openings: &OpeningGraph,
+    start: &Chess,
+    target: &Chess,
+) -> Option<Vec<SanPlus>> {
+    let epd = |x: &Chess| Epd::from_position(x.clone(), EnPassantMode::Legal).to_string();
+    let target = epd(target);
+    if epd(start) == target {
+        return Some(vec![]);
+    }
+    let mut stack = next_moves(openings, None)
+        .into_iter()
+        .map(|x| (x, start.clone(), vec![]))
+        .collect::<Vec<_>>();
+    while let Some((node, position, mut path)) = stack.pop() {
+        let san = &openings[node];
+        let Ok(mv) = san.san.to_move(&position) else {
+            continue;
+        };
+        let Ok(position) = position.play(&mv) else {
+            continue;
+        };
+        path.push(san.clone());
+        if epd(&position) == target {
+            return Some(path);
+        }
+        for next in next_moves(openings, Some(node)) {
+            stack.push((next, position.clone(), path.clone()));
+        }
+    }
+    None
+}
+
+/// Follow a sequence of moves from the start through the prep returning the node it ends on. If
+/// the moves leave the prep then `None` is returned.
+pub fn find_node(openings: &OpeningGraph, moves: &[SanPlus]) -> Option<NodeIndex> {
+    let mut node = None;
+    for m in moves {
+        node = Some(openings.find_child(node, m)?);
+    }
+    node
+}
+
+/// Load the openings in a single chapter (prep file) for a player, or all of them if no chapter is
+/// given.
+pub fn load_chapter(repertoire: &[PathBuf], chapter: Option<&str>) -> anyhow::Result<OpeningGraph> {
+    let files = repertoire_files(repertoire)?;
+    match chapter {
+        Some(chapter) => {
+            let path = files
+                .into_iter()
+                .find(|x| chapter_name(x) == chapter)
+                .ok_or_else(|| anyhow::anyhow!("No chapter called {}", chapter))?;
+            info!("Loading: {}", path.display());
+            read_prep(fs::File::open(&path)?, OpeningGraph::default())
+                .map_err(|e| e.context(format!("Can't read {}", path.display())))
+        }
+        None => load_files(files),
+    }
+}
+
+/// Where the lines in a chapter, or in the whole repertoire, start from. Every file has to start
+/// from the same position.
+pub fn load_start(repertoire: &[PathBuf], chapter: Option<&str>) -> anyhow::Result<Chess> {
+    let files = repertoire_files(repertoire)?
+        .into_iter()
+        .filter(|x| chapter.is_none_or(|chapter| chapter_name(x) == chapter));
+    let mut start: Option<(Chess, PathBuf)> = None;
+    for path in files {
+        let position = fs::File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(read_start)
+            .map_err(|e| e.context(format!("Can't read {}", path.display())))?;
+        match &start {
+            Some((first, other)) if *first != position => anyhow::bail!(
+                "{} starts from a different position to {}, give them a repertoire each",
+                path.display(),
+                other.display()
+            ),
+            Some(_) => {}
+            None => start = Some((position, path)),
+        }
+    }
+    Ok(start.map(|x| x.0).unwrap_or_default())
+}
+
+#[derive(Default)]
+struct StartReader {
+    fen: Option<String>,
+    chess960: bool,
+}
+
+impl Visitor for StartReader {
+    type Result = ();
+
+    fn header(&mut self, key: &[u8], value: RawHeader) {
+        let value = value.decode_utf8_lossy();
+        match key {
+            b"FEN" => self.fen = Some(value.to_string()),
+            b"Variant" => {
+                let variant = value.to_lowercase();
+                self.chess960 = variant.contains("960") || variant.contains("fischer");
+            }
+            _ => {}
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {}
+}
+
+/// The position the first game in a PGN starts from from its `FEN` header. Chess960 castling
+/// rights are picked up from the `Variant` header or the FEN itself.
+pub fn read_start(pgn: impl io::Read) -> anyhow::Result<Chess> {
+    let mut reader = StartReader::default();
+    BufferedReader::new(pgn).read_game(&mut reader)?;
+    let Some(fen) = reader.fen else {
+        return Ok(Chess::new());
+    };
+    parse_position(&fen, reader.chess960)
+}
+
+/// List the names of the chapters (prep files) in a repertoire
+pub fn list_chapters(repertoire: &[PathBuf]) -> Vec<String> {
+    let mut chapters = repertoire_files(repertoire)
+        .unwrap_or_default()
+        .iter()
+        .map(|x| chapter_name(x))
+        .collect::<Vec<_>>();
+    chapters.sort();
+    chapters
+}
+
+/// Problems with the prep files in a repertoire: ones that are missing, can't be read, have no
+/// moves or have moves that can't be played
+pub fn validate_repertoire(repertoire: &[PathBuf]) -> Vec<String> {
+    let mut problems = vec![];
+    let mut files = vec![];
+    for path in repertoire {
+        match repertoire_files(std::slice::from_ref(path)) {
+            Ok(mut x) => files.append(&mut x),
+            Err(e) => problems.push(e.to_string()),
+        }
+    }
+    for path in files {
+        let graph = fs::File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|x| read_prep(x, OpeningGraph::default()))
+            .and_then(|graph| Ok((graph, read_start(fs::File::open(&path)?)?)));
+        match graph {
+            Ok((graph, _)) if graph.node_count() == 0 => {
+                problems.push(format!("{} has no moves in it", path.display()))
+            }
+            Ok((graph, start)) => problems.extend(
+                illegal_moves(&graph, &start)
+                    .into_iter()
+                    .map(|x| format!("{}: {}", path.display(), x)),
+            ),
+            Err(e) => problems.push(format!("Can't read {}: {}", path.display(), e)),
+        }
+    }
+    problems
+}
+
+/// Moves in the prep going from `start` that can't be played in their position, with the line
+/// leading up to them
+pub fn illegal_moves(openings: &OpeningGraph, start: &Chess) -> Vec<String> {
+    let mut illegal = vec![];
+    let mut stack = next_moves(openings, None)
+        .into_iter()
+        .map(|x| (x, start.clone(), vec![]))
+        .collect::<Vec<_>>();
+    while let Some((node, position, mut line)) = stack.pop() {
+        let san = &openings[node];
+        let Ok(mv) = san.san.to_move(&position) else {
+            illegal.push(match line.is_empty() {
+                true => format!("{} can't be played from the start", san),
+                false => format!("{} can't be played after {}", san, numbered_line(&line)),
+            });
+            continue;
+        };
+        let mut position = position;
+        position.play_unchecked(&mv);
+        line.push(san.to_string());
+        for next in next_moves(openings, Some(node)) {
+            stack.push((next, position.clone(), line.clone()));
+        }
+    }
+    illegal
+}
+
+/// Write a repertoire, or one chapter of it, out as PGN. Each chapter is a game named after it
+/// with the other moves as variations.
+pub fn repertoire_pgn(repertoire: &[PathBuf], chapter: Option<&str>) -> anyhow::Result<String> {
+    let files = repertoire_files(repertoire)?
+        .into_iter()
+        .filter(|x| chapter.is_none_or(|chapter| chapter_name(x) == chapter))
+        .collect::<Vec<_>>();
+    if let (Some(chapter), true) = (chapter, files.is_empty()) {
+        anyhow::bail!("No chapter called {}", chapter);
+    }
+    let mut pgn = String::new();
+    for path in files {
+        let graph = read_prep(fs::File::open(&path)?, OpeningGraph::default())?;
+        // Variations of the first move don't load, so those are games of their own
+        for root in next_moves(&graph, None) {
+            if !pgn.is_empty() {
+                pgn.push('\n');
+            }
+            pgn += &format!("[Event \"{}\"]\n\n", chapter_name(&path).replace('"', "'"));
+            write_moves(&graph, &[root], 0, false, &mut pgn);
+            pgn += "*\n";
+        }
+    }
+    Ok(pgn)
+}
+
+/// Add a PGN to the first folder in the repertoire as a new chapter, once it's been checked it
+/// loads. Returns where it was written.
+pub fn import_chapter(repertoire: &[PathBuf], name: &str, pgn: &str) -> anyhow::Result<PathBuf> {
+    let graph = read_prep(pgn.as_bytes(), OpeningGraph::default())?;
+    if graph.node_count() == 0 {
+        anyhow::bail!("There are no moves in it");
+    }
+    if let Some(problem) = illegal_moves(&graph, &read_start(pgn.as_bytes())?)
+        .into_iter()
+        .next()
+    {
+        anyhow::bail!("{}", problem);
+    }
+    if list_chapters(repertoire).iter().any(|x| x == name) {
+        anyhow::bail!("There's already a chapter called {}", name);
+    }
+    let folder = repertoire
+        .iter()
+        .find(|x| x.is_dir())
+        .ok_or_else(|| anyhow::anyhow!("There's no folder in the repertoire to add it to"))?;
+    let path = folder.join(format!("{}.pgn", name));
+    fs::write(&path, pgn)?;
+    info!("Imported {}", path.display());
+    Ok(path)
+}
+
+/// Moves with a number before white's, e.g. `1. e4 e5 2. Nf3`
+pub fn numbered_line(moves: &[String]) -> String {
+    moves
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| format!("{}. {}", i + 1, pair.join(" ")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Movetext for `nodes` and everything after them, the first is carried on as the main line and
+/// the others are variations of it. `number` is whether black's move needs its number.
+fn write_moves(
+    openings: &OpeningGraph,
+    nodes: &[NodeIndex],
+    ply: usize,
+    number: bool,
+    pgn: &mut String,
+) {
+    let Some((main, others)) = nodes.split_first() else {
+        return;
+    };
+    let write = |node: NodeIndex, number: bool, pgn: &mut String| {
+        if ply.is_multiple_of(2) {
+            *pgn += &format!("{}. ", ply / 2 + 1);
+        } else if number {
+            *pgn += &format!("{}... ", ply / 2 + 1);
+        }
+        *pgn += &format!("{} ", openings[node]);
+    };
+    write(*main, number, pgn);
+    for other in others {
+        pgn.push('(');
+        write(*other, true, pgn);
+        write_moves(
+            openings,
+            &replies(openings, Some(*other)),
+            ply + 1,
+            false,
+            pgn,
+        );
+        pgn.pop();
+        pgn.push_str(") ");
+    }
+    write_moves(
+        openings,
+        &replies(openings, Some(*main)),
+        ply + 1,
+        !others.is_empty(),
+        pgn,
+    );
+}
+
+/// [`next_moves`] in the order they were read, so the main line comes first
+pub(crate) fn replies(openings: &OpeningGraph, node: Option<NodeIndex>) -> Vec<NodeIndex> {
+    let mut replies = next_moves(openings, node);
+    if node.is_some() {
+        replies.reverse();
+    }
+    replies
+}
+
+/// The repertoire for a player when there's nothing in the config, their colour's folder in the
+/// prep folder
+pub fn default_repertoire(root: &Path, player: Color) -> Vec<PathBuf> {
+    vec![root.join(color_folder(player))]
+}
+
+/// All the prep files in a repertoire, this can be a mix of PGN files and folders of them
+pub(crate) fn repertoire_files(repertoire: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in repertoire {
+        if path.is_dir() {
+            files.append(&mut chapter_files(path));
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            anyhow::bail!("Repertoire file {} doesn't exist", path.display());
+        }
+    }
+    Ok(files)
+}
+
+fn color_folder(player: Color) -> &'static str {
+    match player {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+pub(crate) fn chapter_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn chapter_files(folder: &Path) -> Vec<PathBuf> {
+    WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+pub(crate) fn read_prep(prep: impl io::Read, graph: OpeningGraph) -> anyhow::Result<OpeningGraph> {
+    let mut reader = BufferedReader::new(prep);
+    let mut pgn_visitor = PgnVisitor::new_with_graph(graph);
+    reader.read_game(&mut pgn_visitor)?;
+
+    match pgn_visitor.pgn {
+        Pgn::Single { player } => Ok(player),
+        _ => unreachable!(),
+    }
+}
+
+fn load_folder(folder: &Path) -> anyhow::Result<OpeningGraph> {
+    load_files(chapter_files(folder))
+}
+
+/// Each file is read on a thread of its own and they're merged in order after
+fn load_files(files: Vec<PathBuf>) -> anyhow::Result<OpeningGraph> {
+    let graphs = in_parallel(files, |path| {
+        info!("Loading: {}", path.display());
+        let load = fs::File::open(&path);
+        let load = match load {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to load {}. Error: {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+        read_prep(load, OpeningGraph::default())
+            .map(Some)
+            .map_err(|e| e.context(format!("Can't read {}", path.display())))
+    })?;
+    let mut graph = OpeningGraph::default();
+    for part in graphs.iter().flatten() {
+        merge_graph(&mut graph, part);
+    }
+    graph.compact();
+    Ok(graph)
+}
+
+fn parse_threads() -> usize {
+    thread::available_parallelism().map_or(1, |x| x.get())
+}
+
+/// Run `f` on every item spread over [`parse_threads`] threads, giving back the results in the
+/// same order as the items
+fn in_parallel<T: Send, R: Send>(
+    items: Vec<T>,
+    f: impl Fn(T) -> anyhow::Result<R> + Sync,
+) -> anyhow::Result<Vec<R>> {
+    if items.len() < 2 {
+        return items.into_iter().map(f).collect();
+    }
+    let per_thread = items.len().div_ceil(parse_threads());
+    let mut chunks = vec![];
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        chunks.push(items.by_ref().take(per_thread).collect::<Vec<_>>());
+    }
+    let f = &f;
+    thread::scope(|scope| {
+        let threads = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .flat_map(|x| x.join().expect("PGN parsing thread panicked"))
+            .collect()
+    })
+}
+
+/// Split PGN text into `parts` runs of whole games, fewer if there aren't that many games. A game
+/// starts at a header line that comes after some moves, braces are followed so a comment's text
+/// can't start one.
+fn split_games(pgn: &[u8], parts: usize) -> Vec<&[u8]> {
+    let mut starts = vec![0];
+    let mut in_comment = false;
+    let mut in_moves = false;
+    let mut offset = 0;
+    for line in pgn.split_inclusive(|x| *x == b'\n') {
+        let trimmed = line.trim_ascii_start();
+        if !in_comment && trimmed.starts_with(b"[") {
+            if in_moves {
+                starts.push(offset);
+            }
+            in_moves = false;
+        } else if !trimmed.is_empty() {
+            in_moves = true;
+            for byte in trimmed {
+                match byte {
+                    b'{' => in_comment = true,
+                    b'}' => in_comment = false,
+                    _ => {}
+                }
+            }
+        }
+        offset += line.len();
+    }
+    let per_part = starts.len().div_ceil(parts.max(1));
+    let mut split = starts
+        .chunks(per_part)
+        .skip(1)
+        .map(|x| x[0])
+        .collect::<Vec<_>>();
+    split.push(pgn.len());
+    let mut start = 0;
+    split
+        .into_iter()
+        .map(|end| {
+            let part = &pgn[start..end];
+            start = end;
+            part
+        })
+        .collect()
+}
+
+/// Add the lines in `from` to `into`, following the moves it already has as far as they go
+fn merge_graph(into: &mut OpeningGraph, from: &OpeningGraph) {
+    let mut stack = next_moves(from, None)
+        .into_iter()
+        .rev()
+        .map(|x| (None, x))
+        .collect::<Vec<_>>();
+    while let Some((parent, node)) = stack.pop() {
+        let san = &from[node];
+        let merged = into
+            .find_child(parent, san)
+            .unwrap_or_else(|| into.add_node(san.clone(), parent));
+        // Pushed backwards so they're added in the order they were read
+        stack.extend(
+            replies(from, Some(node))
+                .into_iter()
+                .rev()
+                .map(|x| (Some(merged), x)),
+        );
+    }
+}
+
+#[derive(Debug)]
+enum Pgn {
+    Dual {
+        white: OpeningGraph,
+        black: OpeningGraph,
+    },
+    Single {
+        player: OpeningGraph,
+    },
+}
+
+impl Default for Pgn {
+    fn default() -> Self {
+        Self::Single {
+            player: Default::default(),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+struct PgnVisitor {
+    pgn: Pgn,
+    node_stack: Vec<NodeIndex>,
+    first: bool,
+    /// Used to show if we want to filter on player
+    player: Option<String>,
+    store_in_backup: bool,
+}
+
+impl PgnVisitor {
+    pub fn new_with_graph(player: OpeningGraph) -> Self {
+        Self {
+            pgn: Pgn::Single { player },
+            node_stack: vec![],
+            first: true,
+            player: None,
+            store_in_backup: false,
+        }
+    }
+
+    pub fn new_game_recorder(white: OpeningGraph, black: OpeningGraph, player: String) -> Self {
+        Self {
+            pgn: Pgn::Dual { white, black },
+            player: Some(player),
+            first: true,
+            node_stack: vec![],
+            store_in_backup: false,
+        }
+    }
+}
+
+impl Visitor for PgnVisitor {
+    type Result = ();
+
+    fn header(&mut self, key: &[u8], value: pgn_reader::RawHeader) {
+        if let Some(player) = self.player.as_ref() {
+            let color_key = match std::str::from_utf8(key) {
+                Ok("White") => Color::White,
+                Ok("Black") => Color::Black,
+                _ => return,
+            };
+
+            if let Ok(pgn_player) = std::str::from_utf8(value.0) {
+                if pgn_player == player {
+                    self.store_in_backup = color_key == Color::Black;
+                }
+            }
+        }
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.node_stack.clear();
+        self.first = true;
+        ()
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let pgn = match &mut self.pgn {
+            Pgn::Dual { black, .. } if self.store_in_backup => black,
+            Pgn::Single { .. } if self.store_in_backup => {
+                panic!("Trying to filter on player but no black graph!?")
+            }
+            Pgn::Single { player } => player,
+            Pgn::Dual { white, .. } => white,
+        };
+        if self.first {
+            assert!(self.node_stack.is_empty());
+            self.first = false;
+            let node = pgn
+                .find_child(None, &san_plus)
+                .unwrap_or_else(|| pgn.add_node(san_plus, None));
+            self.node_stack.push(node);
+        } else {
+            if let Some(old_node) = self.node_stack.last_mut() {
+                if let Some(next) = pgn.find_child(Some(*old_node), &san_plus) {
+                    self.node_stack.push(next);
+                    return;
+                }
+                *old_node = pgn.add_node(san_plus, Some(*old_node));
+            } else {
+                let node = pgn.add_node(san_plus, None);
+                self.node_stack.push(node);
+            }
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        let pgn = match &mut self.pgn {
+            Pgn::Dual { black, .. } if self.store_in_backup => black,
+            Pgn::Single { .. } if self.store_in_backup => {
+                panic!("Trying to filter on player but no black graph!?")
+            }
+            Pgn::Single { player } => player,
+            Pgn::Dual { white, .. } => white,
+        };
+        // Variation is an alternative for last move pushed so we want to join to two moves back
+        // This won't work well with variations right at the start (should probably be
+        // `Vec<Option<NodeStack>>` to create new tree roots...
+        if let Some(last) = self.node_stack.last() {
+            if let Some(parent) = pgn.parent(*last) {
+                self.node_stack.push(parent);
+            }
+        } else {
+            warn!("No root?");
+        }
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        // We now want to add to last node before variation
+        self.node_stack.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_multiline_pgn() {
+        let load = fs::File::open("tests/resources/games.pgn");
+        let load = match load {
+            Ok(s) => s,
+            Err(e) => {
+                panic!("Failed to load Error: {}", e);
+            }
+        };
+
+        let db = OpeningDatabase::load_multigame_pgn(load, "xd009642".to_string()).unwrap();
+
+        // Lets make sure for white we have a QGD and for black a caro kann
+
+        let caro_kann = &[
+            SanPlus::from_ascii(b"e4").unwrap(),
+            SanPlus::from_ascii(b"c6").unwrap(),
+        ];
+        let _state = db.start_drill(Color::Black, caro_kann).unwrap();
+
+        let qgd = &[
+            SanPlus::from_ascii(b"d4").unwrap(),
+            SanPlus::from_ascii(b"d5").unwrap(),
+            SanPlus::from_ascii(b"c4").unwrap(),
+            SanPlus::from_ascii(b"e6").unwrap(),
+        ];
+        let _state = db.start_drill(Color::White, qgd).unwrap();
+    }
+
+    #[test]
+    fn add_prep_to_player() {
+        let load = fs::File::open("tests/resources/games.pgn").unwrap();
+        let mut db = OpeningDatabase::default();
+        db.add_prep(Color::White, load).unwrap();
+
+        let qgd = &[
+            SanPlus::from_ascii(b"d4").unwrap(),
+            SanPlus::from_ascii(b"d5").unwrap(),
+            SanPlus::from_ascii(b"c4").unwrap(),
+            SanPlus::from_ascii(b"e6").unwrap(),
+        ];
+        assert!(db.start_drill(Color::White, qgd).is_some());
+        assert_eq!(db.graph(Color::Black).node_count(), 0);
+    }
+
+    #[test]
+    fn find_prep_position() {
+        let db = OpeningDatabase::load(Path::new("../prep")).unwrap();
+        let moves = ["e4", "c6", "d4", "d5"]
+            .iter()
+            .map(|x| SanPlus::from_ascii(x.as_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        let mut position = Chess::new();
+        for m in &moves {
+            let mv = m.san.to_move(&position).unwrap();
+            position = position.play(&mv).unwrap();
+        }
+        assert_eq!(
+            find_position(db.graph(Color::Black), &Chess::new(), &position),
+            Some(moves)
+        );
+        assert_eq!(
+            find_position(db.graph(Color::White), &Chess::new(), &position),
+            None
+        );
+    }
+
+    #[test]
+    fn hint_player_moves() {
+        let db = OpeningDatabase::load(Path::new("../prep")).unwrap();
+        let e4 = SanPlus::from_ascii(b"e4").unwrap();
+        let state = db.start_drill(Color::White, &[]).unwrap();
+        assert_eq!(state.hint(db.graph(Color::White)), vec![e4.clone()]);
+
+        // Nothing to suggest while waiting on the reply
+        let state = db.start_drill(Color::White, &[e4]).unwrap();
+        assert!(state.hint(db.graph(Color::White)).is_empty());
+    }
+
+    #[test]
+    fn drill_from_black_to_move() {
+        let mut db = OpeningDatabase::default();
+        let pgn = "[FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1\"]\n\n1... c5 2. Nf3 d6 *";
+        db.add_prep(Color::Black, pgn.as_bytes()).unwrap();
+        db.add_prep(Color::White, pgn.as_bytes()).unwrap();
+        let openings = db.graph(Color::Black);
+        let c5 = SanPlus::from_ascii(b"c5").unwrap();
+        let mut state = db.start_drill(Color::Black, &[]).unwrap();
+        assert!(state.is_player_turn());
+        assert_eq!(state.hint(openings), vec![c5.clone()]);
+        assert_eq!(state.apply_move(&c5, openings), MoveAssessment::InPrep);
+        assert_eq!(state.make_move(openings).unwrap().to_string(), "Nf3");
+
+        // White waits on black's first move
+        let mut state = db.start_drill(Color::White, &[]).unwrap();
+        assert!(!state.is_player_turn());
+        assert_eq!(state.make_move(db.graph(Color::White)), Some(c5));
+    }
+
+    #[test]
+    fn drill_settings() {
+        let db = OpeningDatabase::load(Path::new("../prep")).unwrap();
+        let openings = db.graph(Color::White);
+        let san = |x: &str| SanPlus::from_ascii(x.as_bytes()).unwrap();
+        let settings = Drill {
+            max_depth: Some(2),
+            attempts: 2,
+            ..Default::default()
+        };
+        let mut state = db
+            .start_drill(Color::White, &[])
+            .unwrap()
+            .with_settings(settings);
+
+        assert_eq!(
+            state.apply_move(&san("d4"), openings),
+            MoveAssessment::TryAgain
+        );
+        assert!(state.still_running());
+        assert_eq!(
+            state.apply_move(&san("e4"), openings),
+            MoveAssessment::InPrep
+        );
+        let reply = state.make_move(openings).unwrap();
+        let next = state.hint(openings);
+        assert!(!next.is_empty(), "no prep after 1. e4 {}", reply);
+        assert_eq!(
+            state.apply_move(&next[0], openings),
+            MoveAssessment::PrepEnded
+        );
+        assert!(!state.still_running());
+    }
+
+    #[test]
+    fn load_test_prep() {
+        OpeningDatabase::load(Path::new("../prep")).unwrap();
+    }
+
+    #[test]
+    fn load_single_chapter() {
+        let repertoire = default_repertoire(Path::new("../prep"), Color::Black);
+        let chapters = list_chapters(&repertoire);
+        assert_eq!(chapters.len(), 3);
+
+        let caro_kann = chapters.iter().find(|x| x.contains("caro-kann")).unwrap();
+        let mut db = OpeningDatabase::default();
+        db.load_chapter(Color::Black, &repertoire, Some(caro_kann))
+            .unwrap();
+
+        let e4 = &[SanPlus::from_ascii(b"e4").unwrap()];
+        assert!(db.start_drill(Color::Black, e4).is_some());
+        let d4 = &[SanPlus::from_ascii(b"d4").unwrap()];
+        assert!(db.start_drill(Color::Black, d4).is_none());
+
+        assert!(db
+            .load_chapter(Color::Black, &repertoire, Some("missing"))
+            .is_err());
+        let missing = vec![PathBuf::from("prep/missing.pgn")];
+        assert!(db.load_chapter(Color::Black, &missing, None).is_err());
+    }
+
+    #[test]
+    fn export_and_import_chapters() {
+        let repertoire = default_repertoire(Path::new("../prep"), Color::Black);
+        assert!(validate_repertoire(&repertoire).is_empty());
+        let pgn = repertoire_pgn(&repertoire, None).unwrap();
+        assert_eq!(pgn.matches("[Event ").count(), 3);
+
+        let dir = std::env::temp_dir().join("chess-driller-import-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let copy = vec![dir.clone()];
+        for chapter in list_chapters(&repertoire) {
+            let pgn = repertoire_pgn(&repertoire, Some(&chapter)).unwrap();
+            import_chapter(&copy, &chapter, &pgn).unwrap();
+        }
+        assert!(import_chapter(&copy, &list_chapters(&repertoire)[0], &pgn).is_err());
+        let lines = |repertoire: &[PathBuf]| {
+            let mut lines = crate::prep_lines(&load_chapter(repertoire, None).unwrap());
+            lines.sort();
+            lines
+        };
+        assert_eq!(lines(&repertoire), lines(&copy));
+
+        let error = import_chapter(&copy, "bad", "1. e4 e5 2. Ke3 *").unwrap_err();
+        assert_eq!(error.to_string(), "Ke3 can't be played after 1. e4 e5");
+        fs::write(dir.join("bad.pgn"), "1. e4 e5 2. Ke3 *").unwrap();
+        assert_eq!(validate_repertoire(&copy).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_games_in_parallel() {
+        let pgn = "[Event \"a\"]\n\n1. e4 e5 2. Nf3 { see\n[Event] below } Nc6 *\n\n\
+                   [Event \"b\"]\n[Site \"?\"]\n\n1. e4 e5 2. Nc3 *\n\n\
+                   [Event \"c\"]\n\n1. d4 d5 *\n";
+        let parts = split_games(pgn.as_bytes(), 8);
+        assert_eq!(parts.len(), 3);
+        assert!(parts[1].starts_with(b"[Event \"b\"]"));
+        assert_eq!(split_games(pgn.as_bytes(), 2).len(), 2);
+
+        let mut db = OpeningDatabase::default();
+        db.add_prep(Color::White, pgn.as_bytes()).unwrap();
+        let graph = db.graph(Color::White);
+        assert_eq!(graph.node_count(), 7);
+        let lines = crate::prep_lines(graph);
+        assert_eq!(lines.len(), 3);
+        let roots = next_moves(graph, None);
+        assert_eq!(graph[roots[0]].to_string(), "e4");
+        let replies = replies(graph, Some(next_moves(graph, Some(roots[0]))[0]))
+            .into_iter()
+            .map(|x| graph[x].to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(replies, vec!["Nf3", "Nc3"]);
+    }
+}
@@ -0,0 +1,106 @@
+//! Endgame positions to drill. Each `.fen` file in the endgames folder is a chapter with a FEN
+//! on each line, blank lines and ones starting with `#` are skipped.
+use anyhow::Context;
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn chapter_files(folder: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.extension().is_some_and(|x| x == "fen"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn chapter_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Names of the chapters in the endgames folder
+pub fn list_endgames(folder: &Path) -> Vec<String> {
+    chapter_files(folder)
+        .iter()
+        .map(|x| chapter_name(x))
+        .collect()
+}
+
+/// The positions in a chapter, or every chapter if none is given. It's an error for there to be
+/// none, like when the files only have comments in them.
+pub fn load_endgames(folder: &Path, chapter: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let files = chapter_files(folder)
+        .into_iter()
+        .filter(|x| chapter.is_none_or(|chapter| chapter_name(x) == chapter))
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        match chapter {
+            Some(chapter) => anyhow::bail!("No endgame chapter called {}", chapter),
+            None => anyhow::bail!("There are no .fen files in {}", folder.display()),
+        }
+    }
+    let mut positions = vec![];
+    for path in files {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        positions
+            .append(&mut parse_positions(&text).with_context(|| format!("In {}", path.display()))?);
+    }
+    if positions.is_empty() {
+        match chapter {
+            Some(chapter) => anyhow::bail!("No positions in the {} chapter", chapter),
+            None => anyhow::bail!("No positions in the .fen files in {}", folder.display()),
+        }
+    }
+    Ok(positions)
+}
+
+fn parse_positions(text: &str) -> anyhow::Result<Vec<String>> {
+    let mut positions = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let _: Chess = Fen::from_ascii(line.as_bytes())
+            .ok()
+            .and_then(|x| x.into_position(CastlingMode::Standard).ok())
+            .with_context(|| format!("Line {} isn't a valid position", i + 1))?;
+        positions.push(line.to_string());
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_positions() {
+        let text =
+            "# Lucena\n\n1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1\n  8/8/8/8/8/4k3/4p3/4K3 w - - 0 1 \n";
+        assert_eq!(
+            parse_positions(text).unwrap(),
+            vec![
+                "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+                "8/8/8/8/8/4k3/4p3/4K3 w - - 0 1"
+            ]
+        );
+        let e = parse_positions("8/8/8/8/8/8/8/8 w - - 0 1\n").unwrap_err();
+        assert_eq!(e.to_string(), "Line 1 isn't a valid position");
+
+        let dir =
+            std::env::temp_dir().join(format!("chess-driller-endgames-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("rooks.fen"), "# Nothing yet\n\n").unwrap();
+        let e = load_endgames(&dir, Some("rooks")).unwrap_err();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(e.to_string(), "No positions in the rooks chapter");
+    }
+}
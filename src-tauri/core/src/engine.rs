@@ -0,0 +1,468 @@
+//! Talks to a UCI engine like stockfish running as its own process. Searches are queued up and
+//! answered on a channel, so nothing has to wait around while the engine thinks.
+use crate::config;
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::CastlingMode;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// How long the engine gets to answer when it's started
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Centipawns ahead that count as winning when the engine doesn't say
+const WINNING: i32 = 300;
+
+/// When to stop searching, whichever comes first. With none set it searches until stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    /// Milliseconds
+    pub movetime: Option<u64>,
+    pub nodes: Option<u64>,
+}
+
+impl Limits {
+    fn to_uci(self) -> String {
+        let mut go = String::from("go");
+        if let Some(depth) = self.depth {
+            go += &format!(" depth {}", depth);
+        }
+        if let Some(movetime) = self.movetime {
+            go += &format!(" movetime {}", movetime);
+        }
+        if let Some(nodes) = self.nodes {
+            go += &format!(" nodes {}", nodes);
+        }
+        if self == Self::default() {
+            go += " infinite";
+        }
+        go
+    }
+}
+
+/// Score from the point of view of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Score {
+    /// Centipawns
+    Cp(i32),
+    /// Moves until mate, negative when the side to move is the one getting mated
+    Mate(i32),
+}
+
+impl Score {
+    /// Mates count as more than any number of centipawns, sooner ones more so
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Cp(x) => x,
+            Score::Mate(x) if x > 0 => 100_000 - x,
+            Score::Mate(x) => -100_000 - x,
+        }
+    }
+
+    /// The same score for the other side
+    pub fn flip(self) -> Self {
+        match self {
+            Score::Cp(x) => Score::Cp(-x),
+            Score::Mate(x) => Score::Mate(-x),
+        }
+    }
+
+    /// Score for white when it's `turn` to move
+    pub fn for_white(self, turn: shakmaty::Color) -> Self {
+        match turn {
+            shakmaty::Color::White => self,
+            shakmaty::Color::Black => self.flip(),
+        }
+    }
+}
+
+/// What the position comes to with best play for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wdl {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Wdl {
+    /// The same result for the other side
+    pub fn flip(self) -> Self {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eval {
+    pub score: Score,
+    /// Chances of a win, draw and loss out of 1000, tablebase positions are certain
+    pub wdl: Option<[u32; 3]>,
+    pub depth: u32,
+    /// Best line found in UCI notation e.g. `e2e4`
+    pub pv: Vec<String>,
+    /// Which of the lines asked for this is, 1 is the best
+    pub multipv: u32,
+}
+
+impl Eval {
+    pub fn best_move(&self) -> Option<&str> {
+        self.pv.first().map(|x| x.as_str())
+    }
+
+    /// The principal variation in SAN from `position`, up to any move that doesn't make sense
+    pub fn san_line(&self, position: &shakmaty::Chess) -> Vec<String> {
+        let mut moves = vec![];
+        let mut position = position.clone();
+        for uci in &self.pv {
+            let Some(mv) = uci
+                .parse::<shakmaty::uci::Uci>()
+                .ok()
+                .and_then(|x| x.to_move(&position).ok())
+            else {
+                break;
+            };
+            moves.push(
+                shakmaty::san::SanPlus::from_move_and_play_unchecked(&mut position, &mv)
+                    .to_string(),
+            );
+        }
+        moves
+    }
+
+    /// The likeliest result, going by the score if the engine doesn't give the chances
+    pub fn result(&self) -> Wdl {
+        match self.wdl {
+            Some([win, _, _]) if win > 500 => Wdl::Win,
+            Some([_, _, loss]) if loss > 500 => Wdl::Loss,
+            Some(_) => Wdl::Draw,
+            None => match self.score.centipawns() {
+                x if x > WINNING => Wdl::Win,
+                x if x < -WINNING => Wdl::Loss,
+                _ => Wdl::Draw,
+            },
+        }
+    }
+}
+
+struct Request {
+    fen: String,
+    limits: Limits,
+    reply: Sender<anyhow::Result<Eval>>,
+    /// Sent each eval as the search goes deeper
+    updates: Option<Sender<Eval>>,
+    /// Number of lines to search, only the best comes back in the reply
+    lines: u32,
+}
+
+pub struct UciEngine {
+    /// What the engine calls itself
+    pub name: String,
+    requests: Option<Sender<Request>>,
+    stdin: Arc<Mutex<ChildStdin>>,
+    child: Child,
+}
+
+impl UciEngine {
+    /// Run the engine from the config and wait until it's ready
+    pub fn start(settings: &config::Engine) -> anyhow::Result<Self> {
+        let path = settings
+            .binary()
+            .context("There's no engine set in the config and stockfish couldn't be found")?;
+        info!("Starting engine {}", path.display());
+        let mut child = Command::new(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Couldn't run the engine {}", path.display()))?;
+
+        let stdout = child.stdout.take().unwrap();
+        let (lines_tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                debug!("Engine: {}", line);
+                if lines_tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let stdin = Arc::new(Mutex::new(child.stdin.take().unwrap()));
+        let wait_for = |expected: &str, name: &mut String| -> anyhow::Result<()> {
+            loop {
+                let line = lines
+                    .recv_timeout(STARTUP_TIMEOUT)
+                    .map_err(|_| anyhow!("{} didn't answer like a UCI engine", path.display()))?;
+                if let Some(id) = line.strip_prefix("id name ") {
+                    *name = id.to_string();
+                }
+                if line.trim() == expected {
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut name = path.display().to_string();
+        send(&stdin, "uci")?;
+        wait_for("uciok", &mut name)?;
+        send(
+            &stdin,
+            &format!("setoption name Hash value {}", settings.hash),
+        )?;
+        send(
+            &stdin,
+            &format!("setoption name Threads value {}", settings.threads),
+        )?;
+        if let Some(level) = settings.skill_level {
+            send(
+                &stdin,
+                &format!("setoption name Skill Level value {}", level),
+            )?;
+        }
+        if let Some(path) = settings.syzygy_path.as_ref() {
+            send(
+                &stdin,
+                &format!("setoption name SyzygyPath value {}", path.display()),
+            )?;
+        }
+        // Engines that don't know it ignore it
+        send(&stdin, "setoption name UCI_ShowWDL value true")?;
+        if let Some(elo) = settings.elo {
+            send(&stdin, "setoption name UCI_LimitStrength value true")?;
+            send(&stdin, &format!("setoption name UCI_Elo value {}", elo))?;
+        }
+        send(&stdin, "isready")?;
+        wait_for("readyok", &mut name)?;
+        info!("Engine {} is ready", name);
+
+        let (requests, queue) = mpsc::channel();
+        let worker = stdin.clone();
+        thread::spawn(move || run(worker, lines, queue));
+        Ok(Self {
+            name,
+            requests: Some(requests),
+            stdin,
+            child,
+        })
+    }
+
+    /// Queue up a search of the position, the result is sent on the channel once it's done
+    pub fn analyze(&self, fen: &str, limits: Limits) -> Receiver<anyhow::Result<Eval>> {
+        self.queue(fen, limits, None, 1)
+    }
+
+    /// Queue up a search of the best few `lines` sending the evals each time the engine gets
+    /// further, the channel closes once the search is over
+    pub fn watch(&self, fen: &str, limits: Limits, lines: u32) -> Receiver<Eval> {
+        let (updates, res) = mpsc::channel();
+        self.queue(fen, limits, Some(updates), lines);
+        res
+    }
+
+    fn queue(
+        &self,
+        fen: &str,
+        limits: Limits,
+        updates: Option<Sender<Eval>>,
+        lines: u32,
+    ) -> Receiver<anyhow::Result<Eval>> {
+        let (reply, res) = mpsc::channel();
+        let request = Request {
+            fen: fen.to_string(),
+            limits,
+            reply,
+            updates,
+            lines: lines.max(1),
+        };
+        if let Err(mpsc::SendError(request)) = self.requests.as_ref().unwrap().send(request) {
+            let _ = request.reply.send(Err(anyhow!("The engine has stopped")));
+        }
+        res
+    }
+
+    /// Finish the current search early, it still answers with the best it found
+    pub fn stop(&self) -> anyhow::Result<()> {
+        send(&self.stdin, "stop")
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.requests = None;
+        let _ = send(&self.stdin, "quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn send(stdin: &Mutex<ChildStdin>, command: &str) -> anyhow::Result<()> {
+    debug!("To engine: {}", command);
+    let mut stdin = stdin.lock().unwrap();
+    writeln!(stdin, "{}", command)
+        .and_then(|_| stdin.flush())
+        .map_err(|e: io::Error| anyhow!("Couldn't talk to the engine: {}", e))
+}
+
+/// Answer requests one at a time until the engine or `UciEngine` goes away
+fn run(stdin: Arc<Mutex<ChildStdin>>, lines: Receiver<String>, requests: Receiver<Request>) {
+    let mut chess960 = false;
+    for request in requests {
+        let res = send(
+            &stdin,
+            &format!("setoption name MultiPV value {}", request.lines),
+        )
+        .and_then(|_| {
+            // Only changed when it has to be, so engines without it never see it for normal games
+            if is_chess960(&request.fen) != chess960 {
+                chess960 = !chess960;
+                send(
+                    &stdin,
+                    &format!("setoption name UCI_Chess960 value {}", chess960),
+                )?;
+            }
+            search(
+                &stdin,
+                &lines,
+                &request.fen,
+                request.limits,
+                request.updates.as_ref(),
+            )
+        });
+        if let Err(e) = res.as_ref() {
+            error!("Engine search failed: {}", e);
+        }
+        // Whoever asked might not be waiting any more
+        let _ = request.reply.send(res);
+    }
+}
+
+/// Castling rights that can't be written the normal way need the engine told it's Chess960, it
+/// then writes castling as the king taking the rook
+fn is_chess960(fen: &str) -> bool {
+    Fen::from_ascii(fen.as_bytes())
+        .is_ok_and(|x| CastlingMode::detect(x.as_setup()) == CastlingMode::Chess960)
+}
+
+fn search(
+    stdin: &Mutex<ChildStdin>,
+    lines: &Receiver<String>,
+    fen: &str,
+    limits: Limits,
+    updates: Option<&Sender<Eval>>,
+) -> anyhow::Result<Eval> {
+    send(stdin, &format!("position fen {}", fen))?;
+    send(stdin, &limits.to_uci())?;
+    let mut eval: Option<Eval> = None;
+    for line in lines.iter() {
+        if let Some(info) = parse_info(&line) {
+            if let Some(updates) = updates {
+                let _ = updates.send(info.clone());
+            }
+            if info.multipv == 1 {
+                eval = Some(info);
+            }
+        } else if let Some(best) = line.strip_prefix("bestmove") {
+            let mut eval = eval.context("The engine didn't give a score")?;
+            let best = best.split_whitespace().next().filter(|x| *x != "(none)");
+            if let (Some(best), true) = (best, eval.pv.is_empty()) {
+                eval.pv.push(best.to_string());
+            }
+            return Ok(eval);
+        }
+    }
+    bail!("The engine quit")
+}
+
+/// Read the score out of an `info` line, ones without a score or that are only a bound are skipped
+fn parse_info(line: &str) -> Option<Eval> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("info") {
+        return None;
+    }
+    let mut depth = 0;
+    let mut multipv = 1;
+    let mut score = None;
+    let mut wdl = None;
+    let mut pv = vec![];
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => depth = tokens.next()?.parse().ok()?,
+            "score" => {
+                score = match (tokens.next()?, tokens.next()?.parse().ok()?) {
+                    ("cp", x) => Some(Score::Cp(x)),
+                    ("mate", x) => Some(Score::Mate(x)),
+                    _ => None,
+                }
+            }
+            "wdl" => {
+                wdl = Some([
+                    tokens.next()?.parse().ok()?,
+                    tokens.next()?.parse().ok()?,
+                    tokens.next()?.parse().ok()?,
+                ])
+            }
+            "lowerbound" | "upperbound" => return None,
+            "multipv" => multipv = tokens.next()?.parse().ok()?,
+            "pv" => pv = tokens.by_ref().map(|x| x.to_string()).collect(),
+            _ => {}
+        }
+    }
+    Some(Eval {
+        score: score?,
+        wdl,
+        depth,
+        pv,
+        multipv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_engine_output() {
+        let eval = parse_info(
+            "info depth 12 seldepth 16 multipv 1 score cp -35 nodes 40960 nps 819200 pv e7e5 g1f3",
+        )
+        .unwrap();
+        assert_eq!(eval.score, Score::Cp(-35));
+        assert_eq!(eval.depth, 12);
+        assert_eq!(eval.best_move(), Some("e7e5"));
+        assert_eq!(eval.result(), Wdl::Draw);
+
+        let won =
+            parse_info("info depth 30 score cp 20000 wdl 1000 0 0 tbhits 5 pv h7h8q").unwrap();
+        assert_eq!(won.wdl, Some([1000, 0, 0]));
+        assert_eq!(won.result(), Wdl::Win);
+        assert_eq!(won.result().flip(), Wdl::Loss);
+
+        let mate = parse_info("info depth 5 score mate -2 pv a1a2").unwrap();
+        assert_eq!(mate.score, Score::Mate(-2));
+        assert_eq!(mate.multipv, 1);
+        let second = parse_info("info depth 12 multipv 2 score cp -50 pv d7d5").unwrap();
+        assert_eq!(second.multipv, 2);
+        assert!(parse_info("info depth 10 score cp 20 lowerbound pv e2e4").is_none());
+        assert!(parse_info("info string NNUE enabled").is_none());
+
+        let limits = Limits {
+            depth: Some(20),
+            movetime: Some(500),
+            nodes: None,
+        };
+        assert_eq!(limits.to_uci(), "go depth 20 movetime 500");
+        assert_eq!(Limits::default().to_uci(), "go infinite");
+    }
+}
@@ -0,0 +1,81 @@
+//! Works out why the repertoire's move is better than a wrong one played in a drill, so there's
+//! something to learn from the mistake.
+use crate::engine::{Limits, Score, UciEngine};
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Chess, EnPassantMode, Position};
+
+/// A wrong move in a drill, waiting for the engine to look at it
+#[derive(Clone, Debug)]
+pub struct WrongMove {
+    /// Where the move was played
+    pub position: Chess,
+    pub played: SanPlus,
+    /// What the repertoire has instead
+    pub expected: Vec<SanPlus>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Explanation {
+    pub played: String,
+    /// The repertoire's move the engine likes best
+    pub expected: String,
+    /// For the player
+    pub played_eval: Score,
+    pub expected_eval: Score,
+    /// Centipawns the wrong move gives away, it can be negative if the engine prefers it
+    pub loss: i32,
+    /// The engine's best line after the wrong move, starting with the opponent's reply
+    pub refutation: Vec<String>,
+}
+
+/// Search what the position comes to after `san`, for the side that played it
+fn after(
+    engine: &UciEngine,
+    position: &Chess,
+    san: &SanPlus,
+    limits: Limits,
+) -> anyhow::Result<(Score, Vec<String>)> {
+    let mv = san.san.to_move(position)?;
+    let after = position.clone().play(&mv)?;
+    // Mate can't be beaten, whatever the number
+    if after.is_checkmate() {
+        return Ok((Score::Mate(1), vec![]));
+    }
+    if after.is_game_over() {
+        return Ok((Score::Cp(0), vec![]));
+    }
+    let fen = Fen::from_position(after.clone(), EnPassantMode::Legal).to_string();
+    let eval = engine
+        .analyze(&fen, limits)
+        .recv()
+        .map_err(|_| anyhow!("The engine stopped"))??;
+    Ok((eval.score.flip(), eval.san_line(&after)))
+}
+
+/// Compare the wrong move with the best of the repertoire's
+pub fn explain(
+    engine: &UciEngine,
+    wrong: &WrongMove,
+    limits: Limits,
+) -> anyhow::Result<Explanation> {
+    let (played_eval, refutation) = after(engine, &wrong.position, &wrong.played, limits)?;
+    let mut best: Option<(&SanPlus, Score)> = None;
+    for san in &wrong.expected {
+        let (eval, _) = after(engine, &wrong.position, san, limits)?;
+        if best.is_none_or(|(_, x)| eval.centipawns() > x.centipawns()) {
+            best = Some((san, eval));
+        }
+    }
+    let (expected, expected_eval) = best.context("The repertoire has no move here")?;
+    Ok(Explanation {
+        played: wrong.played.to_string(),
+        expected: expected.to_string(),
+        played_eval,
+        expected_eval,
+        loss: expected_eval.centipawns() - played_eval.centipawns(),
+        refutation,
+    })
+}
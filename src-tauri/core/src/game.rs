@@ -0,0 +1,526 @@
+//! Keeps track of the positions in the current game so we can step back through them, and
+//! wander off into the other moves the prep has at each point.
+use crate::annotate::Annotation;
+use crate::db::{find_node, next_moves, OpeningGraph};
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::{
+    san::SanPlus, Bitboard, Board, CastlingMode, Chess, Color, EnPassantMode, Outcome, Position,
+    Square,
+};
+
+#[derive(Clone)]
+struct Ply {
+    /// Move that reached this position, `None` for the starting position
+    san: Option<SanPlus>,
+    position: Chess,
+}
+
+/// Rules that draw a game before anyone runs out of moves
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawRule {
+    /// The same position three times with the same side to move
+    Repetition,
+    /// Fifty moves each without a capture or a pawn move
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+/// A position from a FEN. Chess960 castling rights, like `HAha` or ones for rooks that aren't in
+/// the corners, are worked out from the FEN unless `chess960` says it's from a Chess960 game.
+pub fn parse_position(fen: &str, chess960: bool) -> anyhow::Result<Chess> {
+    let fen = Fen::from_ascii(fen.trim().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid FEN: {}", e))?;
+    let mode = match chess960 {
+        true => CastlingMode::Chess960,
+        false => CastlingMode::detect(fen.as_setup()),
+    };
+    fen.into_position(mode)
+        .map_err(|e| anyhow::anyhow!("Invalid position: {}", e))
+}
+
+/// The Chess960 starting position with that number, 518 is the normal one. The bishops, queen
+/// and knights go on the back rank in turn by what's left of the number, then the rooks either
+/// side of the king.
+pub fn chess960_start(number: u32) -> anyhow::Result<Chess> {
+    if number >= 960 {
+        anyhow::bail!("Chess960 positions go from 0 to 959, not {}", number);
+    }
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let mut rank = [None; 8];
+    let mut n = number as usize;
+    rank[n % 4 * 2 + 1] = Some('b');
+    n /= 4;
+    rank[n % 4 * 2] = Some('b');
+    n /= 4;
+    let empty =
+        |rank: &[Option<char>; 8]| (0..8).filter(|x| rank[*x].is_none()).collect::<Vec<_>>();
+    rank[empty(&rank)[n % 6]] = Some('q');
+    n /= 6;
+    let (a, b) = KNIGHTS[n];
+    let squares = empty(&rank);
+    rank[squares[a]] = Some('n');
+    rank[squares[b]] = Some('n');
+    for (square, piece) in empty(&rank).into_iter().zip(['r', 'k', 'r']) {
+        rank[square] = Some(piece);
+    }
+    let black = rank.iter().flatten().collect::<String>();
+    parse_position(
+        &format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+            black,
+            black.to_uppercase()
+        ),
+        true,
+    )
+}
+
+/// The moves of a PGN with their numbers and annotations, ending with the result
+pub(crate) fn movetext(moves: &[SanPlus], annotations: &[Annotation], result: &str) -> String {
+    let mut pgn = String::new();
+    let mut commented = false;
+    for (i, san) in moves.iter().enumerate() {
+        if i % 2 == 0 {
+            pgn.push_str(&format!("{}. ", i / 2 + 1));
+        } else if commented {
+            // Black's move needs its number again after a comment
+            pgn.push_str(&format!("{}... ", i / 2 + 1));
+        }
+        let annotation = annotations.get(i).map(|x| x.to_pgn()).unwrap_or_default();
+        commented = annotation.contains('{');
+        pgn.push_str(&format!("{}{} ", san, annotation));
+    }
+    pgn.push_str(result);
+    pgn.push('\n');
+    pgn
+}
+
+/// What has to match for positions to count as repeated, the move counters don't
+fn repetition_key(position: &Chess) -> (Board, Color, Bitboard, Option<Square>) {
+    (
+        position.board().clone(),
+        position.turn(),
+        position.castles().castling_rights(),
+        position.ep_square(EnPassantMode::Legal),
+    )
+}
+
+#[derive(Clone)]
+pub struct MoveHistory {
+    /// Positions actually played, the first is the starting position
+    played: Vec<Ply>,
+    /// Line being looked at, this is the same as `played` unless we've switched to a variation
+    line: Vec<Ply>,
+    /// Index into `line` of the position being looked at
+    cursor: usize,
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::from_position(Chess::new())
+    }
+}
+
+impl MoveHistory {
+    /// Start a game from a position set up from a FEN
+    pub fn from_position(position: Chess) -> Self {
+        let start = vec![Ply {
+            san: None,
+            position,
+        }];
+        Self {
+            played: start.clone(),
+            line: start,
+            cursor: 0,
+        }
+    }
+
+    /// If the game started from `start`, where the repertoire's lines go from. Drills and the
+    /// prep only work from there.
+    pub fn from_start(&self, start: &Chess) -> bool {
+        self.played[0].position == *start
+    }
+
+    /// Record a move played in the game, this also jumps back to the latest position.
+    pub fn push(&mut self, san: SanPlus, position: Chess) {
+        self.played.push(Ply {
+            san: Some(san),
+            position,
+        });
+        self.latest();
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Take back the last move made by `player` along with everything played after it. Returns
+    /// false if they've not made a move.
+    pub fn take_back(&mut self, player: Color) -> bool {
+        // The position before a move has the mover to play
+        let last = self
+            .played
+            .iter()
+            .rposition(|x| x.san.is_some() && x.position.turn() != player);
+        match last {
+            Some(ply) => {
+                self.played.truncate(ply);
+                self.latest();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take back the very last move, whoever played it
+    pub fn pop(&mut self) -> Option<SanPlus> {
+        if self.played.len() == 1 {
+            return None;
+        }
+        let ply = self.played.pop();
+        self.latest();
+        ply.and_then(|x| x.san)
+    }
+
+    /// Moves actually played in the game
+    pub fn moves(&self) -> Vec<SanPlus> {
+        self.played.iter().filter_map(|x| x.san.clone()).collect()
+    }
+
+    /// Moves in the line being looked at along with how far through it we are, the cursor is the
+    /// number of moves played to reach the position shown.
+    pub fn line(&self) -> (Vec<SanPlus>, usize) {
+        let moves = self.line.iter().filter_map(|x| x.san.clone()).collect();
+        (moves, self.cursor)
+    }
+
+    /// The rule drawing the game at its latest position, if one does. Repetition and fifty moves
+    /// only let a player claim the draw, here they end the game like a claim would.
+    pub fn draw_rule(&self) -> Option<DrawRule> {
+        let game = self.game();
+        if game.is_checkmate() {
+            return None;
+        }
+        if game.is_insufficient_material() {
+            return Some(DrawRule::InsufficientMaterial);
+        }
+        if game.halfmoves() >= 100 {
+            return Some(DrawRule::FiftyMoves);
+        }
+        let key = repetition_key(game);
+        let seen = self
+            .played
+            .iter()
+            .filter(|x| repetition_key(&x.position) == key)
+            .count();
+        (seen >= 3).then_some(DrawRule::Repetition)
+    }
+
+    /// How the game ended, if it has, counting the draw rules
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.game()
+            .outcome()
+            .or_else(|| self.draw_rule().map(|_| Outcome::Draw))
+    }
+
+    /// Position the game started from
+    pub fn start(&self) -> &Chess {
+        &self.played[0].position
+    }
+
+    /// The game played so far written out as a PGN
+    pub fn to_pgn(&self) -> String {
+        self.to_annotated_pgn(&[])
+    }
+
+    /// The game written out as a PGN with a move's annotation after it, moves past the end of
+    /// `annotations` don't get any
+    pub fn to_annotated_pgn(&self, annotations: &[Annotation]) -> String {
+        let result = self
+            .outcome()
+            .map(|x| x.to_string())
+            .unwrap_or("*".to_string());
+        let mut pgn = format!(
+            "[Event \"chess-driller\"]\n[Date \"{}\"]\n[Result \"{}\"]\n",
+            chrono::Local::now().format("%Y.%m.%d"),
+            result
+        );
+        if self.start().castles().mode() == CastlingMode::Chess960 {
+            pgn += "[Variant \"Chess960\"]\n";
+        }
+        if *self.start() != Chess::new() {
+            let fen = Fen::from_position(self.start().clone(), EnPassantMode::Legal);
+            pgn += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen);
+        }
+        pgn.push('\n');
+        pgn + &movetext(&self.moves(), annotations, &result)
+    }
+
+    /// Latest position in the game
+    pub fn game(&self) -> &Chess {
+        &self.played[self.played.len() - 1].position
+    }
+
+    /// Position being looked at
+    pub fn current(&self) -> &Chess {
+        &self.line[self.cursor].position
+    }
+
+    pub fn is_latest(&self) -> bool {
+        self.cursor + 1 == self.played.len() && self.on_played_line()
+    }
+
+    pub fn latest(&mut self) {
+        self.line = self.played.clone();
+        self.cursor = self.line.len() - 1;
+    }
+
+    pub fn back(&mut self) -> bool {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Step forward through the line. Once past the end of a variation this carries on with the
+    /// first move the prep has.
+    pub fn forward(&mut self, openings: &OpeningGraph) -> bool {
+        if self.cursor + 1 < self.line.len() {
+            self.cursor += 1;
+            return true;
+        }
+        if self.on_played_line() && self.line.len() < self.played.len() {
+            self.line.push(self.played[self.line.len()].clone());
+            self.cursor += 1;
+            return true;
+        }
+        let node = find_node(openings, &self.sans(self.line.len()));
+        if node.is_none() && self.line.len() > 1 {
+            // Out of the prep there's nothing to follow
+            return false;
+        }
+        match next_moves(openings, node).first() {
+            Some(next) => {
+                let san = openings[*next].clone();
+                self.play_in_line(san)
+            }
+            None => false,
+        }
+    }
+
+    /// Swap the move that reached the current position for another move the prep has from the
+    /// previous position. `offset` is how far through the alternatives to move.
+    pub fn sibling(&mut self, openings: &OpeningGraph, offset: isize) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        let parent = find_node(openings, &self.sans(self.cursor));
+        if parent.is_none() && self.cursor > 1 {
+            return false;
+        }
+        let siblings = next_moves(openings, parent);
+        if siblings.is_empty() {
+            return false;
+        }
+        let current = self.line[self.cursor].san.as_ref();
+        let index = siblings
+            .iter()
+            .position(|x| Some(&openings[*x]) == current)
+            .map(|x| x as isize)
+            .unwrap_or(-offset.signum());
+        let next = (index + offset).rem_euclid(siblings.len() as isize) as usize;
+        let san = openings[siblings[next]].clone();
+        if Some(&san) == current {
+            return false;
+        }
+        self.line.truncate(self.cursor);
+        self.cursor -= 1;
+        self.play_in_line(san)
+    }
+
+    fn play_in_line(&mut self, san: SanPlus) -> bool {
+        let position = self.line[self.cursor].position.clone();
+        let Ok(mv) = san.san.to_move(&position) else {
+            return false;
+        };
+        let Ok(position) = position.play(&mv) else {
+            return false;
+        };
+        self.line.truncate(self.cursor + 1);
+        self.line.push(Ply {
+            san: Some(san),
+            position,
+        });
+        self.cursor += 1;
+        true
+    }
+
+    /// The moves leading up to the ply `end` in the line
+    fn sans(&self, end: usize) -> Vec<SanPlus> {
+        self.line[1..end]
+            .iter()
+            .filter_map(|x| x.san.clone())
+            .collect()
+    }
+
+    fn on_played_line(&self) -> bool {
+        self.line.len() <= self.played.len()
+            && self
+                .line
+                .iter()
+                .zip(self.played.iter())
+                .all(|(a, b)| a.san == b.san)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::db::OpeningDatabase;
+    use crate::engine::Score;
+    use shakmaty::Color;
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn play(history: &mut MoveHistory, moves: &[&str]) {
+        for m in moves {
+            let san = SanPlus::from_ascii(m.as_bytes()).unwrap();
+            let mv = san.san.to_move(history.current()).unwrap();
+            let position = history.current().clone().play(&mv).unwrap();
+            history.push(san, position);
+        }
+    }
+
+    #[test]
+    fn browse_history_and_variations() {
+        let db = OpeningDatabase::load(Path::new("../prep")).unwrap();
+        let openings = db.graph(Color::Black);
+
+        let mut history = MoveHistory::default();
+        play(&mut history, &["e4", "c6"]);
+        let latest = history.current().clone();
+        assert!(history.is_latest());
+
+        assert!(history.back());
+        assert!(history.back());
+        assert!(!history.back());
+        assert_eq!(history.current(), &Chess::new());
+
+        assert!(history.forward(openings));
+        assert!(!history.is_latest());
+
+        // Root moves in the black prep are 1. e4 and 1. d4
+        assert!(history.sibling(openings, 1));
+        assert!(history.forward(openings));
+        let d4_d5 = history.current().clone();
+        assert_ne!(d4_d5, latest);
+        assert_eq!(d4_d5.fullmoves().get(), 2);
+
+        history.latest();
+        assert!(history.is_latest());
+        assert_eq!(history.current(), &latest);
+    }
+
+    #[test]
+    fn take_back_players_move() {
+        let mut history = MoveHistory::default();
+        play(&mut history, &["e4", "c6", "d4", "d5"]);
+
+        // Black takes back d5, white takes back d4 and the reply to it
+        assert!(history.take_back(Color::Black));
+        assert_eq!(history.moves().len(), 3);
+        assert!(history.take_back(Color::White));
+        assert_eq!(history.moves().len(), 2);
+        assert_eq!(history.game(), history.current());
+
+        assert_eq!(history.pop(), Some(SanPlus::from_ascii(b"c6").unwrap()));
+        assert!(!history.take_back(Color::Black));
+        assert!(history.pop().is_some());
+        assert!(history.pop().is_none());
+    }
+
+    #[test]
+    fn chess960_starting_positions() {
+        assert_eq!(chess960_start(518).unwrap().board(), Chess::new().board());
+        let position = chess960_start(0).unwrap();
+        assert_eq!(
+            position.board().to_string(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR"
+        );
+        assert_eq!(position.castles().mode(), CastlingMode::Chess960);
+        assert!(chess960_start(960).is_err());
+
+        // Shredder-FEN castling rights are worked out without being told it's Chess960
+        let position = parse_position(
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1",
+            false,
+        )
+        .unwrap();
+        assert_eq!(position.castles().mode(), CastlingMode::Chess960);
+        assert!(parse_position("not a fen", false).is_err());
+    }
+
+    #[test]
+    fn draw_by_the_rules() {
+        let mut history = MoveHistory::default();
+        play(
+            &mut history,
+            &["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1"],
+        );
+        assert_eq!(history.draw_rule(), None);
+        play(&mut history, &["Ng8"]);
+        assert_eq!(history.draw_rule(), Some(DrawRule::Repetition));
+        assert!(history.to_pgn().ends_with("1/2-1/2\n"));
+
+        let fen = |x: &str| {
+            shakmaty::fen::Fen::from_ascii(x.as_bytes())
+                .unwrap()
+                .into_position::<Chess>(shakmaty::CastlingMode::Standard)
+                .unwrap()
+        };
+        let mut history = MoveHistory::from_position(fen("8/8/4k3/8/8/8/2R5/4K3 w - - 99 80"));
+        assert_eq!(history.draw_rule(), None);
+        play(&mut history, &["Rc3"]);
+        assert_eq!(history.draw_rule(), Some(DrawRule::FiftyMoves));
+        let history = MoveHistory::from_position(fen("8/8/4k3/8/8/8/2N5/4K3 w - - 0 1"));
+        assert_eq!(history.draw_rule(), Some(DrawRule::InsufficientMaterial));
+    }
+
+    #[test]
+    fn write_pgn() {
+        let mut history = MoveHistory::default();
+        play(&mut history, &["e4", "c6", "d4"]);
+        let pgn = history.to_pgn();
+        assert!(pgn.starts_with("[Event \"chess-driller\"]"));
+        assert!(pgn.ends_with("\n\n1. e4 c6 2. d4 *\n"));
+
+        let annotations = [
+            Annotation {
+                eval: Some(Score::Cp(30)),
+                nag: None,
+                clock: Clock {
+                    left: Some(Duration::from_secs(298)),
+                    spent: None,
+                },
+            },
+            Annotation::default(),
+        ];
+        let pgn = history.to_annotated_pgn(&annotations);
+        assert!(pgn.ends_with("\n\n1. e4 { [%eval 0.30] [%clk 0:04:58] } 1... c6 2. d4 *\n"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Finds where opponents have taken the player out of their repertoire in real games, so the
+//! holes that actually come up can be filled first.
+use crate::db::{count_lines, next_moves, OpeningGraph};
+use crate::engine::{Limits, Score, UciEngine};
+use crate::graph::NodeIndex;
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Color, EnPassantMode, Position};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Gap {
+    /// Moves from the start up to and including the opponent's move the repertoire doesn't have
+    pub moves: Vec<String>,
+    /// How many of the player's games went this way, games that went exactly the same way
+    /// all the way through only count once
+    pub games: usize,
+}
+
+/// Compare the player's games with their repertoire, returning the opponent moves the
+/// repertoire doesn't cover, most played first. Lines where the repertoire has run out or the
+/// player left it themselves aren't gaps.
+pub fn find_gaps(repertoire: &OpeningGraph, games: &OpeningGraph, player: Color) -> Vec<Gap> {
+    let mut gaps = vec![];
+    let mut stack: Vec<(Option<NodeIndex>, Option<NodeIndex>, Vec<String>)> =
+        vec![(None, None, vec![])];
+    while let Some((prep, game, moves)) = stack.pop() {
+        let covered = next_moves(repertoire, prep);
+        if covered.is_empty() {
+            continue;
+        }
+        let turn = Color::from_white(moves.len() % 2 == 0);
+        for next in next_moves(games, game) {
+            let mut line = moves.clone();
+            line.push(games[next].to_string());
+            match covered.iter().find(|x| repertoire[**x] == games[next]) {
+                Some(prep) => stack.push((Some(*prep), Some(next), line)),
+                None if turn != player => gaps.push(Gap {
+                    moves: line,
+                    games: count_lines(games, next),
+                }),
+                None => {}
+            }
+        }
+    }
+    gaps.sort_by(|a, b| b.games.cmp(&a.games).then(a.moves.cmp(&b.moves)));
+    gaps
+}
+
+/// What the engine would play against a gap
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub reply: String,
+    /// For the player
+    pub score: Score,
+}
+
+/// Ask the engine for the player's best reply in the position at the end of the gap
+pub fn suggest(engine: &UciEngine, gap: &Gap, limits: Limits) -> anyhow::Result<Suggestion> {
+    let mut position = Chess::new();
+    for san in &gap.moves {
+        let mv = SanPlus::from_str(san)?.san.to_move(&position)?;
+        position.play_unchecked(&mv);
+    }
+    let fen = Fen::from_position(position.clone(), EnPassantMode::Legal).to_string();
+    let eval = engine
+        .analyze(&fen, limits)
+        .recv()
+        .map_err(|_| anyhow!("The engine stopped"))??;
+    let best = eval
+        .best_move()
+        .context("The engine didn't give a move")?
+        .parse::<Uci>()?
+        .to_move(&position)?;
+    Ok(Suggestion {
+        reply: SanPlus::from_move(position, &best).to_string(),
+        score: eval.score,
+    })
+}
+
+/// Where lines filling gaps are written, a `gaps.pgn` chapter if the repertoire starts with a
+/// folder otherwise the first file in it
+pub fn gap_file(repertoire: &[PathBuf]) -> Option<PathBuf> {
+    let first = repertoire.first()?;
+    if first.is_file() {
+        Some(first.clone())
+    } else {
+        Some(first.join("gaps.pgn"))
+    }
+}
+
+/// Add a line to the end of a prep file as a game of its own
+pub fn append_line(path: &Path, moves: &[String]) -> anyhow::Result<String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let pgn = line_pgn(moves);
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() > 0 {
+        writeln!(file)?;
+    }
+    file.write_all(pgn.as_bytes())?;
+    Ok(pgn)
+}
+
+/// A line written out as a PGN game to add to the repertoire
+pub fn line_pgn(moves: &[String]) -> String {
+    let mut pgn = String::from("[Event \"Filled gap\"]\n\n");
+    for (i, san) in moves.iter().enumerate() {
+        if i % 2 == 0 {
+            pgn.push_str(&format!("{}. ", i / 2 + 1));
+        }
+        pgn.push_str(&format!("{} ", san));
+    }
+    pgn.push_str("*\n");
+    pgn
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn find_uncovered_replies() {
+        let mut db = OpeningDatabase::default();
+        db.add_prep(Color::White, "1. e4 e5 2. Nf3 Nc6 3. Bb5 *".as_bytes())
+            .unwrap();
+        let games = [
+            "1. e4 c5 2. Nf3 d6 *",
+            "1. e4 c5 2. Nc3 *",
+            "1. e4 e5 2. Nf3 d6 3. d4 *",
+            // Left the prep themselves so it isn't a gap
+            "1. d4 d5 *",
+            // Ran out of prep
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 *",
+        ];
+        let mut played = OpeningDatabase::default();
+        for game in games {
+            played.add_prep(Color::White, game.as_bytes()).unwrap();
+        }
+
+        let gaps = find_gaps(
+            db.graph(Color::White),
+            played.graph(Color::White),
+            Color::White,
+        );
+        assert_eq!(
+            gaps,
+            vec![
+                Gap {
+                    moves: vec!["e4".to_string(), "c5".to_string()],
+                    games: 2
+                },
+                Gap {
+                    moves: ["e4", "e5", "Nf3", "d6"].map(|x| x.to_string()).to_vec(),
+                    games: 1
+                },
+            ]
+        );
+        assert_eq!(
+            line_pgn(&gaps[0].moves),
+            "[Event \"Filled gap\"]\n\n1. e4 c5 *\n"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! The tree of moves the prep and downloaded games are read into. A few years of games make for a
+//! lot of nodes so each is kept small: its move is a number into a table of the different moves
+//! the tree has, as each one gets played over and over, and the children of a node are a run in
+//! one array shared by the whole tree rather than edges of their own.
+use shakmaty::san::SanPlus;
+use std::collections::HashMap;
+use std::ops::{Index, Range};
+
+/// A node in an [`OpeningGraph`], only meaningful for the graph it came from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeIndex(u32);
+
+/// Parent of the first moves
+const NO_PARENT: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    parent: u32,
+    /// Where its children start in `children`
+    start: u32,
+    len: u16,
+    /// Room in `children` from `start`, more than `len` while it's still being added to
+    room: u16,
+    /// In `moves`, 32 bits takes no more room than 16 as the node's padded out to that anyway
+    san: u32,
+}
+
+impl Node {
+    fn children(&self) -> Range<usize> {
+        self.start as usize..self.start as usize + self.len as usize
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OpeningGraph {
+    nodes: Vec<Node>,
+    /// Every child of every node, a node's are next to each other in the order they were added
+    children: Vec<NodeIndex>,
+    roots: Vec<NodeIndex>,
+    /// Each different move once
+    moves: Vec<SanPlus>,
+    ids: HashMap<SanPlus, u32>,
+}
+
+impl OpeningGraph {
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The first moves in the order they were added
+    pub fn roots(&self) -> &[NodeIndex] {
+        &self.roots
+    }
+
+    /// Moves following a node, the last added first
+    pub fn children(&self, node: NodeIndex) -> impl DoubleEndedIterator<Item = NodeIndex> + '_ {
+        self.children[self.node(node).children()]
+            .iter()
+            .rev()
+            .copied()
+    }
+
+    pub fn parent(&self, node: NodeIndex) -> Option<NodeIndex> {
+        let parent = self.node(node).parent;
+        (parent != NO_PARENT).then_some(NodeIndex(parent))
+    }
+
+    /// The move `san` after `parent`, or as a first move for none
+    pub fn find_child(&self, parent: Option<NodeIndex>, san: &SanPlus) -> Option<NodeIndex> {
+        let id = *self.ids.get(san)?;
+        let siblings = match parent {
+            Some(parent) => &self.children[self.node(parent).children()],
+            None => &self.roots,
+        };
+        siblings.iter().copied().find(|x| self.node(*x).san == id)
+    }
+
+    /// Add `san` after `parent`, or as a first move for none
+    pub fn add_node(&mut self, san: SanPlus, parent: Option<NodeIndex>) -> NodeIndex {
+        let san = self.intern(san);
+        let added =
+            NodeIndex(u32::try_from(self.nodes.len()).expect("Too many moves in the graph"));
+        self.nodes.push(Node {
+            parent: parent.map_or(NO_PARENT, |x| x.0),
+            start: 0,
+            len: 0,
+            room: 0,
+            san,
+        });
+        match parent {
+            Some(parent) => self.add_child(parent, added),
+            None => self.roots.push(added),
+        }
+        added
+    }
+
+    /// Pack the children up with no room left between them, for when it's done being added to
+    pub fn compact(&mut self) {
+        let mut children = Vec::with_capacity(self.nodes.iter().map(|x| x.len as usize).sum());
+        for node in &mut self.nodes {
+            let start = children.len() as u32;
+            children.extend_from_slice(&self.children[node.children()]);
+            node.start = start;
+            node.room = node.len;
+        }
+        self.children = children;
+        self.nodes.shrink_to_fit();
+        self.roots.shrink_to_fit();
+    }
+
+    fn node(&self, node: NodeIndex) -> &Node {
+        &self.nodes[node.0 as usize]
+    }
+
+    fn intern(&mut self, san: SanPlus) -> u32 {
+        if let Some(id) = self.ids.get(&san) {
+            return *id;
+        }
+        let id = u32::try_from(self.moves.len()).expect("Too many different moves in the graph");
+        self.moves.push(san.clone());
+        self.ids.insert(san, id);
+        id
+    }
+
+    fn add_child(&mut self, parent: NodeIndex, child: NodeIndex) {
+        let end = self.children.len();
+        let node = &mut self.nodes[parent.0 as usize];
+        if node.len == node.room {
+            let room = node.room.max(1);
+            if node.start as usize + node.room as usize == end {
+                // Already at the end so there's space right after it, true of most nodes as a
+                // line gets read in one move after another
+                node.room += room;
+            } else {
+                // Moved to the end with room for as many again, what's left behind goes when
+                // it's compacted
+                let range = node.children();
+                node.start = end as u32;
+                node.room = node.len + room;
+                self.children.extend_from_within(range);
+            }
+            let size = node.start as usize + node.room as usize;
+            self.children.resize(size, NodeIndex::default());
+        }
+        let node = &mut self.nodes[parent.0 as usize];
+        self.children[node.start as usize + node.len as usize] = child;
+        node.len += 1;
+    }
+}
+
+impl Index<NodeIndex> for OpeningGraph {
+    type Output = SanPlus;
+
+    fn index(&self, node: NodeIndex) -> &SanPlus {
+        &self.moves[self.node(node).san as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn san(x: &str) -> SanPlus {
+        SanPlus::from_str(x).unwrap()
+    }
+
+    #[test]
+    fn more_moves_than_fit_in_16_bits() {
+        let mut graph = OpeningGraph::default();
+        let files = "abcdefgh".chars();
+        let squares = files
+            .flat_map(|f| (1..=8).map(move |r| format!("{}{}", f, r)))
+            .collect::<Vec<_>>();
+        // Every piece from every square to every square, taking or not and with or without check,
+        // is a lot more than 65536 ways of writing a move
+        let mut last = NodeIndex::default();
+        for piece in ["N", "B", "R", "Q"] {
+            for from in &squares {
+                for to in &squares {
+                    for takes in ["", "x"] {
+                        for suffix in ["", "+", "#"] {
+                            let written = format!("{}{}{}{}{}", piece, from, takes, to, suffix);
+                            last = graph.add_node(san(&written), None);
+                        }
+                    }
+                }
+            }
+        }
+        assert!(graph.moves.len() > u16::MAX as usize);
+        assert_eq!(graph[last], san("Qh8xh8#"));
+        assert_eq!(std::mem::size_of::<Node>(), 16);
+    }
+
+    #[test]
+    fn build_and_compact() {
+        let mut graph = OpeningGraph::default();
+        let e4 = graph.add_node(san("e4"), None);
+        let d4 = graph.add_node(san("d4"), None);
+        let e5 = graph.add_node(san("e5"), Some(e4));
+        let nf3 = graph.add_node(san("Nf3"), Some(e5));
+        // Children added after the node's run stopped being at the end have to move
+        let c5 = graph.add_node(san("c5"), Some(e4));
+        let d5 = graph.add_node(san("d5"), Some(d4));
+        let e6 = graph.add_node(san("e6"), Some(e4));
+        let nf3_d5 = graph.add_node(san("Nf3"), Some(d5));
+        let check = |graph: &OpeningGraph| {
+            assert_eq!(graph.node_count(), 8);
+            assert_eq!(graph.roots(), &[e4, d4]);
+            assert_eq!(graph.children(e4).collect::<Vec<_>>(), vec![e6, c5, e5]);
+            assert_eq!(graph.children(d5).collect::<Vec<_>>(), vec![nf3_d5]);
+            assert_eq!(graph.children(nf3).count(), 0);
+            assert_eq!(graph.parent(nf3), Some(e5));
+            assert_eq!(graph.parent(d4), None);
+            assert_eq!(graph[nf3_d5], san("Nf3"));
+            assert_eq!(graph.find_child(Some(e4), &san("c5")), Some(c5));
+            assert_eq!(graph.find_child(None, &san("d4")), Some(d4));
+            assert_eq!(graph.find_child(Some(d4), &san("e5")), None);
+        };
+        check(&graph);
+        // Both Nf3 are the same move in the table
+        assert_eq!(graph.moves.len(), 7);
+        assert!(graph.children.len() > 7);
+        graph.compact();
+        check(&graph);
+        assert_eq!(graph.children.len(), 6);
+    }
+}
@@ -0,0 +1,241 @@
+//! Programs from the config that are run at points in a drill, so how lines are picked and moves
+//! are scored can be changed without changing chess-driller. Each one is given JSON on stdin and
+//! can answer with JSON on stdout, printing nothing leaves things as they were.
+use crate::db::MoveAssessment;
+use crate::schedule::LineSchedule;
+use crate::stats::Attempt;
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// How long a hook gets before it's killed, they hold up the drill while they run
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the `select_line` hook is given
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LineChoice {
+    pub color: String,
+    pub chapter: Option<String>,
+    /// Moves played before the drill started, the line has to start with these
+    pub moves: Vec<String>,
+    pub lines: Vec<DueLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DueLine {
+    pub line: Vec<String>,
+    /// YYYY-MM-DD
+    pub due: String,
+    pub interval: f64,
+    pub reviews: u32,
+}
+
+impl From<&LineSchedule> for DueLine {
+    fn from(schedule: &LineSchedule) -> Self {
+        Self {
+            line: schedule.line.clone(),
+            due: schedule.due.to_string(),
+            interval: schedule.interval,
+            reviews: schedule.reviews,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct PickedLine {
+    line: Vec<String>,
+}
+
+/// What the `assess_move` hook is given
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MoveCheck {
+    pub color: String,
+    /// Moves before this one
+    pub moves: Vec<String>,
+    pub played: String,
+    /// What the prep had
+    pub expected: Vec<String>,
+    /// `in_prep`, `prep_ended`, `try_again` or `out_of_prep`
+    pub assessment: String,
+    /// Whether it would count as a mistake without the hook
+    pub mistake: bool,
+}
+
+impl MoveCheck {
+    pub fn assessment_name(assessment: MoveAssessment) -> &'static str {
+        match assessment {
+            MoveAssessment::InPrep => "in_prep",
+            MoveAssessment::PrepEnded => "prep_ended",
+            MoveAssessment::TryAgain => "try_again",
+            MoveAssessment::OutOfPrep => "out_of_prep",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Assessment {
+    mistake: bool,
+}
+
+/// Wait for `child` to exit, killing it if it's still going after `timeout`
+fn wait(child: &mut Child, program: &str, timeout: Option<Duration>) -> anyhow::Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return Ok(child.wait()?);
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!(
+                "{} was killed after taking longer than {:?}",
+                program,
+                timeout
+            );
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Run `command` with `input` on its stdin, `None` if it prints nothing. Plugins are run this way
+/// too, with no `timeout` as downloading can take a while.
+pub fn run_program<T: DeserializeOwned>(
+    command: &[String],
+    input: &impl Serialize,
+    timeout: Option<Duration>,
+) -> anyhow::Result<Option<T>> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("The command is empty"))?;
+    let mut input = serde_json::to_vec(input)?;
+    input.push(b'\n');
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Couldn't run {}", program))?;
+    // Both pipes are seen to on threads of their own so a program that doesn't read all of its
+    // input, or prints a lot, can't block it from being waited on
+    let stdin = child.stdin.take();
+    let writer = thread::spawn(move || match stdin {
+        Some(mut stdin) => stdin.write_all(&input),
+        None => Ok(()),
+    });
+    let mut stdout = child.stdout.take();
+    let reader = thread::spawn(move || {
+        let mut out = vec![];
+        if let Some(stdout) = stdout.as_mut() {
+            stdout.read_to_end(&mut out)?;
+        }
+        io::Result::Ok(out)
+    });
+    let status = wait(&mut child, program, timeout);
+    // It's exited or been killed by now so the pipes are closed and both threads finish
+    let written = writer.join().expect("Writing to the program panicked");
+    let read = reader.join().expect("Reading from the program panicked");
+    let status = status?;
+    if !status.success() {
+        anyhow::bail!("{} failed with {}", program, status);
+    }
+    // One that's done without reading its input closes the pipe on it, that's fine
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => {}
+    }
+    let stdout = read?;
+    let stdout = String::from_utf8_lossy(&stdout);
+    if stdout.trim().is_empty() {
+        return Ok(None);
+    }
+    let answer = serde_json::from_str(&stdout)
+        .with_context(|| format!("{} didn't answer with the JSON it should", program))?;
+    Ok(Some(answer))
+}
+
+/// The line the `select_line` hook wants drilled, `None` to pick one as usual
+pub fn select_line(command: &[String], choice: &LineChoice) -> Option<Vec<String>> {
+    match run_program::<PickedLine>(command, choice, Some(HOOK_TIMEOUT)) {
+        Ok(picked) => {
+            let line = picked?.line;
+            info!("The select_line hook picked {}", line.join(" "));
+            Some(line)
+        }
+        Err(e) => {
+            error!("The select_line hook went wrong: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Whether the `assess_move` hook counts the move as a mistake, `None` to leave it to the prep
+pub fn assess_move(command: &[String], check: &MoveCheck) -> Option<bool> {
+    match run_program::<Assessment>(command, check, Some(HOOK_TIMEOUT)) {
+        Ok(assessment) => assessment.map(|x| x.mistake),
+        Err(e) => {
+            error!("The assess_move hook went wrong: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Tell the `session_end` hook about a finished attempt, anything it prints is ignored
+pub fn session_end(command: &[String], attempt: &Attempt) {
+    if let Err(e) = run_program::<serde_json::Value>(command, attempt, Some(HOOK_TIMEOUT)) {
+        error!("The session_end hook went wrong: {:#}", e);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn run_hooks() {
+        let choice = LineChoice {
+            color: "white".to_string(),
+            chapter: None,
+            moves: vec![],
+            lines: vec![],
+        };
+        let picked = select_line(
+            &sh(r#"cat > /dev/null; echo '{"line": ["e4", "e5"]}'"#),
+            &choice,
+        );
+        assert_eq!(picked, Some(vec!["e4".to_string(), "e5".to_string()]));
+        assert_eq!(select_line(&sh("cat > /dev/null"), &choice), None);
+        assert_eq!(select_line(&sh("exit 1"), &choice), None);
+        assert_eq!(select_line(&[], &choice), None);
+
+        // The hook sees what it's given
+        let check = MoveCheck {
+            color: "white".to_string(),
+            moves: vec!["e4".to_string(), "e5".to_string()],
+            played: "Bc4".to_string(),
+            expected: vec!["Nf3".to_string()],
+            assessment: "out_of_prep".to_string(),
+            mistake: true,
+        };
+        let lenient = sh(r#"grep -q '"played":"Bc4"' && echo '{"mistake": false}'"#);
+        assert_eq!(assess_move(&lenient, &check), Some(false));
+        // Not reading the input is fine, taking too long isn't
+        let answer = sh(r#"echo '{"mistake": false}'"#);
+        assert_eq!(assess_move(&answer, &check), Some(false));
+        // Exec'd so there's no shell left holding the pipes open once it's killed
+        let slow = sh("exec sleep 5");
+        let e = run_program::<Assessment>(&slow, &check, Some(Duration::from_millis(100)));
+        assert!(e.unwrap_err().to_string().contains("was killed"));
+    }
+}
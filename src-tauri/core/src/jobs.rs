@@ -0,0 +1,282 @@
+//! Queue for long jobs like audits and scans. They run one at a time on their own thread, telling
+//! whoever's listening how far they've got, and can be paused or cancelled between steps.
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use tracing::{error, info};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Cancelled,
+    Finished,
+    Failed,
+}
+
+impl JobState {
+    fn is_over(self) -> bool {
+        matches!(self, Self::Cancelled | Self::Finished | Self::Failed)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub name: String,
+    pub state: JobState,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Sent whenever a job changes, the result or error is only there once it's over
+#[derive(Clone, Debug, Serialize)]
+pub struct JobUpdate {
+    #[serde(flatten)]
+    pub status: JobStatus,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// What a cancelled job stops with
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+type Listener = Arc<dyn Fn(JobUpdate) + Send + Sync>;
+
+/// Handed to a job so it can say how it's getting on and find out whether to carry on
+pub struct JobControl {
+    status: Mutex<JobStatus>,
+    changed: Condvar,
+    listener: Listener,
+}
+
+impl JobControl {
+    /// Report progress, this is also where pausing and cancelling take effect so call it often
+    pub fn progress(&self, done: usize, total: usize) -> anyhow::Result<()> {
+        {
+            let mut status = self.status.lock().unwrap();
+            status.done = done;
+            status.total = total;
+        }
+        self.send(None, None);
+        self.checkpoint()
+    }
+
+    /// Wait while the job is paused, fails with `Cancelled` if it's been cancelled
+    pub fn checkpoint(&self) -> anyhow::Result<()> {
+        let status = self.status.lock().unwrap();
+        let status = self
+            .changed
+            .wait_while(status, |x| x.state == JobState::Paused)
+            .unwrap();
+        if status.state == JobState::Cancelled {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Move to a new state unless the job is over already
+    fn set_state(&self, state: JobState) {
+        let mut status = self.status.lock().unwrap();
+        if status.state.is_over() || status.state == state {
+            return;
+        }
+        status.state = state;
+        drop(status);
+        self.changed.notify_all();
+        self.send(None, None);
+    }
+
+    fn send(&self, result: Option<Value>, error: Option<String>) {
+        (self.listener)(JobUpdate {
+            status: self.status(),
+            result,
+            error,
+        });
+    }
+}
+
+type Work = Box<dyn FnOnce(&JobControl) -> anyhow::Result<Value> + Send>;
+
+pub struct JobQueue {
+    next_id: AtomicU64,
+    jobs: Mutex<Vec<Arc<JobControl>>>,
+    queue: Mutex<Sender<(Arc<JobControl>, Work)>>,
+    listener: Listener,
+}
+
+impl JobQueue {
+    /// Start the worker, `listener` is given every update to every job
+    pub fn new(listener: impl Fn(JobUpdate) + Send + Sync + 'static) -> Self {
+        let (queue, jobs) = mpsc::channel::<(Arc<JobControl>, Work)>();
+        thread::spawn(move || {
+            for (job, work) in jobs {
+                match job.status().state {
+                    x if x.is_over() => continue,
+                    JobState::Queued => {
+                        job.set_state(JobState::Running);
+                    }
+                    // Paused jobs start and wait at their first checkpoint
+                    _ => {}
+                }
+                let res = work(&job);
+                let mut status = job.status.lock().unwrap();
+                let (state, result, error) = match res {
+                    Ok(result) => (JobState::Finished, Some(result), None),
+                    Err(e) if e.is::<Cancelled>() => (JobState::Cancelled, None, None),
+                    Err(e) => {
+                        error!("{} failed: {}", status.name, e);
+                        (JobState::Failed, None, Some(e.to_string()))
+                    }
+                };
+                info!("{} is {:?}", status.name, state);
+                status.state = state;
+                drop(status);
+                job.send(result, error);
+            }
+        });
+        Self {
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(vec![]),
+            queue: Mutex::new(queue),
+            listener: Arc::new(listener),
+        }
+    }
+
+    /// Queue up a job, it starts once the ones before it are over. Returns its id.
+    pub fn push(
+        &self,
+        name: impl Into<String>,
+        work: impl FnOnce(&JobControl) -> anyhow::Result<Value> + Send + 'static,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Arc::new(JobControl {
+            status: Mutex::new(JobStatus {
+                id,
+                name: name.into(),
+                state: JobState::Queued,
+                done: 0,
+                total: 0,
+            }),
+            changed: Condvar::new(),
+            listener: self.listener.clone(),
+        });
+        info!("Queueing {}", job.status().name);
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|x| !x.status().state.is_over());
+        jobs.push(job.clone());
+        job.send(None, None);
+        if self
+            .queue
+            .lock()
+            .unwrap()
+            .send((job.clone(), Box::new(work)))
+            .is_err()
+        {
+            job.set_state(JobState::Failed);
+        }
+        id
+    }
+
+    /// Jobs that are queued or running
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|x| x.status())
+            .filter(|x| !x.state.is_over())
+            .collect()
+    }
+
+    pub fn pause(&self, id: u64) -> anyhow::Result<()> {
+        self.find(id)?.set_state(JobState::Paused);
+        Ok(())
+    }
+
+    pub fn resume(&self, id: u64) -> anyhow::Result<()> {
+        let job = self.find(id)?;
+        if job.status().state == JobState::Paused {
+            job.set_state(JobState::Running);
+        }
+        Ok(())
+    }
+
+    /// Jobs that have started stop at their next checkpoint, queued ones never start
+    pub fn cancel(&self, id: u64) -> anyhow::Result<()> {
+        self.find(id)?.set_state(JobState::Cancelled);
+        Ok(())
+    }
+
+    fn find(&self, id: u64) -> anyhow::Result<Arc<JobControl>> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|x| x.status().id == id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("There's no job {}", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn pause_and_cancel() {
+        let (updates_tx, updates) = mpsc::channel();
+        let queue = JobQueue::new(move |x| updates_tx.send(x).unwrap());
+        let (go_tx, go) = mpsc::channel::<()>();
+        let first = queue.push("first", move |job| {
+            go.recv().unwrap();
+            job.progress(1, 2)?;
+            Ok(Value::from(1))
+        });
+        let second = queue.push("second", |_| panic!("Cancelled jobs shouldn't run"));
+        queue.cancel(second).unwrap();
+
+        let wait_for = |id: u64, state: JobState| loop {
+            let update = updates.recv_timeout(Duration::from_secs(5)).unwrap();
+            if update.status.id == id && update.status.state == state {
+                break update;
+            }
+        };
+        let update = wait_for(first, JobState::Running);
+        assert_eq!(update.status.name, "first");
+        queue.pause(first).unwrap();
+        go_tx.send(()).unwrap();
+        wait_for(first, JobState::Paused);
+        // Sits at the checkpoint after the progress until resumed
+        let progress = wait_for(first, JobState::Paused);
+        assert_eq!(progress.status.done, 1);
+        assert!(updates.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(queue.list().len(), 1);
+
+        queue.resume(first).unwrap();
+        let finished = wait_for(first, JobState::Finished);
+        assert_eq!(finished.result, Some(Value::from(1)));
+        assert!(queue.list().is_empty());
+        assert!(queue.pause(100).is_err());
+    }
+}
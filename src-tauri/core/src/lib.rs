@@ -0,0 +1,68 @@
+//! Everything about drilling a repertoire that doesn't need a window: reading the prep, running
+//! drills, the engines, the stats and schedule, and talking to chess.com and sync servers. The
+//! app's window and terminal frontends are built on this, and so can anything else.
+pub mod anki;
+pub mod annotate;
+pub mod archive;
+pub mod audit;
+pub mod booklet;
+pub mod cache;
+pub mod clients;
+pub mod clock;
+pub mod compare;
+pub mod compliance;
+pub mod config;
+pub mod db;
+pub mod endgame;
+pub mod engine;
+pub mod explain;
+pub mod game;
+pub mod gaps;
+pub mod graph;
+pub mod hooks;
+pub mod jobs;
+pub mod notation;
+pub mod novelty;
+pub mod plugins;
+pub mod reminders;
+pub mod results;
+pub mod scan;
+pub mod schedule;
+pub mod speech;
+pub mod stats;
+pub mod sync;
+pub mod usage;
+
+pub use crate::anki::*;
+pub use crate::annotate::*;
+pub use crate::archive::*;
+pub use crate::audit::*;
+pub use crate::booklet::*;
+pub use crate::cache::*;
+#[cfg(feature = "chess-com")]
+pub use crate::clients::chess_com::*;
+#[cfg(feature = "webdav")]
+pub use crate::clients::webdav::*;
+pub use crate::clock::*;
+pub use crate::compare::*;
+pub use crate::compliance::*;
+pub use crate::config::*;
+pub use crate::db::*;
+pub use crate::endgame::*;
+pub use crate::engine::*;
+pub use crate::explain::*;
+pub use crate::game::*;
+pub use crate::gaps::*;
+pub use crate::graph::*;
+pub use crate::jobs::*;
+pub use crate::notation::*;
+pub use crate::novelty::*;
+pub use crate::plugins::*;
+pub use crate::reminders::*;
+pub use crate::results::*;
+pub use crate::scan::*;
+pub use crate::schedule::*;
+pub use crate::speech::*;
+pub use crate::stats::*;
+pub use crate::sync::*;
+pub use crate::usage::*;
@@ -0,0 +1,89 @@
+//! Piece letters in other languages and figurines, for typing moves in and showing them the way
+//! players are used to. Moves are kept in English SAN everywhere else.
+
+/// King, queen, rook, bishop and knight in each language there's a translation for
+const PIECES: [(&str, [&str; 5]); 4] = [
+    ("en", ["K", "Q", "R", "B", "N"]),
+    ("de", ["K", "D", "T", "L", "S"]),
+    ("es", ["R", "D", "T", "A", "C"]),
+    ("ru", ["Кр", "Ф", "Л", "С", "К"]),
+];
+
+const FIGURINES: [&str; 5] = ["♔", "♕", "♖", "♗", "♘"];
+
+/// Black's figurines, only read in as they aren't how moves usually get written
+const BLACK_FIGURINES: [&str; 5] = ["♚", "♛", "♜", "♝", "♞"];
+
+fn letters(locale: &str) -> [&'static str; 5] {
+    PIECES
+        .iter()
+        .find(|x| x.0 == locale)
+        .map_or(PIECES[0].1, |x| x.1)
+}
+
+/// Swap each of `from` in `san` for the one in the same place in `to`, longest first so `Кр`
+/// isn't read as a knight
+fn swap_pieces(san: &str, from: &[&str], to: &[&str]) -> String {
+    let mut order = (0..from.len()).collect::<Vec<_>>();
+    order.sort_by_key(|x| std::cmp::Reverse(from[*x].len()));
+    let mut swapped = String::new();
+    let mut rest = san;
+    'outer: while let Some(c) = rest.chars().next() {
+        for i in &order {
+            if let Some(after) = rest.strip_prefix(from[*i]) {
+                swapped += to[*i];
+                rest = after;
+                continue 'outer;
+            }
+        }
+        swapped.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    swapped
+}
+
+/// A move typed with `locale`'s piece letters, or with figurines, as English SAN. English letters
+/// the locale doesn't use for something else still work.
+pub fn english_san(san: &str, locale: &str) -> String {
+    let english = letters("en");
+    let mut from = letters(locale).to_vec();
+    from.extend(FIGURINES);
+    from.extend(BLACK_FIGURINES);
+    let to = english.repeat(3);
+    swap_pieces(san, &from, &to)
+}
+
+/// An English SAN move written with `locale`'s piece letters, or figurines
+pub fn localized_san(san: &str, locale: &str, figurine: bool) -> String {
+    let to = match figurine {
+        true => FIGURINES,
+        false => letters(locale),
+    };
+    swap_pieces(san, &letters("en"), &to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translate_pieces() {
+        assert_eq!(english_san("Sf3", "de"), "Nf3");
+        assert_eq!(english_san("e8=D+", "de"), "e8=Q+");
+        // N and B aren't German so they still mean the same
+        assert_eq!(english_san("Nf3", "de"), "Nf3");
+        // In Spanish R is the king and T the rook
+        assert_eq!(english_san("Rxe2", "es"), "Kxe2");
+        assert_eq!(english_san("Tad1", "es"), "Rad1");
+        assert_eq!(english_san("Крg1", "ru"), "Kg1");
+        assert_eq!(english_san("Кc3", "ru"), "Nc3");
+        assert_eq!(english_san("♞f6", "en"), "Nf6");
+        assert_eq!(english_san("O-O", "es"), "O-O");
+
+        assert_eq!(localized_san("Rad1", "es", false), "Tad1");
+        assert_eq!(localized_san("Kxe2", "es", false), "Rxe2");
+        assert_eq!(localized_san("Kg1", "ru", false), "Крg1");
+        assert_eq!(localized_san("Qxd8#", "de", true), "♕xd8#");
+        assert_eq!(localized_san("exd5", "xx", false), "exd5");
+    }
+}
@@ -0,0 +1,297 @@
+//! Compares the repertoire with a reference database of master games to find where each line
+//! leaves known theory, then asks the engine whether the player's new move holds up.
+use crate::cache::EvalCache;
+use crate::config;
+use crate::db::{next_moves, OpeningGraph};
+use crate::engine::{Limits, Score, UciEngine};
+use crate::graph::NodeIndex;
+use pgn_reader::{BufferedReader, RawHeader, SanPlus, Skip, Visitor};
+use serde::Serialize;
+use shakmaty::san::San;
+use shakmaty::uci::Uci;
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::{Chess, Color, EnPassantMode, Position};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+/// Moves into a game past this aren't theory any more, and it keeps big databases small
+const MAX_PLIES: usize = 40;
+
+fn hash(position: &Chess) -> u64 {
+    position.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0
+}
+
+/// How many times each position came up in the reference games, so transpositions count too
+#[derive(Debug, Default)]
+pub struct Reference {
+    positions: HashMap<u64, usize>,
+    pub games: usize,
+}
+
+impl Reference {
+    /// Read every PGN in the folder, or the file if it's just one
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            anyhow::bail!(
+                "There's no reference database at {}, put some master games there as PGNs",
+                path.display()
+            );
+        }
+        let mut reference = Self::default();
+        let files = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|x| x.ok())
+            .filter(|x| x.file_type().is_file())
+            .map(|x| x.into_path())
+            .filter(|x| x.extension().is_some_and(|x| x == "pgn"));
+        for file in files {
+            match fs::File::open(&file) {
+                Ok(pgn) => reference.read(pgn)?,
+                Err(e) => error!("Couldn't read {}: {}", file.display(), e),
+            }
+        }
+        info!(
+            "Loaded {} reference games from {}",
+            reference.games,
+            path.display()
+        );
+        Ok(reference)
+    }
+
+    pub fn read(&mut self, pgn: impl io::Read) -> anyhow::Result<()> {
+        let mut reader = BufferedReader::new(pgn);
+        let mut visitor = ReferenceReader {
+            reference: self,
+            position: Chess::new(),
+            plies: 0,
+            skip: false,
+        };
+        while reader.read_game(&mut visitor)?.is_some() {}
+        Ok(())
+    }
+
+    /// Games that reached the position
+    pub fn count(&self, position: &Chess) -> usize {
+        self.positions.get(&hash(position)).copied().unwrap_or(0)
+    }
+}
+
+struct ReferenceReader<'a> {
+    reference: &'a mut Reference,
+    position: Chess,
+    plies: usize,
+    /// Set up from a position, or a move in it didn't make sense
+    skip: bool,
+}
+
+impl Visitor for ReferenceReader<'_> {
+    type Result = ();
+
+    fn begin_game(&mut self) {
+        self.position = Chess::new();
+        self.plies = 0;
+        self.skip = false;
+    }
+
+    fn header(&mut self, key: &[u8], _value: RawHeader<'_>) {
+        if key == b"FEN" {
+            self.skip = true;
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        if !self.skip {
+            self.reference.games += 1;
+            *self
+                .reference
+                .positions
+                .entry(hash(&self.position))
+                .or_default() += 1;
+        }
+        Skip(self.skip)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.skip || self.plies >= MAX_PLIES {
+            return;
+        }
+        let Ok(mv) = san_plus.san.to_move(&self.position) else {
+            self.skip = true;
+            return;
+        };
+        self.position.play_unchecked(&mv);
+        self.plies += 1;
+        *self
+            .reference
+            .positions
+            .entry(hash(&self.position))
+            .or_default() += 1;
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) {}
+}
+
+/// A move of the player's in the repertoire reaching a position none of the reference games did
+struct Candidate {
+    moves: Vec<String>,
+    played: shakmaty::Move,
+    before: Chess,
+    after: Chess,
+    /// Reference games that got to the position before it
+    games: usize,
+}
+
+/// The first move in each line that leaves the reference games, if it's the player's. Lines the
+/// opponent leaves theory in first aren't the player's novelty.
+fn find_novelties(openings: &OpeningGraph, player: Color, reference: &Reference) -> Vec<Candidate> {
+    let mut candidates = vec![];
+    let mut stack: Vec<(NodeIndex, Chess, Vec<String>)> = next_moves(openings, None)
+        .into_iter()
+        .map(|x| (x, Chess::new(), vec![]))
+        .collect();
+    while let Some((node, position, mut moves)) = stack.pop() {
+        let san = &openings[node];
+        let Ok(mv) = san.san.to_move(&position) else {
+            continue;
+        };
+        let Ok(after) = position.clone().play(&mv) else {
+            continue;
+        };
+        moves.push(san.to_string());
+        if reference.count(&after) > 0 {
+            for next in next_moves(openings, Some(node)) {
+                stack.push((next, after.clone(), moves.clone()));
+            }
+        } else if position.turn() == player {
+            candidates.push(Candidate {
+                moves,
+                played: mv,
+                games: reference.count(&position),
+                before: position,
+                after,
+            });
+        }
+    }
+    candidates.sort_by(|a, b| a.moves.cmp(&b.moves));
+    candidates
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Novelty {
+    /// Moves from the start up to and including the player's new move
+    pub moves: Vec<String>,
+    /// Reference games that reached the position before it
+    pub games: usize,
+    /// The engine's choice, in SAN
+    pub best: String,
+    /// Centipawns worse than the engine's choice
+    pub loss: i32,
+    /// For the player after their move
+    pub eval: Score,
+    /// Within the audit threshold, a pet line rather than a mistake
+    pub sound: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NoveltyReport {
+    pub color: String,
+    pub limits: Limits,
+    pub threshold: i32,
+    /// Games in the reference database
+    pub reference_games: usize,
+    /// Worst first
+    pub novelties: Vec<Novelty>,
+}
+
+/// Find where the repertoire leaves the reference games and have the engine judge the new moves
+/// with the audit's settings. `progress` works the same as for an audit.
+pub fn check_novelties(
+    engine: &UciEngine,
+    cache: &EvalCache,
+    openings: &OpeningGraph,
+    player: Color,
+    reference: &Reference,
+    settings: &config::Audit,
+    mut progress: impl FnMut(usize, usize) -> anyhow::Result<()>,
+) -> anyhow::Result<NoveltyReport> {
+    let candidates = find_novelties(openings, player, reference);
+    let limits = settings.limits();
+    let mut novelties = vec![];
+    for (i, candidate) in candidates.iter().enumerate() {
+        progress(i, candidates.len())?;
+        let best = cache.analyze(engine, &candidate.before, limits)?;
+        let eval = cache
+            .analyze(engine, &candidate.after, limits)?
+            .score
+            .flip();
+        let best_move = best
+            .best_move()
+            .and_then(|x| Uci::from_str(x).ok())
+            .and_then(|x| x.to_move(&candidate.before).ok());
+        // Searching the position after goes a move deeper, which can be enough to disagree
+        let loss = match best_move.as_ref() {
+            Some(x) if *x != candidate.played => best.score.centipawns() - eval.centipawns(),
+            _ => 0,
+        };
+        novelties.push(Novelty {
+            moves: candidate.moves.clone(),
+            games: candidate.games,
+            best: best_move
+                .map(|x| San::from_move(&candidate.before, &x).to_string())
+                .unwrap_or_default(),
+            loss,
+            eval,
+            sound: loss <= settings.threshold,
+        });
+    }
+    progress(candidates.len(), candidates.len())?;
+    novelties.sort_by_key(|x| -x.loss);
+    Ok(NoveltyReport {
+        color: player.to_string(),
+        limits,
+        threshold: settings.threshold,
+        reference_games: reference.games,
+        novelties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn leave_the_reference_games() {
+        let mut reference = Reference::default();
+        let games = "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 1-0\n\n1. Nf3 e5 2. e4 d6 1/2-1/2\n\n\
+            [FEN \"8/8/8/8/8/4k3/4p3/4K3 w - - 0 1\"]\n\n1. Kf2 *\n";
+        reference.read(games.as_bytes()).unwrap();
+        assert_eq!(reference.games, 2);
+        assert_eq!(reference.count(&Chess::new()), 2);
+
+        let mut db = OpeningDatabase::default();
+        let prep = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 (2... d6 3. Bc4) 3. Bc4 *";
+        db.add_prep(Color::White, prep.as_bytes()).unwrap();
+        let moves = find_novelties(db.graph(Color::White), Color::White, &reference)
+            .into_iter()
+            .map(|x| (x.moves.join(" "), x.games))
+            .collect::<Vec<_>>();
+        // d6 transposes to the second game and c5 is the opponent's novelty
+        assert_eq!(
+            moves,
+            vec![
+                ("e4 e5 Nf3 Nc6 Bc4".to_string(), 1),
+                ("e4 e5 Nf3 d6 Bc4".to_string(), 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Where games come from, where training progress is kept in step and how booklets get written,
+//! behind traits so more of them can be added without touching the rest of the app.
+//!
+//! The built in ones are chess.com and WebDAV, each behind a cargo feature of the same name, and
+//! the HTML booklet. Anything else, like another server's games or a course bought somewhere,
+//! goes in `plugins` in the config as a program that's run the same way as the hooks: it's given
+//! a JSON request on stdin and answers with JSON on stdout. Programs are used rather than
+//! libraries loaded at runtime as Rust has no stable ABI to load them with.
+use crate::booklet::{booklet_html, Booklet};
+use crate::config::{Config, Plugin};
+use crate::hooks::{run_program, DueLine};
+use crate::schedule::prep_lines;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Somewhere the player's games can be downloaded from
+pub trait GameSource {
+    /// Used in messages and as the name of its folder in the cache, like `chess.com`
+    fn name(&self) -> &str;
+    /// The player's accounts there
+    fn users(&self, config: &Config) -> Vec<String>;
+    /// Download the user's games into the cache, or just the new ones if some already are
+    fn download(&self, config: &Config, user: &str) -> anyhow::Result<()>;
+    /// The PGN files downloaded for the user, oldest first
+    fn downloaded(&self, config: &Config, user: &str) -> Vec<PathBuf> {
+        numbered_files(config.cache_dir().join(self.name()).join(user))
+    }
+}
+
+/// A copy of the training history to keep in step with, see [`crate::sync_progress`]
+pub trait Storage {
+    /// Used in messages, like the server's URL
+    fn name(&self) -> String;
+    /// The stored copy of a file, empty if there isn't one yet
+    fn get(&self, file: &str) -> anyhow::Result<String>;
+    fn put(&self, file: &str, text: String) -> anyhow::Result<()>;
+}
+
+/// A way of writing out a booklet of the repertoire
+pub trait BookletRenderer {
+    /// For the file written, like `html`
+    fn extension(&self) -> &str;
+    fn render(&self, booklet: &Booklet) -> anyhow::Result<String>;
+}
+
+/// Files named `0.pgn`, `1.pgn` and so on in a folder in order, everything else in it is left out
+pub fn numbered_files(folder: PathBuf) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter_map(|x| {
+            let number = x.file_stem()?.to_str()?.parse::<usize>().ok()?;
+            (x.extension()? == "pgn").then_some((number, x))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.into_iter().map(|(_, x)| x).collect()
+}
+
+/// Every source of games in the config
+pub fn game_sources(config: &Config) -> Vec<Box<dyn GameSource>> {
+    let mut sources: Vec<Box<dyn GameSource>> = vec![];
+    #[cfg(feature = "chess-com")]
+    sources.push(Box::new(crate::ChessComClient::new()));
+    #[cfg(not(feature = "chess-com"))]
+    if !config.chess_com.is_empty() {
+        tracing::warn!("This build doesn't download chess.com games");
+    }
+    for (name, plugin) in &config.plugins {
+        if let Plugin::Games { command, users } = plugin {
+            sources.push(Box::new(ProgramSource {
+                name: name.clone(),
+                command: command.clone(),
+                users: users.clone(),
+            }));
+        }
+    }
+    sources
+}
+
+/// Every copy of the training history in the config
+pub fn storages(config: &Config) -> Vec<Box<dyn Storage>> {
+    let mut storages: Vec<Box<dyn Storage>> = vec![];
+    #[cfg(feature = "webdav")]
+    if let Some(remote) = config.sync.as_ref() {
+        storages.push(Box::new(crate::SyncClient::new(remote)));
+    }
+    #[cfg(not(feature = "webdav"))]
+    if config.sync.is_some() {
+        tracing::warn!("This build can't sync with a WebDAV server");
+    }
+    for (name, plugin) in &config.plugins {
+        if let Plugin::Storage { command } = plugin {
+            storages.push(Box::new(ProgramStorage {
+                name: name.clone(),
+                command: command.clone(),
+            }));
+        }
+    }
+    storages
+}
+
+/// The booklet plugin called `name`, or HTML for none
+pub fn booklet_renderer(
+    config: &Config,
+    name: Option<&str>,
+) -> anyhow::Result<Box<dyn BookletRenderer>> {
+    let Some(name) = name else {
+        return Ok(Box::new(HtmlBooklet));
+    };
+    match config.plugins.get(name) {
+        Some(Plugin::Booklet { command, extension }) => Ok(Box::new(ProgramBooklet {
+            command: command.clone(),
+            extension: extension.clone(),
+        })),
+        Some(_) => anyhow::bail!("The {} plugin doesn't write booklets", name),
+        None => anyhow::bail!("There's no plugin called {}", name),
+    }
+}
+
+pub struct HtmlBooklet;
+
+impl BookletRenderer for HtmlBooklet {
+    fn extension(&self) -> &str {
+        "html"
+    }
+
+    fn render(&self, booklet: &Booklet) -> anyhow::Result<String> {
+        Ok(booklet_html(booklet))
+    }
+}
+
+/// What plugin programs are asked for
+#[derive(Debug, Serialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+enum Request<'a> {
+    /// Games played by `user` since the time given, all of them for none
+    Games {
+        user: &'a str,
+        since: Option<String>,
+    },
+    Get {
+        file: &'a str,
+    },
+    Put {
+        file: &'a str,
+        text: &'a str,
+    },
+    Booklet(BookletRequest),
+}
+
+#[derive(Debug, Deserialize)]
+struct Games {
+    pgn: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Text {
+    text: String,
+}
+
+/// A plugin that downloads games
+pub struct ProgramSource {
+    name: String,
+    command: Vec<String>,
+    users: Vec<String>,
+}
+
+impl GameSource for ProgramSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn users(&self, _: &Config) -> Vec<String> {
+        self.users.clone()
+    }
+
+    /// Each download goes in the next numbered file, with when it was asked for in `since` so
+    /// the next one only needs the games after it
+    fn download(&self, config: &Config, user: &str) -> anyhow::Result<()> {
+        let folder = config.cache_dir().join(&self.name).join(user);
+        let since_file = folder.join("since");
+        let since = fs::read_to_string(&since_file).ok();
+        let now = chrono::Utc::now().to_rfc3339();
+        let request = Request::Games {
+            user,
+            since: since.map(|x| x.trim().to_string()),
+        };
+        let games = run_program::<Games>(&self.command, &request, None)?;
+        fs::create_dir_all(&folder)?;
+        if let Some(games) = games.filter(|x| !x.pgn.trim().is_empty()) {
+            let path = folder.join(format!("{}.pgn", self.downloaded(config, user).len()));
+            fs::write(&path, games.pgn).context("Failed to cache in cache dir")?;
+            info!("Downloaded {} games to {}", self.name, path.display());
+        }
+        fs::write(since_file, now)?;
+        Ok(())
+    }
+}
+
+/// A plugin that keeps a copy of the training history
+pub struct ProgramStorage {
+    name: String,
+    command: Vec<String>,
+}
+
+impl Storage for ProgramStorage {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get(&self, file: &str) -> anyhow::Result<String> {
+        let text = run_program::<Text>(&self.command, &Request::Get { file }, None)?;
+        Ok(text.map(|x| x.text).unwrap_or_default())
+    }
+
+    fn put(&self, file: &str, text: String) -> anyhow::Result<()> {
+        run_program::<serde_json::Value>(&self.command, &Request::Put { file, text: &text }, None)?;
+        Ok(())
+    }
+}
+
+/// The booklet as it's given to plugins, every line in each chapter rather than the tree
+#[derive(Debug, Serialize)]
+struct BookletRequest {
+    player: String,
+    /// YYYY-MM-DD
+    made: String,
+    chapters: Vec<ChapterLines>,
+    /// Times the move after `moves` has been got wrong
+    mistakes: Vec<Mistakes>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChapterLines {
+    name: String,
+    lines: Vec<Vec<String>>,
+    comments: Vec<Comment>,
+    schedule: Vec<DueLine>,
+}
+
+#[derive(Debug, Serialize)]
+struct Comment {
+    moves: Vec<String>,
+    text: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Mistakes {
+    moves: Vec<String>,
+    count: usize,
+}
+
+impl From<&Booklet> for BookletRequest {
+    fn from(booklet: &Booklet) -> Self {
+        let chapters = booklet
+            .chapters
+            .iter()
+            .map(|chapter| {
+                let mut comments = chapter
+                    .comments
+                    .iter()
+                    .map(|(moves, text)| Comment {
+                        moves: moves.clone(),
+                        text: text.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                comments.sort_by(|a, b| a.moves.cmp(&b.moves));
+                ChapterLines {
+                    name: chapter.name.clone(),
+                    lines: prep_lines(&chapter.openings),
+                    comments,
+                    schedule: chapter.schedule.iter().map(DueLine::from).collect(),
+                }
+            })
+            .collect();
+        let mut mistakes = booklet
+            .mistakes
+            .iter()
+            .map(|(moves, count)| Mistakes {
+                moves: moves.clone(),
+                count: *count,
+            })
+            .collect::<Vec<_>>();
+        mistakes.sort_by(|a, b| a.moves.cmp(&b.moves));
+        Self {
+            player: booklet.player.to_string(),
+            made: booklet.made.to_string(),
+            chapters,
+            mistakes,
+        }
+    }
+}
+
+/// A plugin that writes booklets
+pub struct ProgramBooklet {
+    command: Vec<String>,
+    extension: String,
+}
+
+impl BookletRenderer for ProgramBooklet {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn render(&self, booklet: &Booklet) -> anyhow::Result<String> {
+        let request = Request::Booklet(booklet.into());
+        run_program::<Text>(&self.command, &request, None)?
+            .map(|x| x.text)
+            .context("The plugin didn't write a booklet")
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn sh(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn run_plugins() {
+        let dir =
+            std::env::temp_dir().join(format!("chess-driller-plugins-{}", std::process::id()));
+        let games = r#"grep -q '"request":"games","user":"me"' && echo '{"pgn": "1. e4 e5 *"}'"#;
+        let storage = r#"grep -q '"file":"attempts.jsonl"' && echo '{"text": "{}"}' || true"#;
+        let config = Config {
+            cache_path: Some(dir.clone()),
+            plugins: BTreeMap::from([
+                (
+                    "icc".to_string(),
+                    Plugin::Games {
+                        command: sh(games),
+                        users: vec!["me".to_string()],
+                    },
+                ),
+                (
+                    "store".to_string(),
+                    Plugin::Storage {
+                        command: sh(storage),
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+        let sources = game_sources(&config);
+        let icc = sources.iter().find(|x| x.name() == "icc").unwrap();
+        assert_eq!(icc.users(&config), vec!["me"]);
+        icc.download(&config, "me").unwrap();
+        icc.download(&config, "me").unwrap();
+        let downloaded = icc.downloaded(&config, "me");
+        assert_eq!(downloaded.len(), 2);
+        assert_eq!(fs::read_to_string(&downloaded[1]).unwrap(), "1. e4 e5 *");
+        let _ = fs::remove_dir_all(&dir);
+
+        let storages = storages(&config);
+        assert_eq!(storages.len(), 1);
+        assert_eq!(storages[0].get("attempts.jsonl").unwrap(), "{}");
+        assert!(storages[0].get("seeds.jsonl").unwrap().is_empty());
+        assert!(booklet_renderer(&config, Some("store")).is_err());
+        assert_eq!(booklet_renderer(&config, None).unwrap().extension(), "html");
+    }
+}
@@ -0,0 +1,96 @@
+//! Working out when to remind the player to drill, for `chess-driller remind`. What's been said
+//! each day is saved so a reminder only comes once however often it's checked.
+use crate::config::{Config, Reminders};
+use crate::stats::{Attempt, DrillResult};
+use chrono::{NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Days, YYYY-MM-DD, each reminder was last given
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reminded {
+    pub due: Option<String>,
+    pub goal: Option<String>,
+}
+
+pub fn reminded_file(config: &Config) -> PathBuf {
+    config.data_dir().join("reminders.json")
+}
+
+pub fn load_reminded(path: &Path) -> anyhow::Result<Reminded> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Reminded::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn save_reminded(path: &Path, reminded: &Reminded) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(reminded)?)?;
+    Ok(())
+}
+
+/// Drills finished or failed on `day`, YYYY-MM-DD
+pub fn drills_on(attempts: &[Attempt], day: &str) -> usize {
+    attempts
+        .iter()
+        .filter(|x| x.result != DrillResult::Abandoned && x.started.starts_with(day))
+        .count()
+}
+
+/// What to remind the player of now, given how many lines are `due` and the drills `done` today.
+/// `reminded` is updated with the ones returned.
+pub fn reminders(
+    settings: &Reminders,
+    due: usize,
+    done: usize,
+    now: NaiveDateTime,
+    reminded: &mut Reminded,
+) -> Vec<String> {
+    let today = now.date().to_string();
+    let mut messages = vec![];
+    if settings.due && due > 0 && reminded.due.as_ref() != Some(&today) {
+        messages.push(match due {
+            1 => "A line is due for review".to_string(),
+            n => format!("{} lines are due for review", n),
+        });
+        reminded.due = Some(today.clone());
+    }
+    let goal_time = NaiveTime::parse_from_str(&settings.goal_time, "%H:%M").unwrap_or_default();
+    if let Some(goal) = settings.daily_goal {
+        if done < goal as usize && now.time() >= goal_time && reminded.goal.as_ref() != Some(&today)
+        {
+            messages.push(format!("{} of today's {} drills done so far", done, goal));
+            reminded.goal = Some(today);
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remind_once_a_day() {
+        let settings = Reminders {
+            daily_goal: Some(5),
+            ..Default::default()
+        };
+        let at = |x: &str| NaiveDateTime::parse_from_str(x, "%Y-%m-%d %H:%M").unwrap();
+        let mut reminded = Reminded::default();
+        let morning = reminders(&settings, 3, 0, at("2026-10-14 09:00"), &mut reminded);
+        assert_eq!(morning, vec!["3 lines are due for review"]);
+        assert!(reminders(&settings, 4, 0, at("2026-10-14 12:00"), &mut reminded).is_empty());
+        let evening = reminders(&settings, 4, 2, at("2026-10-14 19:30"), &mut reminded);
+        assert_eq!(evening, vec!["2 of today's 5 drills done so far"]);
+        assert!(reminders(&settings, 4, 2, at("2026-10-14 20:00"), &mut reminded).is_empty());
+
+        let mut reminded = Reminded::default();
+        assert!(reminders(&settings, 0, 5, at("2026-10-15 20:00"), &mut reminded).is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! How games went in the openings that have been drilled compared with the ones that haven't,
+//! to see whether the training shows up in results.
+use crate::scan::PlayedGame;
+use crate::stats::{Attempt, DrillResult};
+use serde::Serialize;
+use shakmaty::Color;
+use std::collections::HashMap;
+
+/// Games are grouped into openings by their first few moves
+pub(crate) const OPENING_PLIES: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Record {
+    pub games: usize,
+    /// A point for a win and half for a draw
+    pub points: f64,
+}
+
+impl Record {
+    fn add(&mut self, points: f64) {
+        self.games += 1;
+        self.points += points;
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OpeningResults {
+    pub color: String,
+    /// From the first game in the opening that had one
+    pub eco: Option<String>,
+    pub moves: Vec<String>,
+    /// Drills that went through all of `moves`
+    pub drills: usize,
+    #[serde(flatten)]
+    pub record: Record,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ColorResults {
+    pub color: String,
+    pub drilled: Record,
+    pub undrilled: Record,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainingResults {
+    pub colors: Vec<ColorResults>,
+    /// Most games first
+    pub openings: Vec<OpeningResults>,
+}
+
+/// Whether the drill got through the opening
+fn drilled(attempt: &Attempt, color: Color, moves: &[String]) -> bool {
+    attempt.result != DrillResult::Abandoned
+        && attempt.color == color.to_string()
+        && attempt.prep_line().starts_with(moves)
+}
+
+/// Score the finished games in each opening, and overall for openings with and without drills
+pub fn compare_results(games: &[(PlayedGame, Color)], attempts: &[Attempt]) -> TrainingResults {
+    let mut openings: HashMap<(Color, Vec<String>), OpeningResults> = HashMap::new();
+    for (game, color) in games {
+        let Some(points) = game.points(*color) else {
+            continue;
+        };
+        let moves = game
+            .moves
+            .iter()
+            .take(OPENING_PLIES)
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        let opening = openings
+            .entry((*color, moves.clone()))
+            .or_insert_with(|| OpeningResults {
+                color: color.to_string(),
+                eco: None,
+                drills: attempts
+                    .iter()
+                    .filter(|x| drilled(x, *color, &moves))
+                    .count(),
+                moves,
+                record: Record::default(),
+            });
+        if opening.eco.is_none() {
+            opening.eco = game.eco.clone();
+        }
+        opening.record.add(points);
+    }
+
+    let colors = [Color::White, Color::Black]
+        .into_iter()
+        .map(|color| {
+            let mut results = ColorResults {
+                color: color.to_string(),
+                drilled: Record::default(),
+                undrilled: Record::default(),
+            };
+            for opening in openings.values().filter(|x| x.color == color.to_string()) {
+                let record = match opening.drills {
+                    0 => &mut results.undrilled,
+                    _ => &mut results.drilled,
+                };
+                record.games += opening.record.games;
+                record.points += opening.record.points;
+            }
+            results
+        })
+        .collect();
+    let mut openings = openings.into_values().collect::<Vec<_>>();
+    openings.sort_by(|a, b| {
+        (b.record.games, &a.color, &a.moves).cmp(&(a.record.games, &b.color, &b.moves))
+    });
+    TrainingResults { colors, openings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::read_games;
+    use crate::stats::AttemptTracker;
+
+    #[test]
+    fn score_drilled_openings() {
+        let pgn = "[White \"me\"]\n[Black \"a\"]\n[Result \"1-0\"]\n[ECO \"C78\"]\n\n\
+            1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O 1-0\n\n\
+            [White \"b\"]\n[Black \"me\"]\n[Result \"1/2-1/2\"]\n\n1. e4 e5 2. Nf3 Nc6 1/2-1/2\n\n\
+            [White \"me\"]\n[Black \"c\"]\n[Result \"0-1\"]\n\n1. d4 d5 2. c4 0-1\n\n\
+            [White \"me\"]\n[Black \"d\"]\n[Result \"*\"]\n\n1. d4 *\n";
+        let games = read_games(pgn.as_bytes(), "me").unwrap();
+        let line = "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O";
+        let attempt = AttemptTracker::new("white".to_string(), None)
+            .finish(false, line.split(' ').map(|x| x.to_string()).collect());
+
+        let results = compare_results(&games, &[attempt]);
+        assert_eq!(results.openings.len(), 3);
+        assert_eq!(
+            results.colors[0].drilled,
+            Record {
+                games: 1,
+                points: 1.0
+            }
+        );
+        assert_eq!(results.colors[0].undrilled.games, 1);
+        assert_eq!(results.colors[1].undrilled.points, 0.5);
+        let ruy = results.openings.iter().find(|x| x.drills > 0).unwrap();
+        assert_eq!(ruy.eco.as_deref(), Some("C78"));
+        assert_eq!(ruy.moves.len(), OPENING_PLIES);
+    }
+}
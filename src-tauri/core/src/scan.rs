@@ -0,0 +1,375 @@
+//! Runs the engine over the player's downloaded games looking for blunders made while still in
+//! the repertoire or soon after leaving it, the ones more drilling would have saved.
+use crate::annotate::Annotation;
+use crate::cache::EvalCache;
+use crate::clock::Clock;
+use crate::config;
+use crate::db::{next_moves, OpeningDatabase};
+use crate::engine::{Limits, UciEngine};
+use crate::game::movetext;
+use pgn_reader::{BufferedReader, RawComment, RawHeader, SanPlus, Skip, Visitor};
+use serde::Serialize;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Color, Position};
+use std::io;
+use std::str::FromStr;
+
+/// A game the player played
+#[derive(Debug, Clone, Default)]
+pub struct PlayedGame {
+    pub white: String,
+    pub black: String,
+    pub date: String,
+    /// Where to see the game, from chess.com's `Link` header
+    pub link: Option<String>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*` if it isn't finished
+    pub result: String,
+    pub eco: Option<String>,
+    /// Like `180+2`, for working out the time spent on the first moves
+    pub time_control: Option<String>,
+    pub moves: Vec<SanPlus>,
+    /// One for each move, empty if the game has no clock comments
+    pub clocks: Vec<Clock>,
+}
+
+impl PlayedGame {
+    /// What the game was worth to `color`, `None` if it didn't finish
+    pub fn points(&self, color: Color) -> Option<f64> {
+        let white = match self.result.as_str() {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            _ => return None,
+        };
+        Some(color.fold_wb(white, 1.0 - white))
+    }
+
+    /// Written back out as a PGN, keeping the clocks
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let headers = [
+            ("White", Some(&self.white)),
+            ("Black", Some(&self.black)),
+            ("Date", Some(&self.date)),
+            ("Result", Some(&self.result)),
+            ("ECO", self.eco.as_ref()),
+            ("TimeControl", self.time_control.as_ref()),
+            ("Link", self.link.as_ref()),
+        ];
+        for (key, value) in headers {
+            if let Some(value) = value {
+                pgn += &format!("[{} \"{}\"]\n", key, value.replace('"', "\\\""));
+            }
+        }
+        let annotations = self
+            .clocks
+            .iter()
+            .map(|x| Annotation {
+                clock: *x,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        pgn.push('\n');
+        pgn += &movetext(&self.moves, &annotations, &self.result);
+        pgn
+    }
+}
+
+#[derive(Default)]
+struct GameReader {
+    game: PlayedGame,
+    games: Vec<PlayedGame>,
+}
+
+impl Visitor for GameReader {
+    type Result = ();
+
+    fn header(&mut self, key: &[u8], value: RawHeader) {
+        let value = value.decode_utf8_lossy().to_string();
+        match key {
+            b"White" => self.game.white = value,
+            b"Black" => self.game.black = value,
+            b"Date" => self.game.date = value,
+            b"Link" => self.game.link = Some(value),
+            b"Result" => self.game.result = value,
+            b"ECO" => self.game.eco = Some(value),
+            b"TimeControl" => self.game.time_control = Some(value),
+            _ => {}
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.game.moves.push(san_plus);
+        self.game.clocks.push(Clock::default());
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let clock = Clock::from_comment(&String::from_utf8_lossy(comment.as_bytes()));
+        if let Some(last) = self.game.clocks.last_mut() {
+            if last.left.is_none() {
+                last.left = clock.left;
+            }
+            if last.spent.is_none() {
+                last.spent = clock.spent;
+            }
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        if self.game.clocks.iter().all(Clock::is_empty) {
+            self.game.clocks.clear();
+        }
+        self.games.push(std::mem::take(&mut self.game));
+    }
+}
+
+/// The games in a PGN file the user played in and which colour they had
+pub fn read_games(pgn: impl io::Read, user: &str) -> anyhow::Result<Vec<(PlayedGame, Color)>> {
+    let mut reader = BufferedReader::new(pgn);
+    let mut visitor = GameReader::default();
+    while reader.has_more()? {
+        reader.read_game(&mut visitor)?;
+    }
+    Ok(visitor
+        .games
+        .into_iter()
+        .filter_map(|game| {
+            let color = if game.white.eq_ignore_ascii_case(user) {
+                Color::White
+            } else if game.black.eq_ignore_ascii_case(user) {
+                Color::Black
+            } else {
+                return None;
+            };
+            Some((game, color))
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Blunder {
+    /// Moves from the start up to and including the blunder
+    pub moves: Vec<String>,
+    /// What the engine would have played
+    pub best: String,
+    /// Centipawns lost
+    pub loss: i32,
+    /// The repertoire had a move for the position, otherwise it came after leaving it
+    pub in_book: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannedGame {
+    pub white: String,
+    pub black: String,
+    pub date: String,
+    pub link: Option<String>,
+    pub color: String,
+    pub blunders: Vec<Blunder>,
+}
+
+/// A line of the repertoire that keeps going wrong, to train next
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrainLine {
+    /// Moves up to where the games left the repertoire
+    pub moves: Vec<String>,
+    pub blunders: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanReport {
+    /// How long each position was searched
+    pub limits: Limits,
+    pub threshold: i32,
+    /// Games looked at
+    pub scanned: usize,
+    /// Only the games with blunders in
+    pub games: Vec<ScannedGame>,
+    /// Most blunders first
+    pub train: Vec<TrainLine>,
+}
+
+/// A move of the player's to check
+struct Check {
+    moves: Vec<String>,
+    played: shakmaty::Move,
+    before: Chess,
+    after: Chess,
+    in_book: bool,
+}
+
+/// The player's moves while in the repertoire and the first `after_book` after leaving it, along
+/// with the moves up to where it was left
+fn moves_to_check(
+    game: &PlayedGame,
+    player: Color,
+    repertoire: &OpeningDatabase,
+    after_book: usize,
+) -> (Vec<Check>, Vec<String>) {
+    let openings = repertoire.graph(player);
+    let mut checks = vec![];
+    let mut position = Chess::new();
+    let mut node = None;
+    let mut in_book = true;
+    let mut book_line = vec![];
+    let mut moves = vec![];
+    let mut left = 0;
+    for san in &game.moves {
+        let prep = if in_book {
+            next_moves(openings, node)
+        } else {
+            vec![]
+        };
+        in_book = !prep.is_empty();
+        if !in_book && position.turn() == player {
+            if left == after_book {
+                break;
+            }
+            left += 1;
+        }
+        let Ok(mv) = san.san.to_move(&position) else {
+            break;
+        };
+        let before = position.clone();
+        position.play_unchecked(&mv);
+        moves.push(san.to_string());
+        if before.turn() == player && !position.is_game_over() {
+            checks.push(Check {
+                moves: moves.clone(),
+                played: mv,
+                before,
+                after: position.clone(),
+                in_book,
+            });
+        }
+        node = prep.into_iter().find(|x| openings[*x] == *san);
+        if node.is_some() {
+            book_line.push(san.to_string());
+        } else {
+            in_book = false;
+        }
+    }
+    (checks, book_line)
+}
+
+/// Look for blunders in the games, the newest `settings.games` of them. `progress` is told how
+/// many games have been looked at out of how many, an error from it stops the scan.
+pub fn scan(
+    engine: &UciEngine,
+    cache: &EvalCache,
+    games: &[(PlayedGame, Color)],
+    repertoire: &OpeningDatabase,
+    settings: &config::Scan,
+    mut progress: impl FnMut(usize, usize) -> anyhow::Result<()>,
+) -> anyhow::Result<ScanReport> {
+    let games = &games[games.len().saturating_sub(settings.games)..];
+    let limits = settings.limits();
+    // The same openings come up again and again, the cache saves searching them each time
+    let eval = |position: &Chess| cache.analyze(engine, position, limits);
+
+    let mut scanned = vec![];
+    let mut train: Vec<TrainLine> = vec![];
+    for (i, (game, player)) in games.iter().enumerate() {
+        progress(i, games.len())?;
+        let (checks, book_line) = moves_to_check(game, *player, repertoire, settings.after_book);
+        let mut blunders = vec![];
+        for check in checks {
+            let best = eval(&check.before)?;
+            let played = eval(&check.after)?.score.flip();
+            let loss = best.score.centipawns() - played.centipawns();
+            if loss <= settings.threshold {
+                continue;
+            }
+            let Some(best_move) = best
+                .best_move()
+                .and_then(|x| Uci::from_str(x).ok())
+                .and_then(|x| x.to_move(&check.before).ok())
+            else {
+                continue;
+            };
+            if best_move == check.played {
+                continue;
+            }
+            blunders.push(Blunder {
+                moves: check.moves,
+                best: SanPlus::from_move(check.before, &best_move).to_string(),
+                loss,
+                in_book: check.in_book,
+            });
+        }
+        if blunders.is_empty() {
+            continue;
+        }
+        match train.iter_mut().find(|x| x.moves == book_line) {
+            Some(line) => line.blunders += blunders.len(),
+            None => train.push(TrainLine {
+                moves: book_line,
+                blunders: blunders.len(),
+            }),
+        }
+        scanned.push(ScannedGame {
+            white: game.white.clone(),
+            black: game.black.clone(),
+            date: game.date.clone(),
+            link: game.link.clone(),
+            color: player.to_string(),
+            blunders,
+        });
+    }
+    progress(games.len(), games.len())?;
+    train.sort_by_key(|x| std::cmp::Reverse(x.blunders));
+    Ok(ScanReport {
+        limits,
+        threshold: settings.threshold,
+        scanned: games.len(),
+        games: scanned,
+        train,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_moves_near_the_book() {
+        let pgn = r#"[White "someone"]
+[Black "me"]
+[Link "https://www.chess.com/game/live/1"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. c3 Nf6 5. d4 exd4 6. cxd4 Bb4+ 7. Bd2 Bxd2+ *
+
+[White "nobody"]
+[Black "else"]
+
+1. d4 *
+"#;
+        let games = read_games(pgn.as_bytes(), "Me").unwrap();
+        assert_eq!(games.len(), 1);
+        let (game, player) = &games[0];
+        assert_eq!(*player, Color::Black);
+        assert_eq!(
+            game.link.as_deref(),
+            Some("https://www.chess.com/game/live/1")
+        );
+
+        let mut repertoire = OpeningDatabase::default();
+        repertoire
+            .add_prep(Color::Black, "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 *".as_bytes())
+            .unwrap();
+        let (checks, book_line) = moves_to_check(game, *player, &repertoire, 2);
+        assert_eq!(book_line, vec!["e4", "e5", "Nf3", "Nc6"]);
+        let checked = checks
+            .iter()
+            .map(|x| (x.moves.last().unwrap().as_str(), x.in_book))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checked,
+            vec![("e5", true), ("Nc6", true), ("Bc5", false), ("Nf6", false)]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! When each line in the prep is next due for review, spaced out further each time it's drilled
+//! without a mistake and brought back the day after one. It's worked out from the drill attempts
+//! and any reviews imported from elsewhere, so there's nothing else to keep in step.
+use crate::archive::{archive_file, load_archive};
+use crate::config::{Config, Drill};
+use crate::db::{next_moves, OpeningGraph};
+use crate::stats::{attempts_file, load_attempts, Attempt, DrillResult};
+use anyhow::Context;
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use shakmaty::Color;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Review history for a line from somewhere else, it counts as a clean review on `reviewed`
+/// that left the line `interval` days until it's due
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Seed {
+    pub color: String,
+    pub line: Vec<String>,
+    pub interval: f64,
+    /// YYYY-MM-DD, or a full RFC 3339 time
+    pub reviewed: String,
+}
+
+/// Where imported reviews are kept
+pub fn seeds_file(config: &Config) -> PathBuf {
+    config.data_dir().join("reviews.jsonl")
+}
+
+pub fn record_seeds(path: &Path, seeds: &[Seed]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for seed in seeds {
+        writeln!(file, "{}", serde_json::to_string(seed)?)?;
+    }
+    Ok(())
+}
+
+pub fn load_seeds(path: &Path) -> anyhow::Result<Vec<Seed>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut seeds = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        match serde_json::from_str(&line?) {
+            Ok(seed) => seeds.push(seed),
+            Err(e) => warn!("Skipping review {} in {}: {}", i + 1, path.display(), e),
+        }
+    }
+    Ok(seeds)
+}
+
+/// Longest a line's left between reviews, about a hundred years
+pub const MAX_INTERVAL: f64 = 36500.0;
+
+#[derive(Clone, Debug)]
+pub struct LineSchedule {
+    pub line: Vec<String>,
+    /// Days between the last review and the next
+    pub interval: f64,
+    /// New lines are due today
+    pub due: NaiveDate,
+    pub reviews: u32,
+}
+
+impl LineSchedule {
+    /// Drilling a line before it's due doesn't space it out any further
+    fn passed(&mut self, on: NaiveDate, settings: &Drill) {
+        if on < self.due {
+            self.reviews += 1;
+            return;
+        }
+        self.interval = if self.interval < settings.first_interval {
+            settings.first_interval
+        } else {
+            self.interval * settings.interval_growth
+        };
+        self.reviewed(on);
+    }
+
+    fn failed(&mut self, on: NaiveDate, settings: &Drill) {
+        self.interval = settings.first_interval;
+        self.reviewed(on);
+    }
+
+    fn reviewed(&mut self, on: NaiveDate) {
+        self.reviews += 1;
+        self.interval = self.interval.clamp(0.0, MAX_INTERVAL);
+        self.due = on
+            .checked_add_days(Days::new(self.interval.round() as u64))
+            .unwrap_or(NaiveDate::MAX);
+    }
+}
+
+/// Every line from the start to the end of the prep
+pub fn prep_lines(openings: &OpeningGraph) -> Vec<Vec<String>> {
+    let mut lines = vec![];
+    let mut stack = next_moves(openings, None)
+        .into_iter()
+        .map(|x| (x, vec![]))
+        .collect::<Vec<_>>();
+    while let Some((node, mut moves)) = stack.pop() {
+        moves.push(openings[node].to_string());
+        let next = next_moves(openings, Some(node));
+        if next.is_empty() {
+            lines.push(moves);
+        } else {
+            stack.extend(next.into_iter().map(|x| (x, moves.clone())));
+        }
+    }
+    lines.sort();
+    lines
+}
+
+/// The day an attempt or review happened on, from the start of its timestamp
+fn day(timestamp: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(timestamp.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// What a review says about the lines it covers
+enum Review<'a> {
+    Seed(&'a Seed),
+    Attempt(&'a Attempt),
+}
+
+/// When each of `player`'s lines is due, going through the reviews in the order they happened.
+/// A clean drill passes every line that goes through its moves, one with a mistake fails every
+/// line through the position it went wrong in. The intervals come from `settings`.
+pub fn schedule(
+    lines: Vec<Vec<String>>,
+    player: &str,
+    attempts: &[Attempt],
+    seeds: &[Seed],
+    today: NaiveDate,
+    settings: &Drill,
+) -> Vec<LineSchedule> {
+    let mut schedule = lines
+        .into_iter()
+        .map(|line| LineSchedule {
+            line,
+            interval: 0.0,
+            // Any review is the first one on time, it's due today if there's none
+            due: NaiveDate::MIN,
+            reviews: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut reviews = seeds
+        .iter()
+        .filter(|x| x.color == player)
+        .filter_map(|x| Some((day(&x.reviewed)?, Review::Seed(x))))
+        .chain(
+            attempts
+                .iter()
+                .filter(|x| x.color == player && x.result != DrillResult::Abandoned)
+                .filter_map(|x| Some((day(&x.started)?, Review::Attempt(x)))),
+        )
+        .collect::<Vec<_>>();
+    reviews.sort_by_key(|(day, _)| *day);
+
+    for (on, review) in reviews {
+        match review {
+            Review::Seed(seed) => {
+                for line in schedule
+                    .iter_mut()
+                    .filter(|x| x.line.starts_with(&seed.line) || seed.line.starts_with(&x.line))
+                {
+                    line.interval = seed.interval;
+                    line.reviewed(on);
+                }
+            }
+            Review::Attempt(attempt) => match attempt.mistakes.first() {
+                None if attempt.result == DrillResult::Completed => {
+                    for line in schedule
+                        .iter_mut()
+                        .filter(|x| x.line.starts_with(&attempt.line))
+                    {
+                        line.passed(on, settings);
+                    }
+                }
+                Some(mistake) => {
+                    let reached = attempt.line.get(..mistake.ply).unwrap_or(&attempt.line);
+                    for line in schedule
+                        .iter_mut()
+                        .filter(|x| x.line.len() > reached.len() && x.line.starts_with(reached))
+                    {
+                        line.failed(on, settings);
+                    }
+                }
+                None => {}
+            },
+        }
+    }
+    for line in schedule.iter_mut().filter(|x| x.reviews == 0) {
+        line.due = today;
+    }
+    schedule
+}
+
+/// When each of a colour's lines is due, from the attempts, imported reviews and archive
+pub fn load_schedule(
+    config: &Config,
+    color: Color,
+    openings: &OpeningGraph,
+    today: NaiveDate,
+) -> anyhow::Result<Vec<LineSchedule>> {
+    let attempts = load_attempts(&attempts_file(config))?;
+    let mut seeds = load_seeds(&seeds_file(config))?;
+    seeds.append(&mut load_archive(&archive_file(config))?.seeds);
+    Ok(schedule(
+        prep_lines(openings),
+        &color.to_string(),
+        &attempts,
+        &seeds,
+        today,
+        &config.drill,
+    ))
+}
+
+/// Lines coming due on a day
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DueDay {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub white: usize,
+    pub black: usize,
+}
+
+/// How many of each colour's lines come due each day from `today` for `days` days, ones that are
+/// already overdue count towards today
+pub fn forecast(
+    white: &[LineSchedule],
+    black: &[LineSchedule],
+    today: NaiveDate,
+    days: u32,
+) -> Vec<DueDay> {
+    let mut forecast = (0..days)
+        .map(|i| DueDay {
+            day: (today + Days::new(i.into())).to_string(),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    let index = |line: &LineSchedule| {
+        let day = (line.due - today).num_days().max(0) as usize;
+        (day < days as usize).then_some(day)
+    };
+    for i in white.iter().filter_map(index) {
+        forecast[i].white += 1;
+    }
+    for i in black.iter().filter_map(index) {
+        forecast[i].black += 1;
+    }
+    forecast
+}
+
+/// Moves from a line written out with move numbers or question marks, e.g. `1. e4 e5 2. Nf3`
+fn parse_line(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter(|x| !["*", "1-0", "0-1", "1/2-1/2"].contains(x))
+        .map(|x| x.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.'))
+        .filter(|x| !x.is_empty() && *x != "?")
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Fields of a delimited line, double quotes around a field keep delimiters in it
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Reviews from a CSV or tab separated export with a header row. The line comes from a column
+/// called `line`, `moves` or `pgn` and the interval in days from `interval` or `ivl`. When it was
+/// last reviewed comes from `reviewed`, `last review` or `date` if there's one, otherwise it's
+/// taken to be `today`.
+pub fn read_review_export(text: &str, player: &str, today: NaiveDate) -> anyhow::Result<Vec<Seed>> {
+    let mut rows = text
+        .lines()
+        .filter(|x| !x.trim().is_empty() && !x.starts_with('#'));
+    let header = rows.next().context("The export is empty")?;
+    let delimiter = if header.contains('\t') { '\t' } else { ',' };
+    let header = split_fields(header, delimiter)
+        .into_iter()
+        .map(|x| x.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| header.iter().position(|x| names.contains(&x.as_str()));
+    let line_column = column(&["line", "moves", "pgn"])
+        .context("There's no line, moves or pgn column in the export")?;
+    let interval_column =
+        column(&["interval", "ivl"]).context("There's no interval or ivl column in the export")?;
+    let reviewed_column = column(&["reviewed", "last review", "date"]);
+
+    let mut seeds = vec![];
+    for (i, row) in rows.enumerate() {
+        let fields = split_fields(row, delimiter);
+        let line = parse_line(fields.get(line_column).map_or("", |x| x.as_str()));
+        let interval = fields
+            .get(interval_column)
+            .and_then(|x| x.trim().trim_end_matches('d').parse::<f64>().ok())
+            .filter(|x| (0.0..=MAX_INTERVAL).contains(x));
+        let (Some(interval), false) = (interval, line.is_empty()) else {
+            warn!(
+                "Skipping review {} of the export, it needs a line and an interval of up to {} days",
+                i + 1,
+                MAX_INTERVAL
+            );
+            continue;
+        };
+        let reviewed = reviewed_column
+            .and_then(|x| fields.get(x))
+            .map(|x| x.trim().to_string())
+            .filter(|x| day(x).is_some())
+            .unwrap_or_else(|| today.to_string());
+        seeds.push(Seed {
+            color: player.to_string(),
+            line,
+            interval,
+            reviewed,
+        });
+    }
+    Ok(seeds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{AttemptTracker, Mistake};
+    use crate::OpeningDatabase;
+    use shakmaty::Color;
+
+    fn date(x: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(x, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn space_out_reviews() {
+        let mut db = OpeningDatabase::default();
+        let prep = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 *";
+        db.add_prep(Color::White, prep.as_bytes()).unwrap();
+        let lines = prep_lines(db.graph(Color::White));
+        assert_eq!(lines.len(), 2);
+
+        let moves = |x: &str| x.split(' ').map(|x| x.to_string()).collect::<Vec<_>>();
+        let drill = |started: &str, mistake: Option<Mistake>| {
+            let mut tracker = AttemptTracker::new("white".to_string(), None);
+            let failed = mistake.is_some();
+            tracker.moved(mistake, failed);
+            Attempt {
+                started: started.to_string(),
+                ..tracker.finish(false, moves("e4 e5 Nf3 Nc6 Bb5"))
+            }
+        };
+        let attempts = [
+            drill("2026-10-01T10:00:00+02:00", None),
+            drill("2026-10-02T10:00:00+02:00", None),
+        ];
+        let seeds = [Seed {
+            color: "white".to_string(),
+            line: moves("e4 c5"),
+            interval: 30.0,
+            reviewed: "2026-09-20".to_string(),
+        }];
+        let today = date("2026-10-03");
+        let settings = Drill::default();
+        let due = schedule(lines.clone(), "white", &attempts, &seeds, today, &settings)
+            .iter()
+            .map(|x| (x.line.join(" "), x.due))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            due,
+            vec![
+                ("e4 c5 Nf3".to_string(), date("2026-10-20")),
+                ("e4 e5 Nf3 Nc6 Bb5".to_string(), date("2026-10-05"))
+            ]
+        );
+
+        let mistake = Mistake {
+            ply: 2,
+            played: "Bc4".to_string(),
+            expected: vec!["Nf3".to_string()],
+        };
+        let failed = drill("2026-10-03T10:00:00+02:00", Some(mistake));
+        let failed = schedule(lines.clone(), "white", &[failed], &[], today, &settings);
+        assert_eq!(failed[0].due, today);
+        assert_eq!(failed[1].due, date("2026-10-04"));
+
+        let settings = Drill {
+            first_interval: 3.0,
+            interval_growth: 2.0,
+            ..Default::default()
+        };
+        let spaced = schedule(lines.clone(), "white", &attempts, &[], today, &settings);
+        assert_eq!(spaced[1].interval, 3.0);
+        assert_eq!(spaced[1].due, date("2026-10-04"));
+        let attempts = [
+            drill("2026-10-01T10:00:00+02:00", None),
+            drill("2026-10-04T10:00:00+02:00", None),
+        ];
+        let spaced = schedule(lines.clone(), "white", &attempts, &[], today, &settings);
+        assert_eq!(spaced[1].interval, 6.0);
+        assert_eq!(spaced[1].due, date("2026-10-10"));
+
+        // Drilled every day it only spaces out as it comes due, and a huge interval doesn't run
+        // off the end of the calendar
+        let every_day = (0..60)
+            .map(|x| {
+                let on = date("2026-10-01") + Days::new(x);
+                drill(&format!("{}T10:00:00+02:00", on), None)
+            })
+            .collect::<Vec<_>>();
+        let daily = schedule(
+            lines.clone(),
+            "white",
+            &every_day,
+            &[],
+            today,
+            &Drill::default(),
+        );
+        assert_eq!(daily[1].reviews, 60);
+        assert!(daily[1].interval < 100.0);
+        let seeds = [Seed {
+            interval: 1e300,
+            ..seeds[0].clone()
+        }];
+        let far = schedule(lines, "white", &[], &seeds, today, &settings);
+        assert_eq!(far[0].interval, MAX_INTERVAL);
+    }
+
+    #[test]
+    fn forecast_due_lines() {
+        let line = |due: &str| LineSchedule {
+            line: vec![],
+            interval: 1.0,
+            due: date(due),
+            reviews: 1,
+        };
+        let white = [line("2026-09-01"), line("2026-10-04"), line("2026-12-01")];
+        let black = [line("2026-10-04")];
+        let forecast = forecast(&white, &black, date("2026-10-03"), 3)
+            .iter()
+            .map(|x| (x.white, x.black))
+            .collect::<Vec<_>>();
+        assert_eq!(forecast, vec![(1, 0), (1, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn read_an_export() {
+        let export = "Line,Interval,Last review\n\"1. e4 c5 2. Nf3 ?\",12,2026-09-01\n1. d4,x,\n1. e4 e5,3d,\n";
+        let seeds = read_review_export(export, "white", date("2026-10-03")).unwrap();
+        assert_eq!(seeds.len(), 2);
+        assert_eq!(seeds[0].line, vec!["e4", "c5", "Nf3"]);
+        assert_eq!(seeds[0].reviewed, "2026-09-01");
+        assert_eq!(
+            (seeds[1].interval, seeds[1].reviewed.as_str()),
+            (3.0, "2026-10-03")
+        );
+        assert!(read_review_export("moves\n1. e4\n", "white", date("2026-10-03")).is_err());
+        let export = "line,ivl\n1. e4,NaN\n1. e4,-3\n1. e4,1e9\n1. e4,inf\n";
+        assert!(read_review_export(export, "white", date("2026-10-03"))
+            .unwrap()
+            .is_empty());
+    }
+}
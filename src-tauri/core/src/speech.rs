@@ -0,0 +1,89 @@
+//! Moves and hints as they'd be said out loud, for reading them out to blind players and for
+//! blindfold training.
+use shakmaty::san::{San, SanPlus, Suffix};
+use shakmaty::{CastlingSide, Role, Square};
+
+pub fn spoken_piece(role: Role) -> &'static str {
+    match role {
+        Role::King => "king",
+        Role::Queen => "queen",
+        Role::Rook => "rook",
+        Role::Bishop => "bishop",
+        Role::Knight => "knight",
+        Role::Pawn => "pawn",
+    }
+}
+
+/// e.g. `Nbxd2+` is "knight b takes d2, check"
+pub fn spoken_move(san: &SanPlus) -> String {
+    let mut words = match &san.san {
+        San::Castle(CastlingSide::KingSide) => "castles kingside".to_string(),
+        San::Castle(CastlingSide::QueenSide) => "castles queenside".to_string(),
+        San::Put { role, to } => format!("{} drops on {}", spoken_piece(*role), to),
+        San::Null => "passes".to_string(),
+        San::Normal {
+            role,
+            file,
+            rank,
+            capture,
+            to,
+            promotion,
+        } => {
+            let mut words = vec![];
+            if *role != Role::Pawn {
+                words.push(spoken_piece(*role).to_string());
+            }
+            // Pawn captures always say the file they're from
+            let from = file.map(|x| x.char().to_string()).unwrap_or_default()
+                + &rank.map(|x| x.char().to_string()).unwrap_or_default();
+            if !from.is_empty() {
+                words.push(from);
+            }
+            if *capture {
+                words.push("takes".to_string());
+            }
+            words.push(to.to_string());
+            if let Some(promotion) = promotion {
+                words.push(format!("promotes to {}", spoken_piece(*promotion)));
+            }
+            words.join(" ")
+        }
+    };
+    match san.suffix {
+        Some(Suffix::Check) => words += ", check",
+        Some(Suffix::Checkmate) => words += ", checkmate",
+        None => {}
+    }
+    words
+}
+
+/// Which pieces a hint says to move, without saying where they go
+pub fn spoken_hint(pieces: &[(Role, Square)]) -> String {
+    let pieces = pieces
+        .iter()
+        .map(|(role, square)| format!("the {} on {}", spoken_piece(*role), square))
+        .collect::<Vec<_>>();
+    match pieces.is_empty() {
+        true => "There's no hint here".to_string(),
+        false => format!("Move {}", pieces.join(" or ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speak_moves() {
+        let spoken = |x: &str| spoken_move(&SanPlus::from_ascii(x.as_bytes()).unwrap());
+        assert_eq!(spoken("e4"), "e4");
+        assert_eq!(spoken("exd5"), "e takes d5");
+        assert_eq!(spoken("Nbxd2+"), "knight b takes d2, check");
+        assert_eq!(spoken("e8=Q#"), "e8 promotes to queen, checkmate");
+        assert_eq!(spoken("O-O-O"), "castles queenside");
+        assert_eq!(
+            spoken_hint(&[(Role::Knight, Square::G1), (Role::Pawn, Square::E2)]),
+            "Move the knight on g1 or the pawn on e2"
+        );
+    }
+}
@@ -0,0 +1,739 @@
+//! Every drill attempt, kept so there's a record of how training's going to show and plan from.
+//! Attempts are appended to a JSON lines file in the data directory as they finish.
+use crate::archive::ArchivedDay;
+use crate::config::Config;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrillResult {
+    /// Got to the end of the prep, or as far as the drill goes
+    Completed,
+    /// Ran out of attempts at a move
+    Failed,
+    /// Something else was started before it finished
+    Abandoned,
+}
+
+/// A wrong move in an attempt
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mistake {
+    /// Moves played before it
+    pub ply: usize,
+    pub played: String,
+    /// What the prep had instead
+    pub expected: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attempt {
+    /// Moves of the line as far as the drill got, this is what identifies the line
+    pub line: Vec<String>,
+    pub color: String,
+    /// The chapter being drilled, `None` for the whole repertoire
+    pub chapter: Option<String>,
+    /// When it was started, RFC 3339
+    pub started: String,
+    pub result: DrillResult,
+    /// From starting the drill to finishing it
+    pub seconds: f64,
+    /// Moves the player made, wrong ones included
+    pub moves: u32,
+    pub hints: u32,
+    pub mistakes: Vec<Mistake>,
+}
+
+impl Attempt {
+    /// The line as far as it's in the prep, without the move that failed it
+    pub fn prep_line(&self) -> &[String] {
+        match (self.result, self.mistakes.last()) {
+            (DrillResult::Failed, Some(x)) => self.line.get(..x.ply).unwrap_or(&self.line),
+            _ => &self.line,
+        }
+    }
+}
+
+/// An attempt that's still going
+#[derive(Clone, Debug)]
+pub struct AttemptTracker {
+    attempt: Attempt,
+    clock: Instant,
+    failed: bool,
+}
+
+impl AttemptTracker {
+    pub fn new(color: String, chapter: Option<String>) -> Self {
+        Self {
+            attempt: Attempt {
+                line: vec![],
+                color,
+                chapter,
+                started: chrono::Local::now().to_rfc3339(),
+                result: DrillResult::Abandoned,
+                seconds: 0.0,
+                moves: 0,
+                hints: 0,
+                mistakes: vec![],
+            },
+            clock: Instant::now(),
+            failed: false,
+        }
+    }
+
+    /// The player made a move, `mistake` if it was wrong and `failed` if that ended the drill
+    pub fn moved(&mut self, mistake: Option<Mistake>, failed: bool) {
+        self.attempt.moves += 1;
+        self.attempt.mistakes.extend(mistake);
+        self.failed |= failed;
+    }
+
+    pub fn hint(&mut self) {
+        self.attempt.hints += 1;
+    }
+
+    /// The attempt as it stands, to be saved and carried on with by [`AttemptTracker::resume`]
+    pub fn so_far(&self, line: Vec<String>) -> Attempt {
+        let result = match self.failed {
+            true => DrillResult::Failed,
+            false => DrillResult::Abandoned,
+        };
+        Attempt {
+            line,
+            result,
+            seconds: self.clock.elapsed().as_secs_f64(),
+            ..self.attempt.clone()
+        }
+    }
+
+    /// Carry on with an attempt from [`AttemptTracker::so_far`], the time it had already taken
+    /// still counts
+    pub fn resume(attempt: Attempt) -> Self {
+        let clock = Instant::now()
+            .checked_sub(Duration::from_secs_f64(attempt.seconds))
+            .unwrap_or_else(Instant::now);
+        Self {
+            failed: attempt.result == DrillResult::Failed,
+            attempt,
+            clock,
+        }
+    }
+
+    /// Whether anything's happened worth recording if it's abandoned
+    pub fn started(&self) -> bool {
+        self.attempt.moves > 0
+    }
+
+    /// The attempt as it ended, with the moves that got played. It's completed unless a move
+    /// failed it, or `abandoned`.
+    pub fn finish(self, abandoned: bool, line: Vec<String>) -> Attempt {
+        let result = match (abandoned, self.failed) {
+            (true, _) => DrillResult::Abandoned,
+            (false, true) => DrillResult::Failed,
+            (false, false) => DrillResult::Completed,
+        };
+        Attempt {
+            line,
+            result,
+            seconds: self.clock.elapsed().as_secs_f64(),
+            ..self.attempt
+        }
+    }
+}
+
+/// Where attempts are kept
+pub fn attempts_file(config: &Config) -> PathBuf {
+    config.data_dir().join("attempts.jsonl")
+}
+
+pub fn record_attempt(path: &Path, attempt: &Attempt) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(attempt)?)?;
+    Ok(())
+}
+
+/// Replace the recorded attempts with `attempts`
+pub fn save_attempts(path: &Path, attempts: &[Attempt]) -> anyhow::Result<()> {
+    let mut text = String::new();
+    for attempt in attempts {
+        text += &serde_json::to_string(attempt)?;
+        text.push('\n');
+    }
+    let partial = path.with_extension("jsonl.partial");
+    fs::write(&partial, text)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Every attempt recorded so far, oldest first. Lines that don't read are skipped, they can be
+/// half written if the app was killed.
+pub fn load_attempts(path: &Path) -> anyhow::Result<Vec<Attempt>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut attempts = vec![];
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        match serde_json::from_str(&line?) {
+            Ok(attempt) => attempts.push(attempt),
+            Err(e) => warn!("Skipping attempt {} in {}: {}", i + 1, path.display(), e),
+        }
+    }
+    Ok(attempts)
+}
+
+/// Moves in the positions that went wrong most often on the stats screen
+const HARDEST: usize = 10;
+
+/// Moves from the start that tell openings apart when adding up the time spent on each
+const OPENING_PLIES: usize = 4;
+
+/// A break longer than this between drills starts a new session
+const SESSION_GAP: f64 = 30.0 * 60.0;
+
+/// Drills done one after the other
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    /// RFC 3339
+    pub started: String,
+    /// Oldest first
+    pub attempts: Vec<Attempt>,
+}
+
+/// Group the attempts into sessions, most recent first
+pub fn sessions(attempts: Vec<Attempt>) -> Vec<Session> {
+    let mut sessions: Vec<Session> = vec![];
+    // Seconds since the epoch when the last attempt finished
+    let mut finished: Option<f64> = None;
+    for attempt in attempts {
+        let started = chrono::DateTime::parse_from_rfc3339(&attempt.started)
+            .ok()
+            .map(|x| x.timestamp_millis() as f64 / 1000.0);
+        let gap = started
+            .zip(finished)
+            .map(|(started, finished)| started - finished);
+        finished = started.map(|x| x + attempt.seconds);
+        match sessions.last_mut() {
+            Some(session) if gap.is_some_and(|x| x < SESSION_GAP) => session.attempts.push(attempt),
+            _ => sessions.push(Session {
+                started: attempt.started.clone(),
+                attempts: vec![attempt],
+            }),
+        }
+    }
+    sessions.reverse();
+    sessions
+}
+
+/// Totals for some attempts
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Totals {
+    pub attempts: usize,
+    pub completed: usize,
+    pub moves: u32,
+    pub mistakes: usize,
+    pub seconds: f64,
+}
+
+impl Totals {
+    pub fn add(&mut self, attempt: &Attempt) {
+        self.attempts += 1;
+        self.completed += (attempt.result == DrillResult::Completed) as usize;
+        self.moves += attempt.moves;
+        self.mistakes += attempt.mistakes.len();
+        self.seconds += attempt.seconds;
+    }
+
+    pub fn merge(&mut self, other: &Totals) {
+        self.attempts += other.attempts;
+        self.completed += other.completed;
+        self.moves += other.moves;
+        self.mistakes += other.mistakes;
+        self.seconds += other.seconds;
+    }
+}
+
+/// The moves an attempt at `line` is counted under when adding up openings
+pub fn opening(line: &[String]) -> &[String] {
+    &line[..line.len().min(OPENING_PLIES)]
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChapterStats {
+    pub color: String,
+    pub chapter: Option<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DayStats {
+    /// In the local time zone, YYYY-MM-DD
+    pub day: String,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Time and drills spent on an opening, whichever chapters it's in
+#[derive(Clone, Debug, Serialize)]
+pub struct OpeningStats {
+    pub color: String,
+    /// The first few moves of the lines
+    pub moves: Vec<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Days in a row with drills
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Streak {
+    /// Ending today, or yesterday if there's been nothing yet today
+    pub current: usize,
+    pub longest: usize,
+}
+
+/// The streaks in `days`, which are oldest first
+pub fn streaks(days: &[DayStats], today: NaiveDate) -> Streak {
+    let mut streak = Streak::default();
+    let mut run = 0;
+    let mut last: Option<NaiveDate> = None;
+    for day in days {
+        let Ok(date) = NaiveDate::parse_from_str(&day.day, "%Y-%m-%d") else {
+            continue;
+        };
+        run = match last {
+            Some(x) if x.succ_opt() == Some(date) => run + 1,
+            _ => 1,
+        };
+        streak.longest = streak.longest.max(run);
+        last = Some(date);
+    }
+    if last.is_some_and(|x| x == today || x.succ_opt() == Some(today)) {
+        streak.current = run;
+    }
+    streak
+}
+
+/// A position the player keeps getting wrong
+#[derive(Clone, Debug, Serialize)]
+pub struct HardMove {
+    pub color: String,
+    /// To get there from the start
+    pub moves: Vec<String>,
+    pub expected: Vec<String>,
+    pub mistakes: usize,
+    /// Attempts where it was the player's move here
+    pub reached: usize,
+}
+
+/// Where in the chapter's lines moves get missed, indexed by move number from 1
+#[derive(Clone, Debug, Serialize)]
+pub struct HeatmapRow {
+    pub color: String,
+    pub chapter: Option<String>,
+    pub mistakes: Vec<usize>,
+    /// Attempts where the player had a move to make at that number
+    pub reached: Vec<usize>,
+}
+
+impl HeatmapRow {
+    fn add(&mut self, attempt: &Attempt) {
+        // White moves on even plies
+        let first = (attempt.color == "black") as usize;
+        let moves = (attempt.line.len() + 1 - first) / 2;
+        if self.reached.len() < moves {
+            self.reached.resize(moves, 0);
+            self.mistakes.resize(moves, 0);
+        }
+        for reached in &mut self.reached[..moves] {
+            *reached += 1;
+        }
+        for mistake in &attempt.mistakes {
+            if let Some(x) = self.mistakes.get_mut(mistake.ply / 2) {
+                *x += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Summary {
+    #[serde(flatten)]
+    pub totals: Totals,
+    pub chapters: Vec<ChapterStats>,
+    /// Oldest first, days without any are left out
+    pub days: Vec<DayStats>,
+    pub streak: Streak,
+    /// Most mistakes first
+    pub hardest: Vec<HardMove>,
+    /// In the same order as the chapters
+    pub heatmap: Vec<HeatmapRow>,
+    /// Most time spent first
+    pub openings: Vec<OpeningStats>,
+}
+
+/// Add up the attempts per chapter, per day and per position that was got wrong. Archived days
+/// only count towards the totals, they don't have the moves that went wrong.
+pub fn summarize(attempts: &[Attempt], archived: &[ArchivedDay]) -> Summary {
+    let mut summary = Summary::default();
+    let mut chapters: BTreeMap<(&str, Option<&str>), (Totals, HeatmapRow)> = BTreeMap::new();
+    let mut days: BTreeMap<&str, Totals> = BTreeMap::new();
+    let mut hard: HashMap<(&str, &[String]), HardMove> = HashMap::new();
+    let mut openings: BTreeMap<(&str, &[String]), Totals> = BTreeMap::new();
+    let chapter = |color: &str, chapter: Option<&str>| {
+        let heatmap = HeatmapRow {
+            color: color.to_string(),
+            chapter: chapter.map(|x| x.to_string()),
+            mistakes: vec![],
+            reached: vec![],
+        };
+        (Totals::default(), heatmap)
+    };
+    for day in archived {
+        summary.totals.merge(&day.totals);
+        chapters
+            .entry((&day.color, day.chapter.as_deref()))
+            .or_insert_with(|| chapter(&day.color, day.chapter.as_deref()))
+            .0
+            .merge(&day.totals);
+        days.entry(&day.day).or_default().merge(&day.totals);
+        openings
+            .entry((&day.color, &day.opening))
+            .or_default()
+            .merge(&day.totals);
+    }
+    for attempt in attempts {
+        summary.totals.add(attempt);
+        let (totals, heatmap) = chapters
+            .entry((&attempt.color, attempt.chapter.as_deref()))
+            .or_insert_with(|| chapter(&attempt.color, attempt.chapter.as_deref()));
+        totals.add(attempt);
+        heatmap.add(attempt);
+        days.entry(attempt.started.get(..10).unwrap_or_default())
+            .or_default()
+            .add(attempt);
+        openings
+            .entry((&attempt.color, opening(&attempt.line)))
+            .or_default()
+            .add(attempt);
+        for mistake in &attempt.mistakes {
+            let Some(moves) = attempt.line.get(..mistake.ply) else {
+                continue;
+            };
+            let hard = hard
+                .entry((&attempt.color, moves))
+                .or_insert_with(|| HardMove {
+                    color: attempt.color.clone(),
+                    moves: moves.to_vec(),
+                    expected: mistake.expected.clone(),
+                    mistakes: 0,
+                    reached: 0,
+                });
+            hard.mistakes += 1;
+        }
+    }
+    for attempt in attempts {
+        for hard in hard.values_mut() {
+            if attempt.color == hard.color
+                && attempt.line.len() > hard.moves.len()
+                && attempt.line.starts_with(&hard.moves)
+            {
+                hard.reached += 1;
+            }
+        }
+    }
+    for (totals, heatmap) in chapters.into_values() {
+        summary.chapters.push(ChapterStats {
+            color: heatmap.color.clone(),
+            chapter: heatmap.chapter.clone(),
+            totals,
+        });
+        summary.heatmap.push(heatmap);
+    }
+    summary.days = days
+        .into_iter()
+        .map(|(day, totals)| DayStats {
+            day: day.to_string(),
+            totals,
+        })
+        .collect();
+    summary.openings = openings
+        .into_iter()
+        .map(|((color, moves), totals)| OpeningStats {
+            color: color.to_string(),
+            moves: moves.to_vec(),
+            totals,
+        })
+        .collect();
+    summary
+        .openings
+        .sort_by(|a, b| b.totals.seconds.total_cmp(&a.totals.seconds));
+    summary.streak = streaks(&summary.days, chrono::Local::now().date_naive());
+    let mut hardest = hard.into_values().collect::<Vec<_>>();
+    hardest
+        .sort_by(|a, b| (b.mistakes, a.reached, &a.moves).cmp(&(a.mistakes, b.reached, &b.moves)));
+    hardest.truncate(HARDEST);
+    summary.hardest = hardest;
+    summary
+}
+
+/// Totals for each line drilled, for the export
+#[derive(Clone, Debug, Serialize)]
+pub struct LineStats {
+    pub color: String,
+    pub line: Vec<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+    pub hints: u32,
+    /// When it was last drilled, RFC 3339
+    pub last: String,
+}
+
+/// The attempts grouped by the line they were on, sorted by line
+pub fn line_stats(attempts: &[Attempt]) -> Vec<LineStats> {
+    let mut lines: BTreeMap<(&str, &[String]), LineStats> = BTreeMap::new();
+    for attempt in attempts {
+        let line = attempt.prep_line();
+        let stats = lines
+            .entry((&attempt.color, line))
+            .or_insert_with(|| LineStats {
+                color: attempt.color.clone(),
+                line: line.to_vec(),
+                totals: Totals::default(),
+                hints: 0,
+                last: String::new(),
+            });
+        stats.totals.add(attempt);
+        stats.hints += attempt.hints;
+        if attempt.started > stats.last {
+            stats.last = attempt.started.clone();
+        }
+    }
+    lines.into_values().collect()
+}
+
+/// Quoted if it needs to be
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub(crate) fn csv_row(fields: &[String]) -> String {
+    let fields = fields.iter().map(|x| csv_field(x)).collect::<Vec<_>>();
+    fields.join(",") + "\n"
+}
+
+/// One row per attempt, the wrong moves are given as `ply:played` separated by spaces
+pub fn attempts_csv(attempts: &[Attempt]) -> String {
+    let mut csv =
+        "started,color,chapter,result,line,seconds,moves,hints,mistakes,wrong\n".to_string();
+    for attempt in attempts {
+        let wrong = attempt
+            .mistakes
+            .iter()
+            .map(|x| format!("{}:{}", x.ply, x.played))
+            .collect::<Vec<_>>();
+        csv += &csv_row(&[
+            attempt.started.clone(),
+            attempt.color.clone(),
+            attempt.chapter.clone().unwrap_or_default(),
+            format!("{:?}", attempt.result).to_lowercase(),
+            attempt.line.join(" "),
+            format!("{:.1}", attempt.seconds),
+            attempt.moves.to_string(),
+            attempt.hints.to_string(),
+            attempt.mistakes.len().to_string(),
+            wrong.join(" "),
+        ]);
+    }
+    csv
+}
+
+pub fn lines_csv(lines: &[LineStats]) -> String {
+    let mut csv = "color,line,attempts,completed,moves,mistakes,hints,seconds,last\n".to_string();
+    for line in lines {
+        csv += &csv_row(&[
+            line.color.clone(),
+            line.line.join(" "),
+            line.totals.attempts.to_string(),
+            line.totals.completed.to_string(),
+            line.totals.moves.to_string(),
+            line.totals.mistakes.to_string(),
+            line.hints.to_string(),
+            format!("{:.1}", line.totals.seconds),
+            line.last.clone(),
+        ]);
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("chess-driller-attempts-{}", std::process::id()));
+        let mut tracker = AttemptTracker::new("white".to_string(), None);
+        tracker.hint();
+        let mistake = Mistake {
+            ply: 2,
+            played: "Bc4".to_string(),
+            expected: vec!["Nf3".to_string()],
+        };
+        tracker.moved(Some(mistake), true);
+        let tracker = AttemptTracker::resume(tracker.so_far(vec![]));
+        let attempt = tracker.finish(false, vec!["e4".to_string(), "e5".to_string()]);
+        record_attempt(&path, &attempt).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"line\": [\"d4\"")
+            .unwrap();
+
+        let attempts = load_attempts(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].result, DrillResult::Failed);
+        assert_eq!(attempts[0].hints, 1);
+        assert_eq!(attempts[0].mistakes[0].played, "Bc4");
+        assert!(load_attempts(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn count_streaks() {
+        let days = [
+            "2026-09-01",
+            "2026-09-02",
+            "2026-09-03",
+            "2026-10-12",
+            "2026-10-13",
+        ]
+        .iter()
+        .map(|day| DayStats {
+            day: day.to_string(),
+            totals: Totals::default(),
+        })
+        .collect::<Vec<_>>();
+        let date = |x| NaiveDate::parse_from_str(x, "%Y-%m-%d").unwrap();
+        let streak = streaks(&days, date("2026-10-14"));
+        assert_eq!(
+            streak,
+            Streak {
+                current: 2,
+                longest: 3
+            }
+        );
+        assert_eq!(streaks(&days, date("2026-10-15")).current, 0);
+    }
+
+    #[test]
+    fn split_sessions() {
+        let attempt = |started: &str| Attempt {
+            started: started.to_string(),
+            seconds: 600.0,
+            ..AttemptTracker::new("white".to_string(), None).finish(false, vec![])
+        };
+        let attempts = vec![
+            attempt("2026-10-01T10:00:00+02:00"),
+            attempt("2026-10-01T10:35:00+02:00"),
+            attempt("2026-10-01T11:30:00+02:00"),
+        ];
+        let sessions = sessions(attempts)
+            .iter()
+            .map(|x| x.attempts.len())
+            .collect::<Vec<_>>();
+        assert_eq!(sessions, vec![1, 2]);
+    }
+
+    #[test]
+    fn add_up_attempts() {
+        let attempt = |line: &str, started: &str, mistake: Option<usize>| Attempt {
+            line: line.split(' ').map(|x| x.to_string()).collect(),
+            color: "white".to_string(),
+            chapter: None,
+            started: started.to_string(),
+            result: if mistake.is_some() {
+                DrillResult::Failed
+            } else {
+                DrillResult::Completed
+            },
+            seconds: 6.0,
+            moves: 3,
+            hints: 0,
+            mistakes: mistake
+                .into_iter()
+                .map(|ply| Mistake {
+                    ply,
+                    played: "Bc4".to_string(),
+                    expected: vec!["Bb5".to_string()],
+                })
+                .collect(),
+        };
+        let attempts = [
+            attempt("e4 e5 Nf3 Nc6 Bb5", "2026-10-01T10:00:00+02:00", None),
+            attempt("e4 e5 Nf3 Nc6 Bc4", "2026-10-01T11:00:00+02:00", Some(4)),
+            attempt("e4 c5 Nf3", "2026-10-03T09:00:00+02:00", None),
+        ];
+        let summary = summarize(&attempts, &[]);
+        assert_eq!(summary.totals.attempts, 3);
+        assert_eq!(summary.totals.mistakes, 1);
+        assert_eq!(summary.chapters.len(), 1);
+        let days = summary
+            .days
+            .iter()
+            .map(|x| (x.day.as_str(), x.totals.attempts))
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![("2026-10-01", 2), ("2026-10-03", 1)]);
+        assert_eq!(summary.hardest.len(), 1);
+        assert_eq!(summary.hardest[0].moves.join(" "), "e4 e5 Nf3 Nc6");
+        assert_eq!(
+            (summary.hardest[0].mistakes, summary.hardest[0].reached),
+            (1, 2)
+        );
+        let lines = line_stats(&attempts);
+        let lines = lines
+            .iter()
+            .map(|x| (x.line.join(" "), x.totals.attempts))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                ("e4 c5 Nf3".to_string(), 1),
+                ("e4 e5 Nf3 Nc6".to_string(), 1),
+                ("e4 e5 Nf3 Nc6 Bb5".to_string(), 1)
+            ]
+        );
+        let openings = summary
+            .openings
+            .iter()
+            .map(|x| (x.moves.join(" "), x.totals.seconds))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            openings,
+            vec![
+                ("e4 e5 Nf3 Nc6".to_string(), 12.0),
+                ("e4 c5 Nf3".to_string(), 6.0)
+            ]
+        );
+        assert_eq!(csv_field("Sicilian, Taimanov"), "\"Sicilian, Taimanov\"");
+        // Bc4 is white's third move
+        assert_eq!(summary.heatmap[0].mistakes, vec![0, 0, 1]);
+        assert_eq!(summary.heatmap[0].reached, vec![3, 3, 2]);
+    }
+}
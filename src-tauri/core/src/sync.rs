@@ -0,0 +1,154 @@
+//! Keeps the training history in step with copies kept elsewhere, like a WebDAV server, so drills
+//! done on one machine count on the others.
+//!
+//! The attempts and imported reviews are only ever added to, never changed, so two copies are
+//! merged by taking every record in either. Records that are the same in both are kept once.
+use crate::archive::{archive_file, load_archive};
+use crate::config::Config;
+use crate::plugins::{storages, Storage};
+use crate::schedule::seeds_file;
+use crate::stats::attempts_file;
+use anyhow::Context;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Every record in `local` and `remote` once each, sorted by their `key` field. Lines that aren't
+/// JSON are dropped, they'd be half written.
+pub fn merge_records(local: &str, remote: &str, key: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut records = local
+        .lines()
+        .chain(remote.lines())
+        .filter_map(|x| serde_json::from_str::<Value>(x).ok())
+        .filter(|x| seen.insert(x.to_string()))
+        .collect::<Vec<_>>();
+    records.sort_by(|a, b| {
+        let key = |x: &Value| {
+            x.get(key)
+                .and_then(|x| x.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        key(a).cmp(&key(b))
+    });
+    records.iter().map(|x| x.to_string()).collect()
+}
+
+/// Whether a record's `key` is before the day `since`
+fn archived(record: &str, key: &str, since: &str) -> bool {
+    serde_json::from_str::<Value>(record)
+        .ok()
+        .and_then(|x| Some(x.get(key)?.as_str()?.get(..10)? < since))
+        .unwrap_or(false)
+}
+
+fn count_records(text: &str) -> usize {
+    text.lines()
+        .filter(|x| serde_json::from_str::<Value>(x).is_ok())
+        .count()
+}
+
+/// Merge the local file with the stored one and write the result back to both if either was
+/// missing anything, returning how many records came from the remote. Records with a `key`
+/// before `since` have been archived here so they're only kept on the remote.
+pub fn sync_file(
+    storage: &dyn Storage,
+    path: &Path,
+    key: &str,
+    since: Option<&str>,
+) -> anyhow::Result<usize> {
+    let name = path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .context("Nothing to sync")?;
+    let local = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let remote = storage
+        .get(name)
+        .with_context(|| format!("Couldn't download {}", name))?;
+    let merged = merge_records(&local, &remote, key);
+    let (local_count, remote_count) = (count_records(&local), count_records(&remote));
+    let text = |records: &[&String]| {
+        records
+            .iter()
+            .map(|x| format!("{}\n", x))
+            .collect::<String>()
+    };
+    let kept = merged
+        .iter()
+        .filter(|x| since.is_none_or(|since| !archived(x, key, since)))
+        .collect::<Vec<_>>();
+    if kept.len() > local_count {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("jsonl.partial");
+        fs::write(&partial, text(&kept))?;
+        fs::rename(&partial, path)?;
+    }
+    if merged.len() > remote_count {
+        storage
+            .put(name, text(&merged.iter().collect::<Vec<_>>()))
+            .with_context(|| format!("Couldn't upload {}", name))?;
+    }
+    Ok(kept.len().saturating_sub(local_count))
+}
+
+/// Sync the attempts and imported reviews with every copy in the config, `None` if there aren't
+/// any. Returns how many records were new here.
+pub fn sync_progress(config: &Config) -> anyhow::Result<Option<usize>> {
+    let storages = storages(config);
+    if storages.is_empty() {
+        return Ok(None);
+    }
+    let archived = load_archive(&archive_file(config))?.before;
+    let mut count = 0;
+    for storage in storages {
+        let name = storage.name();
+        let attempts = sync_file(
+            &*storage,
+            &attempts_file(config),
+            "started",
+            archived.as_deref(),
+        )
+        .with_context(|| format!("Couldn't sync with {}", name))?;
+        let reviews = sync_file(&*storage, &seeds_file(config), "reviewed", None)
+            .with_context(|| format!("Couldn't sync with {}", name))?;
+        info!(
+            "Synced with {}, got {} attempts and {} reviews",
+            name, attempts, reviews
+        );
+        count += attempts + reviews;
+    }
+    Ok(Some(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_both_copies() {
+        let local = "{\"started\":\"2026-10-02\",\"n\":2}\n{\"started\":\"2026-10-01\",\"n\":1}\n";
+        let remote =
+            "{\"n\":1,\"started\":\"2026-10-01\"}\n{\"started\":\"2026-10-03\",\"n\":3}\n{\"sta";
+        let merged = merge_records(local, remote, "started");
+        assert_eq!(
+            merged,
+            vec![
+                "{\"n\":1,\"started\":\"2026-10-01\"}",
+                "{\"n\":2,\"started\":\"2026-10-02\"}",
+                "{\"n\":3,\"started\":\"2026-10-03\"}"
+            ]
+        );
+        assert_eq!(count_records(remote), 2);
+        assert!(archived(&merged[0], "started", "2026-10-02"));
+        assert!(!archived(&merged[1], "started", "2026-10-02"));
+    }
+}
@@ -0,0 +1,216 @@
+//! A log of what gets used in the app and when, kept only if `usage_log` is on in the config. It
+//! stays in the data directory and is never sent anywhere, it's there so players can see how they
+//! train: which parts they use and how long they sit down for.
+use crate::config::Config;
+use chrono::{DateTime, Datelike, FixedOffset, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+/// Longer than this without doing anything starts a new session
+pub const SESSION_GAP_MINUTES: i64 = 30;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// RFC 3339
+    pub at: String,
+    /// Like `start` or `play_san`, the events on the board go by the name they're recorded with
+    pub feature: String,
+}
+
+pub fn usage_file(config: &Config) -> PathBuf {
+    config.data_dir().join("usage.jsonl")
+}
+
+/// Add a use of `feature` to the log, if it's being kept
+pub fn record_usage(config: &Config, feature: &str) {
+    if !config.usage_log {
+        return;
+    }
+    let path = usage_file(config);
+    let record = UsageRecord {
+        at: Local::now().to_rfc3339(),
+        feature: feature.to_string(),
+    };
+    if let Err(e) = append_usage(&path, &record) {
+        error!("Failed to log usage to {}: {}", path.display(), e);
+    }
+}
+
+pub fn append_usage(path: &Path, record: &UsageRecord) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// Every use in the log, none if there isn't one. Lines that can't be read are skipped, the last
+/// one can be half written.
+pub fn load_usage(path: &Path) -> anyhow::Result<Vec<UsageRecord>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|x| serde_json::from_str(x).ok())
+        .collect())
+}
+
+/// Throw the log away
+pub fn clear_usage(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => {
+            info!("Removed the usage log");
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FeatureUsage {
+    pub feature: String,
+    pub count: usize,
+    /// RFC 3339
+    pub last: String,
+}
+
+/// Uses with no more than [`SESSION_GAP_MINUTES`] between them
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct UsageSession {
+    /// RFC 3339
+    pub started: String,
+    /// From the first use to the last
+    pub minutes: f64,
+    pub uses: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct UsageSummary {
+    /// Most used first
+    pub features: Vec<FeatureUsage>,
+    /// Newest first
+    pub sessions: Vec<UsageSession>,
+    pub average_minutes: f64,
+    pub longest_minutes: f64,
+    /// Sessions started on each day of the week, Monday first
+    pub weekdays: [usize; 7],
+    /// Sessions started in each hour of the day
+    pub hours: [usize; 24],
+}
+
+/// What's been used most and how long each sitting was
+pub fn summarize_usage(records: &[UsageRecord]) -> UsageSummary {
+    let mut times = records
+        .iter()
+        .filter_map(|x| Some((DateTime::parse_from_rfc3339(&x.at).ok()?, x)))
+        .collect::<Vec<_>>();
+    times.sort_by_key(|x| x.0);
+
+    let mut features: BTreeMap<&str, FeatureUsage> = BTreeMap::new();
+    for (_, record) in &times {
+        let usage = features
+            .entry(&record.feature)
+            .or_insert_with(|| FeatureUsage {
+                feature: record.feature.clone(),
+                count: 0,
+                last: String::new(),
+            });
+        usage.count += 1;
+        usage.last = record.at.clone();
+    }
+    let mut features = features.into_values().collect::<Vec<_>>();
+    features.sort_by(|a, b| b.count.cmp(&a.count).then(a.feature.cmp(&b.feature)));
+
+    let mut summary = UsageSummary {
+        features,
+        ..Default::default()
+    };
+    let mut runs: Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>, usize)> = vec![];
+    for (at, _) in &times {
+        match runs.last_mut() {
+            Some((_, last, uses)) if (*at - *last).num_minutes() <= SESSION_GAP_MINUTES => {
+                *last = *at;
+                *uses += 1;
+            }
+            _ => runs.push((*at, *at, 1)),
+        }
+    }
+    for (started, last, uses) in runs.into_iter().rev() {
+        let minutes = (last - started).num_seconds() as f64 / 60.0;
+        summary.longest_minutes = summary.longest_minutes.max(minutes);
+        summary.weekdays[started.weekday().num_days_from_monday() as usize] += 1;
+        summary.hours[started.hour() as usize] += 1;
+        summary.sessions.push(UsageSession {
+            started: started.to_rfc3339(),
+            minutes,
+            uses,
+        });
+    }
+    if !summary.sessions.is_empty() {
+        summary.average_minutes =
+            summary.sessions.iter().map(|x| x.minutes).sum::<f64>() / summary.sessions.len() as f64;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn used(at: &str, feature: &str) -> UsageRecord {
+        UsageRecord {
+            at: at.to_string(),
+            feature: feature.to_string(),
+        }
+    }
+
+    #[test]
+    fn sessions_and_features() {
+        let records = vec![
+            used("2026-10-12T19:00:00+02:00", "start"),
+            used("2026-10-12T19:10:00+02:00", "play_san"),
+            used("2026-10-12T19:30:00+02:00", "play_san"),
+            // An hour later is another session
+            used("2026-10-12T20:30:00+02:00", "stats"),
+            used("2026-10-14T08:00:00+02:00", "start"),
+            used("2026-10-14T08:20:00+02:00", "start"),
+            used("yesterday", "start"),
+        ];
+        let summary = summarize_usage(&records);
+        assert_eq!(summary.features[0].feature, "start");
+        assert_eq!(summary.features[0].count, 3);
+        assert_eq!(summary.features[0].last, "2026-10-14T08:20:00+02:00");
+        let lengths = summary
+            .sessions
+            .iter()
+            .map(|x| (x.minutes, x.uses))
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, vec![(20.0, 2), (0.0, 1), (30.0, 3)]);
+        assert_eq!(summary.longest_minutes, 30.0);
+        assert!((summary.average_minutes - 50.0 / 3.0).abs() < 1e-9);
+        // The 12th is a Monday and the 14th a Wednesday
+        assert_eq!(summary.weekdays, [2, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(
+            (summary.hours[19], summary.hours[20], summary.hours[8]),
+            (1, 1, 1)
+        );
+
+        let dir = std::env::temp_dir().join(format!("chess-driller-usage-{}", std::process::id()));
+        let path = dir.join("usage.jsonl");
+        append_usage(&path, &records[0]).unwrap();
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "{\"at\":").unwrap();
+        assert_eq!(load_usage(&path).unwrap(), vec![records[0].clone()]);
+        clear_usage(&path).unwrap();
+        assert!(load_usage(&path).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
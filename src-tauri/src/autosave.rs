@@ -0,0 +1,227 @@
+//! Saves the drill or game that's going every few seconds, so a crash or the power going loses
+//! hardly any of it. The next launch offers to carry on from the save.
+use crate::config::Config;
+use crate::engine::Wdl;
+use crate::game::{parse_position, MoveHistory};
+use crate::stats::{Attempt, AttemptTracker};
+use crate::{lock_app, App};
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Color, EnPassantMode, Position};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// A drill, sparring game or endgame that was going, enough to set it up again
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub color: String,
+    pub chapter: Option<String>,
+    /// Where it started from, only endgames don't start from the starting position
+    pub fen: String,
+    pub moves: Vec<String>,
+    pub sparring: bool,
+    /// The result an endgame drill should come to
+    pub endgame: Option<Wdl>,
+    /// The drill's attempt so far
+    pub attempt: Option<Attempt>,
+    /// When it was saved, RFC 3339
+    pub saved: String,
+}
+
+pub fn autosave_file(config: &Config) -> PathBuf {
+    config.data_dir().join("autosave.json")
+}
+
+/// The save left behind by a run that didn't get to close properly
+pub fn load_autosave(path: &Path) -> Option<Snapshot> {
+    let text = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&text) {
+        Ok(snapshot) => Some(snapshot),
+        Err(e) => {
+            error!("Can't read the autosave {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Write to a file next to it and rename that over the old one, so a crash halfway through
+/// writing still leaves the last save
+pub fn save_autosave(path: &Path, snapshot: &Snapshot) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_vec(snapshot)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+pub fn remove_autosave(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => info!("Removed the autosave"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => error!("Can't remove the autosave {}: {}", path.display(), e),
+    }
+}
+
+impl App {
+    /// What's going on the board, `None` if there's nothing that would be lost
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        if !self.in_session() {
+            return None;
+        }
+        let moves = self
+            .history
+            .moves()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        Some(Snapshot {
+            color: self.color.to_string(),
+            chapter: self.chapters.get(self.color).clone(),
+            fen: Fen::from_position(self.history.start().clone(), EnPassantMode::Legal).to_string(),
+            sparring: self.sparring,
+            endgame: self.endgame,
+            attempt: self.attempt.as_ref().map(|x| x.so_far(moves.clone())),
+            moves,
+            saved: chrono::Local::now().to_rfc3339(),
+        })
+    }
+
+    /// Set a saved drill or game up again to carry on with it
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<String, String> {
+        let color = Color::from_str(&snapshot.color).map_err(|e| e.to_string())?;
+        info!(
+            "Carrying on from the autosave at {} with {} moves",
+            snapshot.saved,
+            snapshot.moves.len()
+        );
+        match snapshot.endgame {
+            Some(result) => {
+                self.start_endgame(&snapshot.fen, result)?;
+            }
+            None => {
+                if snapshot.chapter != *self.chapters.get(color) {
+                    let repertoire = self.config.repertoire(color);
+                    self.db
+                        .load_chapter(color, &repertoire, snapshot.chapter.as_deref())
+                        .map_err(|e| e.to_string())?;
+                    *self.chapters.get_mut(color) = snapshot.chapter;
+                }
+                self.reset(color);
+                // Games against the engine can start anywhere, like a Chess960 position
+                let start = parse_position(&snapshot.fen, false).map_err(|e| e.to_string())?;
+                if snapshot.sparring && start != self.game {
+                    self.history = MoveHistory::from_position(start.clone());
+                    self.game = start;
+                }
+            }
+        }
+        for san in &snapshot.moves {
+            let san = SanPlus::from_str(san).map_err(|e| e.to_string())?;
+            let mv = san
+                .san
+                .to_move(self.history.game())
+                .map_err(|e| format!("{} isn't legal: {}", san, e))?;
+            let mut next = self.history.game().clone();
+            next.play_unchecked(&mv);
+            self.history.push(san.clone(), next);
+            if snapshot.endgame.is_none() {
+                self.moves.push(san);
+            }
+        }
+        self.game = self.history.game().clone();
+        if snapshot.endgame.is_some() {
+            return Ok(self.board());
+        }
+        if snapshot.sparring {
+            self.spar();
+            return Ok(self.board());
+        }
+        let board = self.start();
+        if let Some(attempt) = snapshot.attempt {
+            self.attempt = Some(AttemptTracker::resume(attempt));
+        }
+        Ok(board)
+    }
+}
+
+/// Save whatever's going every `autosave` seconds from the drill settings, until the process
+/// ends. A save from the last run is left alone until it's been carried on with or thrown away.
+pub fn run(state: Arc<Mutex<App>>) {
+    let mut saved = false;
+    loop {
+        let seconds = lock_app(&state).config.drill.autosave;
+        thread::sleep(Duration::from_secs(seconds.max(1)));
+        let state = lock_app(&state);
+        if seconds == 0 || state.recovery.is_some() {
+            continue;
+        }
+        let path = autosave_file(&state.config);
+        match state.snapshot() {
+            Some(snapshot) => match save_autosave(&path, &snapshot) {
+                Ok(()) => saved = true,
+                Err(e) => error!("Can't autosave to {}: {}", path.display(), e),
+            },
+            None if saved => {
+                remove_autosave(&path);
+                saved = false;
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::{create_app, OpeningDatabase};
+
+    #[test]
+    fn carry_on_after_a_crash() {
+        let dir =
+            std::env::temp_dir().join(format!("chess-driller-autosave-{}", std::process::id()));
+        let mut config = Config {
+            data_path: Some(dir.clone()),
+            ..Default::default()
+        };
+        // The wrong move is taken back rather than ending the drill
+        config.drill.attempts = 2;
+        config.drill.spar = false;
+        let prep = "1. e4 e5 2. Nf3 Nc6 3. Bb5 *";
+        let mut app = create_app(&config);
+        app.db = OpeningDatabase::default();
+        app.db.add_prep(Color::White, prep.as_bytes()).unwrap();
+        assert!(app.snapshot().is_none());
+        app.handle(Event::Start).unwrap();
+        for san in ["e4", "Bc4"] {
+            app.handle(Event::PlaySan {
+                san: san.to_string(),
+            })
+            .unwrap();
+        }
+        let path = autosave_file(&app.config);
+        save_autosave(&path, &app.snapshot().unwrap()).unwrap();
+
+        let mut restored = create_app(&config);
+        restored.db = OpeningDatabase::default();
+        restored.db.add_prep(Color::White, prep.as_bytes()).unwrap();
+        let snapshot = load_autosave(&path).unwrap();
+        remove_autosave(&path);
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(snapshot.moves, vec!["e4", "e5"]);
+        restored.handle(Event::Restore { snapshot }).unwrap();
+        assert!(restored.is_drilling());
+        assert_eq!(restored.history.moves(), app.history.moves());
+        let attempt = restored.attempt.as_ref().unwrap().so_far(vec![]);
+        assert_eq!(attempt.mistakes[0].played, "Bc4");
+        assert!(load_autosave(&path).is_none());
+    }
+}
@@ -0,0 +1,298 @@
+//! Command line flags, these override whatever is in the config file for this run only.
+use crate::config::Config;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CliColor {
+    White,
+    Black,
+}
+
+/// Ways of working through the prep, matching the ones on the menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Drill,
+    /// Play out positions from the endgames folder against the engine
+    Endgame,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Start drilling straight away, the same as `--mode drill`
+    Drill(TerminalArgs),
+    /// Add PGN files to the repertoire for `--color` as new chapters
+    ImportPgn(ImportArgs),
+    /// Check every chapter in the repertoire loads and only has legal moves, exits with an error
+    /// if any don't
+    ValidateRepertoire,
+    /// Write the repertoire for `--color` out as PGN, one game per chapter
+    ExportPgn(ExportArgs),
+    /// Write the repertoire for `--color` out as an HTML booklet to print or read offline
+    Booklet(BookletArgs),
+    /// Print how drilling's been going
+    Stats(StatsArgs),
+    /// Check games against the repertoire, saying where each one left it and who by
+    Compliance(ComplianceArgs),
+    /// Stay in the background and send a desktop notification when lines come due or the daily
+    /// goal hasn't been met, see `[reminders]` in the config
+    Remind(RemindArgs),
+    /// Play scripts of events through a drill without a window, checking the `expect` lines in
+    /// them. Exits with an error if one isn't met.
+    RunScript(ScriptArgs),
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ImportArgs {
+    #[arg(required = true, value_name = "PGN")]
+    pub files: Vec<PathBuf>,
+    /// Name of the chapter, the file's name is used otherwise. Only for a single file.
+    #[arg(long, value_name = "NAME")]
+    pub chapter: Option<String>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ExportArgs {
+    /// Only export this chapter
+    #[arg(long, value_name = "NAME")]
+    pub chapter: Option<String>,
+    /// File to write to rather than printing it
+    #[arg(long, short, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct BookletArgs {
+    #[command(flatten)]
+    pub export: ExportArgs,
+    /// Have this booklet plugin from the config write it instead of making HTML
+    #[arg(long, value_name = "NAME")]
+    pub plugin: Option<String>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ComplianceArgs {
+    /// PGN files of the games
+    #[arg(required = true, value_name = "PGN")]
+    pub files: Vec<PathBuf>,
+    /// Whose games to check, as they're named in the PGN. With `--color` only their games with
+    /// that colour are checked.
+    #[arg(long, value_name = "NAME")]
+    pub player: String,
+    /// Print the report as JSON
+    #[arg(long, conflicts_with = "csv")]
+    pub json: bool,
+    /// Print the report as CSV, a row per game
+    #[arg(long)]
+    pub csv: bool,
+    /// File to write to rather than printing it
+    #[arg(long, short, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct RemindArgs {
+    /// Check once and exit, for running from cron or a scheduled task
+    #[arg(long)]
+    pub once: bool,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ScriptArgs {
+    #[arg(required = true, value_name = "SCRIPT")]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct StatsArgs {
+    /// Print everything the stats screen shows as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Clone, Debug, Default, Args)]
+pub struct TerminalArgs {
+    /// Drill in the terminal instead of opening a window
+    #[arg(long)]
+    pub no_gui: bool,
+    /// Drill in the terminal taking up the whole of it with a coloured board, rather than
+    /// printing the board after each move
+    #[arg(long)]
+    pub tui: bool,
+    /// Draw the pieces with letters rather than chess symbols in the terminal
+    #[arg(long)]
+    pub ascii: bool,
+}
+
+#[derive(Clone, Debug, Default, Parser)]
+#[command(version, about = "Drill your opening repertoire")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Profile to use, one is made if it doesn't exist yet
+    #[arg(
+        long,
+        value_name = "NAME",
+        env = "CHESS_DRILLER_PROFILE",
+        global = true
+    )]
+    pub profile: Option<String>,
+    /// PGN files or folders to drill instead of the repertoire in the config, they're used for
+    /// the colour given by `--color`
+    #[arg(long, value_name = "PGN", global = true)]
+    pub repertoire: Vec<PathBuf>,
+    /// Colour to drill, skips the menu
+    #[arg(long, global = true)]
+    pub color: Option<CliColor>,
+    /// Start drilling straight away in this mode
+    #[arg(long)]
+    pub mode: Option<Mode>,
+    /// Seconds allowed per move
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub move_time_limit: Option<u64>,
+    /// Chess.com usernames to sync games for, replacing the ones in the config
+    #[arg(long = "chess-com", value_name = "USER", global = true)]
+    pub chess_com: Vec<String>,
+    /// Record everything done on the board to this file
+    #[arg(long, value_name = "FILE", global = true)]
+    pub record_events: Option<PathBuf>,
+    /// Drive drills over JSON-RPC on this address instead of opening a window
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = crate::serve::DEFAULT_ADDRESS
+    )]
+    pub serve: Option<String>,
+}
+
+/// What to do once loading has finished when it's been given on the command line
+#[derive(Clone, Debug, Serialize)]
+pub struct Startup {
+    pub color: CliColor,
+    pub mode: Option<Mode>,
+}
+
+impl Cli {
+    /// Override the config with any flags that were given
+    pub fn apply(&self, config: &mut Config) {
+        if self.move_time_limit.is_some() {
+            config.move_time_limit = self.move_time_limit;
+        }
+        if !self.chess_com.is_empty() {
+            config.chess_com = self.chess_com.clone();
+        }
+        if self.record_events.is_some() {
+            config.record_events = self.record_events.clone();
+        }
+        if !self.repertoire.is_empty() {
+            match self.color.unwrap_or(CliColor::White) {
+                CliColor::White => config.white_repertoire = self.repertoire.clone(),
+                CliColor::Black => config.black_repertoire = self.repertoire.clone(),
+            }
+        }
+    }
+
+    /// Whether the menu can be skipped, picking a repertoire or mode without a colour drills white
+    pub fn startup(&self) -> Option<Startup> {
+        let mode = match self.command {
+            Some(Command::Drill(_)) => self.mode.or(Some(Mode::Drill)),
+            _ => self.mode,
+        };
+        if self.color.is_none() && mode.is_none() && self.repertoire.is_empty() {
+            return None;
+        }
+        Some(Startup {
+            color: self.color.unwrap_or(CliColor::White),
+            mode,
+        })
+    }
+
+    /// Whether to drill in the terminal rather than open the window
+    pub fn terminal(&self) -> Option<&TerminalArgs> {
+        match self.command.as_ref() {
+            Some(Command::Drill(args)) if args.no_gui || args.tui => Some(args),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a subcommand that runs and exits without drilling
+    pub fn scripted(&self) -> bool {
+        self.command
+            .as_ref()
+            .is_some_and(|x| !matches!(x, Command::Drill(_)))
+    }
+}
+
+impl From<CliColor> for shakmaty::Color {
+    fn from(color: CliColor) -> Self {
+        match color {
+            CliColor::White => shakmaty::Color::White,
+            CliColor::Black => shakmaty::Color::Black,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_override_config() {
+        let cli = Cli::parse_from([
+            "chess-driller",
+            "--repertoire",
+            "vienna.pgn",
+            "--color",
+            "black",
+            "--move-time-limit",
+            "10",
+        ]);
+        let mut config = Config {
+            move_time_limit: Some(30),
+            chess_com: vec!["someone".to_string()],
+            ..Default::default()
+        };
+        cli.apply(&mut config);
+        assert_eq!(config.move_time_limit, Some(10));
+        assert_eq!(config.chess_com, vec!["someone".to_string()]);
+        assert_eq!(config.black_repertoire, vec![PathBuf::from("vienna.pgn")]);
+        assert!(config.white_repertoire.is_empty());
+
+        let startup = cli.startup().unwrap();
+        assert_eq!(startup.color, CliColor::Black);
+        assert_eq!(startup.mode, None);
+        assert!(Cli::parse_from(["chess-driller"]).startup().is_none());
+
+        let cli = Cli::parse_from([
+            "chess-driller",
+            "drill",
+            "--no-gui",
+            "--ascii",
+            "--color",
+            "black",
+        ]);
+        assert!(cli.terminal().is_some_and(|x| x.ascii));
+        assert_eq!(cli.color, Some(CliColor::Black));
+        assert_eq!(cli.startup().unwrap().mode, Some(Mode::Drill));
+        assert!(Cli::parse_from(["chess-driller", "drill"])
+            .terminal()
+            .is_none());
+        assert!(!cli.scripted());
+
+        let cli = Cli::parse_from(["chess-driller", "stats", "--json", "--profile", "club"]);
+        assert!(cli.scripted());
+        assert!(matches!(
+            cli.command,
+            Some(Command::Stats(StatsArgs { json: true }))
+        ));
+        assert_eq!(cli.profile.as_deref(), Some("club"));
+        assert!(Cli::try_parse_from(["chess-driller", "import-pgn"]).is_err());
+
+        let cli = Cli::parse_from(["chess-driller", "--serve"]);
+        assert_eq!(cli.serve.as_deref(), Some(crate::serve::DEFAULT_ADDRESS));
+    }
+}
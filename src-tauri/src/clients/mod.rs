@@ -1 +0,0 @@
-pub mod chess_com;
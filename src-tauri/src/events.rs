@@ -0,0 +1,509 @@
+//! Everything done to the board goes through an [`Event`], these can be written out as they happen
+//! and fed back in later to replay a session, handy for reproducing bugs.
+use crate::autosave::Snapshot;
+use crate::engine::Wdl;
+use crate::{record_usage, App};
+use serde::{Deserialize, Serialize};
+use shakmaty::Color;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{error, info};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    /// Seed for the replies picked from the prep, this comes first so a replay gets the same ones
+    Seed {
+        seed: u64,
+    },
+    Start,
+    Reset {
+        color: String,
+    },
+    MovePiece {
+        from: String,
+        to: String,
+        promotion: String,
+    },
+    PlaySan {
+        san: String,
+    },
+    Undo,
+    HistoryBack,
+    HistoryForward,
+    HistorySibling {
+        offset: isize,
+    },
+    SetFen {
+        fen: String,
+    },
+    SelectChapter {
+        color: String,
+        chapter: Option<String>,
+    },
+    ImportPgn {
+        color: String,
+        pgn: String,
+    },
+    /// Start playing the engine from the latest position
+    Spar,
+    /// The engine's reply in UCI notation, recorded so a replay doesn't need the engine
+    EngineMove {
+        uci: String,
+    },
+    /// Start an endgame drill, `result` is what the engine said it should come to
+    Endgame {
+        fen: String,
+        result: Wdl,
+    },
+    /// Set up a line from the start to step through
+    ReplayLine {
+        color: String,
+        moves: Vec<String>,
+    },
+    /// Carry on with a drill that was autosaved
+    Restore {
+        snapshot: Snapshot,
+    },
+}
+
+impl Event {
+    /// What it goes down as in the usage log, the same as its `kind`. `None` for the ones the
+    /// player doesn't do themselves.
+    pub fn feature(&self) -> Option<&'static str> {
+        Some(match self {
+            Event::Seed { .. } | Event::EngineMove { .. } => return None,
+            Event::Start => "start",
+            Event::Reset { .. } => "reset",
+            Event::MovePiece { .. } => "move_piece",
+            Event::PlaySan { .. } => "play_san",
+            Event::Undo => "undo",
+            Event::HistoryBack => "history_back",
+            Event::HistoryForward => "history_forward",
+            Event::HistorySibling { .. } => "history_sibling",
+            Event::SetFen { .. } => "set_fen",
+            Event::SelectChapter { .. } => "select_chapter",
+            Event::ImportPgn { .. } => "import_pgn",
+            Event::Spar => "spar",
+            Event::Endgame { .. } => "endgame",
+            Event::ReplayLine { .. } => "replay_line",
+            Event::Restore { .. } => "restore",
+        })
+    }
+}
+
+impl App {
+    /// Write out a new recording to `path`, everything handled after this is added to it
+    pub fn start_recording(&mut self, path: &Path) -> anyhow::Result<()> {
+        info!("Recording events to {}", path.display());
+        fs::write(path, "")?;
+        self.recording = Some(path.to_path_buf());
+        let seed = fastrand::u64(..);
+        fastrand::seed(seed);
+        self.record(&Event::Seed { seed });
+        Ok(())
+    }
+
+    /// Add an event to the recording if there is one
+    pub fn record(&self, event: &Event) {
+        let Some(path) = self.recording.as_ref() else {
+            return;
+        };
+        let res = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(event)?));
+        if let Err(e) = res {
+            error!("Failed to record event: {}", e);
+        }
+    }
+
+    /// Record an event and apply it, returns the board afterwards
+    pub fn handle(&mut self, event: Event) -> Result<String, String> {
+        self.sounds.clear();
+        self.announcements.clear();
+        self.problems.clear();
+        self.record(&event);
+        if let Some(feature) = event.feature() {
+            record_usage(&self.config, feature);
+        }
+        self.apply(event)
+    }
+
+    fn apply(&mut self, event: Event) -> Result<String, String> {
+        match event {
+            Event::Seed { seed } => fastrand::seed(seed),
+            Event::Start => return Ok(self.start()),
+            Event::Reset { color } => {
+                let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+                self.reset(color);
+            }
+            Event::MovePiece {
+                from,
+                to,
+                promotion,
+            } => return Ok(self.move_piece(&from, &to, &promotion)),
+            Event::PlaySan { san } => return self.play_san(&san),
+            Event::Undo => return Ok(self.undo()),
+            Event::HistoryBack => return Ok(self.history_back()),
+            Event::HistoryForward => return Ok(self.history_forward()),
+            Event::HistorySibling { offset } => return Ok(self.history_sibling(offset)),
+            Event::SetFen { fen } => return self.set_fen(&fen),
+            Event::SelectChapter { color, chapter } => {
+                let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+                self.db
+                    .load_chapter(color, &self.config.repertoire(color), chapter.as_deref())
+                    .map_err(|e| e.to_string())?;
+                *self.chapters.get_mut(color) = chapter;
+            }
+            Event::Spar => self.spar(),
+            Event::EngineMove { uci } => return self.play_engine_move(&uci),
+            Event::Endgame { fen, result } => return self.start_endgame(&fen, result),
+            Event::ReplayLine { color, moves } => {
+                let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+                return self.replay_line(color, &moves);
+            }
+            Event::Restore { snapshot } => return self.restore(snapshot),
+            Event::ImportPgn { color, pgn } => {
+                let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+                self.db
+                    .add_prep(color, pgn.as_bytes())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(self.board())
+    }
+}
+
+/// Apply a recording made with [`App::start_recording`], returns the board at the end of it.
+/// Events that failed when recorded fail again here, they're skipped rather than stopping the replay.
+pub fn replay(app: &mut App, events: impl io::BufRead) -> anyhow::Result<String> {
+    let mut board = app.board();
+    for line in events.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: Event = serde_json::from_str(&line)?;
+        match app.apply(event) {
+            Ok(b) => board = b,
+            Err(e) => info!("Event failed during replay: {}", e),
+        }
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::MoveCheck;
+    use crate::{
+        attempts_file, create_app, load_attempts, Config, DrillResult, OpeningDatabase,
+        OpeningGraph,
+    };
+    use std::io::BufReader;
+
+    fn app() -> App {
+        let mut app = create_app(&Config::default());
+        app.db = OpeningDatabase::load(Path::new("prep")).unwrap();
+        app
+    }
+
+    #[test]
+    fn replay_recording() {
+        let path = std::env::temp_dir().join(format!("chess-driller-{}.jsonl", std::process::id()));
+        let mut app = app();
+        app.start_recording(&path).unwrap();
+        app.handle(Event::Reset {
+            color: "black".to_string(),
+        })
+        .unwrap();
+        app.handle(Event::Start).unwrap();
+        app.handle(Event::HistoryBack).unwrap();
+        app.handle(Event::HistorySibling { offset: 1 }).unwrap();
+        let board = app.handle(Event::HistoryForward).unwrap();
+
+        let mut replayed = self::app();
+        let file = fs::File::open(&path).unwrap();
+        let res = replay(&mut replayed, BufReader::new(file)).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(res, board);
+        assert_eq!(replayed.history.moves(), app.history.moves());
+    }
+
+    #[test]
+    fn remember_wrong_moves() {
+        let dir =
+            std::env::temp_dir().join(format!("chess-driller-attempts-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        app.handle(Event::Reset {
+            color: "white".to_string(),
+        })
+        .unwrap();
+        app.handle(Event::Start).unwrap();
+        app.handle(Event::PlaySan {
+            san: "h4".to_string(),
+        })
+        .unwrap();
+        let wrong = app.wrong_move.take().unwrap();
+        assert_eq!(wrong.played.to_string(), "h4");
+        assert_eq!(wrong.position, shakmaty::Chess::new());
+        assert!(!wrong.expected.is_empty());
+
+        // Starting again gives up on the attempt
+        app.handle(Event::Start).unwrap();
+        let attempts = load_attempts(&attempts_file(&app.config)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert_ne!(attempts[0].result, DrillResult::Completed);
+        assert_eq!(attempts[0].mistakes[0].played, "h4");
+    }
+
+    #[test]
+    fn illegal_move_in_the_prep() {
+        let dir = std::env::temp_dir().join(format!("chess-driller-broken-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        app.db = OpeningDatabase::default();
+        app.db
+            .add_prep(Color::White, "1. e4 Ke2 2. d4 *".as_bytes())
+            .unwrap();
+        app.handle(Event::Start).unwrap();
+        app.handle(Event::PlaySan {
+            san: "e4".to_string(),
+        })
+        .unwrap();
+        let attempts = load_attempts(&attempts_file(&app.config)).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(!app.is_drilling());
+        assert_eq!(app.history.moves().len(), 1);
+        assert_eq!(
+            app.problems[0].message,
+            "Ke2 in the prep can't be played after 1. e4"
+        );
+        assert_eq!(attempts[0].result, DrillResult::Abandoned);
+    }
+
+    #[test]
+    fn drill_chess960() {
+        let dir = std::env::temp_dir().join(format!("chess-driller-960-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        app.db = OpeningDatabase::default();
+        // With the rooks out of the corners the castling rights can only be Chess960 ones
+        let fen = "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1";
+        app.handle(Event::ImportPgn {
+            color: "white".to_string(),
+            pgn: format!(
+                "[Variant \"Chess960\"]\n[FEN \"{}\"]\n\n1. O-O O-O 2. d4 *",
+                fen
+            ),
+        })
+        .unwrap();
+        app.handle(Event::Reset {
+            color: "white".to_string(),
+        })
+        .unwrap();
+        app.handle(Event::Start).unwrap();
+        // The king dropped on its own rook castles
+        app.handle(Event::MovePiece {
+            from: "e1".to_string(),
+            to: "g1".to_string(),
+            promotion: String::new(),
+        })
+        .unwrap();
+        app.handle(Event::PlaySan {
+            san: "d4".to_string(),
+        })
+        .unwrap();
+        let moves = app
+            .history
+            .moves()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(moves, vec!["O-O", "O-O", "d4"]);
+        let pgn = app.history.to_pgn();
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        assert!(pgn.contains("[FEN \"1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w"));
+
+        // The engine castles as the king taking its rook
+        app.handle(Event::SetFen {
+            fen: fen.to_string(),
+        })
+        .unwrap();
+        app.handle(Event::Spar).unwrap();
+        app.handle(Event::PlaySan {
+            san: "O-O".to_string(),
+        })
+        .unwrap();
+        app.handle(Event::EngineMove {
+            uci: "e8g8".to_string(),
+        })
+        .unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(app.history.moves()[1].to_string(), "O-O");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hook_answers_got_off_the_lock() {
+        let dir = std::env::temp_dir().join(format!("chess-driller-hooks-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        app.db = OpeningDatabase::default();
+        app.db
+            .add_prep(Color::White, "1. e4 e5 2. Nf3 *".as_bytes())
+            .unwrap();
+        // Run while the event's handled the hook fails, leaving it to the prep
+        app.config.hooks.assess_move = Some(vec!["false".to_string()]);
+        app.handle(Event::Start).unwrap();
+        let (_, check) = app.move_check(&app.san_move("d4").unwrap()).unwrap();
+        app.assessed = Some((check, Some(false)));
+        app.handle(Event::PlaySan {
+            san: "d4".to_string(),
+        })
+        .unwrap();
+
+        // An answer about somewhere the drill's not at any more isn't used
+        app.handle(Event::Reset {
+            color: "white".to_string(),
+        })
+        .unwrap();
+        app.handle(Event::Start).unwrap();
+        let (_, check) = app.move_check(&app.san_move("d4").unwrap()).unwrap();
+        app.assessed = Some((
+            MoveCheck {
+                moves: vec!["e4".to_string()],
+                ..check
+            },
+            Some(false),
+        ));
+        app.handle(Event::PlaySan {
+            san: "d4".to_string(),
+        })
+        .unwrap();
+        let attempts = load_attempts(&attempts_file(&app.config)).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(attempts[0].mistakes.is_empty());
+        assert_eq!(attempts[1].mistakes[0].played, "d4");
+    }
+
+    #[test]
+    fn chapters_parsed_out_of_order() {
+        let mut app = app();
+        let first = app.pick_chapter(Color::White);
+        let second = app.pick_chapter(Color::White);
+        let black = app.pick_chapter(Color::Black);
+        let parsed = || (OpeningGraph::default(), shakmaty::Chess::new());
+        let (graph, start) = parsed();
+        assert!(app.chapter_picked(second, Some("najdorf".to_string()), graph, start));
+        // Done parsing after the later pick, it's not wanted any more
+        let (graph, start) = parsed();
+        assert!(!app.chapter_picked(first, Some("dragon".to_string()), graph, start));
+        assert_eq!(app.chapters.white.as_deref(), Some("najdorf"));
+        // Each colour has its own
+        let (graph, start) = parsed();
+        assert!(app.chapter_picked(black, None, graph, start));
+
+        // Nor is one for a profile that's been switched away from
+        let pick = app.pick_chapter(Color::Black);
+        app.load += 1;
+        let (graph, start) = parsed();
+        assert!(!app.chapter_picked(pick, Some("caro".to_string()), graph, start));
+        assert_eq!(app.chapters.black, None);
+    }
+
+    #[test]
+    fn move_one_of_two_knights() {
+        let mut app = app();
+        app.db = OpeningDatabase::default();
+        for san in ["d4", "d5", "Nf3", "e6"] {
+            app.handle(Event::PlaySan {
+                san: san.to_string(),
+            })
+            .unwrap();
+        }
+        // Both knights can go to d2, the square it came from says which
+        let played = app
+            .handle(Event::MovePiece {
+                from: "b1".to_string(),
+                to: "d2".to_string(),
+                promotion: "wN".to_string(),
+            })
+            .unwrap();
+        assert_eq!(app.history.moves()[4].to_string(), "Nbd2");
+        // A square that isn't one leaves the board as it was
+        let board = app
+            .handle(Event::MovePiece {
+                from: "z9".to_string(),
+                to: "d2".to_string(),
+                promotion: String::new(),
+            })
+            .unwrap();
+        assert_eq!(board, played);
+        assert_eq!(app.history.moves().len(), 5);
+    }
+
+    #[test]
+    fn spar_until_mate() {
+        let dir = std::env::temp_dir().join(format!("chess-driller-spar-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        app.handle(Event::Spar).unwrap();
+        for (san, uci) in [("f3", "e7e5"), ("g4", "d8h4")] {
+            app.handle(Event::PlaySan {
+                san: san.to_string(),
+            })
+            .unwrap();
+            assert!(app.engine_to_move());
+            app.handle(Event::EngineMove {
+                uci: uci.to_string(),
+            })
+            .unwrap();
+        }
+        assert!(!app.sparring);
+        assert!(!app.engine_to_move());
+        let saved = fs::read_dir(dir.join("sessions")).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saved, 1);
+        assert!(app.history.to_pgn().ends_with("2. g4 Qh4# 0-1\n"));
+    }
+
+    #[test]
+    fn stalemate_in_a_won_endgame() {
+        let dir =
+            std::env::temp_dir().join(format!("chess-driller-endgame-{}", std::process::id()));
+        let mut app = app();
+        app.config.data_path = Some(dir.clone());
+        let start = app
+            .handle(Event::Endgame {
+                fen: "k7/8/1K6/8/8/8/8/2Q5 w - - 0 1".to_string(),
+                result: Wdl::Win,
+            })
+            .unwrap();
+        // Stalemate, so it's taken back
+        let board = app
+            .handle(Event::PlaySan {
+                san: "Qc7".to_string(),
+            })
+            .unwrap();
+        assert_eq!(board, start);
+        assert!(app.history.moves().is_empty());
+        assert_eq!(app.mistake.take().map(|x| x.result), Some(Wdl::Draw));
+        assert_eq!(app.endgame, Some(Wdl::Win));
+        assert!(app.sparring);
+
+        app.handle(Event::PlaySan {
+            san: "Qc8#".to_string(),
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(app.mistake.is_none());
+        assert!(!app.sparring);
+    }
+}
@@ -1,156 +1,2675 @@
-use shakmaty::{san::SanPlus, Chess, Color, Position, Role, Square};
+use crate::autosave::{autosave_file, load_autosave, remove_autosave, Snapshot};
+use crate::hooks::{DueLine, LineChoice, MoveCheck};
+use chrono::NaiveDate;
+use serde::Serialize;
+use shakmaty::fen::Fen;
+use shakmaty::uci::Uci;
+use shakmaty::{
+    san::SanPlus, ByColor, Chess, Color, EnPassantMode, Move, Outcome, Position, Role, Square,
+};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Mutex;
-use tracing::{error, info};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tracing::{error, info, warn};
 
-pub mod clients;
-pub mod config;
-pub mod db;
-pub mod game;
+pub mod autosave;
+pub mod cli;
+pub mod events;
+pub mod logging;
+pub mod remind;
+pub mod script;
+pub mod serve;
+pub mod subcommands;
+pub mod terminal;
 
-pub use crate::clients::chess_com::*;
-pub use crate::config::*;
-pub use crate::db::*;
+pub use crate::cli::*;
+pub use crate::events::*;
+pub use chess_driller_core::*;
 
-pub struct ChessState(Mutex<App>);
+pub struct ChessState(Arc<Mutex<App>>);
+
+/// Lock the app, carrying on with it as it was left if something panicked while holding it
+/// rather than having every command after that panic too
+pub(crate) fn lock_app(app: &Mutex<App>) -> MutexGuard<'_, App> {
+    app.lock().unwrap_or_else(PoisonError::into_inner)
+}
 
 #[derive(Clone)]
 pub struct App {
-    chess_com_usernames: Vec<String>,
     db: OpeningDatabase,
     color: Color,
     game: Chess,
     game_state: Option<GameState>,
     moves: Vec<SanPlus>,
+    history: MoveHistory,
+    config: Config,
+    loading: LoadingStatus,
+    /// Counts the loads started, one for a profile that's since been switched away from drops
+    /// what it loaded rather than writing it over the new one's
+    load: u64,
+    /// Where events are being recorded to, if they are
+    recording: Option<PathBuf>,
+    /// Flags the app was started with, these apply to whichever profile is picked
+    cli: Cli,
+    /// A drill saved by the last run that's not been carried on with or thrown away yet
+    recovery: Option<Snapshot>,
+    /// Playing the engine rather than drilling
+    sparring: bool,
+    /// Started the first time it's needed and kept running after that
+    engine: Option<Arc<UciEngine>>,
+    /// Which of `engines` in the config sparring and analysis use, `engine` if none
+    active_engine: Option<String>,
+    /// Full strength engine for explaining wrong moves, started the first time one's made
+    explainer: Option<Arc<UciEngine>>,
+    /// A wrong move in the drill for the engine to explain
+    wrong_move: Option<WrongMove>,
+    /// Chapter picked for each colour, `None` for all of them
+    chapters: ByColor<Option<String>>,
+    /// Counts the chapters picked for each colour, one that's done parsing after a later pick is
+    /// thrown away
+    chapter_picks: ByColor<u64>,
+    /// The drill going on now, recorded in the stats when it's over
+    attempt: Option<AttemptTracker>,
+    /// Line the drill's replies head for when they're picked by what's due
+    target: Vec<String>,
+    /// Evals from earlier runs, read the first time they're needed
+    evals: Option<Arc<EvalCache>>,
+    /// FEN of the position the engine is analysing
+    analysis: Option<String>,
+    /// The player's games from chess.com
+    games: OpeningDatabase,
+    /// In an endgame drill, the result the player should get with best play
+    endgame: Option<Wdl>,
+    /// An endgame move that was taken back, for the frontend to hear about
+    mistake: Option<EndgameMistake>,
+    /// Sounds for what the last event did, in the order it happened
+    sounds: Vec<SoundEffect>,
+    /// The opponent's moves in the last event, to be read out
+    announcements: Vec<String>,
+    /// Anything the last event ran into that the user should hear about
+    problems: Vec<Problem>,
+    /// What the `select_line` hook answered when a command asked it off the lock, the drill
+    /// starting uses it if it'd ask the same
+    picked_line: Option<(LineChoice, Option<Vec<String>>)>,
+    /// The same for the `assess_move` hook and the move being played
+    assessed: Option<(MoveCheck, Option<bool>)>,
 }
 
-fn create_app() -> anyhow::Result<App> {
-    let config = Config::load()?;
-    let chess_dot_com = ChessComClient::new();
-    let db = OpeningDatabase::load_default()?;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEffect {
+    Move,
+    Capture,
+    Check,
+    /// A move that counts as a mistake in the drill
+    Mistake,
+    /// The drill got to the end of the line
+    LineComplete,
+}
 
-    let game_state = db.start_drill(Color::White, &[]);
+impl SoundEffect {
+    /// For a move that's been played to reach `position`
+    fn of_move(position: &Chess, mv: &Move) -> Self {
+        if position.is_check() {
+            Self::Check
+        } else if mv.is_capture() {
+            Self::Capture
+        } else {
+            Self::Move
+        }
+    }
+}
 
-    Ok(App {
-        chess_com_usernames: config.chess_com,
-        db,
-        color: Color::White,
-        game: Chess::new(),
-        moves: vec![],
-        game_state,
-    })
+/// A chapter that's being parsed off the lock, to check nothing's changed before it's put in
+#[derive(Clone, Debug)]
+struct ChapterPick {
+    color: Color,
+    repertoire: Vec<PathBuf>,
+    load: u64,
+    pick: u64,
 }
 
-pub fn launch() {
-    tauri::Builder::default()
-        .manage(ChessState(Mutex::new(create_app().unwrap())))
-        .invoke_handler(tauri::generate_handler![
-            commands::move_piece,
-            commands::start,
-            commands::reset
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+/// An endgame drill that's been set up
+#[derive(Clone, Debug, Serialize)]
+pub struct EndgameStart {
+    pub board: String,
+    pub color: String,
+    pub result: Wdl,
 }
 
-pub mod commands {
-    use super::*;
-    use tauri::State;
+/// A move in an endgame drill that made the result worse, it's taken back to try again
+#[derive(Clone, Debug, Serialize)]
+pub struct EndgameMistake {
+    pub expected: Wdl,
+    pub result: Wdl,
+    pub board: String,
+}
 
-    #[tauri::command]
-    pub fn start(state: State<ChessState>) -> String {
-        let mut state = state.0.lock().unwrap();
-        if state.color == Color::White {
-            state.game_state = state.db.start_drill(Color::White, &state.moves);
-        } else {
-            state.game_state = state.db.start_drill(Color::Black, &state.moves);
+#[derive(Clone, Debug, Serialize)]
+pub struct EngineList {
+    /// The ones in `engines` in the config
+    pub names: Vec<String>,
+    pub active: Option<String>,
+}
+
+/// Moves for the move list panel
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct MoveList {
+    pub moves: Vec<String>,
+    /// Number of moves played to reach the position being shown
+    pub cursor: usize,
+    /// How the game ended if it has e.g. `1-0`
+    pub result: Option<String>,
+    /// The rule that drew it, if one did
+    pub draw: Option<DrawRule>,
+}
+
+/// A drill from the last run set up again
+#[derive(Clone, Debug, Serialize)]
+pub struct Recovered {
+    pub board: String,
+    pub color: String,
+    /// Whether it's a drill from the prep rather than playing the engine
+    pub drilling: bool,
+}
+
+/// Result of setting up a position from a FEN
+#[derive(Clone, Debug, Serialize)]
+pub struct Setup {
+    pub board: String,
+    /// If the position is in the prep, then it can be drilled from
+    pub in_prep: bool,
+}
+
+/// Profiles to pick from on the menu
+#[derive(Clone, Serialize)]
+pub struct Profiles {
+    profiles: Vec<String>,
+    current: Option<String>,
+}
+
+/// Progress of the databases being loaded in the background, shown on the splash screen
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LoadingStatus {
+    pub message: String,
+    pub done: usize,
+    pub total: usize,
+    pub finished: bool,
+    /// Anything that went wrong that the user should know about
+    pub problems: Vec<Problem>,
+}
+
+/// Something that went wrong, shown in the window rather than taking the app down
+#[derive(Clone, Debug, Serialize)]
+pub struct Problem {
+    pub message: String,
+    /// What might sort it out
+    pub fix: Option<String>,
+}
+
+impl Problem {
+    pub fn new(message: impl ToString, fix: Option<String>) -> Self {
+        Self {
+            message: message.to_string(),
+            fix,
+        }
+    }
+}
+
+/// The problems found checking a config, they can all be fixed by editing it
+fn config_problems(config: &Config, problems: Vec<String>) -> Vec<Problem> {
+    let fix = format!("Change it in {}", config.path().display());
+    problems
+        .into_iter()
+        .map(|x| Problem::new(x, Some(fix.clone())))
+        .collect()
+}
+
+impl App {
+    /// If a drill has moves played in it, closing the window would lose them
+    pub fn in_session(&self) -> bool {
+        (self.game_state.is_some() || self.sparring) && !self.history.moves().is_empty()
+    }
+
+    /// Write the game so far out as a PGN in the data directory
+    pub fn save_session(&self) -> anyhow::Result<PathBuf> {
+        let dir = self.config.data_dir().join("sessions");
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!(
+            "{}.pgn",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+        ));
+        fs::write(&path, self.history.to_pgn())?;
+        info!("Saved session to {}", path.display());
+        Ok(path)
+    }
+
+    pub fn board(&self) -> String {
+        self.history.current().board().to_string()
+    }
+
+    pub fn start(&mut self) -> String {
+        if !self.history.from_start(self.db.start(self.color)) {
+            info!("Can't drill from a position that isn't in the prep");
+            return self.game.board().to_string();
         }
-        let mut game_state = state.game_state.take();
+        self.target = match self.config.drill.replies {
+            ReplyPolicy::Due => self.next_due(),
+            _ => vec![],
+        };
+        if let Some(line) = self.hooked_line() {
+            self.target = line;
+        }
+        self.game_state = self.start_drill(&self.moves);
+        self.finish_attempt(true);
+        if self.game_state.is_some() {
+            let chapter = self.chapters.get(self.color).clone();
+            self.attempt = Some(AttemptTracker::new(self.color.to_string(), chapter));
+        }
+        let mut game_state = self.game_state.take();
+        let mut broken = false;
         if let Some(game_state) = game_state.as_mut() {
             if !game_state.is_player_turn() {
-                let mv = game_state.make_move(state.db.graph(state.color));
-                if let Some(mv) = mv {
-                    let game = state.game.clone();
-
-                    let mv = mv.san.to_move(&game).unwrap();
-                    let new_game = game.clone().play(&mv).unwrap();
-                    state.game = new_game;
+                let mv = game_state.make_move(self.db.graph(self.color));
+                if let Some(san) = mv {
+                    if let Err(e) = self.play_reply(san) {
+                        self.broken_prep(e);
+                        broken = true;
+                    }
                 }
             }
         }
-        state.game_state = game_state;
-        state.game.board().to_string()
+        self.game_state = game_state.filter(|_| !broken);
+        self.check_drill_over();
+        self.check_attempt_over();
+        self.game.board().to_string()
     }
 
-    #[tauri::command]
-    pub fn reset(color: &str, state: State<ChessState>) {
-        info!("Resetting board for {}", color);
-        let mut state = state.0.lock().unwrap();
-        let color = Color::from_str(color).unwrap();
-        state.color = color;
-        state.game = Chess::new();
-        state.game_state = None;
-        state.moves.clear();
+    /// Play the prep's reply to the player
+    fn play_reply(&mut self, reply: SanPlus) -> anyhow::Result<()> {
+        let mv = reply.san.to_move(&self.game).map_err(|_| {
+            let line = self
+                .history
+                .moves()
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>();
+            match line.is_empty() {
+                true => anyhow::anyhow!("{} in the prep can't be played from the start", reply),
+                false => anyhow::anyhow!(
+                    "{} in the prep can't be played after {}",
+                    reply,
+                    numbered_line(&line)
+                ),
+            }
+        })?;
+        self.game.play_unchecked(&mv);
+        self.sounds.push(SoundEffect::of_move(&self.game, &mv));
+        self.announcements.push(spoken_move(&reply));
+        self.history.push(reply, self.game.clone());
+        Ok(())
+    }
+
+    /// A move in the prep that can't be played ends the drill there, it's abandoned rather than
+    /// counting against the player
+    fn broken_prep(&mut self, e: anyhow::Error) {
+        error!("{}", e);
+        self.finish_attempt(true);
+        self.problems.push(Problem::new(
+            e,
+            Some(
+                "Fix the move in the prep file, `chess-driller validate-repertoire` lists any \
+                 others"
+                    .to_string(),
+            ),
+        ));
+    }
+
+    /// Record the drill's attempt once it's over
+    fn check_attempt_over(&mut self) {
+        if !self.game_state.as_ref().is_some_and(|x| x.still_running()) {
+            self.finish_attempt(false);
+        }
+    }
+
+    /// Record the attempt at the drill as it stands, abandoned ones only if a move was made
+    fn finish_attempt(&mut self, abandoned: bool) {
+        let Some(tracker) = self.attempt.take() else {
+            return;
+        };
+        if abandoned && !tracker.started() {
+            return;
+        }
+        let line = self.history.moves().iter().map(|x| x.to_string()).collect();
+        let attempt = tracker.finish(abandoned, line);
+        info!("Drill {:?} after {} moves", attempt.result, attempt.moves);
+        if attempt.result == DrillResult::Completed {
+            self.sounds.push(SoundEffect::LineComplete);
+        }
+        if let Err(e) = record_attempt(&attempts_file(&self.config), &attempt) {
+            error!("Couldn't record the attempt: {}", e);
+        }
+        // Nothing waits on what it says, so it's run off the lock rather than holding the app up
+        if let Some(command) = self.config.hooks.session_end.clone() {
+            thread::spawn(move || hooks::session_end(&command, &attempt));
+        }
+    }
+
+    pub fn reset(&mut self, color: Color) {
+        self.finish_attempt(true);
+        self.color = color;
+        self.game = self.db.start(color).clone();
+        self.game_state = None;
+        self.sparring = false;
+        self.endgame = None;
+        self.moves.clear();
+        self.history = MoveHistory::from_position(self.game.clone());
         info!("Board reset");
     }
 
-    #[tauri::command]
-    pub fn move_piece(from: &str, to: &str, promotion: &str, state: State<ChessState>) -> String {
-        info!("Args: {}->{} {}", from, to, promotion);
+    /// Take back the last move, while drilling this is the player's last move and the reply to it.
+    pub fn undo(&mut self) -> String {
+        if self.sparring {
+            self.history.take_back(self.color);
+        } else if self.game_state.is_some() {
+            if self.history.take_back(self.color) {
+                info!("Taking back move");
+                self.game_state = self.start_drill(&self.history.moves());
+            }
+        } else {
+            self.history.pop();
+        }
+        self.moves.truncate(self.history.moves().len());
+        self.game = self.history.game().clone();
+        self.game.board().to_string()
+    }
+
+    /// Set the board up from a FEN. If the position is in the prep the moves reaching it are played
+    /// out so a drill can carry on from there
+    pub fn set_fen(&mut self, fen: &str) -> Result<String, String> {
+        let position = parse_fen(fen)?;
+        self.finish_attempt(true);
+        self.game_state = None;
+        self.sparring = false;
+        self.endgame = None;
+        self.moves.clear();
+        let start = self.db.start(self.color);
+        let path = find_position(self.db.graph(self.color), start, &position);
+        match path {
+            Some(path) => {
+                self.history = MoveHistory::from_position(start.clone());
+                for san in path {
+                    let mv = san.san.to_move(self.history.game()).unwrap();
+                    let next = self.history.game().clone().play(&mv).unwrap();
+                    self.history.push(san.clone(), next);
+                    self.moves.push(san);
+                }
+            }
+            None => self.history = MoveHistory::from_position(position),
+        }
+        self.game = self.history.game().clone();
+        Ok(self.game.board().to_string())
+    }
 
-        let mut state = state.0.lock().unwrap();
-        let sel_square = Square::from_ascii(from.as_bytes()).unwrap();
-        let promotion_square = Square::from_ascii(to.as_bytes()).unwrap();
+    /// Play out a line from an earlier drill and go back to the start of it to step through. It
+    /// can be drilled from any point that's still in the prep.
+    pub fn replay_line(&mut self, color: Color, moves: &[String]) -> Result<String, String> {
+        self.reset(color);
+        for san in moves {
+            let san = SanPlus::from_str(san).map_err(|e| e.to_string())?;
+            let mv = san
+                .san
+                .to_move(self.history.game())
+                .map_err(|e| format!("{} isn't legal: {}", san, e))?;
+            let next = self.history.game().clone().play(&mv).unwrap();
+            self.history.push(san.clone(), next);
+            self.moves.push(san);
+        }
+        self.game = self.history.game().clone();
+        while self.history.back() {}
+        Ok(self.board())
+    }
 
-        let board = state.game.board();
+    pub fn history_back(&mut self) -> String {
+        self.history.back();
+        self.board()
+    }
 
-        let piece = board.piece_at(sel_square).unwrap();
+    pub fn history_forward(&mut self) -> String {
+        self.history.forward(self.db.graph(self.color));
+        self.board()
+    }
 
-        let moves = state.game.san_candidates(piece.role, promotion_square);
+    pub fn history_sibling(&mut self, offset: isize) -> String {
+        self.history.sibling(self.db.graph(self.color), offset);
+        self.board()
+    }
 
-        // Move wasn't legal!
-        if moves.is_empty() {
-            return state.game.board().to_string();
+    /// Play a move typed in as SAN
+    pub fn play_san(&mut self, san: &str) -> Result<String, String> {
+        if !self.history.is_latest() {
+            self.history.latest();
         }
+        let game_move = self.san_move(san)?;
+        self.play_move(&game_move);
+        Ok(self.game.board().to_string())
+    }
 
-        // There must be a promotion available!
-        let game_move = if moves.len() > 1 {
-            let promo = Role::from_char(promotion.chars().nth(1).unwrap()).unwrap();
-            moves.iter().find(|x| x.promotion() == Some(promo)).unwrap()
-        } else {
-            &moves[0]
-        };
+    /// The move typed in as SAN
+    fn san_move(&self, san: &str) -> Result<Move, String> {
+        let san = SanPlus::from_ascii(san.trim().as_bytes()).map_err(|e| e.to_string())?;
+        san.san.to_move(&self.game).map_err(|e| e.to_string())
+    }
+
+    pub fn move_piece(&mut self, from: &str, to: &str, promotion: &str) -> String {
+        // Moves can only be made in the latest position, not while looking back through the game
+        if !self.history.is_latest() {
+            self.history.latest();
+            return self.game.board().to_string();
+        }
+        if let Some(game_move) = self.piece_move(from, to, promotion) {
+            self.play_move(&game_move);
+        }
+        self.game.board().to_string()
+    }
+
+    /// The move of the piece dragged from `from` to `to`, `None` if it isn't legal
+    fn piece_move(&self, from: &str, to: &str, promotion: &str) -> Option<Move> {
+        let sel_square = Square::from_ascii(from.as_bytes()).ok()?;
+        let promotion_square = Square::from_ascii(to.as_bytes()).ok()?;
+        let piece = self.game.board().piece_at(sel_square)?;
+
+        // Other pieces of the same kind could go there too, only the one that was moved counts
+        let mut moves = self.game.san_candidates(piece.role, promotion_square);
+        moves.retain(|x| x.from() == Some(sel_square));
+        // Castling is the king dropped on its rook or where it ends up, in Chess960 a plain king
+        // move to the same square comes first
+        if moves.is_empty() && piece.role == Role::King {
+            moves = self.game.legal_moves();
+            moves.retain(|x| {
+                x.is_castle()
+                    && (x.to() == promotion_square
+                        || castled_king(&self.game, x) == Some(promotion_square))
+            });
+        }
 
         info!("Move list: {:?}", moves);
 
-        let san = SanPlus::from_move(state.game.clone(), game_move);
+        // The piece is like "wQ", only looked at when the move promotes
+        let promo = promotion.chars().last().and_then(Role::from_char);
+        moves
+            .into_iter()
+            .find(|x| x.promotion().is_none() || x.promotion() == promo)
+    }
+
+    /// Play the engine from the latest position, the player keeps the colour they were drilling
+    pub fn spar(&mut self) {
+        info!("Playing the engine");
+        self.finish_attempt(true);
+        self.history.latest();
+        self.game_state = None;
+        self.sparring = true;
+        self.endgame = None;
+    }
+
+    /// Play out an endgame against the engine from the position, moves that make `result` worse
+    /// for the side to move are taken back
+    pub fn start_endgame(&mut self, fen: &str, result: Wdl) -> Result<String, String> {
+        let position = parse_fen(fen)?;
+        info!("Drilling the endgame {}, it should be a {:?}", fen, result);
+        self.finish_attempt(true);
+        self.color = position.turn();
+        self.game_state = None;
+        self.moves.clear();
+        self.history = MoveHistory::from_position(position);
+        self.game = self.history.game().clone();
+        self.sparring = true;
+        self.endgame = Some(result);
+        Ok(self.board())
+    }
+
+    /// Whether the player's last move in an endgame drill made it `result` rather than what it
+    /// should be, remembering it if so. Better results from the engine going wrong are kept.
+    fn endgame_mistake(&mut self, result: Wdl) -> bool {
+        let Some(expected) = self.endgame else {
+            return false;
+        };
+        if result >= expected {
+            self.endgame = Some(result);
+            return false;
+        }
+        info!(
+            "That made the endgame a {:?} instead of a {:?}",
+            result, expected
+        );
+        self.mistake = Some(EndgameMistake {
+            expected,
+            result,
+            board: String::new(),
+        });
+        true
+    }
+
+    /// If it's the engine's turn in a game against it
+    pub fn engine_to_move(&self) -> bool {
+        self.sparring && self.game.turn() != self.color && self.history.outcome().is_none()
+    }
+
+    /// The engine, starting it if it isn't running yet
+    pub fn engine(&mut self) -> anyhow::Result<Arc<UciEngine>> {
+        if self.engine.is_none() {
+            self.engine = Some(Arc::new(UciEngine::start(self.engine_settings())?));
+        }
+        Ok(self.engine.clone().unwrap())
+    }
+
+    /// The engine for explaining wrong moves, sparring can be set up to play weaker so it has
+    /// its own at full strength
+    pub fn explainer(&mut self) -> anyhow::Result<Arc<UciEngine>> {
+        if self.explainer.is_none() {
+            let settings = config::Engine {
+                skill_level: None,
+                elo: None,
+                ..self.config.engine.clone()
+            };
+            self.explainer = Some(Arc::new(UciEngine::start(&settings)?));
+        }
+        Ok(self.explainer.clone().unwrap())
+    }
+
+    /// Settings for the engine being used, `engine` if the one picked has gone from the config
+    pub fn engine_settings(&self) -> &config::Engine {
+        self.config
+            .engine_named(self.active_engine.as_deref())
+            .unwrap_or(&self.config.engine)
+    }
+
+    /// Switch to another engine, the one running now is stopped and the new one is started when
+    /// it's next needed
+    pub fn select_engine(&mut self, name: Option<String>) -> anyhow::Result<()> {
+        self.config.engine_named(name.as_deref())?;
+        if name != self.active_engine {
+            info!("Switching to engine {:?}", name);
+            self.analysis = None;
+            self.engine = None;
+            self.active_engine = name;
+        }
+        Ok(())
+    }
+
+    /// The evals saved in the cache directory
+    pub fn eval_cache(&mut self) -> Arc<EvalCache> {
+        let path = self.config.cache_dir().join("evals.jsonl");
+        self.evals
+            .get_or_insert_with(|| Arc::new(EvalCache::open(&path)))
+            .clone()
+    }
+
+    /// Play the move the engine came up with
+    pub fn play_engine_move(&mut self, uci: &str) -> Result<String, String> {
+        let mv = Uci::from_str(uci)
+            .map_err(|e| e.to_string())?
+            .to_move(&self.game)
+            .map_err(|e| e.to_string())?;
+        let san = SanPlus::from_move(self.game.clone(), &mv);
+        info!("Engine played {}", san);
+        self.game = self.game.clone().play(&mv).map_err(|e| e.to_string())?;
+        self.sounds.push(SoundEffect::of_move(&self.game, &mv));
+        self.announcements.push(spoken_move(&san));
+        self.history.push(san.clone(), self.game.clone());
+        self.moves.push(san);
+        self.check_game_over();
+        Ok(self.board())
+    }
+
+    /// Once the drill's over carry on against the engine, if that's turned on
+    fn check_drill_over(&mut self) {
+        let over = self.game_state.as_ref().is_some_and(|x| !x.still_running());
+        if over && self.config.drill.spar && self.history.outcome().is_none() {
+            info!("Out of the prep, carrying on against the engine");
+            self.game_state = None;
+            self.sparring = true;
+        }
+    }
+
+    /// Save games against the engine when they finish
+    fn check_game_over(&mut self) {
+        if !self.sparring {
+            return;
+        }
+        let Some(outcome) = self.history.outcome() else {
+            return;
+        };
+        let result = match outcome {
+            Outcome::Draw => Wdl::Draw,
+            Outcome::Decisive { winner } if winner == self.color => Wdl::Win,
+            Outcome::Decisive { .. } => Wdl::Loss,
+        };
+        // Repeating moves in a won endgame is as much a mistake as stalemating
+        if self.endgame_mistake(result) {
+            self.undo();
+            return;
+        }
+        self.sparring = false;
+        self.endgame = None;
+        info!("Game over: {} {:?}", outcome, self.history.draw_rule());
+        if let Err(e) = self.save_session() {
+            error!("Couldn't save the game: {}", e);
+        }
+    }
+
+    fn start_drill(&self, moves: &[SanPlus]) -> Option<GameState> {
+        self.db
+            .start_drill(self.color, moves)
+            .map(|x| x.with_settings(self.config.drill.clone()))
+            .map(|x| x.with_target(self.target.clone()))
+    }
+
+    /// When each of a colour's lines is due, from the attempts, imported reviews and archive
+    pub fn schedule(&self, color: Color, today: NaiveDate) -> anyhow::Result<Vec<LineSchedule>> {
+        load_schedule(&self.config, color, self.db.graph(color), today)
+    }
+
+    /// The line through the moves so far that's been due longest, or is due soonest
+    fn next_due(&self) -> Vec<String> {
+        let today = chrono::Local::now().date_naive();
+        let schedule = match self.schedule(self.color, today) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                error!("Couldn't work out what's due: {}", e);
+                return vec![];
+            }
+        };
+        let moves = self.moves.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let line = schedule
+            .into_iter()
+            .filter(|x| x.line.starts_with(&moves))
+            .min_by_key(|x| (x.due, x.reviews))
+            .map(|x| x.line)
+            .unwrap_or_default();
+        info!("Heading for {}", line.join(" "));
+        line
+    }
+
+    /// Start picking a chapter for `color`, what's needed to parse it without the lock
+    fn pick_chapter(&mut self, color: Color) -> ChapterPick {
+        let picks = self.chapter_picks.get_mut(color);
+        *picks += 1;
+        ChapterPick {
+            color,
+            repertoire: self.config.repertoire(color),
+            load: self.load,
+            pick: *picks,
+        }
+    }
+
+    /// Put in a chapter that's been parsed, unless the profile's been switched or another chapter
+    /// picked for the colour since. Returns whether it was.
+    fn chapter_picked(
+        &mut self,
+        pick: ChapterPick,
+        chapter: Option<String>,
+        graph: OpeningGraph,
+        start: Chess,
+    ) -> bool {
+        if self.load != pick.load {
+            info!("Dropping a chapter of a profile that's been switched away from");
+            return false;
+        }
+        if *self.chapter_picks.get(pick.color) != pick.pick {
+            info!(
+                "Dropping chapter {:?}, another's been picked since",
+                chapter
+            );
+            return false;
+        }
+        self.record(&Event::SelectChapter {
+            color: pick.color.to_string(),
+            chapter: chapter.clone(),
+        });
+        self.db.set_graph(pick.color, graph);
+        self.db.set_start(pick.color, start);
+        *self.chapters.get_mut(pick.color) = chapter;
+        true
+    }
+
+    /// The line the `select_line` hook picks from the schedule, if there is one
+    fn hooked_line(&mut self) -> Option<Vec<String>> {
+        let (command, choice) = self.line_choice()?;
+        match self.picked_line.take() {
+            Some((asked, picked)) if asked == choice => picked,
+            _ => hooks::select_line(&command, &choice),
+        }
+    }
+
+    /// What the `select_line` hook would be asked if a drill started now, and its command
+    fn line_choice(&self) -> Option<(Vec<String>, LineChoice)> {
+        let command = self.config.hooks.select_line.clone()?;
+        let today = chrono::Local::now().date_naive();
+        let schedule = self
+            .schedule(self.color, today)
+            .map_err(|e| error!("Couldn't work out what's due: {}", e))
+            .ok()?;
+        let choice = LineChoice {
+            color: self.color.to_string(),
+            chapter: self.chapters.get(self.color).clone(),
+            moves: self.moves.iter().map(|x| x.to_string()).collect(),
+            lines: schedule.iter().map(DueLine::from).collect(),
+        };
+        Some((command, choice))
+    }
+
+    /// What the `assess_move` hook would be asked about the player playing `game_move` now, and
+    /// its command
+    fn move_check(&self, game_move: &Move) -> Option<(Vec<String>, MoveCheck)> {
+        let command = self.config.hooks.assess_move.clone()?;
+        let mut game_state = self.game_state.clone()?;
+        let graph = self.db.graph(self.color);
+        let san = SanPlus::from_move(self.game.clone(), game_move);
+        let expected = game_state.hint(graph);
+        let prep_state = game_state.apply_move(&san, graph);
+        let check = MoveCheck {
+            color: self.color.to_string(),
+            moves: self.history.moves().iter().map(|x| x.to_string()).collect(),
+            played: san.to_string(),
+            expected: expected.iter().map(|x| x.to_string()).collect(),
+            assessment: MoveCheck::assessment_name(prep_state).to_string(),
+            mistake: matches!(
+                prep_state,
+                MoveAssessment::TryAgain | MoveAssessment::OutOfPrep
+            ),
+        };
+        Some((command, check))
+    }
 
-        let game = state.game.clone();
-        match game.play(game_move) {
+    /// Play a move for the player, if drilling the prep's reply is played straight after.
+    fn play_move(&mut self, game_move: &Move) {
+        let san = SanPlus::from_move(self.game.clone(), game_move);
+        let asked = self.move_check(game_move);
+
+        let before = self.game.clone();
+        match before.clone().play(game_move) {
             Ok(new_game) => {
-                state.game = new_game;
-                let mut game_state = state.game_state.take();
-                let graph = state.db.graph(state.color);
+                self.game = new_game;
+                let sound = SoundEffect::of_move(&self.game, game_move);
+                let position = self.game.clone();
+                self.history.push(san.clone(), position);
+                let mut game_state = self.game_state.take();
+                let mut broken = false;
+                let graph = self.db.graph(self.color);
                 if let Some(game_state) = game_state.as_mut() {
+                    let expected = game_state.hint(graph);
                     let prep_state = game_state.apply_move(&san, graph);
                     info!("Prep status: {:?}", prep_state);
-                    if let Some(mv) = game_state.make_move(graph) {
-                        let game = state.game.clone();
-
-                        let mv = mv.san.to_move(&game).unwrap();
-                        let new_game = game.clone().play(&mv).unwrap();
-                        state.game = new_game;
+                    let mut wrong = matches!(
+                        prep_state,
+                        MoveAssessment::TryAgain | MoveAssessment::OutOfPrep
+                    );
+                    if let Some((command, check)) = asked {
+                        let answer = match self.assessed.take() {
+                            Some((asked, answer)) if asked == check => answer,
+                            _ => hooks::assess_move(&command, &check),
+                        };
+                        wrong = answer.unwrap_or(wrong);
+                    }
+                    self.sounds.push(match wrong {
+                        true => SoundEffect::Mistake,
+                        false => sound,
+                    });
+                    if let Some(attempt) = self.attempt.as_mut() {
+                        let mistake = wrong.then(|| Mistake {
+                            ply: self.history.moves().len() - 1,
+                            played: san.to_string(),
+                            expected: expected.iter().map(|x| x.to_string()).collect(),
+                        });
+                        attempt.moved(mistake, prep_state == MoveAssessment::OutOfPrep);
+                    }
+                    if wrong && self.config.drill.explain && !expected.is_empty() {
+                        self.wrong_move = Some(WrongMove {
+                            position: before,
+                            played: san.clone(),
+                            expected,
+                        });
+                    }
+                    if prep_state == MoveAssessment::TryAgain {
+                        self.history.pop();
+                        self.game = self.history.game().clone();
+                    } else if let Some(reply) = game_state.make_move(graph) {
+                        if let Err(e) = self.play_reply(reply) {
+                            self.broken_prep(e);
+                            broken = true;
+                        }
                     }
                 } else {
-                    state.moves.push(san);
+                    self.sounds.push(sound);
+                    self.moves.push(san);
                 }
-                state.game_state = game_state;
+                self.game_state = game_state.filter(|_| !broken);
+                self.check_drill_over();
+                self.check_attempt_over();
+                self.check_game_over();
             }
             Err(e) => {
                 error!("{}", e);
             }
         }
-        state.game.board().to_string()
+    }
+}
+
+/// Where the king goes when castling, shakmaty has castling as the king taking its rook
+fn castled_king(position: &Chess, mv: &Move) -> Option<Square> {
+    mv.castling_side().map(|side| side.king_to(position.turn()))
+}
+
+fn parse_fen(fen: &str) -> Result<Chess, String> {
+    parse_position(fen, false).map_err(|e| e.to_string())
+}
+
+fn create_app(config: &Config) -> App {
+    let mut app = App {
+        db: OpeningDatabase::default(),
+        color: Color::White,
+        game: Chess::new(),
+        moves: vec![],
+        history: MoveHistory::default(),
+        game_state: None,
+        config: config.clone(),
+        loading: LoadingStatus {
+            message: "Starting".to_string(),
+            done: 0,
+            total: 2,
+            finished: false,
+            problems: vec![],
+        },
+        load: 0,
+        recording: None,
+        cli: Cli::default(),
+        recovery: None,
+        sparring: false,
+        engine: None,
+        active_engine: None,
+        explainer: None,
+        wrong_move: None,
+        chapters: ByColor::default(),
+        chapter_picks: ByColor::default(),
+        attempt: None,
+        target: vec![],
+        evals: None,
+        analysis: None,
+        games: OpeningDatabase::default(),
+        endgame: None,
+        mistake: None,
+        sounds: vec![],
+        announcements: vec![],
+        problems: vec![],
+        picked_line: None,
+        assessed: None,
+    };
+    if let Some(path) = config.record_events.as_ref() {
+        if let Err(e) = app.start_recording(path) {
+            error!("Can't record events to {}: {}", path.display(), e);
+        }
+    }
+    app
+}
+
+/// Parse the repertoire and sync any chess.com games, this can take a while so is done off the
+/// main thread with the progress written into the app for the frontend to poll. `load` is which
+/// of the app's loads this is, nothing's written once another has started.
+fn load_databases(state: Arc<Mutex<App>>, config: Config, load: u64) {
+    let update = |message: String, done: usize| {
+        let mut state = lock_app(&state);
+        if state.load == load {
+            state.loading.message = message;
+            state.loading.done = done;
+        }
+    };
+    let sources = game_sources(&config);
+    let accounts = sources
+        .iter()
+        .flat_map(|source| source.users(&config).into_iter().map(move |x| (source, x)))
+        .collect::<Vec<_>>();
+    let syncing = !storages(&config).is_empty();
+    {
+        let mut state = lock_app(&state);
+        if state.load == load {
+            state.loading.total = 2 + accounts.len() + syncing as usize;
+        }
+    }
+
+    let mut db = OpeningDatabase::default();
+    let mut problems = vec![];
+    for (i, color) in [Color::White, Color::Black].into_iter().enumerate() {
+        update(format!("Loading {} repertoire", color), i);
+        if let Err(e) = db.load_chapter(color, &config.repertoire(color), None) {
+            error!("Failed to load {} repertoire: {:#}", color, e);
+            problems.push(Problem::new(
+                format!("Failed to load {} repertoire: {:#}", color, e),
+                Some(
+                    "Fix the file, `chess-driller validate-repertoire` says what's wrong with it"
+                        .to_string(),
+                ),
+            ));
+        }
+    }
+
+    let mut games = OpeningDatabase::default();
+    for (i, (source, user)) in accounts.iter().enumerate() {
+        update(
+            format!("Syncing {} games for {}", source.name(), user),
+            i + 2,
+        );
+        if let Err(e) = source.download(&config, user) {
+            error!(
+                "Failed to sync {} games for {}: {:#}",
+                source.name(),
+                user,
+                e
+            );
+        }
+        for path in source.downloaded(&config, user) {
+            let added = fs::File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|x| games.add_multigame_pgn(x, user.to_string()));
+            if let Err(e) = added {
+                error!("Failed to add {} to opening tree: {}", path.display(), e);
+            }
+        }
+    }
+    if syncing {
+        update("Syncing training progress".to_string(), 2 + accounts.len());
+        if let Err(e) = sync_progress(&config) {
+            error!("Failed to sync training progress: {}", e);
+            problems.push(Problem::new(
+                format!("Failed to sync training progress: {:#}", e),
+                Some("Check `sync` in the config and that the server can be reached".to_string()),
+            ));
+        }
+    }
+
+    let mut state = lock_app(&state);
+    if state.load != load {
+        info!("Dropping what was loaded for a profile that's been switched away from");
+        return;
+    }
+    if let Some(months) = config.keep_months {
+        let today = chrono::Local::now().date_naive();
+        if let Err(e) = archive_history(&config, &db, months, today) {
+            error!("Failed to archive old attempts: {}", e);
+            problems.push(Problem::new(
+                format!("Failed to archive old attempts: {:#}", e),
+                None,
+            ));
+        }
+    }
+    state.db = db;
+    state.games = games;
+    state.loading.problems.append(&mut problems);
+    state.loading.message = "Ready".to_string();
+    state.loading.done = state.loading.total;
+    state.loading.finished = true;
+    info!("Finished loading");
+}
+
+/// Pick up changes to the config file while running, the frontend is told so it can fetch the
+/// new settings
+fn watch_config(state: Arc<Mutex<App>>, handle: tauri::AppHandle, cli: Cli) {
+    let modified = |path: &PathBuf| fs::metadata(path).and_then(|x| x.modified()).ok();
+    let (mut path, mut profile) = {
+        let state = lock_app(&state);
+        (state.config.path(), state.config.profile.clone())
+    };
+    let mut last = modified(&path);
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        {
+            // Picking a different profile switches which file is watched
+            let state = lock_app(&state);
+            if state.config.profile != profile {
+                path = state.config.path();
+                profile = state.config.profile.clone();
+                last = modified(&path);
+            }
+        }
+        let current = modified(&path);
+        if current == last {
+            continue;
+        }
+        last = current;
+        let mut config = match Config::load_profile(profile.as_deref()) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("Couldn't reload config: {}", e);
+                continue;
+            }
+        };
+        for problem in config.apply_env(std::env::vars()) {
+            warn!("{}", problem);
+        }
+        cli.apply(&mut config);
+        lock_app(&state).config.reload(config);
+        info!("Reloaded config");
+        if let Err(e) = handle.emit("config-reloaded", ()) {
+            error!("Couldn't send config reload: {}", e);
+        }
+    }
+}
+
+/// Have the engine look at a wrong move the player's just made in a drill, the explanation is
+/// sent as a `wrong-move` event
+fn explain_wrong_move(state: &Arc<Mutex<App>>, handle: &tauri::AppHandle) {
+    let mut app = lock_app(state);
+    let Some(wrong) = app.wrong_move.take() else {
+        return;
+    };
+    let engine = match app.explainer() {
+        Ok(engine) => engine,
+        Err(e) => return warn!("Can't explain the wrong move: {}", e),
+    };
+    let limits = Limits {
+        movetime: Some(app.config.engine.movetime),
+        ..Default::default()
+    };
+    drop(app);
+
+    let handle = handle.clone();
+    thread::spawn(move || match explain(&engine, &wrong, limits) {
+        Ok(explanation) => {
+            if let Err(e) = handle.emit("wrong-move", explanation) {
+                error!("Couldn't send the explanation: {}", e);
+            }
+        }
+        Err(e) => warn!("Couldn't explain the wrong move: {}", e),
+    });
+}
+
+/// Tell the frontend which sounds to play for the last event unless they're muted, what to read
+/// out if speech is on and anything that went wrong
+fn send_feedback(app: &mut App, handle: &tauri::AppHandle) {
+    let sounds = std::mem::take(&mut app.sounds);
+    let announcements = std::mem::take(&mut app.announcements);
+    for problem in std::mem::take(&mut app.problems) {
+        if let Err(e) = handle.emit("problem", problem) {
+            error!("Couldn't send a problem: {}", e);
+        }
+    }
+    if !app.config.sound.muted {
+        for sound in sounds {
+            if let Err(e) = handle.emit("sound", sound) {
+                error!("Couldn't send a sound: {}", e);
+            }
+        }
+    }
+    if app.config.speech.enabled {
+        speak(handle, &announcements.join(". "));
+    }
+}
+
+fn speak(handle: &tauri::AppHandle, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Err(e) = handle.emit("speak", text) {
+        error!("Couldn't send something to say: {}", e);
+    }
+}
+
+/// Get the engine's move in the background if it's its turn, the board is sent to the frontend
+/// in an `engine-moved` event once it's played
+fn engine_reply(state: &Arc<Mutex<App>>, handle: tauri::AppHandle) {
+    let fen = |x: &App| Fen::from_position(x.game.clone(), EnPassantMode::Legal).to_string();
+    fn failed(app: &mut App, handle: &tauri::AppHandle, e: String) {
+        error!("Engine couldn't move: {}", e);
+        app.sparring = false;
+        if let Err(e) = handle.emit("engine-failed", e) {
+            error!("Couldn't send engine failure: {}", e);
+        }
+    }
+    fn mistake(app: &mut App, handle: &tauri::AppHandle) {
+        let Some(mut mistake) = app.mistake.take() else {
+            return;
+        };
+        mistake.board = app.board();
+        if let Err(e) = handle.emit("endgame-mistake", mistake) {
+            error!("Couldn't send the endgame mistake: {}", e);
+        }
+    }
+    let mut app = lock_app(state);
+    mistake(&mut app, &handle);
+    send_feedback(&mut app, &handle);
+    if !app.engine_to_move() {
+        return;
+    }
+    let engine = match app.engine() {
+        Ok(engine) => engine,
+        Err(e) => {
+            let e = format!(
+                "{}, set `spar` in `drill` to false to stop playing the engine after the prep",
+                e
+            );
+            return failed(&mut app, &handle, e);
+        }
+    };
+    let position = fen(&app);
+    let limits = Limits {
+        movetime: Some(app.engine_settings().movetime),
+        ..Default::default()
+    };
+    // Analysis doesn't stop by itself, the frontend starts it again once the engine has moved
+    if app.analysis.take().is_some() {
+        if let Err(e) = engine.stop() {
+            error!("Couldn't stop analysis: {}", e);
+        }
+    }
+    drop(app);
+
+    let state = state.clone();
+    thread::spawn(move || {
+        let res = engine.analyze(&position, limits).recv();
+        let mut app = lock_app(&state);
+        // The board could have been reset or taken back while the engine was thinking
+        if fen(&app) != position || !app.engine_to_move() {
+            return;
+        }
+        let eval = match res {
+            Ok(Ok(eval)) => eval,
+            Ok(Err(e)) => return failed(&mut app, &handle, e.to_string()),
+            Err(_) => return failed(&mut app, &handle, "The engine stopped".to_string()),
+        };
+        // The engine's search says what the player's move left them with
+        if app.endgame_mistake(eval.result().flip()) {
+            if let Err(e) = app.handle(Event::Undo) {
+                error!("Couldn't take back the endgame move: {}", e);
+            }
+            return mistake(&mut app, &handle);
+        }
+        let uci = eval.best_move().map(|x| x.to_string());
+        let Some(uci) = uci else {
+            return failed(
+                &mut app,
+                &handle,
+                "The engine didn't give a move".to_string(),
+            );
+        };
+        match app.handle(Event::EngineMove { uci }) {
+            Ok(board) => {
+                if let Err(e) = handle.emit("engine-moved", board) {
+                    error!("Couldn't send the engine's move: {}", e);
+                }
+                send_feedback(&mut app, &handle);
+            }
+            Err(e) => failed(&mut app, &handle, e),
+        }
+    });
+}
+
+/// What the engine thinks of the position shown
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Analysis {
+    pub depth: u32,
+    /// Squares the best move goes from and to
+    pub best: Option<(String, String)>,
+    /// Best first
+    pub lines: Vec<AnalysisLine>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisLine {
+    /// From white's point of view
+    pub score: Score,
+    /// In SAN
+    pub moves: Vec<String>,
+}
+
+impl Analysis {
+    /// Take in an eval for one of the lines
+    fn update(&mut self, eval: &Eval, position: &Chess) {
+        let index = eval.multipv.max(1) as usize - 1;
+        if index > self.lines.len() {
+            return;
+        }
+        let line = AnalysisLine::new(eval, position);
+        if index == self.lines.len() {
+            self.lines.push(line);
+        } else {
+            self.lines[index] = line;
+        }
+        if index == 0 {
+            self.depth = eval.depth;
+            self.best = eval
+                .best_move()
+                .filter(|x| x.len() >= 4)
+                .map(|x| (x[0..2].to_string(), x[2..4].to_string()));
+        }
+    }
+}
+
+impl AnalysisLine {
+    fn new(eval: &Eval, position: &Chess) -> Self {
+        Self {
+            score: eval.score.for_white(position.turn()),
+            moves: eval.san_line(position),
+        }
+    }
+}
+
+/// Have the engine analyse the position shown until it changes or analysis is turned off, evals
+/// are sent as `analysis` events as it goes deeper
+fn start_analysis(state: &Arc<Mutex<App>>, handle: tauri::AppHandle) -> anyhow::Result<()> {
+    let mut app = lock_app(state);
+    let engine = app.engine()?;
+    let cache = app.eval_cache();
+    if app.analysis.is_some() {
+        engine.stop()?;
+    }
+    let position = app.history.current().clone();
+    let fen = Fen::from_position(position.clone(), EnPassantMode::Legal).to_string();
+    app.analysis = Some(fen.clone());
+    let lines = app.engine_settings().lines;
+    let updates = engine.watch(&fen, app.engine_settings().analysis, lines);
+    drop(app);
+
+    // Lines from before are shown straight away and kept until the engine gets as deep again
+    let mut shown = 0;
+    if let Some(cached) = cache.get(&position, 0, lines as usize) {
+        let mut analysis = Analysis::default();
+        for eval in &cached {
+            analysis.update(eval, &position);
+        }
+        shown = analysis.depth;
+        if let Err(e) = handle.emit("analysis", analysis) {
+            error!("Couldn't send analysis: {}", e);
+        }
+    }
+
+    let state = state.clone();
+    thread::spawn(move || {
+        let mut analysis = Analysis::default();
+        let mut evals: Vec<Eval> = vec![];
+        for eval in updates {
+            // Stale, a newer analysis has been started
+            if lock_app(&state).analysis.as_ref() != Some(&fen) {
+                break;
+            }
+            analysis.update(&eval, &position);
+            let index = eval.multipv.max(1) as usize - 1;
+            if index < evals.len() {
+                evals[index] = eval;
+            } else if index == evals.len() {
+                evals.push(eval);
+            }
+            if analysis.depth < shown {
+                continue;
+            }
+            if let Err(e) = handle.emit("analysis", analysis.clone()) {
+                error!("Couldn't send analysis: {}", e);
+            }
+        }
+        cache.insert(&position, evals);
+    });
+    Ok(())
+}
+
+/// Check a repertoire with its own engine so drilling carries on meanwhile, the report is saved in
+/// the data directory
+fn run_audit(
+    job: &JobControl,
+    config: &Config,
+    cache: &EvalCache,
+    openings: &OpeningGraph,
+    player: Color,
+) -> anyhow::Result<AuditReport> {
+    let engine = UciEngine::start(config.engine_named(config.audit.engine.as_deref())?)?;
+    info!("Auditing the {} repertoire with {}", player, engine.name);
+    let report = audit::audit(
+        &engine,
+        cache,
+        openings,
+        player,
+        &config.audit,
+        |done, total| job.progress(done, total),
+    )?;
+    let dir = config.data_dir().join("audits");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}-{}.json",
+        player,
+        chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+    ));
+    fs::write(&path, serde_json::to_vec_pretty(&report)?)?;
+    info!(
+        "Audit found {} dubious moves, saved to {}",
+        report.findings.len(),
+        path.display()
+    );
+    Ok(report)
+}
+
+/// Compare a repertoire with the reference games with its own engine, the report is saved in the
+/// data directory
+fn run_novelties(
+    job: &JobControl,
+    config: &Config,
+    cache: &EvalCache,
+    openings: &OpeningGraph,
+    player: Color,
+) -> anyhow::Result<NoveltyReport> {
+    let reference = Reference::load(&config.reference())?;
+    let engine = UciEngine::start(config.engine_named(config.audit.engine.as_deref())?)?;
+    let report = check_novelties(
+        &engine,
+        cache,
+        openings,
+        player,
+        &reference,
+        &config.audit,
+        |done, total| job.progress(done, total),
+    )?;
+    let dir = config.data_dir().join("novelties");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}-{}.json",
+        player,
+        chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+    ));
+    fs::write(&path, serde_json::to_vec_pretty(&report)?)?;
+    info!(
+        "Found {} novelties, saved to {}",
+        report.novelties.len(),
+        path.display()
+    );
+    Ok(report)
+}
+
+/// Every downloaded game of the users in the config from each source, with the colour they had
+fn downloaded_games(config: &Config) -> anyhow::Result<Vec<(PlayedGame, Color)>> {
+    let mut games = vec![];
+    for source in game_sources(config) {
+        for user in source.users(config) {
+            for path in source.downloaded(config, &user) {
+                match read_games(fs::File::open(&path)?, &user) {
+                    Ok(mut x) => games.append(&mut x),
+                    Err(e) => error!("Couldn't read games from {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+    if games.is_empty() {
+        anyhow::bail!(
+            "There aren't any downloaded games, add your chess.com username to the config"
+        );
+    }
+    Ok(games)
+}
+
+/// Scan the newest of the player's downloaded games with its own engine, the report is saved in
+/// the data directory
+fn run_scan(job: &JobControl, config: &Config, cache: &EvalCache) -> anyhow::Result<ScanReport> {
+    let games = downloaded_games(config)?;
+    let mut repertoire = OpeningDatabase::default();
+    for color in [Color::White, Color::Black] {
+        repertoire.set_graph(color, load_chapter(&config.repertoire(color), None)?);
+    }
+    let engine = UciEngine::start(config.engine_named(config.scan.engine.as_deref())?)?;
+    info!(
+        "Scanning {} games with {}",
+        games.len().min(config.scan.games),
+        engine.name
+    );
+    let report = scan::scan(
+        &engine,
+        cache,
+        &games,
+        &repertoire,
+        &config.scan,
+        |done, total| job.progress(done, total),
+    )?;
+    let dir = config.data_dir().join("scans");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "{}.json",
+        chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+    ));
+    fs::write(&path, serde_json::to_vec_pretty(&report)?)?;
+    info!(
+        "Found blunders in {} games, saved to {}",
+        report.games.len(),
+        path.display()
+    );
+    Ok(report)
+}
+
+/// The engine's reply to a gap, sent to the frontend as each one is worked out
+#[derive(Clone, Debug, Serialize)]
+pub struct GapSuggestion {
+    pub index: usize,
+    #[serde(flatten)]
+    pub suggestion: Suggestion,
+}
+
+/// Work out replies to the gaps one at a time with their own engine
+fn suggest_replies(handle: &tauri::AppHandle, config: &Config, gaps: &[Gap]) -> anyhow::Result<()> {
+    // The sparring strength shouldn't weaken the suggestions
+    let settings = config::Engine {
+        skill_level: None,
+        elo: None,
+        ..config.engine.clone()
+    };
+    let engine = UciEngine::start(&settings)?;
+    let limits = Limits {
+        movetime: Some(config.engine.movetime),
+        ..Default::default()
+    };
+    for (index, gap) in gaps.iter().enumerate() {
+        let suggestion = suggest(&engine, gap, limits)?;
+        if let Err(e) = handle.emit("gap-suggestion", GapSuggestion { index, suggestion }) {
+            error!("Couldn't send gap suggestion: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Load the databases off the main thread. If that panics the splash screen is still let go of
+/// with what went wrong, rather than waiting on it forever.
+fn spawn_loader(state: Arc<Mutex<App>>, config: Config, load: u64) {
+    thread::spawn(move || {
+        let loader = state.clone();
+        let loaded = panic::catch_unwind(AssertUnwindSafe(|| load_databases(loader, config, load)));
+        if loaded.is_err() {
+            state.clear_poison();
+            let mut state = lock_app(&state);
+            if state.load != load {
+                return;
+            }
+            state.loading.problems.push(Problem::new(
+                "Loading the repertoire went wrong, it's left empty",
+                Some("The log has the details, restarting might help".to_string()),
+            ));
+            state.loading.finished = true;
+        }
+    });
+}
+
+/// Panics are logged and shown in the window as well as the usual output. They only take down
+/// the command or thread they happened in, the app's lock is taken back by [`lock_app`]
+fn report_panics(handle: tauri::AppHandle) {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default(info);
+        error!("{}", info);
+        let problem = Problem::new(
+            format!("Something went wrong: {}", info),
+            Some("This is a bug, it'd help to report it with the log".to_string()),
+        );
+        if let Err(e) = handle.emit("problem", problem) {
+            error!("Couldn't send the panic: {}", e);
+        }
+    }));
+}
+
+pub fn launch(cli: Cli) {
+    // A config that can't be read shouldn't stop the window from opening to say so
+    let (mut config, problems) = match Config::load_checked(cli.profile.as_deref()) {
+        Ok((config, problems)) => {
+            let problems = config_problems(&config, problems);
+            (config, problems)
+        }
+        Err(e) => {
+            let config = Config {
+                profile: cli.profile.clone(),
+                ..Default::default()
+            };
+            let problem = Problem::new(
+                format!("Couldn't read the config: {:#}", e),
+                Some(format!(
+                    "Fix {} and restart, the default settings are used until then",
+                    config.path().display()
+                )),
+            );
+            (config, vec![problem])
+        }
+    };
+    logging::init(&config, true);
+    info!("Starting chess driller");
+    for problem in &problems {
+        warn!("{}", problem.message);
+    }
+    cli.apply(&mut config);
+    let mut app = create_app(&config);
+    app.loading.problems = problems;
+    app.cli = cli.clone();
+    app.recovery = load_autosave(&autosave_file(&config));
+    record_usage(&config, "open");
+    let app = Arc::new(Mutex::new(app));
+    let saving = app.clone();
+    thread::spawn(move || autosave::run(saving));
+    let watched = app.clone();
+    let watch_cli = cli.clone();
+    spawn_loader(app.clone(), config, 0);
+    let closing = app.clone();
+
+    tauri::Builder::default()
+        .manage(ChessState(app))
+        .setup(move |app| {
+            let handle = app.handle().clone();
+            report_panics(handle.clone());
+            let jobs = handle.clone();
+            app.manage(JobQueue::new(move |update| {
+                if let Err(e) = jobs.emit("job-update", update) {
+                    error!("Couldn't send job update: {}", e);
+                }
+            }));
+            thread::spawn(move || watch_config(watched, handle, watch_cli));
+            Ok(())
+        })
+        .on_window_event(move |window, event| {
+            // Let the frontend ask whether to keep the drill rather than dropping it
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if lock_app(&closing).in_session() {
+                    api.prevent_close();
+                    if let Err(e) = window.emit("close-requested", ()) {
+                        error!("Couldn't ask about closing: {}", e);
+                    }
+                }
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::move_piece,
+            commands::start,
+            commands::reset,
+            commands::move_time_limit,
+            commands::chapters,
+            commands::select_chapter,
+            commands::loading_status,
+            commands::recovery,
+            commands::recover,
+            commands::discard_recovery,
+            commands::startup,
+            commands::profiles,
+            commands::select_profile,
+            commands::export_settings,
+            commands::import_settings,
+            commands::appearance,
+            commands::set_appearance,
+            commands::keybindings,
+            commands::input,
+            commands::set_input,
+            commands::sound,
+            commands::set_sound,
+            commands::speech,
+            commands::set_speech,
+            commands::locale,
+            commands::set_locale,
+            commands::notation,
+            commands::set_notation,
+            commands::usage_log,
+            commands::set_usage_log,
+            commands::usage,
+            commands::clear_usage,
+            commands::history_back,
+            commands::history_forward,
+            commands::history_sibling,
+            commands::undo,
+            commands::move_list,
+            commands::legal_moves,
+            commands::play_san,
+            commands::san_completions,
+            commands::import_pgn,
+            commands::fen,
+            commands::pgn,
+            commands::set_fen,
+            commands::chess960,
+            commands::quit,
+            commands::hint,
+            commands::show_log,
+            commands::log_error,
+            commands::audit,
+            commands::scan_games,
+            commands::novelties,
+            commands::endgame_chapters,
+            commands::start_endgame,
+            commands::jobs,
+            commands::pause_job,
+            commands::resume_job,
+            commands::cancel_job,
+            commands::gaps,
+            commands::fill_gap,
+            commands::spar,
+            commands::analyze,
+            commands::stop_analysis,
+            commands::engines,
+            commands::select_engine,
+            commands::stats,
+            commands::export_stats,
+            commands::training_results,
+            commands::opening_times,
+            commands::sessions,
+            commands::replay_line,
+            commands::export_anki,
+            commands::export_booklet,
+            commands::import_reviews,
+            commands::sync_progress,
+            commands::forecast,
+            commands::keep_months,
+            commands::set_keep_months,
+            commands::compare_profiles,
+            commands::export_profiles
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+pub mod commands {
+    use super::*;
+    use tauri::State;
+
+    /// Ask the `assess_move` hook about the move the player's making before playing it, without
+    /// holding the lock so a slow hook doesn't hold up everything else. The move's event uses the
+    /// answer if the drill's still where it was.
+    fn assess_off_lock(state: &Mutex<App>, game_move: impl FnOnce(&App) -> Option<Move>) {
+        let asked = {
+            let app = lock_app(state);
+            game_move(&app).and_then(|x| app.move_check(&x))
+        };
+        if let Some((command, check)) = asked {
+            let answer = hooks::assess_move(&command, &check);
+            lock_app(state).assessed = Some((check, answer));
+        }
+    }
+
+    /// Change a setting in the config file. The app's copy can have command line overrides in it
+    /// so the file is read back rather than writing that out.
+    fn save_config(profile: Option<&str>, change: impl FnOnce(&mut Config)) -> Result<(), String> {
+        let mut config = Config::load_profile(profile).map_err(|e| e.to_string())?;
+        change(&mut config);
+        config.save().map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn start(app: tauri::AppHandle, state: State<ChessState>) -> Result<String, String> {
+        let asked = lock_app(&state.0).line_choice();
+        if let Some((command, choice)) = asked {
+            let picked = hooks::select_line(&command, &choice);
+            lock_app(&state.0).picked_line = Some((choice, picked));
+        }
+        let board = lock_app(&state.0).handle(Event::Start)?;
+        engine_reply(&state.0, app);
+        Ok(board)
+    }
+
+    /// Play the engine from here, its move comes in an `engine-moved` event if it's its turn
+    #[tauri::command]
+    pub fn spar(app: tauri::AppHandle, state: State<ChessState>) -> Result<String, String> {
+        let board = lock_app(&state.0).handle(Event::Spar)?;
+        engine_reply(&state.0, app);
+        Ok(board)
+    }
+
+    /// A drill the last run saved before it stopped, to offer carrying on with
+    #[tauri::command]
+    pub fn recovery(state: State<ChessState>) -> Option<Snapshot> {
+        lock_app(&state.0).recovery.clone()
+    }
+
+    /// Carry on with the drill the last run saved
+    #[tauri::command]
+    pub fn recover(app: tauri::AppHandle, state: State<ChessState>) -> Result<Recovered, String> {
+        let recovered = {
+            let mut state = lock_app(&state.0);
+            let snapshot = state
+                .recovery
+                .take()
+                .ok_or("There's nothing to carry on with")?;
+            let board = state.handle(Event::Restore { snapshot })?;
+            Recovered {
+                board,
+                color: state.color.to_string(),
+                drilling: state.is_drilling(),
+            }
+        };
+        engine_reply(&state.0, app);
+        Ok(recovered)
+    }
+
+    #[tauri::command]
+    pub fn discard_recovery(state: State<ChessState>) {
+        let mut state = lock_app(&state.0);
+        info!("Throwing away the autosave");
+        state.recovery = None;
+        remove_autosave(&autosave_file(&state.config));
+    }
+
+    #[tauri::command]
+    pub fn loading_status(state: State<ChessState>) -> LoadingStatus {
+        lock_app(&state.0).loading.clone()
+    }
+
+    /// What the command line asked for once loading is done, `None` shows the menu
+    #[tauri::command]
+    pub fn startup(state: State<ChessState>) -> Option<Startup> {
+        lock_app(&state.0).cli.startup()
+    }
+
+    #[tauri::command]
+    pub fn move_time_limit(state: State<ChessState>) -> Option<u64> {
+        lock_app(&state.0).config.move_time_limit
+    }
+
+    #[tauri::command]
+    pub fn appearance(state: State<ChessState>) -> Appearance {
+        lock_app(&state.0).config.appearance.clone()
+    }
+
+    #[tauri::command]
+    pub fn input(state: State<ChessState>) -> Input {
+        lock_app(&state.0).config.input.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_input(input: Input, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing input settings to {:?}", input);
+        let mut state = lock_app(&state.0);
+        state.config.input = input.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.input = input
+        })
+    }
+
+    #[tauri::command]
+    pub fn sound(state: State<ChessState>) -> Sound {
+        lock_app(&state.0).config.sound.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_sound(sound: Sound, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing sound settings to {:?}", sound);
+        let mut state = lock_app(&state.0);
+        state.config.sound = sound.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.sound = sound
+        })
+    }
+
+    #[tauri::command]
+    pub fn speech(state: State<ChessState>) -> Speech {
+        lock_app(&state.0).config.speech.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_speech(speech: Speech, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing speech settings to {:?}", speech);
+        let mut state = lock_app(&state.0);
+        state.config.speech = speech.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.speech = speech
+        })
+    }
+
+    #[tauri::command]
+    pub fn locale(state: State<ChessState>) -> Option<String> {
+        lock_app(&state.0).config.locale.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_locale(locale: Option<String>, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing the locale to {:?}", locale);
+        let mut state = lock_app(&state.0);
+        state.config.locale = locale.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.locale = locale
+        })
+    }
+
+    #[tauri::command]
+    pub fn notation(state: State<ChessState>) -> Notation {
+        lock_app(&state.0).config.notation.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_notation(notation: Notation, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing the notation to {:?}", notation);
+        let mut state = lock_app(&state.0);
+        state.config.notation = notation.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.notation = notation
+        })
+    }
+
+    #[tauri::command]
+    pub fn keybindings(state: State<ChessState>) -> Keybindings {
+        lock_app(&state.0).config.keybindings.clone()
+    }
+
+    #[tauri::command]
+    pub fn set_appearance(appearance: Appearance, state: State<ChessState>) -> Result<(), String> {
+        info!("Changing appearance to {:?}", appearance);
+        let mut state = lock_app(&state.0);
+        state.config.appearance = appearance.clone();
+        save_config(state.config.profile.as_deref(), |config| {
+            config.appearance = appearance
+        })
+    }
+
+    /// Write the settings out to a file in the downloads folder, returning where it went
+    #[tauri::command]
+    pub fn export_settings(state: State<ChessState>) -> Result<String, String> {
+        let state = lock_app(&state.0);
+        let config =
+            Config::load_profile(state.config.profile.as_deref()).map_err(|e| e.to_string())?;
+        let bundle = config.export_bundle().map_err(|e| e.to_string())?;
+        let dir = dirs::download_dir().unwrap_or_else(|| config.data_dir());
+        let path = dir.join("chess-driller-settings.json");
+        fs::write(&path, bundle).map_err(|e| e.to_string())?;
+        info!("Exported settings to {}", path.display());
+        Ok(path.display().to_string())
+    }
+
+    /// Replace the settings with ones exported from elsewhere, they're picked up the same as an
+    /// edit to the config file
+    #[tauri::command]
+    pub fn import_settings(bundle: String, state: State<ChessState>) -> Result<(), String> {
+        let state = lock_app(&state.0);
+        let mut config =
+            Config::load_profile(state.config.profile.as_deref()).map_err(|e| e.to_string())?;
+        config.import_bundle(&bundle).map_err(|e| e.to_string())?;
+        info!("Imported settings");
+        config.save().map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn chapters(color: &str, state: State<ChessState>) -> Vec<String> {
+        let color = Color::from_str(color).unwrap();
+        list_chapters(&lock_app(&state.0).config.repertoire(color))
+    }
+
+    #[tauri::command]
+    pub fn profiles(state: State<ChessState>) -> Profiles {
+        Profiles {
+            profiles: Config::profiles(),
+            current: lock_app(&state.0).config.profile.clone(),
+        }
+    }
+
+    /// Switch to another profile, `None` being the top level config. This loads everything again
+    /// so the frontend goes back to the loading screen.
+    #[tauri::command]
+    pub fn select_profile(profile: Option<String>, state: State<ChessState>) -> Result<(), String> {
+        info!("Switching to profile {:?}", profile);
+        let (mut config, problems) =
+            Config::load_checked(profile.as_deref()).map_err(|e| e.to_string())?;
+        let mut app = lock_app(&state.0);
+        let cli = app.cli.clone();
+        cli.apply(&mut config);
+        // The drill going on is over, recorded with the profile it was started in
+        app.finish_attempt(true);
+        let load = app.load + 1;
+        *app = create_app(&config);
+        app.load = load;
+        app.loading.problems = config_problems(&config, problems);
+        app.cli = cli;
+        app.recovery = load_autosave(&autosave_file(&config));
+        spawn_loader(state.0.clone(), config, load);
+        Ok(())
+    }
+
+    /// Parsing a chapter can be slow for big prep files so this runs off the main thread, and
+    /// without holding the lock, to keep the board responsive.
+    #[tauri::command]
+    pub async fn select_chapter(
+        color: String,
+        chapter: Option<String>,
+        state: State<'_, ChessState>,
+    ) -> Result<(), String> {
+        info!("Selecting chapter {:?} for {}", chapter, color);
+        let player = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let pick = lock_app(&state.0).pick_chapter(player);
+        let graph =
+            load_chapter(&pick.repertoire, chapter.as_deref()).map_err(|e| e.to_string())?;
+        let start = load_start(&pick.repertoire, chapter.as_deref()).map_err(|e| e.to_string())?;
+        lock_app(&state.0).chapter_picked(pick, chapter, graph, start);
+        Ok(())
+    }
+
+    /// Add a PGN dropped onto the window to the repertoire, this lasts until a chapter is picked
+    /// from the menu
+    #[tauri::command]
+    pub fn import_pgn(color: String, pgn: String, state: State<ChessState>) -> Result<(), String> {
+        info!("Importing PGN into {} repertoire", color);
+        let mut state = lock_app(&state.0);
+        state.handle(Event::ImportPgn { color, pgn })?;
+        Ok(())
+    }
+
+    #[tauri::command]
+    pub fn reset(color: String, state: State<ChessState>) -> Result<(), String> {
+        info!("Resetting board for {}", color);
+        let mut state = lock_app(&state.0);
+        state.handle(Event::Reset { color })?;
+        Ok(())
+    }
+
+    #[tauri::command]
+    pub fn undo(state: State<ChessState>) -> Result<String, String> {
+        let mut state = lock_app(&state.0);
+        state.handle(Event::Undo)
+    }
+
+    /// Squares the piece on `square` can move to in the position being shown
+    #[tauri::command]
+    pub fn legal_moves(square: &str, state: State<ChessState>) -> Vec<String> {
+        let state = lock_app(&state.0);
+        let Ok(square) = Square::from_ascii(square.as_bytes()) else {
+            return vec![];
+        };
+        let mut targets = state
+            .history
+            .current()
+            .legal_moves()
+            .iter()
+            .filter(|x| x.from() == Some(square))
+            .flat_map(|x| [Some(x.to()), castled_king(state.history.current(), x)])
+            .flatten()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        // Promotions give the same square several times
+        targets.dedup();
+        targets
+    }
+
+    /// Squares of the pieces the prep wants moved next, nothing if not drilling
+    #[tauri::command]
+    pub fn hint(app: tauri::AppHandle, state: State<ChessState>) -> Vec<String> {
+        let mut state = lock_app(&state.0);
+        let Some(game_state) = state.game_state.as_ref() else {
+            return vec![];
+        };
+        if !state.config.drill.hints {
+            return vec![];
+        }
+        let mut pieces = game_state
+            .hint(state.db.graph(state.color))
+            .iter()
+            .filter_map(|x| x.san.to_move(&state.game).ok())
+            .filter_map(|x| Some((x.role(), x.from()?)))
+            .collect::<Vec<_>>();
+        pieces.sort();
+        pieces.dedup();
+        if state.config.speech.enabled {
+            speak(&app, &spoken_hint(&pieces));
+        }
+        let squares = pieces
+            .iter()
+            .map(|(_, x)| x.to_string())
+            .collect::<Vec<_>>();
+        if let Some(attempt) = state.attempt.as_mut().filter(|_| !squares.is_empty()) {
+            attempt.hint();
+        }
+        squares
+    }
+
+    /// Queue up checking a chapter, or the whole repertoire, with the engine. Returns the job's
+    /// id, the report comes in its last `job-update`.
+    #[tauri::command]
+    pub fn audit(
+        color: String,
+        chapter: Option<String>,
+        state: State<ChessState>,
+        jobs: State<JobQueue>,
+    ) -> Result<u64, String> {
+        let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let (config, cache) = {
+            let mut app = lock_app(&state.0);
+            (app.config.clone(), app.eval_cache())
+        };
+        record_usage(&config, "audit");
+        let openings = load_chapter(&config.repertoire(color), chapter.as_deref())
+            .map_err(|e| e.to_string())?;
+        let name = match chapter {
+            Some(chapter) => format!("Audit of {}", chapter),
+            None => format!("Audit of the {} repertoire", color),
+        };
+        Ok(jobs.push(name, move |job| {
+            let report = run_audit(job, &config, &cache, &openings, color)?;
+            Ok(serde_json::to_value(report)?)
+        }))
+    }
+
+    /// Queue up finding where the repertoire leaves the reference games. Returns the job's id,
+    /// the report comes in its last `job-update`.
+    #[tauri::command]
+    pub fn novelties(
+        color: String,
+        chapter: Option<String>,
+        state: State<ChessState>,
+        jobs: State<JobQueue>,
+    ) -> Result<u64, String> {
+        let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let (config, cache) = {
+            let mut app = lock_app(&state.0);
+            (app.config.clone(), app.eval_cache())
+        };
+        record_usage(&config, "novelties");
+        let openings = load_chapter(&config.repertoire(color), chapter.as_deref())
+            .map_err(|e| e.to_string())?;
+        let name = match chapter {
+            Some(chapter) => format!("Novelties in {}", chapter),
+            None => format!("Novelties in the {} repertoire", color),
+        };
+        Ok(jobs.push(name, move |job| {
+            let report = run_novelties(job, &config, &cache, &openings, color)?;
+            Ok(serde_json::to_value(report)?)
+        }))
+    }
+
+    /// Queue up looking for blunders near the repertoire in the downloaded games. Returns the
+    /// job's id, the report comes in its last `job-update`.
+    #[tauri::command]
+    pub fn scan_games(state: State<ChessState>, jobs: State<JobQueue>) -> Result<u64, String> {
+        let (config, cache) = {
+            let mut app = lock_app(&state.0);
+            (app.config.clone(), app.eval_cache())
+        };
+        record_usage(&config, "scan_games");
+        Ok(jobs.push("Scan of your games", move |job| {
+            let report = run_scan(job, &config, &cache)?;
+            Ok(serde_json::to_value(report)?)
+        }))
+    }
+
+    /// Jobs that haven't finished yet
+    #[tauri::command]
+    pub fn jobs(jobs: State<JobQueue>) -> Vec<JobStatus> {
+        jobs.list()
+    }
+
+    #[tauri::command]
+    pub fn pause_job(id: u64, jobs: State<JobQueue>) -> Result<(), String> {
+        jobs.pause(id).map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn resume_job(id: u64, jobs: State<JobQueue>) -> Result<(), String> {
+        jobs.resume(id).map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn cancel_job(id: u64, jobs: State<JobQueue>) -> Result<(), String> {
+        jobs.cancel(id).map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn endgame_chapters(state: State<ChessState>) -> Vec<String> {
+        list_endgames(&lock_app(&state.0).config.endgames())
+    }
+
+    /// Set up a random position from the chapter, or any chapter, to play out against the
+    /// engine. The engine works out what it should come to first so this runs off the main thread.
+    #[tauri::command]
+    pub async fn start_endgame(
+        chapter: Option<String>,
+        state: State<'_, ChessState>,
+    ) -> Result<EndgameStart, String> {
+        let (folder, movetime, engine) = {
+            let mut state = lock_app(&state.0);
+            let engine = state.engine().map_err(|e| e.to_string())?;
+            (
+                state.config.endgames(),
+                state.engine_settings().movetime,
+                engine,
+            )
+        };
+        let positions = load_endgames(&folder, chapter.as_deref()).map_err(|e| e.to_string())?;
+        let fen = fastrand::choice(positions.iter())
+            .ok_or_else(|| "There are no endgame positions".to_string())?
+            .clone();
+        let limits = Limits {
+            movetime: Some(movetime),
+            ..Default::default()
+        };
+        let result = engine
+            .analyze(&fen, limits)
+            .recv()
+            .map_err(|_| "The engine stopped".to_string())?
+            .map_err(|e| e.to_string())?
+            .result();
+        let mut state = lock_app(&state.0);
+        let board = state.handle(Event::Endgame {
+            fen: fen.clone(),
+            result,
+        })?;
+        Ok(EndgameStart {
+            board,
+            color: state.color.to_string(),
+            result,
+        })
+    }
+
+    /// Opponent moves from the player's games that the repertoire doesn't cover. The engine's
+    /// replies follow as `gap-suggestion`, or `gap-failed` if it can't be run.
+    #[tauri::command]
+    pub fn gaps(
+        color: String,
+        app: tauri::AppHandle,
+        state: State<ChessState>,
+    ) -> Result<Vec<Gap>, String> {
+        let color = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let (config, games) = {
+            let state = lock_app(&state.0);
+            (state.config.clone(), state.games.graph(color).clone())
+        };
+        record_usage(&config, "gaps");
+        let repertoire =
+            load_chapter(&config.repertoire(color), None).map_err(|e| e.to_string())?;
+        let gaps = find_gaps(&repertoire, &games, color);
+        info!("Found {} gaps in the {} repertoire", gaps.len(), color);
+        let suggesting = gaps.clone();
+        thread::spawn(move || {
+            if let Err(e) = suggest_replies(&app, &config, &suggesting) {
+                error!("Couldn't suggest replies to gaps: {}", e);
+                if let Err(e) = app.emit("gap-failed", e.to_string()) {
+                    error!("Couldn't send gap failure: {}", e);
+                }
+            }
+        });
+        Ok(gaps)
+    }
+
+    /// Add a gap and the reply to it to the repertoire file and the prep being drilled
+    #[tauri::command]
+    pub fn fill_gap(
+        color: String,
+        mut moves: Vec<String>,
+        reply: String,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        let player = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let mut state = lock_app(&state.0);
+        let path = gap_file(&state.config.repertoire(player))
+            .ok_or_else(|| format!("There's no {} repertoire to add to", color))?;
+        moves.push(reply);
+        let pgn = append_line(&path, &moves).map_err(|e| e.to_string())?;
+        info!("Added {} to {}", moves.join(" "), path.display());
+        state.handle(Event::ImportPgn { color, pgn })?;
+        Ok(path.display().to_string())
+    }
+
+    /// Analyse the position shown, call it again whenever the board changes
+    #[tauri::command]
+    pub fn analyze(app: tauri::AppHandle, state: State<ChessState>) -> Result<(), String> {
+        start_analysis(&state.0, app).map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn stop_analysis(state: State<ChessState>) -> Result<(), String> {
+        let mut state = lock_app(&state.0);
+        if state.analysis.take().is_some() {
+            if let Some(engine) = state.engine.as_ref() {
+                engine.stop().map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the engines that can be switched to and the one in use, `null` being `engine`
+    #[tauri::command]
+    pub fn engines(state: State<ChessState>) -> EngineList {
+        let state = lock_app(&state.0);
+        EngineList {
+            names: state.config.engines.keys().cloned().collect(),
+            active: state.active_engine.clone(),
+        }
+    }
+
+    /// Use another engine for sparring and analysis, `null` for `engine`
+    #[tauri::command]
+    pub fn select_engine(name: Option<String>, state: State<ChessState>) -> Result<(), String> {
+        let mut state = lock_app(&state.0);
+        state.select_engine(name).map_err(|e| e.to_string())
+    }
+
+    /// Open the log in the file manager to attach to a bug report, returns where it is
+    #[tauri::command]
+    pub fn show_log(state: State<ChessState>) -> Result<String, String> {
+        let path = lock_app(&state.0).config.log_file();
+        logging::reveal(&path).map_err(|e| e.to_string())?;
+        Ok(path.display().to_string())
+    }
+
+    /// Errors in the frontend, so they're in the log with everything else
+    #[tauri::command]
+    pub fn log_error(message: String) {
+        error!("Frontend: {}", message);
+    }
+
+    /// FEN of the position being shown
+    #[tauri::command]
+    pub fn fen(state: State<ChessState>) -> String {
+        let state = lock_app(&state.0);
+        Fen::from_position(state.history.current().clone(), EnPassantMode::Legal).to_string()
+    }
+
+    /// Set up a Chess960 starting position to play the engine from, a random one if there's no
+    /// number
+    #[tauri::command]
+    pub fn chess960(number: Option<u32>, state: State<ChessState>) -> Result<Setup, String> {
+        let number = number.unwrap_or_else(|| fastrand::u32(..960));
+        let position = chess960_start(number).map_err(|e| e.to_string())?;
+        let fen = Fen::from_position(position, EnPassantMode::Legal).to_string();
+        info!("Setting up Chess960 position {}: {}", number, fen);
+        let mut state = lock_app(&state.0);
+        let board = state.handle(Event::SetFen { fen })?;
+        Ok(Setup {
+            board,
+            in_prep: state.history.from_start(state.db.start(state.color)),
+        })
+    }
+
+    #[tauri::command]
+    pub fn set_fen(fen: String, state: State<ChessState>) -> Result<Setup, String> {
+        info!("Setting up position: {}", fen);
+        let mut state = lock_app(&state.0);
+        let board = state.handle(Event::SetFen { fen })?;
+        Ok(Setup {
+            board,
+            in_prep: state.history.from_start(state.db.start(state.color)),
+        })
+    }
+
+    /// Close the app after the user has confirmed it mid-drill, optionally keeping the game
+    #[tauri::command]
+    pub fn quit(save: bool, app: tauri::AppHandle, state: State<ChessState>) -> Result<(), String> {
+        let state = lock_app(&state.0);
+        if save {
+            state.save_session().map_err(|e| e.to_string())?;
+        }
+        remove_autosave(&autosave_file(&state.config));
+        app.exit(0);
+        Ok(())
+    }
+
+    /// The game as a PGN, with the engine's evals in it if the config asks for them. Annotating
+    /// starts its own engine and searches every position so this runs off the main thread.
+    #[tauri::command]
+    pub async fn pgn(state: State<'_, ChessState>) -> Result<String, String> {
+        let (history, config, cache) = {
+            let mut state = lock_app(&state.0);
+            if !state.config.export.evals {
+                return Ok(state.history.to_pgn());
+            }
+            (
+                state.history.clone(),
+                state.config.clone(),
+                state.eval_cache(),
+            )
+        };
+        let engine = config
+            .engine_named(config.export.engine.as_deref())
+            .and_then(UciEngine::start)
+            .map_err(|e| e.to_string())?;
+        let annotations = annotate(
+            &engine,
+            &cache,
+            history.start(),
+            &history.moves(),
+            config.export.limits(),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(history.to_annotated_pgn(&annotations))
+    }
+
+    #[tauri::command]
+    pub fn move_list(state: State<ChessState>) -> MoveList {
+        let state = lock_app(&state.0);
+        let (moves, cursor) = state.history.line();
+        let locale = state.config.piece_locale();
+        let figurine = state.config.notation.figurine;
+        MoveList {
+            moves: moves
+                .iter()
+                .map(|x| localized_san(&x.to_string(), locale, figurine))
+                .collect(),
+            cursor,
+            result: state.history.outcome().map(|x| x.to_string()),
+            draw: state.history.draw_rule(),
+        }
+    }
+
+    #[tauri::command]
+    pub fn history_back(state: State<ChessState>) -> Result<String, String> {
+        let mut state = lock_app(&state.0);
+        state.handle(Event::HistoryBack)
+    }
+
+    #[tauri::command]
+    pub fn history_forward(state: State<ChessState>) -> Result<String, String> {
+        let mut state = lock_app(&state.0);
+        state.handle(Event::HistoryForward)
+    }
+
+    #[tauri::command]
+    pub fn history_sibling(offset: isize, state: State<ChessState>) -> Result<String, String> {
+        let mut state = lock_app(&state.0);
+        state.handle(Event::HistorySibling { offset })
+    }
+
+    #[tauri::command]
+    pub fn play_san(
+        san: String,
+        app: tauri::AppHandle,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        info!("Typed move: {}", san);
+        let san = english_san(&san, lock_app(&state.0).config.piece_locale());
+        assess_off_lock(&state.0, |x| x.san_move(&san).ok());
+        let board = lock_app(&state.0).handle(Event::PlaySan { san })?;
+        explain_wrong_move(&state.0, &app);
+        engine_reply(&state.0, app);
+        Ok(board)
+    }
+
+    /// How drilling's been going, from every attempt recorded
+    #[tauri::command]
+    pub fn stats(state: State<ChessState>) -> Result<Summary, String> {
+        let config = lock_app(&state.0).config.clone();
+        record_usage(&config, "stats");
+        let attempts = load_attempts(&attempts_file(&config)).map_err(|e| e.to_string())?;
+        let archive = load_archive(&archive_file(&config)).map_err(|e| e.to_string())?;
+        Ok(summarize(&attempts, &archive.days))
+    }
+
+    /// Write flashcards of the repertoire or a chapter to the downloads folder for Anki to import,
+    /// returning where they went
+    #[tauri::command]
+    pub fn export_anki(
+        color: String,
+        chapter: Option<String>,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        let player = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let config = lock_app(&state.0).config.clone();
+        record_usage(&config, "export_anki");
+        let openings = load_chapter(&config.repertoire(player), chapter.as_deref())
+            .map_err(|e| e.to_string())?;
+        let cards = cards(&openings, player);
+        let name = match chapter.as_deref() {
+            Some(chapter) => format!("chess-driller-{}.txt", chapter),
+            None => format!("chess-driller-{}.txt", player),
+        };
+        let dir = dirs::download_dir().unwrap_or_else(|| config.data_dir());
+        let path = dir.join(name);
+        fs::write(&path, anki_text(&cards, player, chapter.as_deref()))
+            .map_err(|e| e.to_string())?;
+        info!("Exported {} cards to {}", cards.len(), path.display());
+        Ok(path.display().to_string())
+    }
+
+    /// Write a booklet of the repertoire or a chapter to the downloads folder, returning where it
+    /// went
+    #[tauri::command]
+    pub fn export_booklet(
+        color: String,
+        chapter: Option<String>,
+        plugin: Option<String>,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        let player = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let config = lock_app(&state.0).config.clone();
+        record_usage(&config, "export_booklet");
+        let today = chrono::Local::now().date_naive();
+        let renderer = booklet_renderer(&config, plugin.as_deref()).map_err(|e| e.to_string())?;
+        let booklet =
+            load_booklet(&config, player, chapter.as_deref(), today).map_err(|e| e.to_string())?;
+        let name = match chapter.as_deref() {
+            Some(chapter) => format!("chess-driller-{}.{}", chapter, renderer.extension()),
+            None => format!("chess-driller-{}.{}", player, renderer.extension()),
+        };
+        let dir = dirs::download_dir().unwrap_or_else(|| config.data_dir());
+        let path = dir.join(name);
+        let text = renderer.render(&booklet).map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| e.to_string())?;
+        info!("Exported a booklet to {}", path.display());
+        Ok(path.display().to_string())
+    }
+
+    /// Seed the schedule with reviews exported from another app, returning how many were read
+    #[tauri::command]
+    pub fn import_reviews(
+        color: String,
+        export: String,
+        state: State<ChessState>,
+    ) -> Result<usize, String> {
+        let player = Color::from_str(&color).map_err(|e| e.to_string())?;
+        let config = lock_app(&state.0).config.clone();
+        let today = chrono::Local::now().date_naive();
+        let seeds =
+            read_review_export(&export, &player.to_string(), today).map_err(|e| e.to_string())?;
+        record_seeds(&seeds_file(&config), &seeds).map_err(|e| e.to_string())?;
+        info!("Imported {} reviews for {}", seeds.len(), player);
+        Ok(seeds.len())
+    }
+
+    /// Merge the training history here with the copy on the sync server, the job's result is
+    /// how many records came from it
+    #[tauri::command]
+    pub fn sync_progress(state: State<ChessState>, jobs: State<JobQueue>) -> Result<u64, String> {
+        let config = lock_app(&state.0).config.clone();
+        record_usage(&config, "sync_progress");
+        if storages(&config).is_empty() {
+            return Err("There's no sync server in the config".to_string());
+        }
+        Ok(
+            jobs.push("Syncing training progress".to_string(), move |_| {
+                let count = crate::sync_progress(&config)?;
+                Ok(serde_json::to_value(count)?)
+            }),
+        )
+    }
+
+    #[tauri::command]
+    pub fn usage_log(state: State<ChessState>) -> bool {
+        lock_app(&state.0).config.usage_log
+    }
+
+    #[tauri::command]
+    pub fn set_usage_log(enabled: bool, state: State<ChessState>) -> Result<(), String> {
+        info!(
+            "Turning the usage log {}",
+            if enabled { "on" } else { "off" }
+        );
+        let mut state = lock_app(&state.0);
+        state.config.usage_log = enabled;
+        save_config(state.config.profile.as_deref(), |config| {
+            config.usage_log = enabled
+        })
+    }
+
+    /// What's been used in the app and how long for, from the usage log
+    #[tauri::command]
+    pub fn usage(state: State<ChessState>) -> Result<UsageSummary, String> {
+        let config = lock_app(&state.0).config.clone();
+        let records = load_usage(&usage_file(&config)).map_err(|e| e.to_string())?;
+        Ok(summarize_usage(&records))
+    }
+
+    #[tauri::command]
+    pub fn clear_usage(state: State<ChessState>) -> Result<(), String> {
+        let config = lock_app(&state.0).config.clone();
+        crate::clear_usage(&usage_file(&config)).map_err(|e| e.to_string())
+    }
+
+    #[tauri::command]
+    pub fn keep_months(state: State<ChessState>) -> Option<u32> {
+        lock_app(&state.0).config.keep_months
+    }
+
+    /// Keep only the last `months` of attempts from now on, archiving the older ones straight
+    /// away. Returns how many were archived.
+    #[tauri::command]
+    pub fn set_keep_months(months: Option<u32>, state: State<ChessState>) -> Result<usize, String> {
+        info!("Keeping {:?} months of attempts", months);
+        let mut state = lock_app(&state.0);
+        state.config.keep_months = months;
+        save_config(state.config.profile.as_deref(), |config| {
+            config.keep_months = months
+        })?;
+        let Some(months) = months else {
+            return Ok(0);
+        };
+        let today = chrono::Local::now().date_naive();
+        archive_history(&state.config, &state.db, months, today).map_err(|e| e.to_string())
+    }
+
+    /// Lines of the prep being drilled for each colour that come due each day for `days` days
+    #[tauri::command]
+    pub fn forecast(days: u32, state: State<ChessState>) -> Result<Vec<DueDay>, String> {
+        let state = lock_app(&state.0);
+        let today = chrono::Local::now().date_naive();
+        let white = state
+            .schedule(Color::White, today)
+            .map_err(|e| e.to_string())?;
+        let black = state
+            .schedule(Color::Black, today)
+            .map_err(|e| e.to_string())?;
+        Ok(schedule::forecast(&white, &black, today, days))
+    }
+
+    /// Every recorded attempt grouped into sessions, most recent first
+    #[tauri::command]
+    pub fn sessions(state: State<ChessState>) -> Result<Vec<Session>, String> {
+        let path = attempts_file(&lock_app(&state.0).config);
+        let attempts = load_attempts(&path).map_err(|e| e.to_string())?;
+        Ok(stats::sessions(attempts))
+    }
+
+    #[tauri::command]
+    pub fn replay_line(
+        color: String,
+        moves: Vec<String>,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        info!("Replaying {}", moves.join(" "));
+        let mut state = lock_app(&state.0);
+        state.handle(Event::ReplayLine { color, moves })
+    }
+
+    /// Results of the downloaded games in openings that have been drilled and ones that haven't
+    #[tauri::command]
+    pub fn training_results(state: State<ChessState>) -> Result<TrainingResults, String> {
+        let config = lock_app(&state.0).config.clone();
+        let attempts = load_attempts(&attempts_file(&config)).map_err(|e| e.to_string())?;
+        let games = downloaded_games(&config).map_err(|e| e.to_string())?;
+        Ok(compare_results(&games, &attempts))
+    }
+
+    /// How long the player takes over the openings in their downloaded games, from the clocks
+    #[tauri::command]
+    pub fn opening_times(state: State<ChessState>) -> Result<Vec<OpeningTime>, String> {
+        let config = lock_app(&state.0).config.clone();
+        let games = downloaded_games(&config).map_err(|e| e.to_string())?;
+        Ok(crate::opening_times(&games))
+    }
+
+    /// Write every attempt and the totals for each line to the downloads folder, as `csv` or
+    /// `json`, returning the files written
+    #[tauri::command]
+    pub fn export_stats(format: String, state: State<ChessState>) -> Result<Vec<String>, String> {
+        let config = lock_app(&state.0).config.clone();
+        let attempts = load_attempts(&attempts_file(&config)).map_err(|e| e.to_string())?;
+        let lines = line_stats(&attempts);
+        let files = match format.as_str() {
+            "csv" => vec![
+                ("chess-driller-attempts.csv", attempts_csv(&attempts)),
+                ("chess-driller-lines.csv", lines_csv(&lines)),
+            ],
+            "json" => {
+                let stats = serde_json::json!({ "attempts": attempts, "lines": lines });
+                let json = serde_json::to_string_pretty(&stats).map_err(|e| e.to_string())?;
+                vec![("chess-driller-stats.json", json)]
+            }
+            _ => return Err(format!("Can't export stats as {}", format)),
+        };
+        let dir = dirs::download_dir().unwrap_or_else(|| config.data_dir());
+        let mut paths = vec![];
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::write(&path, contents).map_err(|e| e.to_string())?;
+            paths.push(path.display().to_string());
+        }
+        info!("Exported stats to {}", paths.join(", "));
+        Ok(paths)
+    }
+
+    /// How drilling's going in each profile
+    #[tauri::command]
+    pub fn compare_profiles() -> Result<Vec<ProfileStats>, String> {
+        let today = chrono::Local::now().date_naive();
+        crate::compare_profiles(today).map_err(|e| e.to_string())
+    }
+
+    /// Write the comparison of the profiles to the downloads folder as `csv` or `html`,
+    /// returning where it went
+    #[tauri::command]
+    pub fn export_profiles(format: String, state: State<ChessState>) -> Result<String, String> {
+        let config = lock_app(&state.0).config.clone();
+        let today = chrono::Local::now().date_naive();
+        let profiles = crate::compare_profiles(today).map_err(|e| e.to_string())?;
+        let (name, contents) = match format.as_str() {
+            "csv" => ("chess-driller-profiles.csv", profiles_csv(&profiles)),
+            "html" => (
+                "chess-driller-profiles.html",
+                profiles_html(&profiles, today),
+            ),
+            _ => return Err(format!("Can't export profiles as {}", format)),
+        };
+        let path = dirs::download_dir()
+            .unwrap_or_else(|| config.data_dir())
+            .join(name);
+        fs::write(&path, contents).map_err(|e| e.to_string())?;
+        info!("Exported profiles to {}", path.display());
+        Ok(path.display().to_string())
+    }
+
+    /// Legal moves in SAN starting with what's been typed so far
+    #[tauri::command]
+    pub fn san_completions(prefix: &str, state: State<ChessState>) -> Vec<String> {
+        let state = lock_app(&state.0);
+        let locale = state.config.piece_locale();
+        let prefix = english_san(prefix.trim(), locale);
+        let mut moves = state
+            .game
+            .legal_moves()
+            .iter()
+            .map(|x| SanPlus::from_move(state.game.clone(), x).to_string())
+            .filter(|x| x.starts_with(&prefix))
+            .map(|x| localized_san(&x, locale, false))
+            .collect::<Vec<_>>();
+        moves.sort();
+        moves
+    }
+
+    #[tauri::command]
+    pub fn move_piece(
+        from: String,
+        to: String,
+        promotion: String,
+        app: tauri::AppHandle,
+        state: State<ChessState>,
+    ) -> Result<String, String> {
+        info!("Args: {}->{} {}", from, to, promotion);
+        assess_off_lock(&state.0, |x| x.piece_move(&from, &to, &promotion));
+        let board = lock_app(&state.0).handle(Event::MovePiece {
+            from,
+            to,
+            promotion,
+        })?;
+        explain_wrong_move(&state.0, &app);
+        engine_reply(&state.0, app);
+        Ok(board)
     }
 }
 /*
@@ -295,7 +2814,6 @@ pub fn run() -> anyhow::Result<()> {
                         }
                     }
                 }
-                // TODO: long click mouse up mouse down?
                 _ => {}
             }
         }
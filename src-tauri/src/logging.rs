@@ -0,0 +1,71 @@
+//! Logs go to a file as well as the console, there's no console to look at when the app is
+//! started from a desktop launcher.
+use crate::config::Config;
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+
+/// Start logging with the levels in the config, `RUST_LOG` overrides them. It goes to the log
+/// file, and stdout too if `console`. The log from the last run is kept alongside with `.old` on
+/// the end in case it's the one with the crash in it.
+pub fn init(config: &Config, console: bool) {
+    let filter = match env::var("RUST_LOG") {
+        Ok(_) => EnvFilter::from_env("RUST_LOG"),
+        _ => EnvFilter::try_new(config.logging.directives())
+            .unwrap_or_else(|_| EnvFilter::new("chess_driller=info,chess_driller_core=info")),
+    };
+
+    let path = config.log_file();
+    let file = match open(&path) {
+        Ok(file) => Some(
+            fmt::Layer::default()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file)),
+        ),
+        Err(e) => {
+            eprintln!("Couldn't open log file {}: {}", path.display(), e);
+            None
+        }
+    };
+
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(console.then(fmt::Layer::default))
+        .with(file);
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+}
+
+fn open(path: &Path) -> std::io::Result<File> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    if path.exists() {
+        let mut old = path.as_os_str().to_owned();
+        old.push(".old");
+        fs::rename(path, old)?;
+    }
+    File::create(path)
+}
+
+/// Show the log file in the platform's file manager
+pub fn reveal(path: &Path) -> anyhow::Result<()> {
+    let mut command;
+    if cfg!(target_os = "macos") {
+        command = Command::new("open");
+        command.arg("-R").arg(path);
+    } else if cfg!(windows) {
+        command = Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+    } else {
+        // There's no standard way to select a file on Linux, open the folder it's in
+        command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+    }
+    command.spawn()?;
+    Ok(())
+}
@@ -1,22 +1,30 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use std::env;
-use tracing::info;
-use tracing_subscriber::filter::EnvFilter;
-use tracing_subscriber::{Layer, Registry};
+use chess_driller::Cli;
+use clap::Parser;
 
 fn main() {
-    let filter = match env::var("RUST_LOG") {
-        Ok(_) => EnvFilter::from_env("RUST_LOG"),
-        _ => EnvFilter::new("chess_driller=info"),
-    };
-
-    let fmt = tracing_subscriber::fmt::Layer::default();
-
-    let subscriber = filter.and_then(fmt).with_subscriber(Registry::default());
-
-    tracing::subscriber::set_global_default(subscriber).unwrap();
-    info!("Starting chess driller");
-
-    chess_driller::launch();
+    let cli = Cli::parse();
+    if let Some(address) = cli.serve.clone() {
+        if let Err(e) = chess_driller::serve::run(cli, &address) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cli.scripted() {
+        if let Err(e) = chess_driller::subcommands::run(&cli) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(args) = cli.terminal().cloned() {
+        if let Err(e) = chess_driller::terminal::run(cli, &args) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    chess_driller::launch(cli);
 }
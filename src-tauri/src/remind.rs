@@ -0,0 +1,91 @@
+//! `chess-driller remind`, which stays in the background and puts up a desktop notification when
+//! lines come due or the daily goal hasn't been met by the time in the config. Notifications go
+//! through whatever the platform already has, `notify-send`, `osascript` or PowerShell, and are
+//! printed if that doesn't work.
+use crate::cli::{Cli, RemindArgs};
+use crate::{
+    attempts_file, drills_on, load_attempts, load_chapter, load_reminded, load_schedule,
+    reminded_file, reminders, save_reminded, Config,
+};
+use shakmaty::Color;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Check what's due every `reminders.every` minutes, or just the once with `--once` for cron
+pub fn run(cli: &Cli, args: &RemindArgs) -> anyhow::Result<()> {
+    loop {
+        // Loaded each time round so changes to the config or the repertoire get picked up
+        let (mut config, _) = Config::load_checked(cli.profile.as_deref())?;
+        cli.apply(&mut config);
+        if let Err(e) = check(&config) {
+            error!("Couldn't work out what to remind about: {:#}", e);
+            if args.once {
+                return Err(e);
+            }
+        }
+        if args.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(config.reminders.every * 60));
+    }
+}
+
+fn check(config: &Config) -> anyhow::Result<()> {
+    let now = chrono::Local::now().naive_local();
+    let mut due = 0;
+    for color in [Color::White, Color::Black] {
+        let openings = load_chapter(&config.repertoire(color), None)?;
+        due += load_schedule(config, color, &openings, now.date())?
+            .iter()
+            .filter(|x| x.due <= now.date())
+            .count();
+    }
+    let attempts = load_attempts(&attempts_file(config))?;
+    let done = drills_on(&attempts, &now.date().to_string());
+    let path = reminded_file(config);
+    let mut reminded = load_reminded(&path)?;
+    let messages = reminders(&config.reminders, due, done, now, &mut reminded);
+    info!("{} lines due and {} drills done today", due, done);
+    for message in &messages {
+        notify("Chess driller", message);
+    }
+    if !messages.is_empty() {
+        save_reminded(&path, &reminded)?;
+    }
+    Ok(())
+}
+
+fn notify(title: &str, body: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {:?} with title {:?}",
+            body, title
+        ));
+        command
+    } else if cfg!(windows) {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command"]).arg(format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+             $n.ShowBalloonTip(10000, '{}', '{}', 'Info'); Start-Sleep -Seconds 10; $n.Dispose()",
+            title.replace('\'', "''"),
+            body.replace('\'', "''")
+        ));
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", "chess-driller", title, body]);
+        command
+    };
+    match command.status() {
+        Ok(status) if status.success() => info!("Reminded: {}", body),
+        result => {
+            info!("Couldn't send a notification ({:?}), printing it", result);
+            println!("{}: {}", title, body);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Scripted drills for catching regressions without a window. A script is a recording like
+//! [`App::start_recording`] writes, a JSON event a line, with `expect` lines in between saying what
+//! the drill should look like by then:
+//!
+//! ```text
+//! {"kind": "import_pgn", "color": "white", "pgn": "1. e4 e5 2. Nf3 *"}
+//! {"kind": "start"}
+//! {"kind": "play_san", "san": "e4"}
+//! {"expect": {"moves": ["e4", "e5"], "hints": ["Nf3"]}}
+//! ```
+//!
+//! Events go through [`App::handle`] the same as they do from the window. One that fails stops
+//! the script unless the line after it expects the `error`. Lines starting with `//` are comments.
+use crate::events::Event;
+use crate::App;
+use anyhow::Context;
+use serde::Deserialize;
+use shakmaty::fen::Fen;
+use shakmaty::EnPassantMode;
+use std::fmt::Debug;
+use std::io;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Step {
+    Expect { expect: Expectation },
+    Event(Event),
+}
+
+/// What to check, anything left out can be whatever it is
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Expectation {
+    /// Every move in the game so far
+    pub moves: Option<Vec<String>>,
+    /// Of the position on the board
+    pub fen: Option<String>,
+    pub drilling: Option<bool>,
+    pub sparring: Option<bool>,
+    /// Moves the prep has for the player now, in any order
+    pub hints: Option<Vec<String>>,
+    /// Moves got wrong in the drill that's going
+    pub mistakes: Option<Vec<String>>,
+    /// Part of the message the last event failed with
+    pub error: Option<String>,
+}
+
+fn differs<T: PartialEq + Debug>(unmet: &mut Vec<String>, what: &str, expected: Option<T>, got: T) {
+    if let Some(expected) = expected.filter(|x| *x != got) {
+        unmet.push(format!(
+            "{} should be {:?} but is {:?}",
+            what, expected, got
+        ));
+    }
+}
+
+impl App {
+    /// How the app doesn't match up to `expect`, nothing if it does. `error` is what the last
+    /// event failed with.
+    fn unmet(&self, expect: Expectation, error: Option<String>) -> Vec<String> {
+        let mut unmet = vec![];
+        let moves = self.history.moves().iter().map(|x| x.to_string()).collect();
+        differs(&mut unmet, "moves", expect.moves, moves);
+        let fen = Fen::from_position(self.history.current().clone(), EnPassantMode::Legal);
+        differs(&mut unmet, "fen", expect.fen, fen.to_string());
+        differs(&mut unmet, "drilling", expect.drilling, self.is_drilling());
+        differs(&mut unmet, "sparring", expect.sparring, self.sparring);
+        let mut hints = self.game_state.as_ref().map_or(vec![], |x| {
+            x.hint(self.db.graph(self.color))
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+        });
+        hints.sort();
+        let expected = expect.hints.map(|mut x| {
+            x.sort();
+            x
+        });
+        differs(&mut unmet, "hints", expected, hints);
+        let mistakes = self.attempt.as_ref().map_or(vec![], |x| {
+            x.so_far(vec![])
+                .mistakes
+                .into_iter()
+                .map(|x| x.played)
+                .collect()
+        });
+        differs(&mut unmet, "mistakes", expect.mistakes, mistakes);
+        match (expect.error, error) {
+            (None, None) => {}
+            (Some(expected), Some(e)) if e.contains(&expected) => {}
+            (Some(expected), Some(e)) => unmet.push(format!(
+                "should have failed with {:?} but failed with {:?}",
+                expected, e
+            )),
+            (Some(expected), None) => {
+                unmet.push(format!("should have failed with {:?} but didn't", expected))
+            }
+            (None, Some(e)) => unmet.push(format!("the last event failed: {}", e)),
+        }
+        unmet
+    }
+}
+
+/// Play a script through the app, returns how many `expect` lines it checked
+pub fn run_script(app: &mut App, script: impl io::BufRead) -> anyhow::Result<usize> {
+    let mut checked = 0;
+    // The line of the last event and what it failed with, if it did
+    let mut failed: Option<(usize, String)> = None;
+    for (number, line) in script.lines().enumerate() {
+        let (number, line) = (number + 1, line?);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let step = serde_json::from_str(line)
+            .with_context(|| format!("Line {} isn't an event or an expect", number))?;
+        match step {
+            Step::Event(event) => {
+                if let Some((line, e)) = failed.take() {
+                    anyhow::bail!("Line {}: {}", line, e);
+                }
+                if let Err(e) = app.handle(event) {
+                    failed = Some((number, e));
+                }
+            }
+            Step::Expect { expect } => {
+                let unmet = app.unmet(expect, failed.take().map(|x| x.1));
+                if !unmet.is_empty() {
+                    anyhow::bail!("Line {}: {}", number, unmet.join(", "));
+                }
+                checked += 1;
+            }
+        }
+    }
+    if let Some((line, e)) = failed {
+        anyhow::bail!("Line {}: {}", line, e);
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::headless_app;
+    use crate::{Config, OpeningDatabase};
+    use std::fs;
+    use std::io::BufReader;
+    use std::path::Path;
+
+    #[test]
+    fn scripted_drills() {
+        let mut scripts = fs::read_dir("tests/scripts")
+            .unwrap()
+            .map(|x| x.unwrap().path())
+            .collect::<Vec<_>>();
+        scripts.sort();
+        assert!(!scripts.is_empty());
+        let run = |script: &Path| {
+            let name = script.file_stem().unwrap().to_string_lossy();
+            let dir = std::env::temp_dir().join(format!(
+                "chess-driller-script-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let config = Config {
+                data_path: Some(dir.clone()),
+                ..Default::default()
+            };
+            let mut app = headless_app(config);
+            // They bring their own prep so they don't change with the example one
+            app.db = OpeningDatabase::default();
+            let res = run_script(&mut app, BufReader::new(fs::File::open(script).unwrap()));
+            let _ = fs::remove_dir_all(&dir);
+            res
+        };
+        for script in &scripts {
+            if let Err(e) = run(script) {
+                panic!("{} failed: {:#}", script.display(), e);
+            }
+        }
+
+        let mut app = headless_app(Config::default());
+        app.db = OpeningDatabase::default();
+        let script = r#"
+            {"kind": "play_san", "san": "e4"}
+            {"expect": {"moves": ["e4"]}}
+            {"expect": {"moves": ["d4"]}}
+        "#;
+        let e = run_script(&mut app, script.as_bytes()).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            r#"Line 4: moves should be ["d4"] but is ["e4"]"#
+        );
+        let e = run_script(&mut app, r#"{"kind": "play_san", "san": "Ke2"}"#.as_bytes());
+        assert!(e.unwrap_err().to_string().starts_with("Line 1: "));
+    }
+}
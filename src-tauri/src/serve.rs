@@ -0,0 +1,266 @@
+//! Drilling driven over JSON-RPC on a local socket, for other GUIs, editors or bots. Each request
+//! and response is a JSON-RPC 2.0 object on a line of its own. Every connection drives the same
+//! drill, and it counts towards the stats and the schedule like any other.
+//!
+//! The methods are `start_drill` (`color`, and `chapter` to only drill one), `apply_move`
+//! (`san`), `get_state`, `get_hint`, `undo` and `list_chapters` (`color`). All but the last two
+//! answer with the drill's state.
+use crate::cli::Cli;
+use crate::config::Config;
+use crate::db::list_chapters;
+use crate::events::Event;
+use crate::terminal::headless_app;
+use crate::{lock_app, logging, App};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shakmaty::fen::Fen;
+use shakmaty::{Color, EnPassantMode};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::{info, warn};
+
+/// Where to listen when `--serve` isn't given an address
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7313";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Anything that goes wrong with the drill itself, like a move that can't be played
+const DRILL_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    method: String,
+    #[serde(default)]
+    params: Value,
+    /// Left out for notifications, which don't get a response
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct StartParams {
+    color: String,
+    /// Every chapter if it's left out
+    chapter: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MoveParams {
+    san: String,
+}
+
+#[derive(Deserialize)]
+struct ColorParams {
+    color: String,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // No params at all is the same as an empty object
+    let params = match params {
+        Value::Null => json!({}),
+        x => x,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+}
+
+fn color(color: &str) -> Result<Color, RpcError> {
+    Color::from_str(color).map_err(|_| RpcError::new(INVALID_PARAMS, "color is white or black"))
+}
+
+impl App {
+    /// The drill as a JSON-RPC result, with what happened since the last one
+    fn rpc_state(&self, messages: Vec<String>) -> Value {
+        let view = self.view(messages);
+        json!({
+            "fen": Fen::from_position(view.position, EnPassantMode::Legal).to_string(),
+            "color": view.orientation.to_string(),
+            "chapter": view.chapter,
+            "moves": view.moves,
+            "drilling": self.is_drilling(),
+            "messages": view.messages,
+        })
+    }
+
+    fn rpc_call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let drill_error = |e: anyhow::Error| RpcError::new(DRILL_ERROR, e);
+        match method {
+            "start_drill" => {
+                let StartParams {
+                    color: name,
+                    chapter,
+                } = self::params(params)?;
+                let color = color(&name)?;
+                if chapter != *self.chapters.get(color) {
+                    self.send(Event::SelectChapter {
+                        color: name,
+                        chapter,
+                    })
+                    .map_err(drill_error)?;
+                }
+                let started = self.new_drill(color).map_err(drill_error)?;
+                Ok(self.rpc_state(vec![started]))
+            }
+            "apply_move" => {
+                let MoveParams { san } = self::params(params)?;
+                if !self.is_drilling() {
+                    return Err(RpcError::new(DRILL_ERROR, "There's no drill going"));
+                }
+                let mut messages = self.terminal_move(&san).into_iter().collect::<Vec<_>>();
+                self.check_line_over();
+                if !self.is_drilling() {
+                    messages.push("Drill over".to_string());
+                }
+                Ok(self.rpc_state(messages))
+            }
+            "get_state" => Ok(self.rpc_state(vec![])),
+            "get_hint" if !self.config.drill.hints => {
+                Err(RpcError::new(DRILL_ERROR, "Hints are turned off"))
+            }
+            "get_hint" => Ok(json!({ "moves": self.terminal_hint() })),
+            "undo" => {
+                self.send(Event::Undo).map_err(drill_error)?;
+                Ok(self.rpc_state(vec![]))
+            }
+            "list_chapters" => {
+                let ColorParams { color: name } = self::params(params)?;
+                let color = color(&name)?;
+                Ok(json!(list_chapters(&self.config.repertoire(color))))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("There's no method called {}", method),
+            )),
+        }
+    }
+
+    /// Answer a line with a request on it, `None` for notifications
+    pub(crate) fn rpc_line(&mut self, line: &str) -> Option<String> {
+        let response = |id: Value, result: Result<Value, RpcError>| {
+            let response = match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(e) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": e.code, "message": e.message },
+                }),
+            };
+            Some(response.to_string())
+        };
+        let value = match serde_json::from_str::<Value>(line) {
+            Ok(value) => value,
+            Err(e) => return response(Value::Null, Err(RpcError::new(PARSE_ERROR, e))),
+        };
+        let request = match serde_json::from_value::<Request>(value) {
+            Ok(request) => request,
+            Err(e) => return response(Value::Null, Err(RpcError::new(INVALID_REQUEST, e))),
+        };
+        let result = self.rpc_call(&request.method, request.params);
+        response(request.id?, result)
+    }
+}
+
+fn connection(app: &Mutex<App>, stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = lock_app(app).rpc_line(&line);
+        if let Some(response) = response {
+            writeln!(writer, "{}", response)?;
+        }
+    }
+    Ok(())
+}
+
+/// Listen on `address` until the process is stopped
+pub fn run(cli: Cli, address: &str) -> anyhow::Result<()> {
+    let (mut config, problems) = Config::load_checked(cli.profile.as_deref())?;
+    logging::init(&config, true);
+    cli.apply(&mut config);
+    for problem in &problems {
+        warn!("{}", problem);
+    }
+    let app = Arc::new(Mutex::new(headless_app(config)));
+    let listener = TcpListener::bind(address)?;
+    info!("Serving drills on {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let app = app.clone();
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            if let Err(e) = connection(&app, stream) {
+                info!("Connection from {:?} closed: {}", peer, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_app;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn drive_a_drill() {
+        let dir = std::env::temp_dir().join("chess-driller-serve-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = Config {
+            data_path: Some(dir.clone()),
+            ..Default::default()
+        };
+        config.drill.spar = false;
+        let mut app = create_app(&config);
+        app.db = OpeningDatabase::default();
+        app.db
+            .add_prep(Color::White, "1. e4 e5 2. Nf3 *".as_bytes())
+            .unwrap();
+        let mut call =
+            |line: &str| -> Value { serde_json::from_str(&app.rpc_line(line).unwrap()).unwrap() };
+
+        let state = call(r#"{"jsonrpc":"2.0","id":1,"method":"get_state"}"#);
+        assert_eq!(state["id"], 1);
+        assert_eq!(state["result"]["drilling"], false);
+        let state =
+            call(r#"{"jsonrpc":"2.0","id":2,"method":"start_drill","params":{"color":"white"}}"#);
+        assert_eq!(state["result"]["drilling"], true);
+        let hint = call(r#"{"jsonrpc":"2.0","id":3,"method":"get_hint"}"#);
+        assert_eq!(hint["result"]["moves"], json!(["e4"]));
+        let state = call(r#"{"jsonrpc":"2.0","id":4,"method":"apply_move","params":{"san":"e4"}}"#);
+        assert_eq!(state["result"]["moves"], json!(["e4", "e5"]));
+        let state =
+            call(r#"{"jsonrpc":"2.0","id":5,"method":"apply_move","params":{"san":"Nf3"}}"#);
+        assert_eq!(state["result"]["drilling"], false);
+
+        let error = call(r#"{"jsonrpc":"2.0","id":6,"method":"apply_move"}"#);
+        assert_eq!(error["error"]["code"], INVALID_PARAMS);
+        let error = call(r#"{"jsonrpc":"2.0","id":7,"method":"resign"}"#);
+        assert_eq!(error["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(call("{")["error"]["code"], PARSE_ERROR);
+        assert!(app
+            .rpc_line(r#"{"jsonrpc":"2.0","method":"get_state"}"#)
+            .is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
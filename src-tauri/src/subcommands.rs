@@ -0,0 +1,233 @@
+//! Subcommands that do one thing and exit without drilling, so looking after a repertoire can be
+//! scripted or run in CI.
+use crate::cli::{
+    BookletArgs, Cli, CliColor, Command, ComplianceArgs, ExportArgs, ImportArgs, ScriptArgs,
+    StatsArgs,
+};
+use crate::script::run_script;
+use crate::terminal::headless_app;
+use crate::{
+    archive_file, attempts_file, booklet_renderer, check_games, compliance_csv, import_chapter,
+    load_archive, load_attempts, load_booklet, load_chapter, logging, numbered_line, numbered_move,
+    read_games, repertoire_pgn, summarize, validate_repertoire, ComplianceReport, Config, Totals,
+};
+use shakmaty::Color;
+use std::fs;
+use std::io::BufReader;
+
+/// Run whichever subcommand was given
+pub fn run(cli: &Cli) -> anyhow::Result<()> {
+    let (mut config, problems) = Config::load_checked(cli.profile.as_deref())?;
+    logging::init(&config, false);
+    cli.apply(&mut config);
+    match cli.command.as_ref() {
+        Some(Command::ImportPgn(args)) => import(&config, color(cli)?, args),
+        Some(Command::ValidateRepertoire) => validate(&config, cli.color, problems),
+        Some(Command::ExportPgn(args)) => export(&config, color(cli)?, args),
+        Some(Command::Booklet(args)) => booklet(&config, color(cli)?, args),
+        Some(Command::Stats(args)) => stats(&config, args),
+        Some(Command::Compliance(args)) => compliance(&config, cli.color, args),
+        Some(Command::Remind(args)) => crate::remind::run(cli, args),
+        Some(Command::RunScript(args)) => scripts(&config, args),
+        Some(Command::Drill(_)) | None => Ok(()),
+    }
+}
+
+fn color(cli: &Cli) -> anyhow::Result<Color> {
+    cli.color
+        .map(Color::from)
+        .ok_or_else(|| anyhow::anyhow!("Give the repertoire's colour with --color"))
+}
+
+fn import(config: &Config, color: Color, args: &ImportArgs) -> anyhow::Result<()> {
+    if args.chapter.is_some() && args.files.len() > 1 {
+        anyhow::bail!("--chapter can only be given when importing one file");
+    }
+    let repertoire = config.repertoire(color);
+    for file in &args.files {
+        let name = match &args.chapter {
+            Some(name) => name.clone(),
+            None => file
+                .file_stem()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        let pgn = fs::read_to_string(file)?;
+        let path = import_chapter(&repertoire, &name, &pgn)
+            .map_err(|e| e.context(format!("Can't import {}", file.display())))?;
+        println!(
+            "Added {} to the {} repertoire as {}",
+            file.display(),
+            color,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn validate(
+    config: &Config,
+    color: Option<CliColor>,
+    mut problems: Vec<String>,
+) -> anyhow::Result<()> {
+    let colors = match color {
+        Some(color) => vec![color.into()],
+        None => vec![Color::White, Color::Black],
+    };
+    for color in colors {
+        problems.extend(validate_repertoire(&config.repertoire(color)));
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    match problems.len() {
+        0 => {
+            println!("Nothing wrong with the repertoire");
+            Ok(())
+        }
+        1 => anyhow::bail!("Found a problem"),
+        n => anyhow::bail!("Found {} problems", n),
+    }
+}
+
+fn export(config: &Config, color: Color, args: &ExportArgs) -> anyhow::Result<()> {
+    let pgn = repertoire_pgn(&config.repertoire(color), args.chapter.as_deref())?;
+    match &args.output {
+        Some(path) => fs::write(path, pgn)?,
+        None => print!("{}", pgn),
+    }
+    Ok(())
+}
+
+fn booklet(config: &Config, color: Color, args: &BookletArgs) -> anyhow::Result<()> {
+    let today = chrono::Local::now().date_naive();
+    let renderer = booklet_renderer(config, args.plugin.as_deref())?;
+    let booklet = load_booklet(config, color, args.export.chapter.as_deref(), today)?;
+    let text = renderer.render(&booklet)?;
+    match &args.export.output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn stats(config: &Config, args: &StatsArgs) -> anyhow::Result<()> {
+    let attempts = load_attempts(&attempts_file(config))?;
+    let archive = load_archive(&archive_file(config))?;
+    let summary = summarize(&attempts, &archive.days);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    println!("{}", totals(&summary.totals));
+    println!(
+        "Streak of {} days, the longest is {}",
+        summary.streak.current, summary.streak.longest
+    );
+    for chapter in &summary.chapters {
+        let name = match &chapter.chapter {
+            Some(name) => name.clone(),
+            None => format!("The whole {} repertoire", chapter.color),
+        };
+        println!("{}: {}", name, totals(&chapter.totals));
+    }
+    Ok(())
+}
+
+fn compliance(
+    config: &Config,
+    color: Option<CliColor>,
+    args: &ComplianceArgs,
+) -> anyhow::Result<()> {
+    let mut games = vec![];
+    for file in &args.files {
+        games.extend(read_games(fs::File::open(file)?, &args.player)?);
+    }
+    if let Some(color) = color {
+        games.retain(|(_, x)| *x == color.into());
+    }
+    if games.is_empty() {
+        anyhow::bail!("{} didn't play any of the games", args.player);
+    }
+    let white = load_chapter(&config.repertoire(Color::White), None)?;
+    let black = load_chapter(&config.repertoire(Color::Black), None)?;
+    let report = check_games(&games, &white, &black);
+    let text = if args.json {
+        serde_json::to_string_pretty(&report)? + "\n"
+    } else if args.csv {
+        compliance_csv(&report)
+    } else {
+        compliance_text(&report)
+    };
+    match &args.output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn compliance_text(report: &ComplianceReport) -> String {
+    let mut text = String::new();
+    for game in &report.games {
+        text += &format!(
+            "{} - {} {} {}: ",
+            game.white, game.black, game.date, game.result
+        );
+        let Some(deviation) = &game.deviation else {
+            text += &format!("followed the repertoire for all {} moves\n", game.followed);
+            continue;
+        };
+        let who = match deviation.by_player {
+            true => "left the repertoire",
+            false => "the opponent left the repertoire",
+        };
+        text += &format!(
+            "{} with {}, it has {}",
+            who,
+            numbered_move(deviation.ply, &deviation.played),
+            deviation.expected.join(" or ")
+        );
+        if !deviation.suggested.is_empty() {
+            text += &format!(". The line is {}", numbered_line(&deviation.suggested));
+        }
+        text.push('\n');
+    }
+    text += &format!(
+        "{} games: {} followed the repertoire, {} left it and {} opponents did\n",
+        report.games.len(),
+        report.followed,
+        report.player_deviations,
+        report.opponent_deviations
+    );
+    text
+}
+
+/// Each script gets an app of its own with the repertoire loaded, and a data folder that's thrown
+/// away after so its drills don't count in the stats
+fn scripts(config: &Config, args: &ScriptArgs) -> anyhow::Result<()> {
+    for file in &args.files {
+        let dir = std::env::temp_dir().join(format!("chess-driller-script-{}", std::process::id()));
+        let mut config = config.clone();
+        config.data_path = Some(dir.clone());
+        config.usage_log = false;
+        let mut app = headless_app(config);
+        let checked = fs::File::open(file)
+            .map_err(anyhow::Error::from)
+            .and_then(|x| run_script(&mut app, BufReader::new(x)));
+        let _ = fs::remove_dir_all(&dir);
+        let checked = checked.map_err(|e| e.context(format!("{} failed", file.display())))?;
+        println!("{}: all {} checks passed", file.display(), checked);
+    }
+    Ok(())
+}
+
+fn totals(totals: &Totals) -> String {
+    format!(
+        "{} drills, {} completed, {} mistakes in {} moves, {:.0} minutes",
+        totals.attempts,
+        totals.completed,
+        totals.mistakes,
+        totals.moves,
+        totals.seconds / 60.0
+    )
+}
@@ -0,0 +1,363 @@
+//! Drilling in a terminal without opening a window, for SSH sessions and machines without a
+//! desktop. Moves are typed in as SAN. It's the same drill as in the window so attempts count
+//! towards the stats and the schedule the same way.
+//!
+//! The drill's run here and what's shown of it goes through a [`Frontend`]: [`Plain`] prints the
+//! board after every move like a log, [`Screen`] redraws the whole terminal with a coloured board
+//! and the moves beside it.
+mod plain;
+mod screen;
+
+pub use plain::Plain;
+pub use screen::Screen;
+
+use crate::cli::{Cli, CliColor, TerminalArgs};
+use crate::config::Config;
+use crate::db::list_chapters;
+use crate::events::Event;
+use crate::{create_app, english_san, logging, App};
+use shakmaty::{Chess, Color, File, Position, Rank, Square};
+use std::io;
+use tracing::{error, info};
+
+const HELP: &str = "Type moves as SAN (e.g. Nf3), or:
+  hint     the prep's moves here
+  undo     take back your last move
+  new      start another drill
+  flip     drill the other colour
+  menu     pick the colour and chapter
+  quit     stop";
+
+/// Everything there is to show of the drill after a command
+#[derive(Clone, Debug)]
+pub struct View {
+    pub position: Chess,
+    pub orientation: Color,
+    pub moves: Vec<String>,
+    /// `None` for all of them
+    pub chapter: Option<String>,
+    /// What happened since the last time it was shown
+    pub messages: Vec<String>,
+}
+
+/// A way of showing the drill in the terminal and reading what's typed
+pub trait Frontend {
+    /// Show the drill as it is now
+    fn show(&mut self, view: &View) -> io::Result<()>;
+    /// Have one of `options` picked, `None` if the input ends first
+    fn choose(&mut self, title: &str, options: &[String]) -> io::Result<Option<usize>>;
+    /// The next line typed, `None` once the input's ended
+    fn read(&mut self) -> io::Result<Option<String>>;
+}
+
+/// The position from `orientation`'s side with the files and ranks marked
+pub fn draw_board(position: &Chess, orientation: Color, ascii: bool) -> String {
+    let mut text = String::new();
+    for rank in ranks(orientation) {
+        text += &format!("{} ", rank.char());
+        for file in files(orientation) {
+            let square = Square::from_coords(file, rank);
+            let c = match position.board().piece_at(square) {
+                Some(piece) if ascii => piece.char(),
+                Some(piece) => unicode_piece(piece),
+                None => '.',
+            };
+            text += &format!(" {}", c);
+        }
+        text.push('\n');
+    }
+    text += "  ";
+    for file in files(orientation) {
+        text += &format!(" {}", file.char());
+    }
+    text + "\n"
+}
+
+/// Top to bottom as `orientation` sees them
+fn ranks(orientation: Color) -> Vec<Rank> {
+    let mut ranks = Rank::ALL.to_vec();
+    if orientation == Color::White {
+        ranks.reverse();
+    }
+    ranks
+}
+
+/// Left to right as `orientation` sees them
+fn files(orientation: Color) -> Vec<File> {
+    let mut files = File::ALL.to_vec();
+    if orientation == Color::Black {
+        files.reverse();
+    }
+    files
+}
+
+fn unicode_piece(piece: shakmaty::Piece) -> char {
+    use shakmaty::Role;
+    match (piece.color, piece.role) {
+        (Color::White, Role::King) => '♔',
+        (Color::White, Role::Queen) => '♕',
+        (Color::White, Role::Rook) => '♖',
+        (Color::White, Role::Bishop) => '♗',
+        (Color::White, Role::Knight) => '♘',
+        (Color::White, Role::Pawn) => '♙',
+        (Color::Black, Role::King) => '♚',
+        (Color::Black, Role::Queen) => '♛',
+        (Color::Black, Role::Rook) => '♜',
+        (Color::Black, Role::Bishop) => '♝',
+        (Color::Black, Role::Knight) => '♞',
+        (Color::Black, Role::Pawn) => '♟',
+    }
+}
+
+/// The moves with a number before white's, one entry per move number
+fn numbered_moves(moves: &[String]) -> Vec<String> {
+    moves
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| format!("{}. {}", i + 1, pair.join(" ")))
+        .collect()
+}
+
+impl App {
+    pub(crate) fn send(&mut self, event: Event) -> anyhow::Result<()> {
+        self.handle(event).map_err(anyhow::Error::msg)?;
+        Ok(())
+    }
+
+    pub(crate) fn is_drilling(&self) -> bool {
+        self.game_state.as_ref().is_some_and(|x| x.still_running())
+    }
+
+    /// The drill's only over once a move's made past the end of the prep, without the engine to
+    /// play on against it's over as soon as there's nothing left to play
+    pub(crate) fn check_line_over(&mut self) {
+        let ended = self
+            .game_state
+            .as_ref()
+            .is_some_and(|x| x.still_running() && x.hint(self.db.graph(self.color)).is_empty());
+        if ended {
+            self.finish_attempt(false);
+            self.game_state = None;
+        }
+    }
+
+    /// The prep's moves for the player here, counted as a hint if there are any
+    pub(crate) fn terminal_hint(&mut self) -> Vec<String> {
+        let Some(game_state) = self.game_state.as_ref() else {
+            return vec![];
+        };
+        let moves = game_state
+            .hint(self.db.graph(self.color))
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        if let Some(attempt) = self.attempt.as_mut().filter(|_| !moves.is_empty()) {
+            attempt.hint();
+        }
+        moves
+    }
+
+    pub(crate) fn view(&self, messages: Vec<String>) -> View {
+        View {
+            position: self.game.clone(),
+            orientation: self.color,
+            moves: self.history.moves().iter().map(|x| x.to_string()).collect(),
+            chapter: self.chapters.get(self.color).clone(),
+            messages,
+        }
+    }
+
+    /// Play a typed move, saying how it went if it wasn't the prep's
+    pub(crate) fn terminal_move(&mut self, san: &str) -> Option<String> {
+        let expected = self
+            .game_state
+            .as_ref()
+            .map(|x| x.hint(self.db.graph(self.color)))
+            .unwrap_or_default();
+        let played = self.history.moves().len();
+        if let Err(e) = self.handle(Event::PlaySan {
+            san: english_san(san, self.config.piece_locale()),
+        }) {
+            return Some(format!("Can't play {}: {}", san, e));
+        }
+        let expected = expected.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        match self.history.moves().get(played) {
+            None => Some("Not the prep's move, try again".to_string()),
+            Some(mv) if !expected.is_empty() && !expected.contains(&mv.to_string()) => {
+                Some(format!("Out of the prep, it has {}", expected.join(" or ")))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn new_drill(&mut self, color: Color) -> anyhow::Result<String> {
+        self.send(Event::Reset {
+            color: color.to_string(),
+        })?;
+        self.send(Event::Start)?;
+        let chapter = self
+            .chapters
+            .get(color)
+            .as_deref()
+            .unwrap_or("every chapter");
+        Ok(format!("Drilling {} from {}", color, chapter))
+    }
+
+    /// Pick the colour and chapter to drill, returning the colour or `None` if the input ended
+    fn terminal_menu(&mut self, frontend: &mut impl Frontend) -> anyhow::Result<Option<Color>> {
+        let colors = [Color::White, Color::Black];
+        let names = colors.map(|x| x.to_string()).to_vec();
+        let Some(color) = frontend.choose("Drill as", &names)? else {
+            return Ok(None);
+        };
+        let color = colors[color];
+        let chapters = list_chapters(&self.config.repertoire(color));
+        if !chapters.is_empty() {
+            let mut options = vec!["Every chapter".to_string()];
+            options.extend(chapters.iter().cloned());
+            let Some(chapter) = frontend.choose("Chapter", &options)? else {
+                return Ok(None);
+            };
+            let chapter = chapter.checked_sub(1).map(|x| chapters[x].clone());
+            if chapter != *self.chapters.get(color) {
+                self.send(Event::SelectChapter {
+                    color: color.to_string(),
+                    chapter,
+                })?;
+            }
+        }
+        Ok(Some(color))
+    }
+
+    /// Run drills with `frontend` until its input ends or the player quits
+    pub fn run_terminal(
+        &mut self,
+        color: Color,
+        frontend: &mut impl Frontend,
+    ) -> anyhow::Result<()> {
+        let mut color = color;
+        let started = self.new_drill(color)?;
+        frontend.show(&self.view(vec![started, "Type help for the commands".to_string()]))?;
+        while let Some(line) = frontend.read()? {
+            let was_drilling = self.is_drilling();
+            let mut messages = vec![];
+            match line.trim() {
+                "" => continue,
+                "quit" | "q" => break,
+                "help" | "?" => messages.push(HELP.to_string()),
+                "hint" if !self.config.drill.hints => messages.push("Hints are turned off".into()),
+                "hint" => match self.terminal_hint() {
+                    moves if moves.is_empty() => messages.push("Nothing to hint at".into()),
+                    moves => messages.push(format!("The prep plays {}", moves.join(" or "))),
+                },
+                "undo" => self.send(Event::Undo)?,
+                "new" => messages.push(self.new_drill(color)?),
+                "flip" => {
+                    color = !color;
+                    messages.push(self.new_drill(color)?);
+                }
+                "menu" => match self.terminal_menu(frontend)? {
+                    Some(picked) => {
+                        color = picked;
+                        messages.push(self.new_drill(color)?);
+                    }
+                    None => break,
+                },
+                san => {
+                    messages.extend(self.terminal_move(san));
+                    self.check_line_over();
+                }
+            }
+            if was_drilling && !self.is_drilling() {
+                messages.push("Drill over, type new for another".to_string());
+            }
+            frontend.show(&self.view(messages))?;
+        }
+        self.finish_attempt(true);
+        Ok(())
+    }
+}
+
+/// The app with both repertoires loaded, for drilling without the window
+pub(crate) fn headless_app(mut config: Config) -> App {
+    // There's nothing to show the engine's games or explanations on
+    config.drill.spar = false;
+    config.drill.explain = false;
+    let mut app = create_app(&config);
+    for color in [Color::White, Color::Black] {
+        if let Err(e) = app.db.load_chapter(color, &config.repertoire(color), None) {
+            error!("Failed to load {} repertoire: {}", color, e);
+        }
+    }
+    app
+}
+
+/// Drill in the terminal with the repertoire and settings the window would use
+pub fn run(cli: Cli, args: &TerminalArgs) -> anyhow::Result<()> {
+    let (mut config, problems) = Config::load_checked(cli.profile.as_deref())?;
+    logging::init(&config, false);
+    cli.apply(&mut config);
+    for problem in &problems {
+        eprintln!("{}", problem);
+    }
+    let mut app = headless_app(config);
+    info!("Drilling in the terminal");
+    let color = cli.color.unwrap_or(CliColor::White).into();
+    let (input, output) = (io::stdin().lock(), io::stdout());
+    if args.tui {
+        app.run_terminal(color, &mut Screen::new(input, output, args.ascii))
+    } else {
+        app.run_terminal(color, &mut Plain::new(input, output, args.ascii))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpeningDatabase;
+
+    #[test]
+    fn draw_and_drill() {
+        let board = draw_board(&Chess::new(), Color::Black, true);
+        let lines = board.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "1  R N B K Q B N R");
+        assert_eq!(lines[7], "8  r n b k q b n r");
+        assert_eq!(lines[8], "   h g f e d c b a");
+
+        let dir = std::env::temp_dir().join("chess-driller-terminal-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut config = Config {
+            data_path: Some(dir.clone()),
+            ..Default::default()
+        };
+        config.drill.attempts = 2;
+        config.drill.spar = false;
+        let mut app = create_app(&config);
+        app.db = OpeningDatabase::default();
+        app.db
+            .add_prep(Color::White, "1. e4 e5 2. Nf3 *".as_bytes())
+            .unwrap();
+        let input = "d4\ne4\nhint\nNf3\nquit\n";
+        let mut out = vec![];
+        let mut plain = Plain::new(input.as_bytes(), &mut out, true);
+        app.run_terminal(Color::White, &mut plain).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Not the prep's move, try again"));
+        assert!(out.contains("1. e4 e5\n"));
+        assert!(out.contains("The prep plays Nf3"));
+        assert!(out.contains("1. e4 e5 2. Nf3\n"));
+        assert!(out.contains("Drill over"));
+        let attempts = crate::load_attempts(&crate::attempts_file(&config)).unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].result, crate::DrillResult::Completed);
+
+        let mut out = vec![];
+        let mut screen = Screen::new("e4\nflip\nq\n".as_bytes(), &mut out, false);
+        app.run_terminal(Color::White, &mut screen).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(screen::CLEAR));
+        assert!(out.contains("1. e4 e5"));
+        assert!(out.contains("Drilling black from every chapter"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,55 @@
+//! Prints the board after every move, scrolling up like a log so it works anywhere, piped into
+//! a file or over the slowest SSH connection.
+use super::{draw_board, numbered_moves, Frontend, View};
+use std::io::{self, BufRead, Write};
+
+pub struct Plain<R, W> {
+    input: io::Lines<R>,
+    output: W,
+    ascii: bool,
+}
+
+impl<R: BufRead, W: Write> Plain<R, W> {
+    pub fn new(input: R, output: W, ascii: bool) -> Self {
+        Self {
+            input: input.lines(),
+            output,
+            ascii,
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Plain<R, W> {
+    fn show(&mut self, view: &View) -> io::Result<()> {
+        for message in &view.messages {
+            writeln!(self.output, "{}", message)?;
+        }
+        writeln!(self.output)?;
+        let board = draw_board(&view.position, view.orientation, self.ascii);
+        write!(self.output, "{}", board)?;
+        if !view.moves.is_empty() {
+            writeln!(self.output, "{}", numbered_moves(&view.moves).join(" "))?;
+        }
+        Ok(())
+    }
+
+    fn choose(&mut self, title: &str, options: &[String]) -> io::Result<Option<usize>> {
+        writeln!(self.output, "{}:", title)?;
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}. {}", i + 1, option)?;
+        }
+        while let Some(line) = self.read()? {
+            match line.trim().parse::<usize>() {
+                Ok(i) if (1..=options.len()).contains(&i) => return Ok(Some(i - 1)),
+                _ => writeln!(self.output, "Pick 1 to {}", options.len())?,
+            }
+        }
+        Ok(None)
+    }
+
+    fn read(&mut self) -> io::Result<Option<String>> {
+        write!(self.output, "> ")?;
+        self.output.flush()?;
+        self.input.next().transpose()
+    }
+}
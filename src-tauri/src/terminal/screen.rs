@@ -0,0 +1,114 @@
+//! Takes over the whole terminal with ANSI escapes, redrawing it after every command: a board
+//! with coloured squares, the moves beside it and what's just happened underneath.
+use super::{files, numbered_moves, ranks, unicode_piece, Frontend, View};
+use shakmaty::{Color, Position, Square};
+use std::io::{self, BufRead, Write};
+
+/// Clear the screen and go to the top
+pub const CLEAR: &str = "\x1b[2J\x1b[H";
+const RESET: &str = "\x1b[0m";
+const LIGHT: &str = "\x1b[48;5;180m";
+const DARK: &str = "\x1b[48;5;137m";
+const WHITE_PIECE: &str = "\x1b[1;38;5;231m";
+const BLACK_PIECE: &str = "\x1b[1;38;5;16m";
+/// Move numbers shown beside the board, the latest ones
+const MOVE_ROWS: usize = 9;
+
+pub struct Screen<R, W> {
+    input: io::Lines<R>,
+    output: W,
+    ascii: bool,
+}
+
+impl<R: BufRead, W: Write> Screen<R, W> {
+    pub fn new(input: R, output: W, ascii: bool) -> Self {
+        Self {
+            input: input.lines(),
+            output,
+            ascii,
+        }
+    }
+
+    /// A row for each rank and one for the files, without a newline at the end of each
+    fn board_rows(&self, view: &View) -> Vec<String> {
+        let mut rows = vec![];
+        for rank in ranks(view.orientation) {
+            let mut row = format!("{} ", rank.char());
+            for file in files(view.orientation) {
+                let square = Square::from_coords(file, rank);
+                row += if square.is_light() { LIGHT } else { DARK };
+                match view.position.board().piece_at(square) {
+                    Some(piece) => {
+                        let color = piece.color.fold_wb(WHITE_PIECE, BLACK_PIECE);
+                        // The filled symbols read better on both colours of square
+                        let glyph = if self.ascii {
+                            piece.char()
+                        } else {
+                            unicode_piece(piece.role.of(Color::Black))
+                        };
+                        row += &format!("{} {} ", color, glyph);
+                    }
+                    None => row += "   ",
+                }
+                row += RESET;
+            }
+            rows.push(row);
+        }
+        let mut labels = "  ".to_string();
+        for file in files(view.orientation) {
+            labels += &format!(" {} ", file.char());
+        }
+        rows.push(labels);
+        rows
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Screen<R, W> {
+    fn show(&mut self, view: &View) -> io::Result<()> {
+        let chapter = view.chapter.as_deref().unwrap_or("every chapter");
+        write!(self.output, "{}", CLEAR)?;
+        writeln!(
+            self.output,
+            "Drilling {} from {}\n",
+            view.orientation, chapter
+        )?;
+        let moves = numbered_moves(&view.moves);
+        let moves = &moves[moves.len().saturating_sub(MOVE_ROWS)..];
+        for (i, row) in self.board_rows(view).into_iter().enumerate() {
+            let moves = moves.get(i).map(|x| x.as_str()).unwrap_or_default();
+            writeln!(self.output, "{}    {}", row, moves)?;
+        }
+        writeln!(self.output)?;
+        for message in &view.messages {
+            writeln!(self.output, "{}", message)?;
+        }
+        writeln!(self.output, "\nhint  undo  new  flip  menu  help  quit")?;
+        Ok(())
+    }
+
+    fn choose(&mut self, title: &str, options: &[String]) -> io::Result<Option<usize>> {
+        let mut problem = None;
+        loop {
+            write!(self.output, "{}{}\n\n", CLEAR, title)?;
+            for (i, option) in options.iter().enumerate() {
+                writeln!(self.output, "  {}. {}", i + 1, option)?;
+            }
+            if let Some(problem) = problem.take() {
+                writeln!(self.output, "\n{}", problem)?;
+            }
+            let Some(line) = self.read()? else {
+                return Ok(None);
+            };
+            match line.trim().parse::<usize>() {
+                Ok(i) if (1..=options.len()).contains(&i) => return Ok(Some(i - 1)),
+                _ => problem = Some(format!("Pick 1 to {}", options.len())),
+            }
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Option<String>> {
+        write!(self.output, "\n> ")?;
+        self.output.flush()?;
+        self.input.next().transpose()
+    }
+}
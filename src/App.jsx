@@ -1,44 +1,686 @@
-import { useState, useEffect } from 'react'
+import { useState, useEffect, useRef } from 'react'
 import { invoke } from '@tauri-apps/api'
+import { listen } from '@tauri-apps/api/event'
 
 import { Chessboard } from "react-chessboard";
+import Clock from "./Clock.jsx"
+import Menu from "./Menu.jsx"
+import Splash from "./Splash.jsx"
+import Settings from "./Settings.jsx"
+import MoveList from "./MoveList.jsx"
+import { boardStyle, highlightColors } from "./themes.jsx"
+import { matchesKey } from "./keys.js"
+import { squareAt } from "./board.js"
+import { playSound, speak } from "./sound.js"
+import { setLocale, t } from "./i18n.js"
+import MoveInput from "./MoveInput.jsx"
+import { BUTTONS, useGamepad, stepSquare } from "./gamepad.js"
+import { useWindowState } from "./window.js"
+import ImportOverlay from "./ImportOverlay.jsx"
+import ProblemOverlay from "./ProblemOverlay.jsx"
+import QuitOverlay from "./QuitOverlay.jsx"
+import RecoverOverlay from "./RecoverOverlay.jsx"
+import Audit from "./Audit.jsx"
+import Novelties from "./Novelties.jsx"
+import Stats from "./Stats.jsx"
+import History from "./History.jsx"
+import Gaps from "./Gaps.jsx"
+import Scan from "./Scan.jsx"
+import Evaluation from "./Evaluation.jsx"
+import { formatScore } from "./score.js"
+
+const START_POSITION = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"
+// Amount of scrolling on the board that steps one move through the history
+const WHEEL_STEP = 50
+// How long to hold the pointer still on a piece to show its moves
+const LONG_PRESS_MS = 500
+// How far the pointer can move before a press becomes a drag, fingers wobble more than mice
+const LONG_PRESS_SLOP = 5
+const TOUCH_SLOP = 12
+// Room to leave beside the board for the clock and move list
+const PANEL_WIDTH = 240
+// What's said when a rule draws the game rather than stalemate
+const DRAWS = {
+  "repetition": "Draw by threefold repetition",
+  "fifty_moves": "Draw by the fifty-move rule",
+  "insufficient_material": "Draw, neither side has enough to mate",
+}
 
 function App() {
-  const [game, setGame] = useState("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR")
+  const [screen, setScreen] = useState("loading")
+  const [game, setGame] = useState(START_POSITION)
   let [orientation, setOrientation] = useState("white")
+  // Colour of the repertoire being drilled, the board can be flipped without changing it
+  const [color, setColor] = useState("white")
   let [promotion] = useState("Q")
+  const [moveLimit, setMoveLimit] = useState(null)
+  const [sessionStart, setSessionStart] = useState(null)
+  const [moveStart, setMoveStart] = useState(null)
+  const [moveList, setMoveList] = useState({ "moves": [], "cursor": 0 })
+  const [wheelDelta, setWheelDelta] = useState(0)
+  const [highlights, setHighlights] = useState({})
+  const boardRef = useRef(null)
+  const press = useRef(null)
+  const moveInput = useRef(null)
+  const launched = useRef(false)
+  // Square picked by clicking or tapping along with where it can move to
+  const [selected, setSelected] = useState(null)
+  // Square the gamepad is pointing at, hidden until a gamepad is used
+  const [padCursor, setPadCursor] = useState(null)
+  const windowState = useWindowState()
+  // PGN file dropped onto the window waiting to be imported
+  const [droppedFile, setDroppedFile] = useState(null)
+  // Short message shown under the move list e.g. after copying
+  const [status, setStatus] = useState(null)
+  // Errors that need acknowledging before carrying on, each with what might fix it
+  const [problems, setProblems] = useState([])
+  // The window was closed mid-drill, ask what to do with it
+  const [quitting, setQuitting] = useState(false)
+  // Drill autosaved by a run that didn't close properly, offered once loading's done
+  const [recovery, setRecovery] = useState(null)
+  // Whether the engine is analysing the position shown, the ref is for the event listeners
+  const [analysing, setAnalysing] = useState(false)
+  const analysingRef = useRef(false)
+  const [analysis, setAnalysis] = useState(null)
+  // Colour and chapter being checked with the engine
+  const [auditing, setAuditing] = useState(null)
+  const [gapsColor, setGapsColor] = useState(null)
+  const [appearance, setAppearance] = useState({ "board_theme": "brown", "piece_set": "default" })
+  const [input, setInput] = useState({ "drag_threshold": 4, "drag_pieces": true })
+  // The ref is for the event listener
+  const [sound, setSound] = useState({ "volume": 0.5, "muted": false })
+  const soundRef = useRef(sound)
+  soundRef.current = sound
+  // Locale from the config, null for the system's language
+  const [locale, setLocaleName] = useState(null)
+  const [speech, setSpeech] = useState({ "enabled": false, "rate": 1, "voice": null })
+  const speechRef = useRef(speech)
+  speechRef.current = speech
+  // Where the pointer went down on the board and where a drag of a piece has got to, to tell
+  // clicks from drags
+  const dragFrom = useRef(null)
+  const dragAt = useRef(null)
+  const [keys, setKeys] = useState({
+    "flip": "f", "switch_color": "F", "reset": "r", "start": "s", "menu": "m",
+    "back": "ArrowLeft", "forward": "ArrowRight", "previous_variation": "ArrowUp", "next_variation": "ArrowDown",
+    "undo": "Ctrl+z|Backspace", "enter_move": "Enter", "copy_fen": "Ctrl+c", "copy_pgn": "Ctrl+C",
+    "paste_fen": "Ctrl+v", "hint": "h", "show_log": "Ctrl+l", "spar": "e", "analysis": "a",
+    "chess960": "9",
+  })
+
+  function loadSettings() {
+    invoke("move_time_limit", {})
+      .then((response) => setMoveLimit(response))
+    invoke("appearance", {})
+      .then((response) => setAppearance(response))
+    invoke("keybindings", {})
+      .then((response) => setKeys(response))
+    invoke("input", {})
+      .then((response) => setInput(response))
+    invoke("sound", {})
+      .then((response) => setSound(response))
+    invoke("speech", {})
+      .then((response) => setSpeech(response))
+    invoke("locale", {})
+      .then(function(response){
+        setLocale(response)
+        setLocaleName(response)
+      })
+  }
+
+  useEffect(function(){
+    loadSettings()
+    // Edits to the config file are picked up without restarting
+    const unlisten = listen("config-reloaded", () => loadSettings())
+    return () => { unlisten.then((f) => f()) }
+  },[])
+
+  useEffect(function(){
+    document.onkeydown = handleKeyUp
+  })
+
+  useEffect(function(){
+    const unlisten = listen("close-requested", () => setQuitting(true))
+    return () => { unlisten.then((f) => f()) }
+  },[])
+
+  useEffect(function(){
+    function onDragOver(event) {
+      event.preventDefault()
+      dragAt.current = { "x": event.clientX, "y": event.clientY }
+    }
+    function onDrop(event) {
+      event.preventDefault()
+      const file = event.dataTransfer.files[0]
+      if (file != null && file.name.toLowerCase().endsWith(".pgn")) {
+        file.text().then((text) => setDroppedFile({ "name": file.name, "text": text }))
+      }
+    }
+    document.addEventListener("dragover", onDragOver)
+    document.addEventListener("drop", onDrop)
+    return function(){
+      document.removeEventListener("dragover", onDragOver)
+      document.removeEventListener("drop", onDrop)
+    }
+  },[])
+
+  // Show a board sent back from the backend and update the move list to match
+  function showBoard(board) {
+    setGame(board)
+    if (analysingRef.current) {
+      setAnalysis(null)
+      invoke("analyze", {})
+        .catch((e) => showProblem(e))
+    }
+    invoke("move_list", {})
+      .then(function(response){
+        setMoveList(response)
+        if (response.draw != null) {
+          setStatus(t(DRAWS[response.draw]))
+          setSessionStart(null)
+        } else if (response.result != null) {
+          setStatus(t("Game over {result}", { "result": response.result }))
+          setSessionStart(null)
+        }
+      })
+  }
 
+  // Replies from the engine come in once it's thought about them
   useEffect(function(){
-    document.onkeypress = handleKeyUp
+    const listeners = [
+      listen("engine-moved", function(event){
+        showBoard(event.payload)
+        setMoveStart(Date.now())
+      }),
+      listen("engine-failed", (event) => showProblem(event.payload, t("Check `engine` in the config, the log has more about it"))),
+      listen("endgame-mistake", function(event){
+        showBoard(event.payload.board)
+        setStatus(t("That makes it a {result} instead of a {expected}, try again", { "result": t(event.payload.result), "expected": t(event.payload.expected) }))
+      }),
+      listen("wrong-move", function(event){
+        const x = event.payload
+        const values = { "expected": x.expected, "expected_eval": formatScore(x.expected_eval), "played": x.played, "played_eval": formatScore(x.played_eval), "line": x.refutation.slice(0, 6).join(" ") }
+        setStatus(x.refutation.length == 0
+          ? t("{expected} was the move ({expected_eval}), {played} gives {played_eval}", values)
+          : t("{expected} was the move ({expected_eval}), {played} gives {played_eval}, it runs into {line}", values))
+      }),
+      listen("analysis", (event) => setAnalysis(event.payload)),
+      listen("sound", (event) => playSound(event.payload, soundRef.current)),
+      listen("speak", (event) => speak(event.payload, speechRef.current)),
+      listen("problem", (event) => setProblems((x) => [...x, event.payload])),
+    ]
+    return () => { listeners.forEach((x) => x.then((f) => f())) }
   },[])
 
+  function toggleAnalysis() {
+    if (analysingRef.current) {
+      analysingRef.current = false
+      setAnalysing(false)
+      setAnalysis(null)
+      invoke("stop_analysis", {})
+      return
+    }
+    invoke("analyze", {})
+      .then(function(){
+        analysingRef.current = true
+        setAnalysing(true)
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function spar() {
+    invoke("spar", {})
+      .then(function(response){
+        showBoard(response)
+        setStatus(t("Playing the engine"))
+        setMoveStart(Date.now())
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function onBoardWheel(event) {
+    const delta = wheelDelta + event.deltaY
+    if (Math.abs(delta) < WHEEL_STEP) {
+      setWheelDelta(delta)
+      return
+    }
+    setWheelDelta(0)
+    invoke(delta > 0 ? "history_forward" : "history_back", {})
+      .then((response) => showBoard(response))
+  }
+
+  function onBoardPointerDown(event) {
+    setHighlights({})
+    dragFrom.current = { "x": event.clientX, "y": event.clientY }
+    dragAt.current = null
+    const square = squareAt(event, boardRef.current, orientation)
+    if (square == null) {
+      return
+    }
+    const timer = setTimeout(function(){
+      press.current = null
+      invoke("legal_moves", { "square": square })
+        .then((response) => showMoves(square, response))
+    }, LONG_PRESS_MS)
+    const slop = event.pointerType == "touch" ? TOUCH_SLOP : LONG_PRESS_SLOP
+    press.current = { "timer": timer, "x": event.clientX, "y": event.clientY, "slop": slop }
+  }
+
+  function onBoardPointerMove(event) {
+    const start = press.current
+    if (start != null && Math.hypot(event.clientX - start.x, event.clientY - start.y) > start.slop) {
+      cancelLongPress()
+    }
+  }
+
+  function showMoves(square, targets) {
+    const colors = highlightColors(appearance)
+    const styles = { [square]: { "backgroundColor": colors.selected } }
+    for (const target of targets) {
+      styles[target] = { "background": "radial-gradient(circle, " + colors.legal_move + " 25%, transparent 25%)" }
+    }
+    setHighlights(styles)
+  }
+
+  // Moving by clicking the piece and then where it goes, this is also how moves are made on a
+  // touchscreen as dragging needs a mouse
+  function onSquareClick(square) {
+    if (selected != null && selected.targets.includes(square)) {
+      setSelected(null)
+      setHighlights({})
+      // No promotion dialog when clicking so always queen
+      onPieceDrop(selected.square, square, "wQ")
+      return
+    }
+    invoke("legal_moves", { "square": square })
+      .then(function(response){
+        if (response.length == 0 || selected?.square == square) {
+          setSelected(null)
+          setHighlights({})
+        } else {
+          setSelected({ "square": square, "targets": response })
+          showMoves(square, response)
+        }
+      })
+  }
+
+  function cancelLongPress() {
+    if (press.current != null) {
+      clearTimeout(press.current.timer)
+      press.current = null
+    }
+  }
+
+  // Go to the menu unless the command line said what to drill, that's only done when first
+  // opened and not after switching profile
+  function onLoaded(status) {
+    if (status.problems.length > 0) {
+      setProblems(status.problems)
+    }
+    if (launched.current) {
+      setScreen("menu")
+      return
+    }
+    launched.current = true
+    invoke("recovery", {})
+      .then(setRecovery)
+    invoke("startup", {})
+      .then(function(startup){
+        if (startup == null) {
+          setScreen("menu")
+          return
+        }
+        invoke("reset", { "color": startup.color })
+          .then(() => {
+            setColor(startup.color)
+            setOrientation(startup.color)
+            showBoard(START_POSITION)
+            setScreen("board")
+            if (startup.mode == "drill") {
+              startDrill()
+            } else if (startup.mode == "endgame") {
+              startEndgame(null)
+            }
+          })
+      })
+  }
+
+  // Everything is loaded again for the new profile, including its settings
+  function onProfile(profile) {
+    invoke("select_profile", { "profile": profile })
+      .then(() => {
+        loadSettings()
+        setScreen("loading")
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function startEndgame(chapter) {
+    invoke("start_endgame", { "chapter": chapter })
+      .then((start) => {
+        setColor(start.color)
+        setOrientation(start.color)
+        showBoard(start.board)
+        setSessionStart(null)
+        setScreen("board")
+        setStatus(t(start.result == "win" ? "Win this" : start.result == "draw" ? "Hold the draw" : "Make it as hard as you can"))
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function onMenuStart(selection) {
+    if (selection.mode == "endgame") {
+      startEndgame(selection.chapter)
+      return
+    }
+    invoke("select_chapter", { "color": selection.color, "chapter": selection.chapter })
+      .then(() => invoke("reset", { "color": selection.color }))
+      .then(() => {
+        setColor(selection.color)
+        setOrientation(selection.color)
+        showBoard(START_POSITION)
+        setSessionStart(null)
+        setScreen("board")
+      })
+  }
+
+  function recover() {
+    setRecovery(null)
+    invoke("recover", {})
+      .then((recovered) => {
+        setColor(recovered.color)
+        setOrientation(recovered.color)
+        showBoard(recovered.board)
+        setSessionStart(recovered.drilling ? Date.now() : null)
+        setMoveStart(Date.now())
+        setScreen("board")
+        setStatus(t("Carrying on from the autosave"))
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  // Step through a line from an earlier drill
+  function onReplay(attempt) {
+    invoke("replay_line", { "color": attempt.color, "moves": attempt.line })
+      .then((board) => {
+        setColor(attempt.color)
+        setOrientation(attempt.color)
+        showBoard(board)
+        setSessionStart(null)
+        setScreen("board")
+        setStatus(t("Replaying, step through it with {forward} and {back}", { "forward": keys.forward, "back": keys.back }))
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function onAudit(selection) {
+    setAuditing(selection)
+    setScreen("audit")
+  }
+
+  function onNovelties(selection) {
+    setAuditing(selection)
+    setScreen("novelties")
+  }
+
+  function onGaps(color) {
+    setGapsColor(color)
+    setScreen("gaps")
+  }
+
   function onPieceDrop(sourceSquare, targetSquare, piece){
+    // Barely moved so it was meant as a click, put the piece back and select it instead
+    const from = dragFrom.current
+    const at = dragAt.current
+    if (from != null && at != null && Math.hypot(at.x - from.x, at.y - from.y) < input.drag_threshold) {
+      onSquareClick(sourceSquare)
+      return false
+    }
     invoke('move_piece', { 'from': sourceSquare, 'to': targetSquare, "promotion": piece ?? "Q" })
-      .then((response) => setGame(response))
+      .then((response) => {
+        showBoard(response)
+        setMoveStart(Date.now())
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function startDrill() {
+    invoke("start", {  })
+      .then((response) => {
+        showBoard(response)
+        setSessionStart(Date.now())
+        setMoveStart(Date.now())
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  // Drill the other repertoire from the start, carrying on drilling if we were
+  function switchColor() {
+    const next = color == "white" ? "black" : "white"
+    const drilling = sessionStart != null
+    invoke("reset", { "color": next })
+      .then(() => {
+        setColor(next)
+        setOrientation(next)
+        setHighlights({})
+        setSelected(null)
+        showBoard(START_POSITION)
+        setSessionStart(null)
+        if (drilling) {
+          startDrill()
+        }
+      })
+  }
+
+  function undo() {
+    invoke("undo", {})
+      .then((response) => {
+        showBoard(response)
+        setMoveStart(Date.now())
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  useGamepad(function(button){
+    if (screen != "board" || !windowState.focused) {
+      return
+    }
+    if ([BUTTONS.UP, BUTTONS.DOWN, BUTTONS.LEFT, BUTTONS.RIGHT].includes(button)) {
+      setPadCursor(padCursor == null ? (orientation == "white" ? "e2" : "e7") : stepSquare(padCursor, button, orientation))
+    } else if (button == BUTTONS.A && padCursor != null) {
+      onSquareClick(padCursor)
+    } else if (button == BUTTONS.B) {
+      setSelected(null)
+      setHighlights({})
+    } else if (button == BUTTONS.LB) {
+      undo()
+    } else if (button == BUTTONS.RB) {
+      showHint()
+    } else if (button == BUTTONS.START) {
+      startDrill()
+    }
+  })
+
+  // Light up the pieces the prep wants moved without giving away where they go
+  function showHint() {
+    invoke("hint", {})
+      .then(function(squares){
+        if (squares.length == 0) {
+          setStatus(t("No hint, the prep has nothing here"))
+          return
+        }
+        const styles = {}
+        for (const square of squares) {
+          styles[square] = { "backgroundColor": highlightColors(appearance).hint }
+        }
+        setHighlights(styles)
+      })
+  }
+
+  function showProblem(message, fix = null) {
+    setProblems((x) => [...x, { "message": String(message), "fix": fix }])
+  }
+
+  function showLog() {
+    invoke("show_log", {})
+      .then((path) => setStatus(t("The log is at {path}", { "path": path })))
+      .catch((e) => showProblem(t("Couldn't open the log: {error}", { "error": e })))
+  }
+
+  function copy(command, what) {
+    invoke(command, {})
+      .then((text) => navigator.clipboard.writeText(text))
+      .then(() => setStatus(t("Copied {what} to the clipboard", { "what": what })))
+      .catch((e) => setStatus(t("Couldn't copy {what}: {error}", { "what": what, "error": e })))
+  }
+
+  function pasteFen() {
+    navigator.clipboard.readText()
+      .then((text) => invoke("set_fen", { "fen": text }))
+      .then(function(response){
+        showBoard(response.board)
+        setSessionStart(null)
+        if (response.in_prep) {
+          setStatus(t("Position is in your prep, press {key} to drill from it", { "key": keys.start }))
+        } else {
+          setStatus(t("Position set up, it isn't in your prep so can't be drilled"))
+        }
+      })
+      .catch((e) => showProblem(e))
+  }
+
+  function chess960() {
+    invoke("chess960", {})
+      .then(function(response){
+        showBoard(response.board)
+        setSessionStart(null)
+        setStatus(t("Chess960 position set up, press {key} to play the engine from it", { "key": keys.spar }))
+      })
+      .catch((e) => showProblem(e))
   }
 
   function handleKeyUp(event) {
-      if (event.key == 'f') {
-          if (orientation == "white") {
-              setOrientation("black");
-              invoke("reset", {"color": "black" }) 
-          } else {
-              setOrientation("white");
-              invoke("reset", {"color": "white" }) 
+      // Typing in a move shouldn't trigger any of the shortcuts
+      if (screen != "board" || event.target.tagName == "INPUT") {
+          return
+      }
+      if (matchesKey(keys.flip, event)) {
+          setOrientation(orientation == "white" ? "black" : "white")
+      } else if (matchesKey(keys.switch_color, event)) {
+          switchColor()
+      } else if (matchesKey(keys.start, event)) {
+          startDrill()
+      } else if (matchesKey(keys.reset, event)) {
+          setGame(START_POSITION);
+          invoke("reset", {"color": color }) 
+          setMoveList({ "moves": [], "cursor": 0 })
+          setSessionStart(null)
+      } else if (matchesKey(keys.menu, event)) {
+          if (analysingRef.current) {
+            toggleAnalysis()
           }
-      } else if (event.key == "s") {
-          invoke("start", {  })
-            .then((response) => setGame(response))
-      } else if (event.key == "r") {
-          setGame("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
-          invoke("reset", {"color": orientation }) 
+          setSessionStart(null)
+          setScreen("menu")
+      } else if (matchesKey(keys.enter_move, event)) {
+          event.preventDefault()
+          moveInput.current?.focus()
+      } else if (matchesKey(keys.copy_fen, event)) {
+          copy("fen", "FEN")
+      } else if (matchesKey(keys.copy_pgn, event)) {
+          copy("pgn", "PGN")
+      } else if (matchesKey(keys.paste_fen, event)) {
+          pasteFen()
+      } else if (matchesKey(keys.chess960, event)) {
+          chess960()
+      } else if (matchesKey(keys.hint, event)) {
+          showHint()
+      } else if (matchesKey(keys.analysis, event)) {
+          toggleAnalysis()
+      } else if (matchesKey(keys.spar, event)) {
+          spar()
+      } else if (matchesKey(keys.show_log, event)) {
+          showLog()
+      } else if (matchesKey(keys.undo, event)) {
+          event.preventDefault()
+          undo()
+      } else if (matchesKey(keys.back, event)) {
+          invoke("history_back", {})
+            .then((response) => showBoard(response))
+            .catch((e) => showProblem(e))
+      } else if (matchesKey(keys.forward, event)) {
+          invoke("history_forward", {})
+            .then((response) => showBoard(response))
+            .catch((e) => showProblem(e))
+      } else if (matchesKey(keys.previous_variation, event)) {
+          event.preventDefault()
+          invoke("history_sibling", { "offset": -1 })
+            .then((response) => showBoard(response))
+            .catch((e) => showProblem(e))
+      } else if (matchesKey(keys.next_variation, event)) {
+          event.preventDefault()
+          invoke("history_sibling", { "offset": 1 })
+            .then((response) => showBoard(response))
+            .catch((e) => showProblem(e))
       }
   }
 
+  function renderBoard() {
+    const boardWidth = Math.max(200, Math.min(windowState.height, windowState.width - PANEL_WIDTH))
+
+    return (
+      <div className="flex flex-row">
+        <div className="touch-none" style={{ "width": boardWidth, "height": boardWidth }} ref={boardRef} onWheel={onBoardWheel}
+          onPointerDown={onBoardPointerDown} onPointerMove={onBoardPointerMove} onPointerUp={cancelLongPress} onPointerLeave={cancelLongPress}>
+          <Chessboard id="BasicBoard" boardWidth={boardWidth} position={game} onPieceDrop={onPieceDrop} onSquareClick={onSquareClick} arePiecesDraggable={input.drag_pieces} boardOrientation={orientation} animationDuration="0"
+            customArrows={analysing && analysis?.best != null ? [analysis.best] : []}
+            customSquareStyles={padCursor == null ? highlights : { ...highlights, [padCursor]: { ...highlights[padCursor], "boxShadow": "inset 0 0 0 4px " + highlightColors(appearance).cursor } }}
+            {...boardStyle(appearance)}/>
+        </div>
+        <div className="flex flex-col p-4" style={{ "width": PANEL_WIDTH }}>
+          <Clock sessionStart={sessionStart} moveStart={moveStart} moveLimit={moveLimit} visible={windowState.visible}/>
+          {analysing && <Evaluation analysis={analysis}/>}
+          <MoveList moves={moveList.moves} cursor={moveList.cursor}/>
+          <MoveInput inputRef={moveInput} onPlayed={(board) => {
+            showBoard(board)
+            setMoveStart(Date.now())
+          }}/>
+          {status != null && <span className="text-neutral-400">{status}</span>}
+        </div>
+      </div>
+    )
+  }
+
+  let content = null
+  if (screen == "loading") {
+    content = <Splash onFinished={onLoaded}/>
+  } else if (screen == "menu") {
+    content = <Menu onStart={onMenuStart} onAudit={onAudit} onNovelties={onNovelties} onGaps={onGaps} onScan={() => setScreen("scan")} onStats={() => setScreen("stats")} onHistory={() => setScreen("history")} onSettings={() => setScreen("settings")} onProfile={onProfile}/>
+  } else if (screen == "audit") {
+    content = <Audit selection={auditing} onBack={() => setScreen("menu")}/>
+  } else if (screen == "novelties") {
+    content = <Novelties selection={auditing} onBack={() => setScreen("menu")}/>
+  } else if (screen == "gaps") {
+    content = <Gaps color={gapsColor} onBack={() => setScreen("menu")}/>
+  } else if (screen == "history") {
+    content = <History onReplay={onReplay} onBack={() => setScreen("menu")}/>
+  } else if (screen == "stats") {
+    content = <Stats onBack={() => setScreen("menu")}/>
+  } else if (screen == "scan") {
+    content = <Scan onBack={() => setScreen("menu")}/>
+  } else if (screen == "settings") {
+    content = <Settings appearance={appearance} onChange={setAppearance} input={input} onInputChange={setInput} sound={sound} onSoundChange={setSound} speech={speech} onSpeechChange={setSpeech} locale={locale} onLocaleChange={setLocaleName} onBack={() => setScreen("menu")}/>
+  } else {
+    content = renderBoard()
+  }
+
   return (
-    <div className="w-[100vmin] h-[100vmin]">
-      <Chessboard id="BasicBoard" position={game} onPieceDrop={onPieceDrop} boardOrientation={orientation} animationDuration="0"/>
-    </div>
+    <>
+      {content}
+      {droppedFile != null && <ImportOverlay file={droppedFile} onClose={() => setDroppedFile(null)}/>}
+      {problems.length > 0 && <ProblemOverlay problems={problems} onClose={() => setProblems([])}/>}
+      {quitting && <QuitOverlay onCancel={() => setQuitting(false)}/>}
+      {recovery != null && <RecoverOverlay snapshot={recovery} onRecovered={recover} onClose={() => setRecovery(null)}/>}
+    </>
   )
 }
 
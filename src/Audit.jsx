@@ -0,0 +1,52 @@
+import { invoke } from '@tauri-apps/api'
+
+import JobControls, { useJob } from "./Job.jsx"
+import { formatScore } from "./score.js"
+import { t } from "./i18n.js"
+
+// How long the engine had for each position, whichever limit came first
+export function describeLimits(limits) {
+  return [
+    limits.depth != null && t("at depth {depth}", limits),
+    limits.movetime != null && t("in {movetime}ms", limits),
+    limits.nodes != null && t("in {nodes} nodes", limits),
+  ].filter(Boolean).join(t(" or "))
+}
+
+// Moves in the repertoire the engine thinks are worse than they should be
+function Audit({ selection, onBack }) {
+  const { job, error } = useJob(() => invoke("audit", { "color": selection.color, "chapter": selection.chapter }))
+  const report = job?.result ?? null
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{selection.chapter != null ? t("Audit of {chapter}", selection) : t("Audit of the " + selection.color + " repertoire")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {job?.state == "cancelled" && <span>{t("Cancelled")}</span>}
+      {report == null && error == null && job?.state != "cancelled" &&
+        <span>{job?.state == "queued" ? t("Waiting for the jobs before it") : job == null || job.total == 0 ? t("Starting the engine") : t("Checked {done} of {total} positions", job)}{job?.state == "paused" && t(", paused")}</span>}
+      <JobControls job={job}/>
+      {report != null && report.findings.length == 0 &&
+        <span>{t("Nothing is more than {threshold} centipawns worse than the engine's move {limits}", { threshold: report.threshold, limits: describeLimits(report.limits) })}</span>}
+      {report != null && report.findings.length > 0 &&
+        <table>
+          <thead>
+            <tr><th className="text-left">{t("Line")}</th><th>{t("Played")}</th><th>{t("Best")}</th><th>{t("Loss")}</th></tr>
+          </thead>
+          <tbody>
+            {report.findings.map((finding) =>
+              <tr key={finding.moves.join(" ")}>
+                <td>{finding.moves.join(" ")}</td>
+                <td className="px-2">{formatScore(finding.played_eval)}</td>
+                <td className="px-2">{finding.best} {formatScore(finding.best_eval)}</td>
+                <td className="px-2">{(finding.loss / 100).toFixed(2)}</td>
+              </tr>
+            )}
+          </tbody>
+        </table>}
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default Audit
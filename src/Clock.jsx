@@ -0,0 +1,50 @@
+import { useState, useEffect } from 'react'
+
+import { t } from "./i18n.js"
+
+function formatTime(ms) {
+  const seconds = Math.max(0, Math.ceil(ms / 1000))
+  const minutes = Math.floor(seconds / 60)
+  return minutes + ":" + String(seconds % 60).padStart(2, "0")
+}
+
+// Colour of the move countdown, goes amber then red as we run out of time
+function countdownColor(remaining, limit) {
+  if (remaining <= 0 || remaining < limit * 0.1) {
+    return "text-red-500"
+  } else if (remaining < limit * 0.33) {
+    return "text-amber-400"
+  }
+  return ""
+}
+
+function Clock({ sessionStart, moveStart, moveLimit, visible }) {
+  const [now, setNow] = useState(Date.now())
+
+  // No need to tick while the window is hidden, the time catches up when it's shown again
+  useEffect(function(){
+    if (!visible) {
+      return
+    }
+    setNow(Date.now())
+    const timer = setInterval(() => setNow(Date.now()), 250)
+    return () => clearInterval(timer)
+  },[visible])
+
+  if (sessionStart == null) {
+    return null
+  }
+
+  const limit = moveLimit * 1000
+  const remaining = limit - (now - moveStart)
+
+  return (
+    <div className="flex flex-col font-mono text-2xl">
+      <span>{t("Session {time}", { time: formatTime(now - sessionStart) })}</span>
+      {moveLimit != null &&
+        <span className={countdownColor(remaining, limit)}>{t("Move {time}", { time: formatTime(remaining) })}</span>}
+    </div>
+  )
+}
+
+export default Clock
@@ -0,0 +1,35 @@
+import { Component } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import ProblemOverlay from "./ProblemOverlay.jsx"
+import { t } from "./i18n.js"
+
+// Catches anything thrown while rendering so the window says what happened instead of going
+// blank, the error goes in the log too
+class ErrorBoundary extends Component {
+  constructor(props) {
+    super(props)
+    this.state = { "error": null }
+  }
+
+  static getDerivedStateFromError(error) {
+    return { "error": error }
+  }
+
+  componentDidCatch(error, info) {
+    invoke("log_error", { "message": String(error) + info.componentStack }).catch(console.error)
+  }
+
+  render() {
+    if (this.state.error == null) {
+      return this.props.children
+    }
+    const problem = {
+      "message": t("Something went wrong showing this screen: {error}", { "error": this.state.error }),
+      "fix": t("Reloading goes back to the menu, the log has the details"),
+    }
+    return <ProblemOverlay problems={[problem]} onClose={() => window.location.reload()} closeText={t("Reload")}/>
+  }
+}
+
+export default ErrorBoundary
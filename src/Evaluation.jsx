@@ -0,0 +1,23 @@
+import { formatScore } from "./score.js"
+import { t } from "./i18n.js"
+
+// What the engine makes of the position shown, the best few lines with scores for white
+function Evaluation({ analysis }) {
+  if (analysis == null || analysis.lines.length == 0) {
+    return <span className="text-neutral-400">{t("Analysing...")}</span>
+  }
+  return (
+    <div className="flex flex-col">
+      <span className="text-2xl">{formatScore(analysis.lines[0].score)}</span>
+      <span className="text-neutral-400">{t("Depth {depth}", analysis)}</span>
+      {analysis.lines.map((line, i) =>
+        <span key={i} className="truncate">
+          <span className="font-bold pr-2">{formatScore(line.score)}</span>
+          {line.moves.slice(0, 8).join(" ")}
+        </span>
+      )}
+    </div>
+  )
+}
+
+export default Evaluation
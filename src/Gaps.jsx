@@ -0,0 +1,76 @@
+import { useState, useEffect, useRef } from 'react'
+import { invoke } from '@tauri-apps/api'
+import { listen } from '@tauri-apps/api/event'
+
+import { formatScore } from "./score.js"
+import { t } from "./i18n.js"
+
+// Opponent moves from your games the repertoire doesn't cover, with the engine's reply to add
+function Gaps({ color, onBack }) {
+  const [gaps, setGaps] = useState(null)
+  const [suggestions, setSuggestions] = useState({})
+  const [added, setAdded] = useState({})
+  const [error, setError] = useState(null)
+  // Effects run twice in development, the engine should only be started once
+  const started = useRef(false)
+
+  useEffect(function(){
+    const listeners = [
+      listen("gap-suggestion", (event) => {
+        const { index, ...suggestion } = event.payload
+        setSuggestions((x) => ({ ...x, [index]: suggestion }))
+      }),
+      listen("gap-failed", (event) => setError(event.payload)),
+    ]
+    Promise.all(listeners).then(function(){
+      if (!started.current) {
+        started.current = true
+        invoke("gaps", { "color": color })
+          .then(setGaps)
+          .catch((e) => setError(String(e)))
+      }
+    })
+    return () => { listeners.forEach((x) => x.then((f) => f())) }
+  },[])
+
+  function add(index) {
+    invoke("fill_gap", { "color": color, "moves": gaps[index].moves, "reply": suggestions[index].reply })
+      .then((path) => setAdded((x) => ({ ...x, [index]: path })))
+      .catch((e) => setError(String(e)))
+  }
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{t("Gaps in the " + color + " repertoire")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {gaps == null && error == null && <span>{t("Looking through your games")}</span>}
+      {gaps != null && gaps.length == 0 &&
+        <span>{t("Your opponents haven't played anything the repertoire doesn't cover")}</span>}
+      {gaps != null && gaps.length > 0 &&
+        <table>
+          <thead>
+            <tr><th className="text-left">{t("Line")}</th><th>{t("Games")}</th><th>{t("Reply")}</th><th></th></tr>
+          </thead>
+          <tbody>
+            {gaps.map((gap, i) =>
+              <tr key={gap.moves.join(" ")}>
+                <td>{gap.moves.join(" ")}</td>
+                <td className="px-2">{gap.games}</td>
+                <td className="px-2">
+                  {suggestions[i] == null ? "…" : suggestions[i].reply + " " + formatScore(suggestions[i].score)}
+                </td>
+                <td className="px-2">
+                  {added[i] != null
+                    ? <span title={added[i]}>{t("Added")}</span>
+                    : <button disabled={suggestions[i] == null} onClick={() => add(i)}>{t("Add")}</button>}
+                </td>
+              </tr>
+            )}
+          </tbody>
+        </table>}
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default Gaps
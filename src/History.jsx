@@ -0,0 +1,54 @@
+import { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import { t } from "./i18n.js"
+
+const RESULTS = { "completed": "Completed", "failed": "Failed", "abandoned": "Abandoned" }
+
+function formatTime(started) {
+  return new Date(started).toLocaleString([], { dateStyle: "medium", timeStyle: "short" })
+}
+
+// Past drills grouped into sessions, any of them can be stepped through on the board
+function History({ onReplay, onBack }) {
+  const [sessions, setSessions] = useState(null)
+  const [open, setOpen] = useState(0)
+  const [error, setError] = useState(null)
+
+  useEffect(function(){
+    invoke("sessions")
+      .then(setSessions)
+      .catch((e) => setError(String(e)))
+  },[])
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{t("Past sessions")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {sessions != null && sessions.length == 0 && <span>{t("Nothing's been drilled yet")}</span>}
+      {sessions != null && sessions.map((session, i) =>
+        <div key={session.started} className="flex flex-col gap-2">
+          <button onClick={() => setOpen(open == i ? null : i)}>
+            {t("{started}, {drills} drills, {completed} completed", { started: formatTime(session.started), drills: session.attempts.length, completed: session.attempts.filter((x) => x.result == "completed").length })}
+          </button>
+          {open == i &&
+            <table>
+              <tbody>
+                {session.attempts.map((attempt) =>
+                  <tr key={attempt.started}>
+                    <td>{attempt.line.join(" ")}</td>
+                    <td className="px-2">{t(RESULTS[attempt.result])}</td>
+                    <td className="px-2">{attempt.mistakes.map((x) => x.played).join(", ")}</td>
+                    <td><button onClick={() => onReplay(attempt)}>{t("Replay")}</button></td>
+                  </tr>
+                )}
+              </tbody>
+            </table>}
+        </div>
+      )}
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default History
@@ -0,0 +1,30 @@
+import { useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import Overlay from "./Overlay.jsx"
+import { t } from "./i18n.js"
+
+// Confirm which repertoire a dropped PGN should go into
+function ImportOverlay({ file, onClose }) {
+  const [error, setError] = useState(null)
+
+  function importInto(color) {
+    invoke("import_pgn", { "color": color, "pgn": file.text })
+      .then(() => onClose())
+      .catch((e) => setError(e))
+  }
+
+  return (
+    <Overlay>
+      <span>{t("Add {file} to your repertoire?", { file: file.name })}</span>
+      {error != null && <span className="text-red-500">{error}</span>}
+      <div className="flex flex-row gap-2">
+        <button onClick={() => importInto("white")}>{t("As white")}</button>
+        <button onClick={() => importInto("black")}>{t("As black")}</button>
+        <button onClick={onClose}>{t("Cancel")}</button>
+      </div>
+    </Overlay>
+  )
+}
+
+export default ImportOverlay
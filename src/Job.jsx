@@ -0,0 +1,49 @@
+import { useState, useEffect, useRef } from 'react'
+import { invoke } from '@tauri-apps/api'
+import { listen } from '@tauri-apps/api/event'
+
+import { t } from "./i18n.js"
+
+// Start a job in the background queue with `start`, which returns its id, and follow its updates
+export function useJob(start) {
+  const [id, setId] = useState(null)
+  const [updates, setUpdates] = useState({})
+  const [error, setError] = useState(null)
+  // Effects run twice in development, only one job should be started
+  const started = useRef(false)
+
+  useEffect(function(){
+    // Updates can come before the id does so all of them are kept
+    const unlisten = listen("job-update", (event) => {
+      setUpdates((x) => ({ ...x, [event.payload.id]: event.payload }))
+    })
+    unlisten.then(function(){
+      if (!started.current) {
+        started.current = true
+        start().then(setId).catch((e) => setError(String(e)))
+      }
+    })
+    return () => { unlisten.then((f) => f()) }
+  },[])
+
+  const job = id == null ? null : updates[id] ?? null
+  return { job, error: error ?? job?.error ?? null }
+}
+
+// Pause, resume and cancel buttons for a job that isn't over
+function JobControls({ job }) {
+  if (job == null || !["queued", "running", "paused"].includes(job.state)) {
+    return null
+  }
+  const call = (command) => invoke(command, { "id": job.id }).catch(console.error)
+  return (
+    <div className="flex flex-row gap-2">
+      {job.state == "paused"
+        ? <button onClick={() => call("resume_job")}>{t("Resume")}</button>
+        : <button onClick={() => call("pause_job")}>{t("Pause")}</button>}
+      <button onClick={() => call("cancel_job")}>{t("Cancel")}</button>
+    </div>
+  )
+}
+
+export default JobControls
@@ -0,0 +1,138 @@
+import { useState, useEffect, useRef } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import { t } from "./i18n.js"
+
+// Ways of working through the prep, just drilling for now
+const MODES = [
+  { id: "drill", label: "Drill" },
+  { id: "endgame", label: "Endgames" },
+]
+
+function Menu({ onStart, onAudit, onNovelties, onGaps, onScan, onStats, onHistory, onSettings, onProfile }) {
+  const [color, setColor] = useState("white")
+  const [chapters, setChapters] = useState([])
+  // Index into the chapter list, 0 is every chapter
+  const [selected, setSelected] = useState(0)
+  const [mode, setMode] = useState(MODES[0].id)
+  const [profiles, setProfiles] = useState({ "profiles": [], "current": null })
+  const [exported, setExported] = useState(null)
+  const reviewsFile = useRef(null)
+
+  useEffect(function(){
+    invoke("profiles", {})
+      .then((response) => setProfiles(response))
+  },[])
+
+  useEffect(function(){
+    const chapters = mode == "endgame" ? invoke("endgame_chapters", {}) : invoke("chapters", { "color": color })
+    chapters.then((response) => {
+      setChapters(response)
+      setSelected(0)
+    })
+  },[color, mode])
+
+  function swapColor() {
+    setColor(color == "white" ? "black" : "white")
+  }
+
+  function selection() {
+    return { "color": color, "chapter": selected == 0 ? null : chapters[selected - 1], "mode": mode }
+  }
+
+  function start() {
+    onStart(selection())
+  }
+
+  function exportAnki() {
+    const { color, chapter } = selection()
+    invoke("export_anki", { "color": color, "chapter": chapter })
+      .then((path) => setExported(t("Exported to {path}", { path })))
+      .catch((e) => setExported(t("Couldn't export: {error}", { error: e })))
+  }
+
+  function exportBooklet() {
+    const { color, chapter } = selection()
+    invoke("export_booklet", { "color": color, "chapter": chapter })
+      .then((path) => setExported(t("Exported to {path}", { path })))
+      .catch((e) => setExported(t("Couldn't export: {error}", { error: e })))
+  }
+
+  // Reviews from another app count towards when the selected colour's lines are due
+  function importReviews(event) {
+    const file = event.target.files[0]
+    if (file == null) {
+      return
+    }
+    file.text()
+      .then((text) => invoke("import_reviews", { "color": color, "export": text }))
+      .then((count) => setExported(t("Imported {count} reviews from {file}", { count, file: file.name })))
+      .catch((e) => setExported(t("Couldn't import: {error}", { error: e })))
+    event.target.value = ""
+  }
+
+  useEffect(function(){
+    function handleKeyDown(event) {
+      if (event.key == "ArrowUp") {
+        setSelected(Math.max(0, selected - 1))
+      } else if (event.key == "ArrowDown") {
+        setSelected(Math.min(chapters.length, selected + 1))
+      } else if (event.key == "ArrowLeft" || event.key == "ArrowRight") {
+        swapColor()
+      } else if (event.key == "Enter") {
+        start()
+      }
+    }
+    document.addEventListener("keydown", handleKeyDown)
+    return () => document.removeEventListener("keydown", handleKeyDown)
+  })
+
+  function entryClass(active) {
+    return "px-2 cursor-pointer " + (active ? "bg-indigo-600 text-white" : "")
+  }
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>chess-driller</h1>
+      {profiles.profiles.length > 0 &&
+        <div className="flex flex-row gap-2">
+          <button onClick={() => onProfile(null)} className={profiles.current == null ? "border-indigo-500" : ""}>{t("Default")}</button>
+          {profiles.profiles.map((p) =>
+            <button key={p} onClick={() => onProfile(p)} className={profiles.current == p ? "border-indigo-500" : ""}>{p}</button>
+          )}
+        </div>}
+      <div className="flex flex-row gap-2">
+        <button onClick={() => setColor("white")} className={color == "white" ? "border-indigo-500" : ""}>{t("White")}</button>
+        <button onClick={() => setColor("black")} className={color == "black" ? "border-indigo-500" : ""}>{t("Black")}</button>
+      </div>
+      <ul className="flex flex-col">
+        <li className={entryClass(selected == 0)} onClick={() => setSelected(0)}>{t("All chapters")}</li>
+        {chapters.map((chapter, i) =>
+          <li key={chapter} className={entryClass(selected == i + 1)} onClick={() => setSelected(i + 1)}>{chapter}</li>
+        )}
+      </ul>
+      <div className="flex flex-row gap-2">
+        {MODES.map((m) =>
+          <button key={m.id} onClick={() => setMode(m.id)} className={mode == m.id ? "border-indigo-500" : ""}>{t(m.label)}</button>
+        )}
+      </div>
+      <button onClick={start}>{t("Start")}</button>
+      <button onClick={() => onAudit(selection())}>{t("Check with the engine")}</button>
+      <button onClick={() => onNovelties(selection())}>{t("Novelties against master games")}</button>
+      <button onClick={() => onGaps(color)}>{t("Gaps from my games")}</button>
+      <button onClick={onScan}>{t("Blunders in my games")}</button>
+      <button onClick={onStats}>{t("Statistics")}</button>
+      <button onClick={onHistory}>{t("Past sessions")}</button>
+      <div className="flex flex-row gap-2">
+        <button onClick={exportAnki}>{t("Export to Anki")}</button>
+        <button onClick={exportBooklet}>{t("Export booklet")}</button>
+        <button onClick={() => reviewsFile.current.click()}>{t("Import reviews")}</button>
+        <input ref={reviewsFile} type="file" accept=".csv,.tsv,.txt" className="hidden" onChange={importReviews}/>
+      </div>
+      {exported != null && <span>{exported}</span>}
+      <button onClick={onSettings}>{t("Settings")}</button>
+    </div>
+  )
+}
+
+export default Menu
@@ -0,0 +1,59 @@
+import { useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import { t } from "./i18n.js"
+
+function commonPrefix(words) {
+  let prefix = words[0]
+  for (const word of words) {
+    while (!word.startsWith(prefix)) {
+      prefix = prefix.slice(0, -1)
+    }
+  }
+  return prefix
+}
+
+// Type moves in SAN, Tab completes from the legal moves and Enter plays it
+function MoveInput({ inputRef, onPlayed }) {
+  const [text, setText] = useState("")
+  const [options, setOptions] = useState([])
+  const [error, setError] = useState(null)
+
+  function onKeyDown(event) {
+    if (event.key == "Tab") {
+      event.preventDefault()
+      invoke("san_completions", { "prefix": text })
+        .then(function(response){
+          if (response.length > 0) {
+            setText(commonPrefix(response))
+          }
+          setOptions(response.length > 1 ? response : [])
+        })
+    } else if (event.key == "Enter") {
+      invoke("play_san", { "san": text })
+        .then(function(response){
+          setText("")
+          setOptions([])
+          setError(null)
+          onPlayed(response)
+        })
+        .catch((e) => setError(e))
+    } else if (event.key == "Escape") {
+      setText("")
+      setOptions([])
+      setError(null)
+      event.target.blur()
+    }
+  }
+
+  return (
+    <div className="flex flex-col">
+      <input ref={inputRef} className="font-mono px-1" placeholder={t("Type a move")} value={text}
+        onChange={(event) => setText(event.target.value)} onKeyDown={onKeyDown}/>
+      {options.length > 0 && <span className="font-mono text-neutral-500">{options.join(" ")}</span>}
+      {error != null && <span className="text-red-500">{error}</span>}
+    </div>
+  )
+}
+
+export default MoveInput
@@ -0,0 +1,25 @@
+// Scrollable list of the moves in the line being shown, highlighting the current one
+function MoveList({ moves, cursor }) {
+  const rows = []
+  for (let i = 0; i < moves.length; i += 2) {
+    rows.push(i)
+  }
+
+  function moveClass(ply) {
+    return "px-1 " + (ply + 1 == cursor ? "bg-indigo-600 text-white" : "")
+  }
+
+  return (
+    <div className="overflow-y-auto max-h-[60vmin] font-mono">
+      {rows.map((i) =>
+        <div key={i} className="flex flex-row gap-2">
+          <span className="w-8 text-neutral-500">{i / 2 + 1}.</span>
+          <span className={moveClass(i)}>{moves[i]}</span>
+          {i + 1 < moves.length && <span className={moveClass(i + 1)}>{moves[i + 1]}</span>}
+        </div>
+      )}
+    </div>
+  )
+}
+
+export default MoveList
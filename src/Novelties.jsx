@@ -0,0 +1,48 @@
+import { invoke } from '@tauri-apps/api'
+
+import { describeLimits } from "./Audit.jsx"
+import JobControls, { useJob } from "./Job.jsx"
+import { formatScore } from "./score.js"
+import { t } from "./i18n.js"
+
+// Where the repertoire leaves the reference games, and whether the new moves hold up
+function Novelties({ selection, onBack }) {
+  const { job, error } = useJob(() => invoke("novelties", { "color": selection.color, "chapter": selection.chapter }))
+  const report = job?.result ?? null
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{selection.chapter != null ? t("Novelties in {chapter}", selection) : t("Novelties in the " + selection.color + " repertoire")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {job?.state == "cancelled" && <span>{t("Cancelled")}</span>}
+      {report == null && error == null && job?.state != "cancelled" &&
+        <span>{job?.state == "queued" ? t("Waiting for the jobs before it") : job == null || job.total == 0 ? t("Reading the reference games") : t("Checked {done} of {total} novelties", job)}{job?.state == "paused" && t(", paused")}</span>}
+      <JobControls job={job}/>
+      {report != null && report.novelties.length == 0 &&
+        <span>{t("Every line stays in the {games} reference games until the opponent leaves them", { games: report.reference_games })}</span>}
+      {report != null && report.novelties.length > 0 &&
+        <>
+          <span>{t("Compared with {games} games, checked {limits}", { games: report.reference_games, limits: describeLimits(report.limits) })}</span>
+          <table>
+            <thead>
+              <tr><th className="text-left">{t("Line")}</th><th>{t("Games")}</th><th>{t("Eval")}</th><th>{t("Best")}</th><th></th></tr>
+            </thead>
+            <tbody>
+              {report.novelties.map((novelty) =>
+                <tr key={novelty.moves.join(" ")}>
+                  <td>{novelty.moves.join(" ")}</td>
+                  <td className="px-2">{novelty.games}</td>
+                  <td className="px-2">{formatScore(novelty.eval)}</td>
+                  <td className="px-2">{novelty.loss > 0 && novelty.best}</td>
+                  <td className={"px-2 " + (novelty.sound ? "" : "text-red-500")}>{novelty.sound ? t("Pet line") : t("Error, {loss} worse", { loss: (novelty.loss / 100).toFixed(2) })}</td>
+                </tr>
+              )}
+            </tbody>
+          </table>
+        </>}
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default Novelties
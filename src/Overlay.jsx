@@ -0,0 +1,12 @@
+// Panel shown over the top of everything else, for dialogs and messages
+function Overlay({ children }) {
+  return (
+    <div className="fixed inset-0 flex items-center justify-center bg-black/50">
+      <div className="flex flex-col gap-4 p-4 rounded bg-neutral-800">
+        {children}
+      </div>
+    </div>
+  )
+}
+
+export default Overlay
@@ -0,0 +1,34 @@
+import { useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import Overlay from "./Overlay.jsx"
+import { t } from "./i18n.js"
+
+// What went wrong and what might fix it, with the log a click away for the details
+function ProblemOverlay({ problems, onClose, closeText }) {
+  const [log, setLog] = useState(null)
+
+  function showLog() {
+    invoke("show_log", {})
+      .then((path) => setLog(t("The log is at {path}", { "path": path })))
+      .catch((e) => setLog(t("Couldn't open the log: {error}", { "error": e })))
+  }
+
+  return (
+    <Overlay>
+      {problems.map((problem, i) =>
+        <div key={i} className="flex flex-col gap-1 max-w-[80vmin]">
+          <span className="text-red-500 whitespace-pre-line">{problem.message}</span>
+          {problem.fix != null && <span className="text-neutral-400">{problem.fix}</span>}
+        </div>
+      )}
+      {log != null && <span className="text-neutral-400">{log}</span>}
+      <div className="flex flex-row gap-2">
+        <button onClick={showLog}>{t("Open the log")}</button>
+        <button onClick={onClose}>{closeText ?? t("OK")}</button>
+      </div>
+    </Overlay>
+  )
+}
+
+export default ProblemOverlay
@@ -0,0 +1,29 @@
+import { useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import Overlay from "./Overlay.jsx"
+import { t } from "./i18n.js"
+
+// Asked when the window is closed in the middle of a drill
+function QuitOverlay({ onCancel }) {
+  const [error, setError] = useState(null)
+
+  function quit(save) {
+    invoke("quit", { "save": save })
+      .catch((e) => setError(e))
+  }
+
+  return (
+    <Overlay>
+      <span>{t("Quit in the middle of a drill?")}</span>
+      {error != null && <span className="text-red-500">{error}</span>}
+      <div className="flex flex-row gap-2">
+        <button onClick={() => quit(true)}>{t("Save & quit")}</button>
+        <button onClick={() => quit(false)}>{t("Discard")}</button>
+        <button onClick={onCancel}>{t("Cancel")}</button>
+      </div>
+    </Overlay>
+  )
+}
+
+export default QuitOverlay
@@ -0,0 +1,25 @@
+import { invoke } from '@tauri-apps/api'
+
+import Overlay from "./Overlay.jsx"
+import { t } from "./i18n.js"
+
+// Offer to carry on with the drill that was going when the app last stopped without closing
+function RecoverOverlay({ snapshot, onRecovered, onClose }) {
+  function discard() {
+    invoke("discard_recovery", {}).catch(console.error)
+    onClose()
+  }
+
+  const saved = new Date(snapshot.saved).toLocaleString([], { dateStyle: "medium", timeStyle: "short" })
+  return (
+    <Overlay>
+      <span>{t("The app stopped in the middle of a drill on {saved}, {moves} moves in. Carry on with it?", { "saved": saved, "moves": snapshot.moves.length })}</span>
+      <div className="flex flex-row gap-2">
+        <button onClick={onRecovered}>{t("Carry on")}</button>
+        <button onClick={discard}>{t("Discard")}</button>
+      </div>
+    </Overlay>
+  )
+}
+
+export default RecoverOverlay
@@ -0,0 +1,58 @@
+import { invoke } from '@tauri-apps/api'
+
+import JobControls, { useJob } from "./Job.jsx"
+import { t } from "./i18n.js"
+
+// Blunders in your downloaded games while in the repertoire or just after leaving it
+function Scan({ onBack }) {
+  const { job, error } = useJob(() => invoke("scan_games"))
+  const report = job?.result ?? null
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{t("Blunders in your games")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {job?.state == "cancelled" && <span>{t("Cancelled")}</span>}
+      {report == null && error == null && job?.state != "cancelled" &&
+        <span>{job?.state == "queued" ? t("Waiting for the jobs before it") : job == null || job.total == 0 ? t("Starting the engine") : t("Checked {done} of {total} games", job)}{job?.state == "paused" && t(", paused")}</span>}
+      <JobControls job={job}/>
+      {report != null && report.games.length == 0 &&
+        <span>{t("No blunders near the repertoire in the last {scanned} games", report)}</span>}
+      {report != null && report.train.length > 0 &&
+        <>
+          <h2>{t("Train next")}</h2>
+          <ul>
+            {report.train.map((line) =>
+              <li key={line.moves.join(" ")}>
+                {line.moves.length == 0 ? t("Your first move") : line.moves.join(" ")} ({line.blunders})
+              </li>
+            )}
+          </ul>
+        </>}
+      {report != null && report.games.length > 0 &&
+        <table>
+          <thead>
+            <tr><th className="text-left">{t("Game")}</th><th className="text-left">{t("Blunder")}</th><th>{t("Best")}</th><th>{t("Loss")}</th><th></th></tr>
+          </thead>
+          <tbody>
+            {report.games.flatMap((game, i) => game.blunders.map((blunder) =>
+              <tr key={i + blunder.moves.join(" ")}>
+                <td>
+                  {game.link == null
+                    ? game.white + " - " + game.black
+                    : <a href={game.link} target="_blank">{game.white} - {game.black}</a>} {game.date}
+                </td>
+                <td className="px-2">{blunder.moves.join(" ")}</td>
+                <td className="px-2">{blunder.best}</td>
+                <td className="px-2">{(blunder.loss / 100).toFixed(2)}</td>
+                <td className="px-2">{blunder.in_book ? t("In the repertoire") : t("After it")}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>}
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default Scan
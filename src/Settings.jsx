@@ -0,0 +1,241 @@
+import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api'
+import { Chessboard } from "react-chessboard";
+
+import { useJob } from "./Job.jsx"
+import { THEMES, PIECE_SETS, boardStyle } from "./themes.jsx"
+import { LOCALES, setLocale, t } from "./i18n.js"
+
+const PREVIEW_POSITION = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R"
+
+// Choices for how many months of attempts to keep, null keeps them all
+const KEEP_MONTHS = [null, 3, 6, 12, 24]
+
+// Merges the training history with the sync server's copy as soon as it's shown
+function SyncStatus() {
+  const { job, error } = useJob(() => invoke("sync_progress", {}))
+  if (error != null) {
+    return <span className="text-red-500">{error}</span>
+  }
+  if (job?.result != null) {
+    return <span className="text-neutral-400">{t("Synced, {count} new records from the server", { count: job.result })}</span>
+  }
+  return <span className="text-neutral-400">{t("Syncing")}</span>
+}
+
+function Settings({ appearance, onChange, input, onInputChange, sound, onSoundChange, speech, onSpeechChange, locale, onLocaleChange, onBack }) {
+  // Result of exporting or importing the settings
+  const [transfer, setTransfer] = useState(null)
+  const importFile = useRef(null)
+  // Engines in `engines` in the config, null while they're loading
+  const [engines, setEngines] = useState(null)
+  // Bumped to sync again
+  const [syncs, setSyncs] = useState(0)
+  // Months of attempts kept, undefined while it's loading
+  const [keepMonths, setKeepMonths] = useState(undefined)
+  const [archived, setArchived] = useState(null)
+  // Piece letters and figurines in moves, null while it's loading
+  const [notation, setNotation] = useState(null)
+  const [usageLog, setUsageLog] = useState(null)
+
+  useEffect(function(){
+    invoke("engines", {}).then(setEngines).catch(console.error)
+    invoke("keep_months", {}).then(setKeepMonths).catch(console.error)
+    invoke("notation", {}).then(setNotation).catch(console.error)
+    invoke("usage_log", {}).then(setUsageLog).catch(console.error)
+  },[])
+
+  function keep(months) {
+    setKeepMonths(months)
+    invoke("set_keep_months", { "months": months })
+      .then((count) => setArchived(count > 0 ? t("Archived {count} attempts", { count }) : null))
+      .catch((e) => setArchived(t("Couldn't archive: {error}", { error: e })))
+  }
+
+  function logUsage(enabled) {
+    setUsageLog(enabled)
+    invoke("set_usage_log", { "enabled": enabled }).catch(console.error)
+  }
+
+  function selectEngine(name) {
+    invoke("select_engine", { "name": name })
+      .then(() => setEngines({ ...engines, "active": name }))
+      .catch(console.error)
+  }
+
+  function apply(change) {
+    const updated = { ...appearance, ...change }
+    onChange(updated)
+    invoke("set_appearance", { "appearance": updated })
+  }
+
+  function applyInput(change) {
+    const updated = { ...input, ...change }
+    onInputChange(updated)
+    invoke("set_input", { "input": updated })
+  }
+
+  function applySound(change) {
+    const updated = { ...sound, ...change }
+    onSoundChange(updated)
+    invoke("set_sound", { "sound": updated })
+  }
+
+  function applyNotation(change) {
+    const updated = { ...notation, ...change }
+    setNotation(updated)
+    invoke("set_notation", { "notation": updated }).catch(console.error)
+  }
+
+  function applySpeech(change) {
+    const updated = { ...speech, ...change }
+    onSpeechChange(updated)
+    invoke("set_speech", { "speech": updated })
+  }
+
+  function exportSettings() {
+    invoke("export_settings", {})
+      .then((path) => setTransfer(t("Exported to {path}", { path })))
+      .catch((e) => setTransfer(t("Couldn't export: {error}", { error: e })))
+  }
+
+  // The new settings come back through the config reload
+  function importSettings(event) {
+    const file = event.target.files[0]
+    if (file == null) {
+      return
+    }
+    file.text()
+      .then((text) => invoke("import_settings", { "bundle": text }))
+      .then(() => setTransfer(t("Imported {file}", { file: file.name })))
+      .catch((e) => setTransfer(t("Couldn't import: {error}", { error: e })))
+  }
+
+  function optionClass(active) {
+    return active ? "border-indigo-500" : ""
+  }
+
+  // Null goes back to the system's language
+  function applyLocale(name) {
+    setLocale(name)
+    onLocaleChange(name)
+    invoke("set_locale", { "locale": name })
+  }
+
+  return (
+    <div className="flex flex-row gap-4 p-4">
+      <div className="flex flex-col gap-4">
+        <h2>{t("Language")}</h2>
+        <div className="flex flex-row gap-2">
+          <button className={optionClass(locale == null)} onClick={() => applyLocale(null)}>{t("System")}</button>
+          {Object.entries(LOCALES).map(([name, x]) =>
+            <button key={name} className={optionClass(locale == name)} onClick={() => applyLocale(name)}>{x.name}</button>
+          )}
+        </div>
+        {notation != null &&
+          <>
+            <h2>{t("Piece letters")}</h2>
+            <div className="flex flex-row gap-2">
+              <button className={optionClass(notation.pieces == null)} onClick={() => applyNotation({ "pieces": null })}>{t("Same as the language")}</button>
+              {Object.entries(LOCALES).map(([name, x]) =>
+                <button key={name} className={optionClass(notation.pieces == name)} onClick={() => applyNotation({ "pieces": name })}>{x.name}</button>
+              )}
+            </div>
+            <div className="flex flex-row gap-2">
+              <button className={optionClass(!notation.figurine)} onClick={() => applyNotation({ "figurine": false })}>{t("Letters")}</button>
+              <button className={optionClass(notation.figurine)} onClick={() => applyNotation({ "figurine": true })}>{t("Figurines like ♘f3")}</button>
+            </div>
+          </>}
+        <h2>{t("Board")}</h2>
+        <div className="flex flex-row gap-2">
+          {Object.keys(THEMES).map((theme) =>
+            <button key={theme} className={optionClass(appearance.board_theme == theme)} onClick={() => apply({ "board_theme": theme })}>{theme}</button>
+          )}
+        </div>
+        <h2>{t("Pieces")}</h2>
+        <div className="flex flex-row gap-2">
+          {Object.keys(PIECE_SETS).map((set) =>
+            <button key={set} className={optionClass(appearance.piece_set == set)} onClick={() => apply({ "piece_set": set })}>{set}</button>
+          )}
+        </div>
+        <div className="flex flex-row gap-2">
+          <button className={optionClass(appearance.show_coordinates ?? true)} onClick={() => apply({ "show_coordinates": true })}>{t("Coordinates")}</button>
+          <button className={optionClass(!(appearance.show_coordinates ?? true))} onClick={() => apply({ "show_coordinates": false })}>{t("No coordinates")}</button>
+        </div>
+        <h2>{t("Moving pieces")}</h2>
+        <div className="flex flex-row gap-2">
+          <button className={optionClass(input.drag_pieces)} onClick={() => applyInput({ "drag_pieces": true })}>{t("Drag or click")}</button>
+          <button className={optionClass(!input.drag_pieces)} onClick={() => applyInput({ "drag_pieces": false })}>{t("Click only")}</button>
+        </div>
+        <label className="flex flex-row gap-2">
+          {t("Drag threshold")}
+          <input type="number" min="0" className="w-16 text-black" value={input.drag_threshold}
+            onChange={(e) => applyInput({ "drag_threshold": Number(e.target.value) })}/>
+          {t("px")}
+        </label>
+        <h2>{t("Sound")}</h2>
+        <div className="flex flex-row gap-2">
+          <button className={optionClass(!sound.muted)} onClick={() => applySound({ "muted": false })}>{t("On")}</button>
+          <button className={optionClass(sound.muted)} onClick={() => applySound({ "muted": true })}>{t("Muted")}</button>
+        </div>
+        <label className="flex flex-row gap-2">
+          {t("Volume")}
+          <input type="range" min="0" max="1" step="0.05" value={sound.volume} disabled={sound.muted}
+            onChange={(e) => applySound({ "volume": Number(e.target.value) })}/>
+        </label>
+        <h2>{t("Read out replies and hints")}</h2>
+        <div className="flex flex-row gap-2">
+          <button className={optionClass(speech.enabled)} onClick={() => applySpeech({ "enabled": true })}>{t("On")}</button>
+          <button className={optionClass(!speech.enabled)} onClick={() => applySpeech({ "enabled": false })}>{t("Off")}</button>
+        </div>
+        <label className="flex flex-row gap-2">
+          {t("Speed")}
+          <input type="range" min="0.5" max="2" step="0.1" value={speech.rate} disabled={!speech.enabled}
+            onChange={(e) => applySpeech({ "rate": Number(e.target.value) })}/>
+        </label>
+        {engines != null && engines.names.length > 0 &&
+          <>
+            <h2>{t("Engine for sparring and analysis")}</h2>
+            <div className="flex flex-row gap-2">
+              <button className={optionClass(engines.active == null)} onClick={() => selectEngine(null)}>{t("Default")}</button>
+              {engines.names.map((name) =>
+                <button key={name} className={optionClass(engines.active == name)} onClick={() => selectEngine(name)}>{name}</button>
+              )}
+            </div>
+          </>}
+        <h2>{t("Move to another machine")}</h2>
+        <div className="flex flex-row gap-2">
+          <button onClick={exportSettings}>{t("Export")}</button>
+          <button onClick={() => importFile.current.click()}>{t("Import")}</button>
+          <input ref={importFile} type="file" accept=".json" className="hidden" onChange={importSettings}/>
+        </div>
+        {transfer != null && <span className="text-neutral-400">{transfer}</span>}
+        {keepMonths !== undefined &&
+          <>
+            <h2>{t("Keep every attempt for")}</h2>
+            <div className="flex flex-row gap-2">
+              {KEEP_MONTHS.map((months) =>
+                <button key={months ?? "all"} className={optionClass(keepMonths == months)} onClick={() => keep(months)}>{months == null ? t("Ever") : t("{months} months", { months })}</button>
+              )}
+            </div>
+            {archived != null && <span className="text-neutral-400">{archived}</span>}
+          </>}
+        {usageLog != null &&
+          <>
+            <h2>{t("Usage log")}</h2>
+            <div className="flex flex-row gap-2">
+              <button className={optionClass(usageLog)} onClick={() => logUsage(true)}>{t("On")}</button>
+              <button className={optionClass(!usageLog)} onClick={() => logUsage(false)}>{t("Off")}</button>
+            </div>
+            <span className="text-neutral-400">{t("Kept on this machine for the statistics, it's never sent anywhere")}</span>
+          </>}
+        <button onClick={() => setSyncs(syncs + 1)}>{t("Sync training progress")}</button>
+        {syncs > 0 && <SyncStatus key={syncs}/>}
+        <button onClick={onBack}>{t("Back")}</button>
+      </div>
+      <Chessboard id="PreviewBoard" position={PREVIEW_POSITION} boardWidth={240} arePiecesDraggable={false} {...boardStyle(appearance)}/>
+    </div>
+  )
+}
+
+export default Settings
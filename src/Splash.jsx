@@ -0,0 +1,35 @@
+import { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+// Shown while the backend parses the repertoire and syncs games
+function Splash({ onFinished }) {
+  const [status, setStatus] = useState({ "message": "Starting", "done": 0, "total": 1 })
+
+  useEffect(function(){
+    const timer = setInterval(function(){
+      invoke("loading_status", {})
+        .then((response) => {
+          setStatus(response)
+          if (response.finished) {
+            clearInterval(timer)
+            onFinished(response)
+          }
+        })
+    }, 200)
+    return () => clearInterval(timer)
+  },[])
+
+  const percent = status.total == 0 ? 100 : Math.round(100 * status.done / status.total)
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[60vmin]">
+      <h1>chess-driller</h1>
+      <div className="w-full h-2 bg-neutral-700 rounded">
+        <div className="h-2 bg-indigo-500 rounded" style={{ "width": percent + "%" }}></div>
+      </div>
+      <span>{status.message}</span>
+    </div>
+  )
+}
+
+export default Splash
@@ -0,0 +1,472 @@
+import { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api'
+
+import { t } from "./i18n.js"
+
+// Share of the player's moves that were right
+function accuracy(totals) {
+  return totals.moves == 0 ? "-" : Math.round(100 * (1 - totals.mistakes / totals.moves)) + "%"
+}
+
+function secondsPerMove(totals) {
+  return totals.moves == 0 ? "-" : (totals.seconds / totals.moves).toFixed(1) + "s"
+}
+
+// Minutes, or hours and minutes
+function duration(seconds) {
+  const minutes = Math.round(seconds / 60)
+  return minutes < 60 ? minutes + "m" : Math.floor(minutes / 60) + "h " + minutes % 60 + "m"
+}
+
+// How much of all the time drilling went on these
+function share(totals, all) {
+  return all.seconds == 0 ? "-" : Math.round(100 * totals.seconds / all.seconds) + "%"
+}
+
+// Red for how often the move goes wrong
+function HeatmapCell({ mistakes, reached, number }) {
+  const share = reached == 0 ? 0 : mistakes / reached
+  return (
+    <td className="w-6 h-6 text-center text-xs" style={{ backgroundColor: "rgba(239, 68, 68, " + share + ")" }}
+      title={t("Move {number}: {mistakes} wrong of {reached}", { number, mistakes, reached })}>
+      {mistakes > 0 && mistakes}
+    </td>
+  )
+}
+
+function chapterName(row) {
+  return row.chapter ?? t("The whole " + row.color + " repertoire")
+}
+
+function score(record) {
+  return record.games == 0 ? "-" : Math.round(100 * record.points / record.games) + "% of " + record.games
+}
+
+// A move with its number in front, like 3... Nf6
+function numbered({ ply, san }) {
+  return Math.ceil(ply / 2) + (ply % 2 == 1 ? ". " : "... ") + san
+}
+
+// YYYY-MM-DD in local time, the same as the days from the backend
+function dayKey(date) {
+  const pad = (x) => String(x).padStart(2, "0")
+  return date.getFullYear() + "-" + pad(date.getMonth() + 1) + "-" + pad(date.getDate())
+}
+
+const WEEKS = 53
+
+// A square for every day of the last year, a column per week, greener the more drills were done
+function Calendar({ days }) {
+  const counts = Object.fromEntries(days.map((x) => [x.day, x.attempts]))
+  const most = Math.max(1, ...days.map((x) => x.attempts))
+  const first = new Date()
+  first.setDate(first.getDate() - first.getDay() - 7 * (WEEKS - 1))
+  const weeks = [...Array(WEEKS).keys()].map((week) =>
+    [...Array(7).keys()].map(function(weekday){
+      const date = new Date(first)
+      date.setDate(first.getDate() + 7 * week + weekday)
+      return date
+    })
+  )
+  const today = new Date()
+
+  return (
+    <div className="flex gap-[2px]">
+      {weeks.map((week, i) =>
+        <div key={i} className="flex flex-col gap-[2px]">
+          {week.map(function(date){
+            const key = dayKey(date)
+            const count = counts[key] ?? 0
+            return <div key={key} className={"w-2 h-2 " + (date > today ? "invisible" : count == 0 ? "bg-gray-500/20" : "")}
+              style={count > 0 ? { backgroundColor: "rgba(34, 197, 94, " + (0.3 + 0.7 * count / most) + ")" } : {}}
+              title={key + ": " + count + " drills"}/>
+          })}
+        </div>
+      )}
+    </div>
+  )
+}
+
+// Openings shown in the tables
+const OPENINGS = 15
+
+// Days shown in the reviews per day
+const DAYS = 14
+
+// How drilling's been going, from every attempt recorded
+// Every profile side by side, only shown if there's more than one
+function Profiles() {
+  const [profiles, setProfiles] = useState(null)
+  const [error, setError] = useState(null)
+  const [exported, setExported] = useState(null)
+
+  useEffect(function(){
+    invoke("compare_profiles")
+      .then(setProfiles)
+      .catch((e) => setError(String(e)))
+  },[])
+
+  function exportProfiles(format) {
+    invoke("export_profiles", { "format": format })
+      .then((path) => setExported(t("Exported to {path}", { path })))
+      .catch((e) => setExported(t("Couldn't export: {error}", { error: e })))
+  }
+
+  if (error != null) {
+    return <span>{error}</span>
+  }
+  if (profiles == null || profiles.length < 2) {
+    return null
+  }
+  return (
+    <>
+      <h2>{t("Profiles")}</h2>
+      <table>
+        <thead>
+          <tr><th className="text-left">{t("Profile")}</th><th>{t("Drills")}</th><th>{t("Accuracy")}</th><th>{t("Time")}</th><th>{t("Last 30 days")}</th><th>{t("Streak")}</th><th>{t("Last drilled")}</th></tr>
+        </thead>
+        <tbody>
+          {profiles.map((profile) =>
+            <tr key={profile.profile ?? ""}>
+              <td>{profile.profile ?? t("Default")}</td>
+              <td className="px-2">{profile.attempts}</td>
+              <td className="px-2">{accuracy(profile)}</td>
+              <td className="px-2">{duration(profile.seconds)}</td>
+              <td className="px-2">{profile.recent.attempts}, {accuracy(profile.recent)}</td>
+              <td className="px-2">{profile.streak.current}</td>
+              <td className="px-2">{profile.last ?? "-"}</td>
+            </tr>
+          )}
+        </tbody>
+      </table>
+      <div className="flex gap-2">
+        <button onClick={() => exportProfiles("csv")}>{t("Export as CSV")}</button>
+        <button onClick={() => exportProfiles("html")}>{t("Export as HTML")}</button>
+      </div>
+      {exported != null && <span>{exported}</span>}
+    </>
+  )
+}
+
+function Forecast() {
+  const [weeks, setWeeks] = useState(1)
+  const [forecast, setForecast] = useState(null)
+  const [error, setError] = useState(null)
+  const most = Math.max(1, ...(forecast ?? []).map((x) => x.white + x.black))
+
+  useEffect(function(){
+    invoke("forecast", { "days": weeks * 7 })
+      .then(setForecast)
+      .catch((e) => setError(String(e)))
+  },[weeks])
+
+  return (
+    <>
+      <div className="flex gap-2 items-center">
+        <h2>{t("Coming due")}</h2>
+        <select value={weeks} onChange={(e) => setWeeks(Number(e.target.value))}>
+          {[1, 2, 3, 4].map((x) => <option key={x} value={x}>{x == 1 ? "1 week" : x + " weeks"}</option>)}
+        </select>
+      </div>
+      {error != null && <span>{error}</span>}
+      {forecast != null &&
+        <table>
+          <thead>
+            <tr><th></th><th>{t("White")}</th><th>{t("Black")}</th><th></th></tr>
+          </thead>
+          <tbody>
+            {forecast.map((day, i) =>
+              <tr key={day.day}>
+                <td>{i == 0 ? t("Today") : day.day}</td>
+                <td className="px-2">{day.white}</td>
+                <td className="px-2">{day.black}</td>
+                <td className="w-1/2">
+                  <div className="h-3 bg-green-600" style={{ width: (day.white + day.black) / most * 100 + "%" }}/>
+                </td>
+              </tr>
+            )}
+          </tbody>
+        </table>}
+    </>
+  )
+}
+
+// Names for what's in the usage log
+const FEATURES = {
+  "open": "Opening the app",
+  "start": "Drills",
+  "reset": "Switching colour",
+  "move_piece": "Moves on the board",
+  "play_san": "Typed moves",
+  "undo": "Taking back moves",
+  "history_back": "Stepping back",
+  "history_forward": "Stepping forward",
+  "history_sibling": "Other lines",
+  "set_fen": "Setting up positions",
+  "select_chapter": "Picking chapters",
+  "import_pgn": "Importing PGNs",
+  "spar": "Sparring",
+  "endgame": "Endgames",
+  "replay_line": "Replaying lines",
+  "restore": "Carrying on after a crash",
+  "audit": "Checking with the engine",
+  "novelties": "Novelties",
+  "gaps": "Gaps",
+  "scan_games": "Blunders in my games",
+  "stats": "Statistics",
+  "export_anki": "Exporting to Anki",
+  "export_booklet": "Exporting booklets",
+  "sync_progress": "Syncing",
+}
+
+const WEEKDAYS = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+
+// What's been used and for how long, from the usage log if it's on in the settings
+function Usage() {
+  const [usage, setUsage] = useState(null)
+  const [error, setError] = useState(null)
+  const most = Math.max(1, ...(usage?.weekdays ?? []))
+
+  function refresh() {
+    invoke("usage")
+      .then(setUsage)
+      .catch((e) => setError(String(e)))
+  }
+
+  useEffect(refresh, [])
+
+  function clear() {
+    invoke("clear_usage")
+      .then(refresh)
+      .catch((e) => setError(String(e)))
+  }
+
+  if (error != null) {
+    return <span>{error}</span>
+  }
+  if (usage == null || usage.features.length == 0) {
+    return null
+  }
+  return (
+    <>
+      <h2>{t("How I use the app")}</h2>
+      <span>{t("{count} sessions, {average} on average, the longest was {longest}", { count: usage.sessions.length, average: duration(usage.average_minutes * 60), longest: duration(usage.longest_minutes * 60) })}</span>
+      <table>
+        <tbody>
+          {WEEKDAYS.map((day, i) =>
+            <tr key={day}>
+              <td>{t(day)}</td>
+              <td className="px-2">{usage.weekdays[i]}</td>
+              <td className="w-1/2">
+                <div className="h-3 bg-indigo-600" style={{ width: usage.weekdays[i] / most * 100 + "%" }}/>
+              </td>
+            </tr>
+          )}
+        </tbody>
+      </table>
+      <table>
+        <thead>
+          <tr><th className="text-left">{t("Used")}</th><th>{t("Times")}</th><th>{t("Last")}</th></tr>
+        </thead>
+        <tbody>
+          {usage.features.map((feature) =>
+            <tr key={feature.feature}>
+              <td>{FEATURES[feature.feature] == null ? feature.feature : t(FEATURES[feature.feature])}</td>
+              <td className="px-2">{feature.count}</td>
+              <td className="px-2">{feature.last.slice(0, 10)}</td>
+            </tr>
+          )}
+        </tbody>
+      </table>
+      <button onClick={clear}>{t("Clear the usage log")}</button>
+    </>
+  )
+}
+
+function Stats({ onBack }) {
+  const [stats, setStats] = useState(null)
+  const [error, setError] = useState(null)
+  const [exported, setExported] = useState(null)
+  const [results, setResults] = useState(null)
+  const [resultsError, setResultsError] = useState(null)
+  const [times, setTimes] = useState([])
+  const longest = Math.max(0, ...(stats?.heatmap ?? []).map((x) => x.reached.length))
+
+  useEffect(function(){
+    invoke("stats")
+      .then(setStats)
+      .catch((e) => setError(String(e)))
+    invoke("training_results")
+      .then(setResults)
+      .catch((e) => setResultsError(String(e)))
+    invoke("opening_times")
+      .then(setTimes)
+      .catch(() => setTimes([]))
+  },[])
+
+  function exportStats(format) {
+    invoke("export_stats", { "format": format })
+      .then((paths) => setExported(t("Exported to {path}", { path: paths.join(t(" and ")) })))
+      .catch((e) => setExported(t("Couldn't export: {error}", { error: e })))
+  }
+
+  return (
+    <div className="flex flex-col gap-4 p-4 w-[80vmin]">
+      <h1>{t("Statistics")}</h1>
+      {error != null && <span className="text-red-500">{error}</span>}
+      {stats != null && stats.attempts == 0 && <span>{t("Nothing's been drilled yet")}</span>}
+      {stats != null && stats.attempts > 0 &&
+        <>
+          <span>{stats.attempts} drills, {stats.completed} completed, {accuracy(stats)} of moves right, {secondsPerMove(stats)} a move</span>
+          <h2>{t("Chapters")}</h2>
+          <table>
+            <thead>
+              <tr><th className="text-left">{t("Chapter")}</th><th>{t("Drills")}</th><th>{t("Completed")}</th><th>{t("Accuracy")}</th><th>{t("Per move")}</th><th>{t("Time")}</th><th>{t("Share")}</th></tr>
+            </thead>
+            <tbody>
+              {stats.chapters.map((chapter) =>
+                <tr key={chapter.color + chapter.chapter}>
+                  <td>{chapterName(chapter)}</td>
+                  <td className="px-2">{chapter.attempts}</td>
+                  <td className="px-2">{chapter.completed}</td>
+                  <td className="px-2">{accuracy(chapter)}</td>
+                  <td className="px-2">{secondsPerMove(chapter)}</td>
+                  <td className="px-2">{duration(chapter.seconds)}</td>
+                  <td className="px-2">{share(chapter, stats)}</td>
+                </tr>
+              )}
+            </tbody>
+          </table>
+          <h2>{t("Time per opening")}</h2>
+          <span>{duration(stats.seconds)} drilling in all</span>
+          <table>
+            <thead>
+              <tr><th className="text-left">{t("Opening")}</th><th>{t("Drills")}</th><th>{t("Time")}</th><th>{t("Share")}</th></tr>
+            </thead>
+            <tbody>
+              {stats.openings.slice(0, OPENINGS).map((opening) =>
+                <tr key={opening.color + opening.moves.join(" ")}>
+                  <td>{opening.moves.join(" ")} as {opening.color}</td>
+                  <td className="px-2">{opening.attempts}</td>
+                  <td className="px-2">{duration(opening.seconds)}</td>
+                  <td className="px-2">{share(opening, stats)}</td>
+                </tr>
+              )}
+            </tbody>
+          </table>
+          <h2>{t("Where it goes wrong")}</h2>
+          <table>
+            <thead>
+              <tr><th></th>{[...Array(longest).keys()].map((i) => <th key={i} className="text-xs">{i + 1}</th>)}</tr>
+            </thead>
+            <tbody>
+              {stats.heatmap.map((row) =>
+                <tr key={row.color + row.chapter}>
+                  <td className="pr-2">{chapterName(row)}</td>
+                  {[...Array(longest).keys()].map((i) =>
+                    <HeatmapCell key={i} mistakes={row.mistakes[i] ?? 0} reached={row.reached[i] ?? 0} number={i + 1}/>
+                  )}
+                </tr>
+              )}
+            </tbody>
+          </table>
+          <h2>{t("Calendar")}</h2>
+          <span>{stats.streak.current} days in a row now, {stats.streak.longest} at most</span>
+          <Calendar days={stats.days}/>
+          <h2>{t("Drills per day")}</h2>
+          <table>
+            <tbody>
+              {stats.days.slice(-DAYS).reverse().map((day) =>
+                <tr key={day.day}>
+                  <td>{day.day}</td>
+                  <td className="px-2">{day.attempts}</td>
+                  <td className="px-2">{accuracy(day)}</td>
+                </tr>
+              )}
+            </tbody>
+          </table>
+          {stats.hardest.length > 0 &&
+            <>
+              <h2>{t("Hardest moves")}</h2>
+              <table>
+                <thead>
+                  <tr><th className="text-left">{t("Line")}</th><th>{t("Move")}</th><th>{t("Wrong")}</th></tr>
+                </thead>
+                <tbody>
+                  {stats.hardest.map((hard) =>
+                    <tr key={hard.color + hard.moves.join(" ")}>
+                      <td>{hard.moves.join(" ")}</td>
+                      <td className="px-2">{hard.expected.join(", ")}</td>
+                      <td className="px-2">{hard.mistakes} of {hard.reached}</td>
+                    </tr>
+                  )}
+                </tbody>
+              </table>
+            </>}
+          <h2>{t("Results in my games")}</h2>
+          {resultsError != null && <span>{resultsError}</span>}
+          {results != null &&
+            <>
+              <table>
+                <thead>
+                  <tr><th></th><th>{t("Drilled openings")}</th><th>{t("The rest")}</th></tr>
+                </thead>
+                <tbody>
+                  {results.colors.map((color) =>
+                    <tr key={color.color}>
+                      <td>As {color.color}</td>
+                      <td className="px-2">{score(color.drilled)}</td>
+                      <td className="px-2">{score(color.undrilled)}</td>
+                    </tr>
+                  )}
+                </tbody>
+              </table>
+              <table>
+                <thead>
+                  <tr><th className="text-left">{t("Opening")}</th><th>{t("Score")}</th><th>{t("Drills")}</th></tr>
+                </thead>
+                <tbody>
+                  {results.openings.slice(0, OPENINGS).map((opening) =>
+                    <tr key={opening.color + opening.moves.join(" ")}>
+                      <td>{opening.eco != null && opening.eco + " "}{opening.moves.join(" ")} as {opening.color}</td>
+                      <td className="px-2">{score(opening)}</td>
+                      <td className="px-2">{opening.drills}</td>
+                    </tr>
+                  )}
+                </tbody>
+              </table>
+            </>}
+          {times.length > 0 &&
+            <>
+              <h2>{t("Time in my openings")}</h2>
+              <table>
+                <thead>
+                  <tr><th className="text-left">{t("Opening")}</th><th>{t("Seconds")}</th><th>{t("Slowest move")}</th></tr>
+                </thead>
+                <tbody>
+                  {times.slice(0, OPENINGS).map((opening) =>
+                    <tr key={opening.color + opening.moves.join(" ")}>
+                      <td>{opening.eco != null && opening.eco + " "}{opening.moves.join(" ")} as {opening.color}</td>
+                      <td className="px-2">{Math.round(opening.seconds)}</td>
+                      <td className="px-2">{opening.slowest != null &&
+                        t("{move} in {seconds}s", { move: numbered(opening.slowest), seconds: Math.round(opening.slowest.seconds) })}</td>
+                    </tr>
+                  )}
+                </tbody>
+              </table>
+            </>}
+          <div className="flex gap-2">
+            <button onClick={() => exportStats("csv")}>{t("Export as CSV")}</button>
+            <button onClick={() => exportStats("json")}>{t("Export as JSON")}</button>
+          </div>
+          {exported != null && <span>{exported}</span>}
+        </>}
+      {stats != null && <Forecast/>}
+      <Profiles/>
+      <Usage/>
+      <button onClick={onBack}>{t("Back")}</button>
+    </div>
+  )
+}
+
+export default Stats
@@ -0,0 +1,14 @@
+// Name of the square under a pointer event on the board element, e.g. "e4"
+export function squareAt(event, element, orientation) {
+  const rect = element.getBoundingClientRect()
+  const file = Math.floor(8 * (event.clientX - rect.left) / rect.width)
+  const rank = Math.floor(8 * (event.clientY - rect.top) / rect.height)
+  if (file < 0 || file > 7 || rank < 0 || rank > 7) {
+    return null
+  }
+  if (orientation == "white") {
+    return "abcdefgh"[file] + (8 - rank)
+  } else {
+    return "hgfedcba"[file] + (rank + 1)
+  }
+}
@@ -0,0 +1,60 @@
+import { useEffect, useRef } from 'react'
+
+// Buttons in the browser's standard gamepad mapping
+export const BUTTONS = {
+  "A": 0, "B": 1, "LB": 4, "RB": 5, "BACK": 8, "START": 9,
+  "UP": 12, "DOWN": 13, "LEFT": 14, "RIGHT": 15,
+}
+
+// Poll any connected gamepads every frame calling `onButton` with the index of each button as it
+// gets pressed
+export function useGamepad(onButton) {
+  const handler = useRef(onButton)
+  handler.current = onButton
+
+  useEffect(function(){
+    let pressed = {}
+    let frame = null
+
+    function poll() {
+      const next = {}
+      for (const pad of navigator.getGamepads?.() ?? []) {
+        if (pad == null) {
+          continue
+        }
+        pad.buttons.forEach(function(button, i){
+          if (button.pressed) {
+            next[i] = true
+            if (!pressed[i]) {
+              handler.current(i)
+            }
+          }
+        })
+      }
+      pressed = next
+      frame = requestAnimationFrame(poll)
+    }
+
+    frame = requestAnimationFrame(poll)
+    return () => cancelAnimationFrame(frame)
+  },[])
+}
+
+// Move a square name one step in a direction on the screen for the given board orientation
+export function stepSquare(square, button, orientation) {
+  let file = square.charCodeAt(0) - "a".charCodeAt(0)
+  let rank = Number(square[1]) - 1
+  const flip = orientation == "white" ? 1 : -1
+  if (button == BUTTONS.UP) {
+    rank += flip
+  } else if (button == BUTTONS.DOWN) {
+    rank -= flip
+  } else if (button == BUTTONS.LEFT) {
+    file -= flip
+  } else if (button == BUTTONS.RIGHT) {
+    file += flip
+  }
+  file = Math.min(7, Math.max(0, file))
+  rank = Math.min(7, Math.max(0, rank))
+  return "abcdefgh"[file] + (rank + 1)
+}
@@ -0,0 +1,29 @@
+import de from "./locales/de.json"
+import es from "./locales/es.json"
+import ru from "./locales/ru.json"
+
+// Translations keyed by the English text, anything missing from one is shown in English. Names are
+// what each calls itself for the settings screen.
+export const LOCALES = {
+  "en": { "name": "English", "strings": {} },
+  "de": { "name": "Deutsch", "strings": de },
+  "es": { "name": "Español", "strings": es },
+  "ru": { "name": "Русский", "strings": ru },
+}
+
+let strings = {}
+
+// Use the locale from the config, or the system's language if it's not set
+export function setLocale(locale) {
+  const name = (locale ?? navigator.language ?? "en").split("-")[0].toLowerCase()
+  strings = LOCALES[name]?.strings ?? {}
+  document.documentElement.lang = name in LOCALES ? name : "en"
+}
+
+// The text in the current locale, `{name}` in it is filled in from `values`
+export function t(text, values = {}) {
+  const translated = strings[text] ?? text
+  return translated.replace(/\{(\w+)\}/g, (match, key) => key in values ? String(values[key]) : match)
+}
+
+setLocale(null)
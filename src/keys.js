@@ -0,0 +1,12 @@
+// Check if a keyboard event matches a binding from the config, bindings are a key name optionally
+// prefixed with `Ctrl+` and several can be given separated by `|` e.g. `Ctrl+z|Backspace`
+export function matchesKey(binding, event) {
+  if (binding == null) {
+    return false
+  }
+  return binding.split("|").some(function(option){
+    const ctrl = option.startsWith("Ctrl+")
+    const key = ctrl ? option.slice("Ctrl+".length) : option
+    return key == event.key && ctrl == (event.ctrlKey || event.metaKey)
+  })
+}
@@ -1,10 +1,18 @@
 import React from 'react'
 import ReactDOM from 'react-dom/client'
+import { invoke } from '@tauri-apps/api'
 import App from './App.jsx'
+import ErrorBoundary from './ErrorBoundary.jsx'
 import './index.css'
 
+// Errors outside of rendering don't reach the boundary, they're only logged
+window.addEventListener("error", (event) => invoke("log_error", { "message": String(event.error ?? event.message) }))
+window.addEventListener("unhandledrejection", (event) => invoke("log_error", { "message": String(event.reason) }))
+
 ReactDOM.createRoot(document.getElementById('root')).render(
   <React.StrictMode>
-    <App />
+    <ErrorBoundary>
+      <App />
+    </ErrorBoundary>
   </React.StrictMode>,
 )
@@ -0,0 +1,8 @@
+// Scores are centipawns or moves to mate
+export function formatScore(score) {
+  if (score.kind == "mate") {
+    return (score.value < 0 ? "-#" : "#") + Math.abs(score.value)
+  }
+  const pawns = score.value / 100
+  return (pawns > 0 ? "+" : "") + pawns.toFixed(2)
+}
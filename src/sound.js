@@ -0,0 +1,48 @@
+// Sounds for the board and reading moves out loud. Sounds are made with the Web Audio API so
+// there aren't any files to ship, each is a list of notes as [frequency in Hz, start in seconds,
+// length in seconds, waveform].
+const SOUNDS = {
+  "move": [[220, 0, 0.05, "triangle"]],
+  "capture": [[330, 0, 0.04, "square"], [180, 0.04, 0.06, "triangle"]],
+  "check": [[660, 0, 0.08, "sine"], [880, 0.08, 0.1, "sine"]],
+  "mistake": [[160, 0, 0.25, "sawtooth"]],
+  "line_complete": [[523, 0, 0.1, "sine"], [659, 0.1, 0.1, "sine"], [784, 0.2, 0.2, "sine"]],
+}
+
+let context = null
+
+// Play one of the sounds the backend sends in `sound` events at the volume in the config
+export function playSound(name, settings) {
+  const notes = SOUNDS[name]
+  if (notes == null || settings.muted || settings.volume <= 0) {
+    return
+  }
+  context = context ?? new AudioContext()
+  const now = context.currentTime
+  for (const [frequency, start, length, type] of notes) {
+    const oscillator = context.createOscillator()
+    const gain = context.createGain()
+    oscillator.type = type
+    oscillator.frequency.value = frequency
+    // Fade out so the notes don't click as they stop
+    gain.gain.setValueAtTime(0.3 * settings.volume, now + start)
+    gain.gain.exponentialRampToValueAtTime(0.001, now + start + length)
+    oscillator.connect(gain).connect(context.destination)
+    oscillator.start(now + start)
+    oscillator.stop(now + start + length)
+  }
+}
+
+// Read out a move or hint from a `speak` event with the system's speech synthesis
+export function speak(text, settings) {
+  if (window.speechSynthesis == null) {
+    return
+  }
+  const utterance = new SpeechSynthesisUtterance(text)
+  utterance.rate = settings.rate
+  const voice = speechSynthesis.getVoices().find((x) => x.name == settings.voice)
+  if (voice != null) {
+    utterance.voice = voice
+  }
+  speechSynthesis.speak(utterance)
+}
@@ -0,0 +1,59 @@
+// Board colour themes, the light and dark square colours
+export const THEMES = {
+  "brown": { "light": "#f0d9b5", "dark": "#b58863" },
+  "green": { "light": "#eeeed2", "dark": "#769656" },
+  "blue": { "light": "#dee3e6", "dark": "#8ca2ad" },
+  "grey": { "light": "#d9d9d9", "dark": "#8c8c8c" },
+}
+
+const UNICODE_PIECES = {
+  "K": "♚", "Q": "♛", "R": "♜", "B": "♝", "N": "♞", "P": "♟",
+}
+
+function unicodePieces() {
+  const pieces = {}
+  for (const color of ["w", "b"]) {
+    for (const [role, glyph] of Object.entries(UNICODE_PIECES)) {
+      pieces[color + role] = function UnicodePiece({ squareWidth }) {
+        return (
+          <div style={{
+            "width": squareWidth, "height": squareWidth, "fontSize": squareWidth * 0.8,
+            "lineHeight": squareWidth + "px", "textAlign": "center",
+            "color": color == "w" ? "#ffffff" : "#000000",
+            "WebkitTextStroke": color == "w" ? "1px #000000" : "1px #ffffff",
+          }}>{glyph}</div>
+        )
+      }
+    }
+  }
+  return pieces
+}
+
+// Piece sets, undefined uses the chessboard's built in pieces
+export const PIECE_SETS = {
+  "default": undefined,
+  "unicode": unicodePieces(),
+}
+
+const DEFAULT_HIGHLIGHTS = {
+  "selected": "rgba(255, 255, 0, 0.4)",
+  "legal_move": "rgba(0, 0, 0, 0.3)",
+  "hint": "rgba(0, 200, 255, 0.4)",
+  "cursor": "#6366f1",
+}
+
+// Colours for picking out squares, anything missing from the config uses the default
+export function highlightColors(appearance) {
+  return { ...DEFAULT_HIGHLIGHTS, ...appearance.highlights }
+}
+
+// Props to give a `Chessboard` so it's drawn with the given appearance
+export function boardStyle(appearance) {
+  const theme = THEMES[appearance.board_theme] ?? THEMES["brown"]
+  return {
+    "customLightSquareStyle": { "backgroundColor": theme.light },
+    "customDarkSquareStyle": { "backgroundColor": theme.dark },
+    "customPieces": PIECE_SETS[appearance.piece_set],
+    "showBoardNotation": appearance.show_coordinates ?? true,
+  }
+}
@@ -0,0 +1,33 @@
+import { useState, useEffect } from 'react'
+
+function currentState() {
+  return {
+    "width": window.innerWidth,
+    "height": window.innerHeight,
+    // Minimised or otherwise hidden, there's no point doing any work for the screen
+    "visible": document.visibilityState == "visible",
+    "focused": document.hasFocus(),
+  }
+}
+
+// Track the window's size and whether it can be seen so the layout can follow it and timers
+// can stop while hidden
+export function useWindowState() {
+  const [state, setState] = useState(currentState)
+
+  useEffect(function(){
+    const update = () => setState(currentState())
+    window.addEventListener("resize", update)
+    window.addEventListener("focus", update)
+    window.addEventListener("blur", update)
+    document.addEventListener("visibilitychange", update)
+    return function(){
+      window.removeEventListener("resize", update)
+      window.removeEventListener("focus", update)
+      window.removeEventListener("blur", update)
+      document.removeEventListener("visibilitychange", update)
+    }
+  },[])
+
+  return state
+}